@@ -158,7 +158,7 @@ class BrowserRelay {
   // ============================================
 
   async handleFlowQMessage(message) {
-    const { action, tabId, url, selector, text, expression, direction } = message;
+    const { action, tabId, url, selector, text, expression, direction, ignoreCache } = message;
 
     switch (action) {
       case 'ping':
@@ -173,6 +173,15 @@ class BrowserRelay {
       case 'close':
         return this.closeTab(tabId);
 
+      case 'back':
+        return this.goBack(tabId);
+
+      case 'forward':
+        return this.goForward(tabId);
+
+      case 'reload':
+        return this.reloadTab(tabId, ignoreCache);
+
       case 'attach':
         return this.attachToTab(tabId);
 
@@ -234,6 +243,21 @@ class BrowserRelay {
     return { success: true };
   }
 
+  async goBack(tabId) {
+    await chrome.tabs.goBack(tabId);
+    return { success: true };
+  }
+
+  async goForward(tabId) {
+    await chrome.tabs.goForward(tabId);
+    return { success: true };
+  }
+
+  async reloadTab(tabId, ignoreCache = false) {
+    await chrome.tabs.reload(tabId, { bypassCache: !!ignoreCache });
+    return { success: true, ignoreCache: !!ignoreCache };
+  }
+
   // ============================================
   // Debugger Control (CDP)
   // ============================================
@@ -17,7 +17,6 @@ import {
   rssGetCategories,
   rssCreateCategory,
   rssDeleteCategory,
-  rssUpsertArticles,
   rssGetArticles,
   rssGetRecentArticles,
   rssSearchArticles,
@@ -25,12 +24,10 @@ import {
   rssToggleArticleStarred,
   rssGetStarredArticles,
   rssCleanupOldArticles,
-  rssFetch,
+  rssRefreshFeed,
   type StoredFeed,
   type StoredCategory,
   type StoredArticle,
-  type RSSParsedFeed,
-  type RSSParsedItem,
 } from '../tauri-api'
 
 export interface RSSManagerOptions {
@@ -102,6 +99,16 @@ export class RSSManager {
     return feeds
   }
 
+  /**
+   * Re-read one feed from the database into the cache
+   */
+  private async reloadFeed(id: string): Promise<void> {
+    const feed = (await rssGetFeeds()).find(f => f.id === id)
+    if (feed) {
+      this.feeds.set(id, feed)
+    }
+  }
+
   /**
    * Get all managed feeds
    */
@@ -194,61 +201,27 @@ export class RSSManager {
       updated_at: new Date().toISOString(),
     }
 
-    await rssUpdateFeed(updated)
+    // The failure counter belongs to the backend refresh; don't write back a stale copy
+    const { consecutive_failures: _, ...settings } = updated
+    await rssUpdateFeed(settings)
     this.feeds.set(id, updated)
 
     return updated
   }
 
   /**
-   * Refresh a single feed
+   * Refresh a single feed through the backend, which tracks failures and backoff
    */
   async refreshFeed(id: string): Promise<number> {
-    const feed = this.feeds.get(id)
-    if (!feed) {
+    if (!this.feeds.has(id)) {
       throw new Error('Feed not found')
     }
 
     try {
-      // Fetch with conditional headers
-      const result = await rssFetch(feed.url, feed.etag || undefined, feed.last_modified || undefined)
-
-      // Handle 304 Not Modified
-      if (result.status_code === 304) {
-        return 0
-      }
-
-      // Parse content
-      const parsed = await this.parseContent(result.content)
-
-      // Convert to articles
-      const now = new Date().toISOString()
-      const articles: StoredArticle[] = parsed.items
-        .slice(0, this.options.maxArticlesPerFeed)
-        .map(item => this.parsedItemToArticle(item, feed.id, now))
-
-      // Save articles
-      const newCount = await rssUpsertArticles(articles)
-
-      // Update feed metadata
-      const updated = await this.updateFeed(id, {
-        status: 'active',
-        error_message: null,
-        last_fetched_at: now,
-        etag: result.etag,
-        last_modified: result.last_modified,
-        article_count: feed.article_count + newCount,
-        unread_count: feed.unread_count + newCount,
-      })
-
-      return newCount
-    } catch (error) {
-      // Update feed with error
-      await this.updateFeed(id, {
-        status: 'error',
-        error_message: error instanceof Error ? error.message : String(error),
-      })
-      throw error
+      return await rssRefreshFeed(id)
+    } finally {
+      // Pick up the status, validators and counts the backend stored
+      await this.reloadFeed(id)
     }
   }
 
@@ -405,62 +378,6 @@ export class RSSManager {
       return ''
     }
   }
-
-  private async parseContent(content: string): Promise<RSSParsedFeed> {
-    // Use the backend parser
-    const { rssParse } = await import('../tauri-api')
-    return rssParse(content)
-  }
-
-  private parsedItemToArticle(item: RSSParsedItem, feedId: string, fetchedAt: string): StoredArticle {
-    // Generate unique ID from guid or link
-    const id = item.guid || item.link || generateId()
-
-    // Parse publish date
-    let publishedAt = fetchedAt
-    if (item.pub_date) {
-      try {
-        publishedAt = new Date(item.pub_date).toISOString()
-      } catch {
-        publishedAt = fetchedAt
-      }
-    }
-
-    return {
-      id,
-      feed_id: feedId,
-      title: item.title || 'Untitled',
-      link: item.link || '',
-      content: item.content || item.description || '',
-      summary: null,
-      author: item.author || null,
-      image_url: this.extractImageUrl(item),
-      enclosures: item.enclosures.length > 0 ? JSON.stringify(item.enclosures) : null,
-      published_at: publishedAt,
-      fetched_at: fetchedAt,
-      is_read: false,
-      is_starred: false,
-      topics: null,
-    }
-  }
-
-  private extractImageUrl(item: RSSParsedItem): string | null {
-    // Try to find image in content
-    const content = item.content || item.description || ''
-    const imgMatch = content.match(/<img[^>]+src=["']([^"']+)["']/)
-    if (imgMatch) {
-      return imgMatch[1]
-    }
-
-    // Check enclosures for images
-    for (const enclosure of item.enclosures) {
-      if (enclosure.media_type.startsWith('image/')) {
-        return enclosure.url
-      }
-    }
-
-    return null
-  }
 }
 
 // Singleton instance
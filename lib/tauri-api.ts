@@ -29,7 +29,7 @@ export interface Message {
 }
 
 export interface SessionEvent {
-  event_type: 'text_delta' | 'complete' | 'error'
+  event_type: 'text_delta' | 'tool_use' | 'tool_result' | 'complete' | 'error' | 'cancelled'
   session_id: string
   data: Record<string, unknown>
 }
@@ -66,6 +66,8 @@ function apiSettingsToRust(settings: ApiSettings | undefined) {
     bedrock_access_key_id: settings.bedrockAccessKeyId,
     bedrock_secret_access_key: settings.bedrockSecretAccessKey,
     bedrock_model: settings.bedrockModel,
+    max_budget_usd: settings.maxBudgetUsd,
+    max_skill_tokens: settings.maxSkillTokens,
   }
 }
 
@@ -83,6 +85,11 @@ export async function sendMessage(
   })
 }
 
+/** Stop the in-flight turn for a session; resolves false if nothing was running */
+export async function cancelMessage(sessionId: string): Promise<boolean> {
+  return invoke<boolean>('cancel_message', { sessionId })
+}
+
 // Event subscription for streaming responses
 export function onSessionEvent(callback: (event: SessionEvent) => void): Promise<UnlistenFn> {
   return listen<SessionEvent>('session-event', (e) => callback(e.payload));
@@ -194,18 +201,21 @@ export interface WorkspaceFile {
   relative_path: string
   is_dir: boolean
   size?: number
+  score: number
 }
 
-/** Search files in workspace directory */
+/** Search files in workspace directory (.gitignore rules apply unless respectGitignore is false) */
 export async function searchWorkspaceFiles(
   workspace: string,
   query: string,
-  maxResults?: number
+  maxResults?: number,
+  respectGitignore?: boolean
 ): Promise<WorkspaceFile[]> {
   return invoke<WorkspaceFile[]>('search_workspace_files', {
     workspace,
     query,
     max_results: maxResults,
+    respectGitignore,
   })
 }
 
@@ -220,9 +230,12 @@ export async function readFileForMention(
   })
 }
 
-/** Fetch URL content for @url mention */
-export async function fetchUrlForMention(url: string): Promise<string> {
-  return invoke<string>('fetch_url_for_mention', { url })
+/**
+ * Fetch URL content for @url mention
+ * Loopback/private/link-local hosts are refused unless listed in allowedHosts
+ */
+export async function fetchUrlForMention(url: string, allowedHosts?: string[]): Promise<string> {
+  return invoke<string>('fetch_url_for_mention', { url, allowedHosts })
 }
 
 // ============ Event API (via Tauri) ============
@@ -239,6 +252,11 @@ export async function getWorkspaceBackend(): Promise<string | null> {
   return invoke<string | null>('get_workspace')
 }
 
+/** Recently used workspace paths persisted by the backend, most recent first */
+export async function getRecentWorkspaces(): Promise<string[]> {
+  return invoke<string[]>('get_recent_workspaces')
+}
+
 // ============ Workspace API ============
 
 export interface Workspace {
@@ -419,6 +437,10 @@ export interface DbSession {
   is_flagged: boolean | null
   status: string | null  // 'todo' | 'in-progress' | 'needs-review' | 'done' | 'cancelled'
   has_unread: boolean | null
+  /** Set when the user renamed the session; auto-titling leaves it alone */
+  title_edited?: boolean | null
+  /** Free-form labels; change them with dbSetSessionTags */
+  tags?: string[]
 }
 
 export interface DbMessage {
@@ -428,6 +450,22 @@ export interface DbMessage {
   content: string
   timestamp: string
   metadata: string | null  // JSON string for tool_calls, steps, cost, etc.
+  /** Previous message on this branch; defaults to the session's latest message on append */
+  parent_message_id?: string | null
+}
+
+/** One page of a cursor-paginated listing */
+export interface Page<T> {
+  items: T[]
+  /** Pass back as the same cursor for the next page; null on the last page */
+  next_cursor: string | null
+}
+
+export interface DbMessageSearchResult extends DbMessage {
+  /** Relevance score (higher is better) */
+  score: number
+  /** Excerpt with matched terms wrapped in <mark> */
+  snippet: string
 }
 
 export interface DbSessionWithMessages {
@@ -464,8 +502,31 @@ export async function dbAppendMessage(message: DbMessage): Promise<void> {
   return invoke<void>('db_append_message', { message })
 }
 
-export async function dbGetMessages(sessionId: string): Promise<DbMessage[]> {
-  return invoke<DbMessage[]>('db_get_messages', { sessionId })
+/** All messages in a session, or only the branch ending at leafId */
+export async function dbGetMessages(sessionId: string, leafId?: string): Promise<DbMessage[]> {
+  return invoke<DbMessage[]>('db_get_messages', { sessionId, leafId })
+}
+
+/**
+ * Page through a session's active branch, oldest first within each page.
+ * afterId (or no cursor) walks forward; beforeId alone walks backward.
+ */
+export async function dbGetMessagesPage(
+  sessionId: string,
+  limit: number,
+  cursor: { beforeId?: string; afterId?: string } = {}
+): Promise<Page<DbMessage>> {
+  return invoke<Page<DbMessage>>('db_get_messages_page', { sessionId, limit, ...cursor })
+}
+
+/** Store message as a new reply to messageId (e.g. an edited user message) */
+export async function dbBranchFrom(messageId: string, message: DbMessage): Promise<void> {
+  return invoke<void>('db_branch_from', { messageId, message })
+}
+
+/** Full-text search over stored message content */
+export async function dbSearchMessages(query: string, limit?: number): Promise<DbMessageSearchResult[]> {
+  return invoke<DbMessageSearchResult[]>('db_search_messages', { query, limit })
 }
 
 export async function dbGetRecentMessages(sessionId: string, limit: number): Promise<DbMessage[]> {
@@ -481,6 +542,21 @@ export async function dbGetSessionWithMessages(sessionId: string): Promise<DbSes
   return invoke<DbSessionWithMessages | null>('db_get_session_with_messages', { sessionId })
 }
 
+/**
+ * Title a session from its first user/assistant exchange
+ * Returns the new title, or null when the user already renamed the session
+ */
+export async function dbGenerateSessionTitle(sessionId: string, config: ChatApiConfig): Promise<string | null> {
+  return invoke<string | null>('db_generate_session_title', { sessionId, config: toBackendApiConfig(config) })
+}
+
+export type ExportFormat = 'markdown' | 'json'
+
+/** Export a session (metadata, messages, tool runs) as Markdown or a JSON transcript */
+export async function dbExportSession(sessionId: string, format: ExportFormat): Promise<string> {
+  return invoke<string>('db_export_session', { sessionId, format })
+}
+
 // Session Flag/Status operations
 export async function dbUpdateSessionFlag(sessionId: string, isFlagged: boolean): Promise<void> {
   return invoke<void>('db_update_session_flag', { sessionId, isFlagged })
@@ -498,6 +574,15 @@ export async function dbGetFlaggedSessions(workspacePath: string | null): Promis
   return invoke<DbSession[]>('db_get_flagged_sessions', { workspacePath })
 }
 
+/** Replace a session's tags; returns the normalized (trimmed, deduplicated) list */
+export async function dbSetSessionTags(sessionId: string, tags: string[]): Promise<string[]> {
+  return invoke<string[]>('db_set_session_tags', { sessionId, tags })
+}
+
+export async function dbGetSessionsByTag(workspacePath: string | null, tag: string): Promise<DbSession[]> {
+  return invoke<DbSession[]>('db_get_sessions_by_tag', { workspacePath, tag })
+}
+
 export async function dbGetSessionsByStatus(workspacePath: string | null, status: string): Promise<DbSession[]> {
   return invoke<DbSession[]>('db_get_sessions_by_status', { workspacePath, status })
 }
@@ -534,9 +619,10 @@ export async function mcpListServers(): Promise<McpServerInfo[]> {
 
 /**
  * Add a new MCP server configuration
+ * Fails if a stdio command (or its runtime, e.g. node for npx) is not on PATH unless force is set
  */
-export async function mcpAddServer(config: AddMcpServerRequest): Promise<void> {
-  return invoke<void>('mcp_add_server', { config })
+export async function mcpAddServer(config: AddMcpServerRequest, force?: boolean): Promise<void> {
+  return invoke<void>('mcp_add_server', { config, force })
 }
 
 /**
@@ -556,8 +642,8 @@ export async function mcpToggleServer(name: string, disabled: boolean): Promise<
 /**
  * Update an existing MCP server configuration
  */
-export async function mcpUpdateServer(name: string, config: AddMcpServerRequest): Promise<void> {
-  return invoke<void>('mcp_update_server', { name, config })
+export async function mcpUpdateServer(name: string, config: AddMcpServerRequest, force?: boolean): Promise<void> {
+  return invoke<void>('mcp_update_server', { name, config, force })
 }
 
 /**
@@ -567,12 +653,35 @@ export async function mcpGetServer(name: string): Promise<McpServerInfo | null>
   return invoke<McpServerInfo | null>('mcp_get_server', { name })
 }
 
+export interface McpToolInfo {
+  name: string
+  description: string
+}
+
+export interface McpHealth {
+  reachable: boolean
+  tool_count: number
+  tools: McpToolInfo[]
+  error?: string
+}
+
+/**
+ * Start or contact an MCP server and list its tools
+ */
+export async function mcpHealthCheck(name: string): Promise<McpHealth> {
+  return invoke<McpHealth>('mcp_health_check', { name })
+}
+
 // ============ Skills API ============
 
 export interface SkillInfo {
   name: string
   path: string
   token_count: number | null
+  /** Disabled skills stay installed but are left out of the system prompt */
+  enabled: boolean
+  /** Directory the skill was loaded from; workspace skills replace same-named global ones */
+  source_scope: 'global' | 'workspace'
 }
 
 export interface SkillMetadata {
@@ -583,6 +692,7 @@ export interface SkillMetadata {
   author: string | null
   installed_at: string
   updated_at: string
+  enabled: boolean
 }
 
 export interface SkillFileItem {
@@ -592,6 +702,13 @@ export interface SkillFileItem {
   size: number | null
 }
 
+export interface SkillUpdateSummary {
+  name: string
+  files_added: string[]
+  files_changed: string[]
+  files_removed: string[]
+}
+
 export interface SearchSkill {
   name: string
   slug: string
@@ -636,37 +753,55 @@ export async function skillReadFile(name: string, filePath: string): Promise<str
 
 /**
  * Install skill from content (markdown file)
+ * Pass a workspace path to install for that project only
+ * Skills without name/description frontmatter are rejected unless force is set
  */
-export async function skillInstallFromContent(content: string, filename: string): Promise<string> {
-  return invoke<string>('skill_install_from_content', { content, filename })
+export async function skillInstallFromContent(content: string, filename: string, workspace?: string, force?: boolean): Promise<string> {
+  return invoke<string>('skill_install_from_content', { content, filename, workspace, force })
 }
 
 /**
  * Install skill from URL (GitHub directory or direct file)
  */
-export async function skillInstallFromUrl(url: string): Promise<string> {
-  return invoke<string>('skill_install_from_url', { url })
+export async function skillInstallFromUrl(url: string, workspace?: string): Promise<string> {
+  return invoke<string>('skill_install_from_url', { url, workspace })
 }
 
 /**
  * Install skill from ZIP (base64 encoded)
  */
-export async function skillInstallFromZip(zipBase64: string, source: string): Promise<string> {
-  return invoke<string>('skill_install_from_zip', { zipBase64, source })
+export async function skillInstallFromZip(zipBase64: string, source: string, workspace?: string): Promise<string> {
+  return invoke<string>('skill_install_from_zip', { zipBase64, source, workspace })
+}
+
+/**
+ * Re-download a skill from its recorded source, keeping local metadata
+ * Fails if the skill has no recorded source
+ */
+export async function updateSkill(name: string, workspace?: string): Promise<SkillUpdateSummary> {
+  return invoke<SkillUpdateSummary>('skill_update', { name, workspace })
 }
 
 /**
  * Delete a skill
+ * Pass a workspace path to target that project's skill instead of the global one
+ */
+export async function skillDelete(name: string, workspace?: string): Promise<void> {
+  return invoke<void>('skill_delete', { name, workspace })
+}
+
+/**
+ * Enable or disable a skill without deleting it
  */
-export async function skillDelete(name: string): Promise<void> {
-  return invoke<void>('skill_delete', { name })
+export async function setSkillEnabled(name: string, enabled: boolean, workspace?: string): Promise<void> {
+  return invoke<void>('skill_set_enabled', { name, enabled, workspace })
 }
 
 /**
  * Open skill folder in file manager
  */
-export async function skillOpenFolder(name: string): Promise<void> {
-  return invoke<void>('skill_open_folder', { name })
+export async function skillOpenFolder(name: string, workspace?: string): Promise<void> {
+  return invoke<void>('skill_open_folder', { name, workspace })
 }
 
 /**
@@ -707,6 +842,8 @@ export interface ChatApiConfig {
   model?: string
   region?: string  // For bedrock
   awsProfile?: string  // For bedrock
+  deployment?: string  // For azure (defaults to model)
+  apiVersion?: string  // For azure
 }
 
 export interface SimpleChatRequest {
@@ -717,11 +854,19 @@ export interface SimpleChatRequest {
   model?: string
   region?: string
   awsProfile?: string
+  deployment?: string
+  apiVersion?: string
   systemPrompt?: string
   maxTokens?: number
   temperature?: number
   /** Workspace path for memory context injection */
   workspace?: string
+  /** Tools to offer by name (default: memory when a workspace is set); 'workspace_read' is opt-in */
+  tools?: string[]
+  /** Extended thinking (Anthropic and Bedrock); budget must be at least 1024 and below maxTokens */
+  thinking?: { budget_tokens: number }
+  /** Reuse the stored response of an identical earlier request (skipped while the memory tool is offered) */
+  cache?: boolean
 }
 
 export interface ChatApiResponse {
@@ -731,6 +876,8 @@ export interface ChatApiResponse {
     input_tokens: number
     output_tokens: number
   }
+  /** Thinking text, present when extended thinking was enabled */
+  thinking?: string
 }
 
 /**
@@ -741,6 +888,20 @@ export async function chatSend(request: SimpleChatRequest): Promise<ChatApiRespo
   return invoke<ChatApiResponse>('chat_send', { request })
 }
 
+export interface ChatEstimate {
+  input_tokens: number
+  est_cost_usd: number
+  /** False when the model is unknown and a default price was used */
+  pricing_known: boolean
+}
+
+/**
+ * Estimate input tokens and cost of a chat request without sending it
+ */
+export async function chatEstimate(request: SimpleChatRequest): Promise<ChatEstimate> {
+  return invoke<ChatEstimate>('chat_estimate', { request })
+}
+
 // ============ API Settings Storage ============
 
 export type ApiProvider = 'anthropic' | 'bedrock'
@@ -761,6 +922,10 @@ export interface ApiSettings {
   bedrockAccessKeyId?: string
   bedrockSecretAccessKey?: string
   bedrockModel?: string
+  // Hard spending cap per conversation (USD)
+  maxBudgetUsd?: number
+  // Token budget for skills added to the system prompt (default 20000)
+  maxSkillTokens?: number
 }
 
 const API_SETTINGS_KEY = 'api_settings'
@@ -775,6 +940,27 @@ export const DEFAULT_API_SETTINGS: ApiSettings = {
   anthropicModel: 'claude-sonnet-4-5-20250514',
 }
 
+// Credential fields kept in the encrypted secret store instead of settings.json
+const SECRET_SETTINGS_FIELDS = {
+  anthropicApiKey: 'anthropic_api_key',
+  bedrockAccessKeyId: 'bedrock_access_key_id',
+  bedrockSecretAccessKey: 'bedrock_secret_access_key',
+} as const
+
+/**
+ * Encrypt and persist a secret (an empty value deletes it)
+ */
+export async function storeSecret(name: string, value: string): Promise<void> {
+  return invoke<void>('store_secret', { name, value })
+}
+
+/**
+ * Load a decrypted secret
+ */
+export async function loadSecret(name: string): Promise<string | null> {
+  return invoke<string | null>('load_secret', { name })
+}
+
 /**
  * Get saved API settings
  */
@@ -783,7 +969,12 @@ export async function getApiSettings(): Promise<ApiSettings> {
     const { load } = await import('@tauri-apps/plugin-store')
     const store = await load('settings.json', { autoSave: true, defaults: {} })
     const settings = await store.get<ApiSettings>(API_SETTINGS_KEY)
-    return settings ? { ...DEFAULT_API_SETTINGS, ...settings } : DEFAULT_API_SETTINGS
+    const merged: ApiSettings = settings ? { ...DEFAULT_API_SETTINGS, ...settings } : { ...DEFAULT_API_SETTINGS }
+    for (const [field, name] of Object.entries(SECRET_SETTINGS_FIELDS)) {
+      const value = await loadSecret(name)
+      if (value) merged[field as keyof typeof SECRET_SETTINGS_FIELDS] = value
+    }
+    return merged
   } catch (e) {
     console.error('Failed to get API settings:', e)
     return DEFAULT_API_SETTINGS
@@ -791,13 +982,20 @@ export async function getApiSettings(): Promise<ApiSettings> {
 }
 
 /**
- * Save API settings
+ * Save API settings (credentials go to the encrypted secret store)
  */
 export async function saveApiSettings(settings: ApiSettings): Promise<void> {
   try {
+    const plain: ApiSettings = { ...settings }
+    for (const [field, name] of Object.entries(SECRET_SETTINGS_FIELDS)) {
+      const key = field as keyof typeof SECRET_SETTINGS_FIELDS
+      await storeSecret(name, settings[key] || '')
+      delete plain[key]
+    }
+
     const { load } = await import('@tauri-apps/plugin-store')
     const store = await load('settings.json', { autoSave: true, defaults: {} })
-    await store.set(API_SETTINGS_KEY, settings)
+    await store.set(API_SETTINGS_KEY, plain)
     await store.save()
   } catch (e) {
     console.error('Failed to save API settings:', e)
@@ -810,6 +1008,7 @@ export interface ClaudeCodeStatus {
   installed: boolean
   version: string | null
   path: string | null
+  install_command: string | null
 }
 
 /**
@@ -1041,6 +1240,59 @@ export async function browserScreenshot(tabId: number): Promise<{ data: string;
   return result as { data: string; format: string }
 }
 
+export interface BrowserTool {
+  name: string
+  description: string
+  inputSchema: Record<string, unknown>
+}
+
+/**
+ * List the browser tools with their input schemas
+ */
+export async function browserListTools(): Promise<BrowserTool[]> {
+  return invoke<BrowserTool[]>('browser_list_tools')
+}
+
+/**
+ * Invoke a browser tool by name
+ */
+export async function browserCallTool(name: string, args: Record<string, unknown>): Promise<unknown> {
+  return invoke<unknown>('browser_call_tool', { name, args })
+}
+
+/**
+ * Navigate back in a tab's history
+ */
+export async function browserBack(tabId: number): Promise<unknown> {
+  return invoke<unknown>('browser_back', { tabId })
+}
+
+/**
+ * Navigate forward in a tab's history
+ */
+export async function browserForward(tabId: number): Promise<unknown> {
+  return invoke<unknown>('browser_forward', { tabId })
+}
+
+/**
+ * Reload a tab, optionally bypassing the cache
+ */
+export async function browserReload(tabId: number, ignoreCache?: boolean): Promise<unknown> {
+  return invoke<unknown>('browser_reload', { tabId, ignoreCache })
+}
+
+/**
+ * Wait until an element matching the selector exists
+ */
+export async function browserWaitForSelector(
+  tabId: number,
+  selector: string,
+  timeoutMs?: number
+): Promise<{ found: boolean; selector: string }> {
+  const result = await invoke<unknown>('browser_wait_for_selector', { tabId, selector, timeoutMs })
+  return result as { found: boolean; selector: string }
+}
+
 // ============ RSS Feed API ============
 
 export interface RSSFetchResult {
@@ -1094,6 +1346,9 @@ export interface StoredFeed {
   unread_count: number
   created_at: string
   updated_at: string
+  refresh_interval_minutes?: number | null
+  /** Failed fetches since the last success; refreshes back off while > 0 */
+  consecutive_failures?: number
 }
 
 export interface StoredCategory {
@@ -1132,6 +1387,69 @@ export async function rssFetch(
   return invoke<RSSFetchResult>('rss_fetch', { url, etag, lastModified })
 }
 
+/**
+ * Refresh a stored feed using its cached ETag / Last-Modified
+ * Returns the number of new articles (0 when not modified)
+ */
+export async function rssRefreshFeed(feedId: string): Promise<number> {
+  return invoke<number>('rss_refresh_feed', { feedId })
+}
+
+export interface RSSRefreshAllSummary {
+  new_articles: Record<string, number>
+  errors: Record<string, string>
+}
+
+/**
+ * Refresh every non-paused feed, a few at a time
+ * Returns new-article counts and errors keyed by feed id
+ */
+export async function rssRefreshAll(concurrency?: number): Promise<RSSRefreshAllSummary> {
+  return invoke<RSSRefreshAllSummary>('rss_refresh_all', { concurrency })
+}
+
+/** Convert a ChatApiConfig to the snake_case shape the backend expects */
+function toBackendApiConfig(config: ChatApiConfig) {
+  return {
+    provider: config.provider,
+    api_key: config.apiKey,
+    base_url: config.baseUrl,
+    model: config.model,
+    region: config.region,
+    aws_profile: config.awsProfile,
+    deployment: config.deployment,
+    api_version: config.apiVersion,
+  }
+}
+
+/** Provider and model selection for classification; the backend loads the API key itself */
+function toClassifierModel(config: Omit<ChatApiConfig, 'apiKey'>) {
+  return {
+    provider: config.provider,
+    model: config.model,
+    base_url: config.baseUrl,
+    region: config.region,
+    aws_profile: config.awsProfile,
+    deployment: config.deployment,
+    api_version: config.apiVersion,
+  }
+}
+
+/**
+ * Classify an article into topics with the configured LLM and store them
+ */
+export async function rssClassifyArticle(articleId: string, config: Omit<ChatApiConfig, 'apiKey'>): Promise<string[]> {
+  return invoke<string[]>('rss_classify_article', { articleId, model: toClassifierModel(config) })
+}
+
+/**
+ * Classify recent articles that have no topics yet
+ * Returns the number of articles classified
+ */
+export async function rssClassifyRecent(config: Omit<ChatApiConfig, 'apiKey'>, limit?: number): Promise<number> {
+  return invoke<number>('rss_classify_recent', { model: toClassifierModel(config), limit })
+}
+
 /**
  * Fetch and parse RSS feed
  */
@@ -1167,6 +1485,13 @@ export async function rssUpdateFeed(feed: StoredFeed): Promise<void> {
   return invoke<void>('rss_update_feed', { feed })
 }
 
+/**
+ * Clear a feed's failure backoff and error status
+ */
+export async function rssResetFeedHealth(id: string): Promise<void> {
+  return invoke<void>('rss_reset_feed_health', { id })
+}
+
 /**
  * Delete an RSS feed
  */
@@ -1209,6 +1534,18 @@ export async function rssGetArticles(feedId: string, limit: number = 100): Promi
   return invoke<StoredArticle[]>('rss_get_articles', { feedId, limit })
 }
 
+/**
+ * Page through a feed's articles, newest first within each page.
+ * beforeId (or no cursor) walks toward older articles; afterId alone toward newer ones.
+ */
+export async function rssGetArticlesPage(
+  feedId: string,
+  limit: number,
+  cursor: { beforeId?: string; afterId?: string } = {}
+): Promise<Page<StoredArticle>> {
+  return invoke<Page<StoredArticle>>('rss_get_articles_page', { feedId, limit, ...cursor })
+}
+
 /**
  * Get recent articles across all feeds
  */
@@ -1216,11 +1553,20 @@ export async function rssGetRecentArticles(hours: number = 24, limit: number = 5
   return invoke<StoredArticle[]>('rss_get_recent_articles', { hours, limit })
 }
 
+export interface ArticleSearchResult extends StoredArticle {
+  score: number
+  snippet: string  // matched terms wrapped in <mark>
+}
+
 /**
  * Search articles using full-text search
  */
-export async function rssSearchArticles(query: string, limit: number = 50): Promise<StoredArticle[]> {
-  return invoke<StoredArticle[]>('rss_search_articles', { query, limit })
+export async function rssSearchArticles(
+  query: string,
+  limit: number = 50,
+  order: 'relevance' | 'recent' = 'relevance'
+): Promise<ArticleSearchResult[]> {
+  return invoke<ArticleSearchResult[]>('rss_search_articles', { query, limit, order })
 }
 
 /**
@@ -1230,6 +1576,22 @@ export async function rssMarkArticleRead(id: string, isRead: boolean): Promise<v
   return invoke<void>('rss_mark_article_read', { id, isRead })
 }
 
+/**
+ * Mark all articles in a feed as read
+ * Returns the number of articles that were unread
+ */
+export async function rssMarkFeedRead(feedId: string): Promise<number> {
+  return invoke<number>('rss_mark_feed_read', { feedId })
+}
+
+/**
+ * Mark all articles in every feed as read
+ * Returns the number of articles that were unread
+ */
+export async function rssMarkAllRead(): Promise<number> {
+  return invoke<number>('rss_mark_all_read')
+}
+
 /**
  * Toggle article starred status
  */
@@ -1250,3 +1612,36 @@ export async function rssGetStarredArticles(limit: number = 100): Promise<Stored
 export async function rssCleanupOldArticles(days: number = 30): Promise<number> {
   return invoke<number>('rss_cleanup_old_articles', { days })
 }
+
+export interface DiscoveredFeed {
+  url: string
+  title: string | null
+  feed_type: string
+}
+
+/**
+ * Find feeds advertised by a site URL (returns the URL itself if it is already a feed)
+ */
+export async function rssDiscoverFeeds(siteUrl: string): Promise<DiscoveredFeed[]> {
+  return invoke<DiscoveredFeed[]>('rss_discover_feeds', { siteUrl })
+}
+
+export interface OpmlImportResult {
+  feeds_added: number
+  feeds_skipped: number
+  categories_added: number
+}
+
+/**
+ * Import feeds and categories from an OPML document
+ */
+export async function rssImportOpml(xml: string): Promise<OpmlImportResult> {
+  return invoke<OpmlImportResult>('rss_import_opml', { xml })
+}
+
+/**
+ * Export all feeds as an OPML document
+ */
+export async function rssExportOpml(): Promise<string> {
+  return invoke<string>('rss_export_opml')
+}
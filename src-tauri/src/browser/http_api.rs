@@ -4,9 +4,11 @@
 //! This enables agentic browser control where Claude can continuously interact
 //! with web pages like Playwright or browser-use.
 
+use std::collections::HashMap;
 use std::convert::Infallible;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
@@ -15,7 +17,7 @@ use hyper_util::rt::TokioIo;
 use http_body_util::{Full, BodyExt};
 use bytes::Bytes;
 use tokio::net::TcpListener;
-use tokio::sync::RwLock;
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
 use serde::{Deserialize, Serialize};
 
 use super::server::BrowserRelayServer;
@@ -24,21 +26,126 @@ use super::types::*;
 const HTTP_PORT: u16 = 18800;
 const HTTP_HOST: &str = "127.0.0.1";
 
+// ============ Rate Limiting ============
+
+/// Throttling limits for the HTTP API
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    /// Tokens added to each endpoint's bucket per second
+    pub requests_per_second: f64,
+    /// Bucket capacity per endpoint (max burst)
+    pub burst: u32,
+    /// Max requests being handled at once across all endpoints
+    pub max_concurrent: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 10.0,
+            burst: 20,
+            max_concurrent: 4,
+        }
+    }
+}
+
+/// Paths served by `handle_request`; each gets its own rate limit bucket
+const ROUTES: &[&str] = &[
+    "/status", "/tabs", "/open", "/close", "/back", "/forward", "/reload", "/attach", "/detach",
+    "/snapshot", "/click", "/type", "/scroll", "/screenshot", "/wait_for_selector", "/evaluate",
+];
+
+/// Bucket shared by every path that isn't a route, so unknown paths can't grow the map
+const UNMATCHED_BUCKET: &str = "unmatched";
+
+/// Rate limit bucket for a request path
+fn rate_limit_bucket(path: &str) -> &'static str {
+    ROUTES.iter().copied().find(|route| *route == path).unwrap_or(UNMATCHED_BUCKET)
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Per-endpoint token buckets plus a global concurrency cap
+struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<&'static str, TokenBucket>>,
+    in_flight: Arc<Semaphore>,
+}
+
+impl RateLimiter {
+    fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            in_flight: Arc::new(Semaphore::new(config.max_concurrent.max(1))),
+        }
+    }
+
+    /// Take a token for the endpoint and a concurrency slot
+    /// The returned permit must be held while the request is handled
+    fn acquire(&self, endpoint: &'static str) -> Result<OwnedSemaphorePermit, String> {
+        let permit = self
+            .in_flight
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| "Too many concurrent requests".to_string())?;
+
+        let mut buckets = self.buckets.lock().map_err(|e| e.to_string())?;
+        let burst = self.config.burst as f64;
+        let now = Instant::now();
+        let bucket = buckets.entry(endpoint).or_insert(TokenBucket {
+            tokens: burst,
+            last_refill: now,
+        });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.config.requests_per_second).min(burst);
+        bucket.last_refill = now;
+
+        if bucket.tokens < 1.0 {
+            return Err(format!("Rate limit exceeded for {}", endpoint));
+        }
+        bucket.tokens -= 1.0;
+        Ok(permit)
+    }
+}
+
 /// HTTP API Server for browser control
 pub struct BrowserHttpApi {
     running: Arc<RwLock<bool>>,
+    limiter: Arc<RateLimiter>,
 }
 
 impl BrowserHttpApi {
     pub fn new() -> Self {
+        Self::with_limits(RateLimitConfig::default())
+    }
+
+    /// Create the API with custom throttling limits
+    pub fn with_limits(limits: RateLimitConfig) -> Self {
         Self {
             running: Arc::new(RwLock::new(false)),
+            limiter: Arc::new(RateLimiter::new(limits)),
         }
     }
 
     /// Start the HTTP API server
     pub async fn start(&self, relay: Arc<BrowserRelayServer>) -> Result<(), String> {
-        let addr: SocketAddr = format!("{}:{}", HTTP_HOST, HTTP_PORT)
+        let addr = format!("{}:{}", HTTP_HOST, HTTP_PORT);
+        self.start_on(&addr, relay).await.map(|_| ())
+    }
+
+    /// Start the HTTP API server on a specific address
+    /// Returns the bound address, or None if already running
+    pub async fn start_on(
+        &self,
+        addr: &str,
+        relay: Arc<BrowserRelayServer>,
+    ) -> Result<Option<SocketAddr>, String> {
+        let addr: SocketAddr = addr
             .parse()
             .map_err(|e| format!("Invalid address: {}", e))?;
 
@@ -46,13 +153,16 @@ impl BrowserHttpApi {
         {
             let running = self.running.read().await;
             if *running {
-                return Ok(());
+                return Ok(None);
             }
         }
 
         let listener = TcpListener::bind(addr)
             .await
             .map_err(|e| format!("Failed to bind HTTP server to {}: {}", addr, e))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to read local address: {}", e))?;
 
         log::info!("Browser HTTP API listening on http://{}", addr);
 
@@ -63,6 +173,7 @@ impl BrowserHttpApi {
         }
 
         let running = self.running.clone();
+        let limiter = self.limiter.clone();
 
         tokio::spawn(async move {
             loop {
@@ -81,11 +192,12 @@ impl BrowserHttpApi {
                 ).await {
                     Ok(Ok((stream, _addr))) => {
                         let relay = relay.clone();
+                        let limiter = limiter.clone();
                         let io = TokioIo::new(stream);
 
                         tokio::spawn(async move {
                             let service = service_fn(move |req| {
-                                handle_request(req, relay.clone())
+                                handle_request(req, relay.clone(), limiter.clone())
                             });
 
                             if let Err(e) = http1::Builder::new()
@@ -108,7 +220,7 @@ impl BrowserHttpApi {
             log::info!("Browser HTTP API stopped");
         });
 
-        Ok(())
+        Ok(Some(addr))
     }
 
     /// Stop the HTTP API server
@@ -164,6 +276,14 @@ struct OpenRequest {
     url: String,
 }
 
+#[derive(Deserialize)]
+struct ReloadRequest {
+    #[serde(rename = "tabId")]
+    tab_id: u32,
+    #[serde(rename = "ignoreCache", default)]
+    ignore_cache: bool,
+}
+
 #[derive(Deserialize)]
 struct ClickRequest {
     #[serde(rename = "tabId")]
@@ -186,6 +306,19 @@ struct ScrollRequest {
     direction: String,
 }
 
+#[derive(Deserialize)]
+struct WaitForSelectorRequest {
+    #[serde(rename = "tabId")]
+    tab_id: u32,
+    selector: String,
+    #[serde(rename = "timeoutMs", default = "default_wait_timeout_ms")]
+    timeout_ms: u64,
+}
+
+fn default_wait_timeout_ms() -> u64 {
+    5000
+}
+
 #[derive(Deserialize)]
 struct EvaluateRequest {
     #[serde(rename = "tabId")]
@@ -197,6 +330,7 @@ struct EvaluateRequest {
 async fn handle_request(
     req: Request<Incoming>,
     relay: Arc<BrowserRelayServer>,
+    limiter: Arc<RateLimiter>,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
     // Add CORS headers
     let cors_headers = |mut response: Response<Full<Bytes>>| {
@@ -224,6 +358,16 @@ async fn handle_request(
         return Ok(cors_headers(Response::new(Full::new(Bytes::new()))));
     }
 
+    // Held until the response is built
+    let _permit = match limiter.acquire(rate_limit_bucket(req.uri().path())) {
+        Ok(permit) => permit,
+        Err(e) => {
+            let mut resp = json_response(ApiResponse::<()>::error(e));
+            *resp.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+            return Ok(cors_headers(resp));
+        }
+    };
+
     let path = req.uri().path();
     let method = req.method().clone();
 
@@ -268,6 +412,48 @@ async fn handle_request(
             }
         }
 
+        // POST /back - Navigate back
+        (Method::POST, "/back") => {
+            match parse_body::<TabRequest>(req).await {
+                Ok(body) => {
+                    match relay.send_command(BrowserRequest::Back { tab_id: body.tab_id }).await {
+                        Ok(data) => json_response(ApiResponse::success(data)),
+                        Err(e) => json_response(ApiResponse::<()>::error(e)),
+                    }
+                }
+                Err(e) => json_response(ApiResponse::<()>::error(e)),
+            }
+        }
+
+        // POST /forward - Navigate forward
+        (Method::POST, "/forward") => {
+            match parse_body::<TabRequest>(req).await {
+                Ok(body) => {
+                    match relay.send_command(BrowserRequest::Forward { tab_id: body.tab_id }).await {
+                        Ok(data) => json_response(ApiResponse::success(data)),
+                        Err(e) => json_response(ApiResponse::<()>::error(e)),
+                    }
+                }
+                Err(e) => json_response(ApiResponse::<()>::error(e)),
+            }
+        }
+
+        // POST /reload - Reload tab
+        (Method::POST, "/reload") => {
+            match parse_body::<ReloadRequest>(req).await {
+                Ok(body) => {
+                    match relay.send_command(BrowserRequest::Reload {
+                        tab_id: body.tab_id,
+                        ignore_cache: body.ignore_cache,
+                    }).await {
+                        Ok(data) => json_response(ApiResponse::success(data)),
+                        Err(e) => json_response(ApiResponse::<()>::error(e)),
+                    }
+                }
+                Err(e) => json_response(ApiResponse::<()>::error(e)),
+            }
+        }
+
         // POST /attach - Attach debugger to tab
         (Method::POST, "/attach") => {
             match parse_body::<TabRequest>(req).await {
@@ -378,6 +564,23 @@ async fn handle_request(
             }
         }
 
+        // POST /wait_for_selector - Wait until an element exists
+        (Method::POST, "/wait_for_selector") => {
+            match parse_body::<WaitForSelectorRequest>(req).await {
+                Ok(body) => {
+                    match relay.send_command(BrowserRequest::WaitForSelector {
+                        tab_id: body.tab_id,
+                        selector: body.selector,
+                        timeout_ms: body.timeout_ms,
+                    }).await {
+                        Ok(data) => json_response(ApiResponse::success(data)),
+                        Err(e) => json_response(ApiResponse::<()>::error(e)),
+                    }
+                }
+                Err(e) => json_response(ApiResponse::<()>::error(e)),
+            }
+        }
+
         // POST /evaluate - Execute JavaScript
         (Method::POST, "/evaluate") => {
             match parse_body::<EvaluateRequest>(req).await {
@@ -429,3 +632,70 @@ static BROWSER_HTTP_API: std::sync::OnceLock<Arc<BrowserHttpApi>> = std::sync::O
 pub fn get_browser_http_api() -> Arc<BrowserHttpApi> {
     BROWSER_HTTP_API.get_or_init(|| Arc::new(BrowserHttpApi::new())).clone()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limit_returns_429() {
+        let api = BrowserHttpApi::with_limits(RateLimitConfig {
+            requests_per_second: 0.001,
+            burst: 3,
+            max_concurrent: 4,
+        });
+        let relay = Arc::new(BrowserRelayServer::new());
+        let addr = api.start_on("127.0.0.1:0", relay).await.unwrap().unwrap();
+
+        let client = reqwest::Client::new();
+        let mut statuses = Vec::new();
+        for _ in 0..6 {
+            let resp = client
+                .get(format!("http://{}/status", addr))
+                .send()
+                .await
+                .unwrap();
+            statuses.push(resp.status().as_u16());
+        }
+        assert_eq!(statuses, vec![200, 200, 200, 429, 429, 429]);
+
+        // Buckets are per endpoint
+        let resp = client
+            .get(format!("http://{}/missing", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status().as_u16(), 404);
+
+        api.stop().await;
+    }
+
+    #[test]
+    fn test_concurrency_cap() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_second: 100.0,
+            burst: 100,
+            max_concurrent: 2,
+        });
+        let first = limiter.acquire("/tabs").unwrap();
+        let _second = limiter.acquire("/tabs").unwrap();
+        assert!(limiter.acquire("/tabs").is_err());
+        drop(first);
+        assert!(limiter.acquire("/tabs").is_ok());
+    }
+
+    #[test]
+    fn test_unknown_paths_share_one_bucket() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_second: 0.001,
+            burst: 2,
+            max_concurrent: 4,
+        });
+        for i in 0..2 {
+            assert!(limiter.acquire(rate_limit_bucket(&format!("/random-{}", i))).is_ok());
+        }
+        assert!(limiter.acquire(rate_limit_bucket("/random-2")).is_err());
+        assert!(limiter.acquire(rate_limit_bucket("/tabs")).is_ok());
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,239 @@
+//! Browser control surface as MCP-style tools
+//!
+//! Describes each browser action as a tool with a typed JSON input schema and
+//! dispatches tool calls to the relay, so the agent can drive the browser
+//! without shelling out to the HTTP API. The tools are registered with the agent
+//! as an in-process SDK MCP server named `browser`.
+
+use claude_agent_sdk_rs::{create_sdk_mcp_server, tool, McpSdkServerConfig, McpToolResultContent, ToolResult};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::server::{get_browser_relay, BrowserRelayServer};
+use super::types::BrowserRequest;
+
+/// Default timeout for `wait_for_selector` when the caller omits one
+const DEFAULT_WAIT_TIMEOUT_MS: u64 = 5000;
+
+/// Server name the tools are registered under; the agent sees them as `mcp__browser__<tool>`
+pub const BROWSER_MCP_SERVER_NAME: &str = "browser";
+
+/// A browser tool definition
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowserTool {
+    pub name: &'static str,
+    pub description: &'static str,
+    #[serde(rename = "inputSchema")]
+    pub input_schema: Value,
+    /// Relay action the tool maps to
+    #[serde(skip)]
+    action: &'static str,
+}
+
+fn tab_schema(extra: Value, required: &[&str]) -> Value {
+    let mut properties = json!({
+        "tabId": { "type": "integer", "description": "Target tab ID" }
+    });
+    if let (Some(props), Some(extra)) = (properties.as_object_mut(), extra.as_object()) {
+        props.extend(extra.clone());
+    }
+    let mut required_fields = vec!["tabId"];
+    required_fields.extend_from_slice(required);
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required_fields,
+    })
+}
+
+/// All browser tools exposed to the agent
+pub fn browser_tools() -> Vec<BrowserTool> {
+    let tool = |name, action, description, input_schema| BrowserTool {
+        name,
+        description,
+        input_schema,
+        action,
+    };
+
+    vec![
+        tool("list_tabs", "list_tabs", "List all open browser tabs", json!({ "type": "object", "properties": {} })),
+        tool(
+            "open_tab",
+            "open",
+            "Open a URL in a new tab",
+            json!({
+                "type": "object",
+                "properties": { "url": { "type": "string", "description": "URL to open" } },
+                "required": ["url"],
+            }),
+        ),
+        tool("close_tab", "close", "Close a tab", tab_schema(json!({}), &[])),
+        tool("back", "back", "Navigate back in tab history", tab_schema(json!({}), &[])),
+        tool("forward", "forward", "Navigate forward in tab history", tab_schema(json!({}), &[])),
+        tool(
+            "reload",
+            "reload",
+            "Reload a tab",
+            tab_schema(json!({ "ignoreCache": { "type": "boolean" } }), &[]),
+        ),
+        tool("attach", "attach", "Attach the debugger to a tab", tab_schema(json!({}), &[])),
+        tool("detach", "detach", "Detach the debugger from a tab", tab_schema(json!({}), &[])),
+        tool("snapshot", "snapshot", "Get the page accessibility snapshot", tab_schema(json!({}), &[])),
+        tool("screenshot", "screenshot", "Capture a screenshot of a tab", tab_schema(json!({}), &[])),
+        tool(
+            "evaluate",
+            "evaluate",
+            "Evaluate a JavaScript expression in a tab",
+            tab_schema(json!({ "expression": { "type": "string" } }), &["expression"]),
+        ),
+        tool(
+            "click",
+            "click",
+            "Click the element matching a CSS selector",
+            tab_schema(json!({ "selector": { "type": "string" } }), &["selector"]),
+        ),
+        tool(
+            "type",
+            "type",
+            "Type text into the element matching a CSS selector",
+            tab_schema(
+                json!({ "selector": { "type": "string" }, "text": { "type": "string" } }),
+                &["selector", "text"],
+            ),
+        ),
+        tool(
+            "scroll",
+            "scroll",
+            "Scroll the page",
+            tab_schema(
+                json!({ "direction": { "type": "string", "enum": ["up", "down", "left", "right"] } }),
+                &["direction"],
+            ),
+        ),
+        tool(
+            "wait_for_selector",
+            "wait_for_selector",
+            "Wait until an element matching a CSS selector exists",
+            tab_schema(
+                json!({ "selector": { "type": "string" }, "timeoutMs": { "type": "integer" } }),
+                &["selector"],
+            ),
+        ),
+    ]
+}
+
+/// Build the relay request for a tool call
+pub fn tool_request(name: &str, args: &Value) -> Result<BrowserRequest, String> {
+    let tool = browser_tools()
+        .into_iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| format!("Unknown browser tool: {}", name))?;
+
+    let mut payload = match args {
+        Value::Object(map) => map.clone(),
+        Value::Null => serde_json::Map::new(),
+        _ => return Err(format!("Arguments for {} must be an object", name)),
+    };
+    payload.insert("action".to_string(), json!(tool.action));
+    if tool.action == "wait_for_selector" {
+        payload
+            .entry("timeoutMs")
+            .or_insert_with(|| json!(DEFAULT_WAIT_TIMEOUT_MS));
+    }
+
+    serde_json::from_value(Value::Object(payload))
+        .map_err(|e| format!("Invalid arguments for {}: {}", name, e))
+}
+
+/// Invoke a browser tool against the relay
+pub async fn call_browser_tool(
+    relay: &BrowserRelayServer,
+    name: &str,
+    args: &Value,
+) -> Result<Value, String> {
+    let request = tool_request(name, args)?;
+    relay.send_command(request).await
+}
+
+/// Convert a relay reply into an MCP tool result
+fn to_tool_result(result: Result<Value, String>) -> ToolResult {
+    let (text, is_error) = match result {
+        Ok(value) => (value.to_string(), false),
+        Err(e) => (e, true),
+    };
+    ToolResult {
+        content: vec![McpToolResultContent::Text { text }],
+        is_error,
+    }
+}
+
+/// In-process MCP server exposing every browser tool through the global relay
+pub fn browser_mcp_server() -> McpSdkServerConfig {
+    let tools = browser_tools()
+        .into_iter()
+        .map(|t| {
+            let name = t.name;
+            tool!(name, t.description, t.input_schema, move |args: Value| async move {
+                let relay = get_browser_relay();
+                Ok::<_, anyhow::Error>(to_tool_result(call_browser_tool(&relay, name, &args).await))
+            })
+        })
+        .collect();
+    create_sdk_mcp_server(BROWSER_MCP_SERVER_NAME, env!("CARGO_PKG_VERSION"), tools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::server::tests::connected_relay;
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    #[test]
+    fn test_list_tools() {
+        let tools = browser_tools();
+        let names: Vec<_> = tools.iter().map(|t| t.name).collect();
+        for expected in ["open_tab", "click", "type", "snapshot", "screenshot"] {
+            assert!(names.contains(&expected), "missing tool {}", expected);
+        }
+        let click = tools.iter().find(|t| t.name == "click").unwrap();
+        assert_eq!(click.input_schema["required"], json!(["tabId", "selector"]));
+
+        assert!(tool_request("missing", &json!({})).is_err());
+        assert!(tool_request("click", &json!({ "tabId": 1 })).is_err());
+        assert!(matches!(
+            tool_request("wait_for_selector", &json!({ "tabId": 1, "selector": "#a" })),
+            Ok(BrowserRequest::WaitForSelector { timeout_ms: DEFAULT_WAIT_TIMEOUT_MS, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_call_open_tab() {
+        let (server, mut ws) = connected_relay().await;
+
+        let extension = tokio::spawn(async move {
+            loop {
+                if let Some(Ok(Message::Text(text))) = ws.next().await {
+                    let request: Value = serde_json::from_str(&text).unwrap();
+                    let reply = json!({
+                        "type": "response",
+                        "requestId": request["requestId"],
+                        "result": { "tabId": 42 },
+                    });
+                    ws.send(Message::Text(reply.to_string())).await.unwrap();
+                    return request;
+                }
+            }
+        });
+
+        let result = call_browser_tool(&server, "open_tab", &json!({ "url": "https://example.com" }))
+            .await
+            .unwrap();
+        assert_eq!(result["tabId"], 42);
+
+        let sent = extension.await.unwrap();
+        assert_eq!(sent["action"], "open");
+        assert_eq!(sent["url"], "https://example.com");
+        server.stop().await;
+    }
+}
@@ -7,9 +7,11 @@
 //! to interact with the browser continuously (like Playwright or browser-use).
 
 pub mod http_api;
+pub mod mcp_tools;
 pub mod server;
 pub mod types;
 
-pub use http_api::{get_browser_http_api, BrowserHttpApi};
+pub use http_api::{get_browser_http_api, BrowserHttpApi, RateLimitConfig};
+pub use mcp_tools::{browser_mcp_server, browser_tools, call_browser_tool, BrowserTool, BROWSER_MCP_SERVER_NAME};
 pub use server::{get_browser_relay, BrowserRelayServer};
 pub use types::*;
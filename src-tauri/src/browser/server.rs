@@ -14,7 +14,9 @@ fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
     }
     &s[..end]
 }
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, RwLock};
 use tokio_tungstenite::{accept_async, tungstenite::Message};
@@ -26,14 +28,22 @@ use super::types::*;
 const WS_PORT: u16 = 18799;
 const WS_HOST: &str = "127.0.0.1";
 
+/// How long a command waits for the extension's reply by default
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Delay between selector checks in `WaitForSelector`
+const SELECTOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+type PendingRequests = Arc<RwLock<HashMap<String, tokio::sync::oneshot::Sender<BrowserResponse>>>>;
+
 /// Browser relay server state
 pub struct BrowserRelayServer {
     /// Connected extension state
     connection: Arc<RwLock<Option<ExtensionConnection>>>,
     /// Channel to send commands to the connected WebSocket
     outgoing_tx: Arc<RwLock<Option<mpsc::Sender<String>>>>,
-    /// Pending requests waiting for response
-    pending_requests: Arc<RwLock<HashMap<String, tokio::sync::oneshot::Sender<BrowserResponse>>>>,
+    /// Pending requests waiting for response, keyed by request id
+    pending_requests: PendingRequests,
     /// Event broadcast channel
     event_tx: broadcast::Sender<ExtensionEvent>,
     /// Server running flag
@@ -62,18 +72,23 @@ impl BrowserRelayServer {
     /// Start the WebSocket server
     pub async fn start(&self) -> Result<(), String> {
         let addr = format!("{}:{}", WS_HOST, WS_PORT);
+        self.start_on(&addr).await.map(|_| ())
+    }
 
+    /// Start the WebSocket server on a specific address, returning the bound address
+    pub async fn start_on(&self, addr: &str) -> Result<Option<SocketAddr>, String> {
         // Check if already running
         {
             let running = self.running.read().await;
             if *running {
-                return Ok(());
+                return Ok(None);
             }
         }
 
-        let listener = TcpListener::bind(&addr)
+        let listener = TcpListener::bind(addr)
             .await
             .map_err(|e| format!("Failed to bind to {}: {}", addr, e))?;
+        let local_addr = listener.local_addr().ok();
 
         log::info!("Browser relay server listening on ws://{}", addr);
 
@@ -133,7 +148,7 @@ impl BrowserRelayServer {
             log::info!("Browser relay server stopped");
         });
 
-        Ok(())
+        Ok(local_addr)
     }
 
     /// Stop the WebSocket server
@@ -167,6 +182,60 @@ impl BrowserRelayServer {
 
     /// Send a command to the extension and wait for response
     pub async fn send_command(&self, request: BrowserRequest) -> Result<serde_json::Value, String> {
+        self.send_command_with_timeout(request, DEFAULT_COMMAND_TIMEOUT).await
+    }
+
+    /// Send a command and wait up to `timeout` for the reply with the same request id
+    /// Any number of commands may be in flight at once
+    pub async fn send_command_with_timeout(
+        &self,
+        request: BrowserRequest,
+        timeout: Duration,
+    ) -> Result<serde_json::Value, String> {
+        match request {
+            BrowserRequest::WaitForSelector { tab_id, selector, timeout_ms } => {
+                self.wait_for_selector(tab_id, &selector, Duration::from_millis(timeout_ms)).await
+            }
+            request => self.dispatch(request, timeout).await,
+        }
+    }
+
+    /// Poll until `selector` matches an element or `timeout` elapses
+    async fn wait_for_selector(
+        &self,
+        tab_id: u32,
+        selector: &str,
+        timeout: Duration,
+    ) -> Result<serde_json::Value, String> {
+        let selector_literal = serde_json::to_string(selector)
+            .map_err(|e| format!("Invalid selector: {}", e))?;
+        let expression = format!("!!document.querySelector({})", selector_literal);
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            let found = self
+                .dispatch(
+                    BrowserRequest::Evaluate { tab_id, expression: expression.clone() },
+                    remaining.clamp(SELECTOR_POLL_INTERVAL, DEFAULT_COMMAND_TIMEOUT),
+                )
+                .await?
+                .as_bool()
+                .unwrap_or(false);
+
+            if found || tokio::time::Instant::now() + SELECTOR_POLL_INTERVAL > deadline {
+                return Ok(serde_json::json!({ "found": found, "selector": selector }));
+            }
+            tokio::time::sleep(SELECTOR_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Send a single command to the extension and await its correlated reply
+    async fn dispatch(
+        &self,
+        request: BrowserRequest,
+        timeout: Duration,
+    ) -> Result<serde_json::Value, String> {
         // Check connection
         let connection = self.connection.read().await;
         if connection.is_none() {
@@ -199,16 +268,20 @@ impl BrowserRelayServer {
         }
 
         // Serialize and send command
-        let json_msg = serde_json::to_string(&message)
-            .map_err(|e| format!("Failed to serialize message: {}", e))?;
-
-        outgoing_tx.send(json_msg).await
-            .map_err(|e| format!("Failed to send command: {}", e))?;
+        let sent = match serde_json::to_string(&message) {
+            Ok(json_msg) => outgoing_tx.send(json_msg).await
+                .map_err(|e| format!("Failed to send command: {}", e)),
+            Err(e) => Err(format!("Failed to serialize message: {}", e)),
+        };
+        if let Err(e) = sent {
+            self.pending_requests.write().await.remove(&request_id);
+            return Err(e);
+        }
 
         log::debug!("Sent command with request_id: {}", request_id);
 
         // Wait for response with timeout
-        match tokio::time::timeout(std::time::Duration::from_secs(30), rx).await {
+        match tokio::time::timeout(timeout, rx).await {
             Ok(Ok(response)) => {
                 log::debug!("Received response for request_id: {}", request_id);
                 if let Some(error) = response.error {
@@ -226,7 +299,7 @@ impl BrowserRelayServer {
                 let mut pending = self.pending_requests.write().await;
                 pending.remove(&request_id);
                 log::error!("Request timed out for request_id: {}", request_id);
-                Err("Request timed out".to_string())
+                Err(format!("Request timed out after {}ms", timeout.as_millis()))
             }
         }
     }
@@ -242,7 +315,7 @@ async fn handle_connection(
     stream: TcpStream,
     connection: Arc<RwLock<Option<ExtensionConnection>>>,
     outgoing_tx_holder: Arc<RwLock<Option<mpsc::Sender<String>>>>,
-    pending_requests: Arc<RwLock<HashMap<String, tokio::sync::oneshot::Sender<BrowserResponse>>>>,
+    pending_requests: PendingRequests,
     event_tx: broadcast::Sender<ExtensionEvent>,
 ) -> Result<(), String> {
     let ws_stream = accept_async(stream)
@@ -413,3 +486,160 @@ static BROWSER_RELAY: std::sync::OnceLock<Arc<BrowserRelayServer>> = std::sync::
 pub fn get_browser_relay() -> Arc<BrowserRelayServer> {
     BROWSER_RELAY.get_or_init(|| Arc::new(BrowserRelayServer::new())).clone()
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio_tungstenite::connect_async;
+
+    /// Start a relay on an ephemeral port and connect a mock extension to it
+    pub(crate) async fn connected_relay() -> (
+        Arc<BrowserRelayServer>,
+        tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>,
+    ) {
+        let server = Arc::new(BrowserRelayServer::new());
+        let addr = server.start_on("127.0.0.1:0").await.unwrap().unwrap();
+        let (ws, _) = connect_async(format!("ws://{}", addr)).await.unwrap();
+
+        for _ in 0..100 {
+            if server.is_connected().await && server.outgoing_tx.read().await.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        (server, ws)
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_commands_route_by_request_id() {
+        let (server, mut ws) = connected_relay().await;
+
+        // Mock extension: collect both commands, then answer in reverse order
+        let extension = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            while requests.len() < 2 {
+                if let Some(Ok(Message::Text(text))) = ws.next().await {
+                    requests.push(serde_json::from_str::<serde_json::Value>(&text).unwrap());
+                }
+            }
+            for request in requests.iter().rev() {
+                let reply = serde_json::json!({
+                    "type": "response",
+                    "requestId": request["requestId"],
+                    "result": { "echo": request["expression"] },
+                });
+                ws.send(Message::Text(reply.to_string())).await.unwrap();
+            }
+            ws
+        });
+
+        let evaluate = |expression: &str| BrowserRequest::Evaluate {
+            tab_id: 1,
+            expression: expression.to_string(),
+        };
+        let (first, second) = tokio::join!(
+            server.send_command(evaluate("1 + 1")),
+            server.send_command(evaluate("document.title")),
+        );
+
+        assert_eq!(first.unwrap()["echo"], "1 + 1");
+        assert_eq!(second.unwrap()["echo"], "document.title");
+        assert_eq!(server.pending_requests.read().await.len(), 0);
+
+        drop(extension.await.unwrap());
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_selector_polls_until_present() {
+        let (server, mut ws) = connected_relay().await;
+
+        // Mock extension: the selector is missing twice, then present
+        let extension = tokio::spawn(async move {
+            let mut checks = 0;
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert_eq!(request["action"], "evaluate");
+                assert!(request["expression"].as_str().unwrap().contains("\"#submit\""));
+                checks += 1;
+                let reply = serde_json::json!({
+                    "type": "response",
+                    "requestId": request["requestId"],
+                    "result": checks >= 3,
+                });
+                ws.send(Message::Text(reply.to_string())).await.unwrap();
+                if checks >= 3 {
+                    break;
+                }
+            }
+            checks
+        });
+
+        let started = tokio::time::Instant::now();
+        let result = server
+            .send_command(BrowserRequest::WaitForSelector {
+                tab_id: 1,
+                selector: "#submit".to_string(),
+                timeout_ms: 2000,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result["found"], true);
+        assert!(started.elapsed() < Duration::from_millis(2000));
+        assert_eq!(extension.await.unwrap(), 3);
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_navigation_commands_json() {
+        let (server, mut ws) = connected_relay().await;
+
+        let extension = tokio::spawn(async move {
+            let mut seen = Vec::new();
+            while seen.len() < 3 {
+                if let Some(Ok(Message::Text(text))) = ws.next().await {
+                    let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    let reply = serde_json::json!({
+                        "type": "response",
+                        "requestId": request["requestId"],
+                        "result": { "success": true },
+                    });
+                    ws.send(Message::Text(reply.to_string())).await.unwrap();
+                    seen.push(request);
+                }
+            }
+            seen
+        });
+
+        server.send_command(BrowserRequest::Back { tab_id: 7 }).await.unwrap();
+        server.send_command(BrowserRequest::Forward { tab_id: 7 }).await.unwrap();
+        server
+            .send_command(BrowserRequest::Reload { tab_id: 7, ignore_cache: true })
+            .await
+            .unwrap();
+
+        let seen = extension.await.unwrap();
+        assert_eq!(seen[0]["action"], "back");
+        assert_eq!(seen[0]["tabId"], 7);
+        assert_eq!(seen[1]["action"], "forward");
+        assert_eq!(seen[1]["tabId"], 7);
+        assert_eq!(seen[2]["action"], "reload");
+        assert_eq!(seen[2]["tabId"], 7);
+        assert_eq!(seen[2]["ignoreCache"], true);
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_command_timeout_clears_pending() {
+        let (server, _ws) = connected_relay().await;
+
+        let result = server
+            .send_command_with_timeout(BrowserRequest::Ping, Duration::from_millis(50))
+            .await;
+
+        assert!(result.unwrap_err().contains("timed out"));
+        assert_eq!(server.pending_requests.read().await.len(), 0);
+        server.stop().await;
+    }
+}
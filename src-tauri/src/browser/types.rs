@@ -19,6 +19,23 @@ pub enum BrowserRequest {
         #[serde(rename = "tabId")]
         tab_id: u32,
     },
+    /// Navigate back in tab history
+    Back {
+        #[serde(rename = "tabId")]
+        tab_id: u32,
+    },
+    /// Navigate forward in tab history
+    Forward {
+        #[serde(rename = "tabId")]
+        tab_id: u32,
+    },
+    /// Reload a tab, optionally bypassing the cache
+    Reload {
+        #[serde(rename = "tabId")]
+        tab_id: u32,
+        #[serde(rename = "ignoreCache", default)]
+        ignore_cache: bool,
+    },
     /// Attach debugger to tab
     Attach {
         #[serde(rename = "tabId")]
@@ -64,6 +81,15 @@ pub enum BrowserRequest {
         #[serde(rename = "tabId")]
         tab_id: u32,
     },
+    /// Wait until an element matching the selector exists
+    /// Handled by the relay by polling `Evaluate`; never sent to the extension
+    WaitForSelector {
+        #[serde(rename = "tabId")]
+        tab_id: u32,
+        selector: String,
+        #[serde(rename = "timeoutMs")]
+        timeout_ms: u64,
+    },
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -6,15 +6,22 @@ use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine as Base64Engine;
 use aws_sdk_bedrockruntime::types::{
     ContentBlock as BedrockContent, ConversationRole, ImageBlock, ImageFormat,
-    ImageSource as BedrockImageSource, Message as BedrockMessage, SystemContentBlock,
+    ImageSource as BedrockImageSource, Message as BedrockMessage, ReasoningContentBlock, SystemContentBlock,
     Tool as BedrockTool, ToolConfiguration, ToolInputSchema, ToolSpecification,
     ToolResultBlock, ToolResultContentBlock, StopReason as BedrockStopReason,
 };
+use aws_sdk_bedrockruntime::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_bedrockruntime::operation::converse::ConverseError;
 use aws_smithy_types::{Blob, Document};
+use futures::Stream;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::memory_tool::MemoryTool;
+use crate::response_cache::ResponseCache;
 
 /// Content block for multimodal messages
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -106,6 +113,131 @@ pub struct ApiConfig {
     pub region: Option<String>,
     /// AWS profile for bedrock (optional, uses default if not specified)
     pub aws_profile: Option<String>,
+    /// Azure OpenAI deployment name (defaults to the model)
+    #[serde(default)]
+    pub deployment: Option<String>,
+    /// Azure OpenAI `api-version` query parameter
+    #[serde(default)]
+    pub api_version: Option<String>,
+    /// Retry behaviour for transient API errors
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+}
+
+/// Azure OpenAI API version used when none is configured
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+/// Chat completions URL and auth header for an OpenAI-compatible provider
+#[derive(Debug, Clone, PartialEq)]
+struct OpenAIEndpoint {
+    url: String,
+    auth_header: &'static str,
+    auth_value: String,
+}
+
+impl OpenAIEndpoint {
+    /// Azure addresses a deployment and authenticates with `api-key`;
+    /// everything else uses `/v1/chat/completions` with a bearer token
+    fn from_config(config: &ApiConfig) -> Result<Self, String> {
+        let api_key = config
+            .api_key
+            .as_deref()
+            .ok_or("API key is required for OpenAI provider")?;
+
+        if config.provider == "azure" {
+            let base_url = config
+                .base_url
+                .as_deref()
+                .ok_or("Base URL is required for Azure OpenAI (https://<resource>.openai.azure.com)")?;
+            let deployment = config
+                .deployment
+                .as_deref()
+                .or(config.model.as_deref())
+                .ok_or("Deployment is required for Azure OpenAI")?;
+            let api_version = config.api_version.as_deref().unwrap_or(DEFAULT_AZURE_API_VERSION);
+            return Ok(Self {
+                url: format!(
+                    "{}/openai/deployments/{}/chat/completions?api-version={}",
+                    base_url.trim_end_matches('/'),
+                    deployment,
+                    api_version
+                ),
+                auth_header: "api-key",
+                auth_value: api_key.to_string(),
+            });
+        }
+
+        let base_url = config.base_url.as_deref().unwrap_or("https://api.openai.com");
+        Ok(Self {
+            url: format!("{}/v1/chat/completions", base_url),
+            auth_header: "Authorization",
+            auth_value: format!("Bearer {}", api_key),
+        })
+    }
+}
+
+/// Exponential-backoff retry policy for transient API errors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each subsequent one
+    pub base_delay: Duration,
+    /// Upper bound for the computed backoff delay
+    pub max_delay: Duration,
+    /// HTTP status codes that trigger a retry
+    pub retry_on: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            retry_on: vec![429, 500, 502, 503, 529],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether a response with `status` should be retried after `attempt` retries so far
+    pub fn should_retry(&self, status: u16, attempt: u32) -> bool {
+        attempt < self.max_retries && self.retry_on.contains(&status)
+    }
+
+    /// Delay before retry number `attempt` (0-based)
+    /// A server-provided Retry-After wins; otherwise jittered exponential backoff
+    /// Either way the delay never exceeds `max_delay`
+    pub fn delay_for(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(delay) = retry_after {
+            return delay.min(self.max_delay);
+        }
+
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        // Equal jitter: half fixed, half random, so concurrent clients spread out
+        let half = backoff / 2;
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let jitter = half.mul_f64(f64::from(nanos % 1000) / 1000.0);
+        half + jitter
+    }
+}
+
+/// Parse a Retry-After header given in (possibly fractional) seconds
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,8 +247,116 @@ pub struct ChatRequest {
     pub system_prompt: Option<String>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
+    /// Nucleus sampling probability mass, must be within [0, 1]
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Custom sequences that stop generation when produced
+    #[serde(default)]
+    pub stop_sequences: Option<Vec<String>>,
     /// Workspace path for memory tool operations
     pub workspace: Option<String>,
+    /// Memories directory relative to the workspace (default `.flowq/memories`)
+    #[serde(default)]
+    pub memories_dir: Option<String>,
+    /// Send the system prompt as a cacheable block (Anthropic only)
+    #[serde(default)]
+    pub enable_prompt_caching: bool,
+    /// Mark the end of earlier turns as cacheable so the history prefix is reused (Anthropic only)
+    #[serde(default)]
+    pub cache_history: bool,
+    /// Maximum tool-use round trips (default 10, clamped to 50)
+    #[serde(default)]
+    pub max_tool_iterations: Option<u32>,
+    /// Names of the tools to offer; `None` offers every available tool
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    /// Enable extended thinking (Anthropic and Bedrock Claude models)
+    #[serde(default)]
+    pub thinking: Option<ThinkingConfig>,
+    /// Serve an identical earlier response from the client's response cache
+    /// Ignored while the memory tool is available, since its writes must not be skipped
+    #[serde(default)]
+    pub cache: bool,
+}
+
+/// Smallest thinking budget the API accepts
+const MIN_THINKING_BUDGET_TOKENS: u32 = 1024;
+
+/// `max_tokens` sent to Anthropic when the request leaves it unset
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Extended thinking settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThinkingConfig {
+    /// Tokens the model may spend reasoning, counted within `max_tokens`
+    pub budget_tokens: u32,
+}
+
+impl ThinkingConfig {
+    /// `thinking` request field shared by the Anthropic and Bedrock APIs
+    fn to_request_field(&self) -> serde_json::Value {
+        serde_json::json!({ "type": "enabled", "budget_tokens": self.budget_tokens })
+    }
+}
+
+/// Default number of tool-use round trips per request
+const DEFAULT_MAX_TOOL_ITERATIONS: u32 = 10;
+/// Upper bound for `max_tool_iterations` to keep runaway loops in check
+const MAX_TOOL_ITERATIONS_CEILING: u32 = 50;
+/// `ChatResponse::stop_reason` when the tool-use loop ran out of iterations
+pub const STOP_REASON_MAX_ITERATIONS: &str = "max_iterations";
+
+impl ChatRequest {
+    /// Whether a tool may be offered for this request
+    fn tool_enabled(&self, name: &str) -> bool {
+        self.tools
+            .as_ref()
+            .map_or(true, |enabled| enabled.iter().any(|n| n == name))
+    }
+
+    /// Check sampling parameters before any provider is contacted
+    fn validate(&self) -> Result<(), String> {
+        if let Some(top_p) = self.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err(format!("top_p must be between 0 and 1, got {}", top_p));
+            }
+        }
+        if let Some(ref thinking) = self.thinking {
+            if thinking.budget_tokens < MIN_THINKING_BUDGET_TOKENS {
+                return Err(format!(
+                    "thinking budget must be at least {} tokens, got {}",
+                    MIN_THINKING_BUDGET_TOKENS, thinking.budget_tokens
+                ));
+            }
+            let max_tokens = self.effective_max_tokens();
+            if thinking.budget_tokens >= max_tokens {
+                return Err(format!(
+                    "thinking budget ({}) must be less than max_tokens ({})",
+                    thinking.budget_tokens, max_tokens
+                ));
+            }
+            // Extended thinking only supports the default sampling settings
+            if self.temperature.is_some_and(|t| t != 1.0) {
+                return Err("temperature must be 1 when thinking is enabled".to_string());
+            }
+            if self.top_p.is_some() {
+                return Err("top_p cannot be set when thinking is enabled".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// `max_tokens` as sent to the provider, falling back to `DEFAULT_MAX_TOKENS`
+    fn effective_max_tokens(&self) -> u32 {
+        self.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)
+    }
+
+    /// Effective tool-use iteration cap for this request
+    fn tool_iteration_limit(&self) -> u32 {
+        self.max_tool_iterations
+            .unwrap_or(DEFAULT_MAX_TOOL_ITERATIONS)
+            .clamp(1, MAX_TOOL_ITERATIONS_CEILING)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -124,12 +364,118 @@ pub struct ChatResponse {
     pub content: String,
     pub model: String,
     pub usage: Option<TokenUsage>,
+    /// Provider stop reason, or "max_iterations" if the tool-use loop was cut off
+    pub stop_reason: Option<String>,
+    /// Thinking text returned when extended thinking was enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TokenUsage {
     pub input_tokens: u32,
     pub output_tokens: u32,
+    /// Tokens written to the prompt cache (Anthropic prompt caching)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<u32>,
+    /// Tokens served from the prompt cache (Anthropic prompt caching)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<u32>,
+}
+
+/// Incremental chunk yielded by a streaming chat response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamChunk {
+    /// Text delta to append to the assistant message
+    Text { text: String },
+    /// The model started a tool call; the caller decides how to continue the loop
+    ToolUseStart { id: String, name: String },
+    /// A fragment of a tool call's JSON arguments (OpenAI-compatible streams)
+    ToolInputDelta { id: String, partial_json: String },
+    /// End of the response
+    Done {
+        stop_reason: Option<String>,
+        usage: Option<TokenUsage>,
+    },
+}
+
+/// Classified Bedrock failure, so throttles can be retried and hard failures shown as such
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BedrockError {
+    Throttling { message: String },
+    AccessDenied { message: String },
+    ValidationException { message: String },
+    GuardrailIntervened { message: String },
+    Other { message: String },
+}
+
+impl BedrockError {
+    /// Classify a Converse API error by its service error variant
+    pub fn from_converse_error<R>(err: &SdkError<ConverseError, R>) -> Self
+    where
+        R: std::fmt::Debug,
+    {
+        let Some(service_error) = err.as_service_error() else {
+            return BedrockError::Other {
+                message: aws_sdk_bedrockruntime::error::DisplayErrorContext(err).to_string(),
+            };
+        };
+
+        let message = service_error
+            .message()
+            .map(str::to_string)
+            .unwrap_or_else(|| service_error.to_string());
+        if service_error.is_throttling_exception() {
+            BedrockError::Throttling { message }
+        } else if service_error.is_access_denied_exception() {
+            BedrockError::AccessDenied { message }
+        } else if service_error.is_validation_exception() {
+            BedrockError::ValidationException { message }
+        } else {
+            BedrockError::Other { message }
+        }
+    }
+
+    /// Error for a response that stopped because a guardrail blocked it
+    /// `message` is the replacement text Bedrock returned; other stop reasons are not errors
+    pub fn from_stop_reason(stop_reason: &BedrockStopReason, message: String) -> Option<Self> {
+        (*stop_reason == BedrockStopReason::GuardrailIntervened)
+            .then_some(BedrockError::GuardrailIntervened { message })
+    }
+
+    /// Stable identifier surfaced in error strings, e.g. `throttling`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BedrockError::Throttling { .. } => "throttling",
+            BedrockError::AccessDenied { .. } => "access_denied",
+            BedrockError::ValidationException { .. } => "validation_exception",
+            BedrockError::GuardrailIntervened { .. } => "guardrail_intervened",
+            BedrockError::Other { .. } => "other",
+        }
+    }
+
+    /// Whether retrying the same request can succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, BedrockError::Throttling { .. })
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            BedrockError::Throttling { message }
+            | BedrockError::AccessDenied { message }
+            | BedrockError::ValidationException { message }
+            | BedrockError::GuardrailIntervened { message }
+            | BedrockError::Other { message } => message,
+        }
+    }
+}
+
+impl std::fmt::Display for BedrockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.kind(), self.message())
+    }
 }
 
 // Anthropic API types
@@ -138,15 +484,61 @@ struct AnthropicRequest {
     model: String,
     max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    system: Option<AnthropicSystem>,
     messages: Vec<AnthropicMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<serde_json::Value>,
     stream: bool,
 }
 
+/// Anthropic system prompt - plain string, or blocks when prompt caching is enabled
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+enum AnthropicSystem {
+    Text(String),
+    Blocks(Vec<AnthropicSystemBlock>),
+}
+
+impl AnthropicSystem {
+    fn new(prompt: String, enable_prompt_caching: bool) -> Self {
+        if enable_prompt_caching {
+            AnthropicSystem::Blocks(vec![AnthropicSystemBlock {
+                block_type: "text".to_string(),
+                text: prompt,
+                cache_control: Some(AnthropicCacheControl::ephemeral()),
+            }])
+        } else {
+            AnthropicSystem::Text(prompt)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AnthropicSystemBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<AnthropicCacheControl>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AnthropicCacheControl {
+    #[serde(rename = "type")]
+    cache_type: String,
+}
+
+/// Beta header value enabling prompt caching on older API versions and proxies
+const PROMPT_CACHING_BETA: &str = "prompt-caching-2024-07-31";
+
 /// Anthropic tool definition
 #[derive(Debug, Clone, Serialize)]
 pub struct AnthropicTool {
@@ -155,22 +547,29 @@ pub struct AnthropicTool {
     input_schema: serde_json::Value,
 }
 
+/// Name of the built-in memory tool
+pub const MEMORY_TOOL_NAME: &str = "memory";
+
 /// Memory Tool - provides file operations for persistent memory
 fn create_memory_tool() -> AnthropicTool {
     AnthropicTool {
-        name: "memory".to_string(),
+        name: MEMORY_TOOL_NAME.to_string(),
         description: "Manage persistent memory files. Use this to save, update, or read information that should persist across conversations. Memory files are stored in .flowq/memories/ directory.".to_string(),
         input_schema: serde_json::json!({
             "type": "object",
             "properties": {
                 "command": {
                     "type": "string",
-                    "enum": ["view", "create", "str_replace", "insert", "delete"],
+                    "enum": ["view", "create", "str_replace", "insert", "delete", "undo", "list", "grep"],
                     "description": "The operation to perform"
                 },
                 "path": {
                     "type": "string",
-                    "description": "File path relative to memories directory (e.g., 'notes.md', 'projects/work.md')"
+                    "description": "File path relative to memories directory (e.g., 'notes.md', 'projects/work.md'). For 'list': directory to list, '' for all"
+                },
+                "pattern": {
+                    "type": "string",
+                    "description": "For 'grep': regex matched against each line of every memory file"
                 },
                 "file_text": {
                     "type": "string",
@@ -184,6 +583,10 @@ fn create_memory_tool() -> AnthropicTool {
                     "type": "string",
                     "description": "For 'str_replace' or 'insert': the new text"
                 },
+                "expected_occurrences": {
+                    "type": "integer",
+                    "description": "For 'str_replace': how many times old_str must occur (default 1); all are replaced"
+                },
                 "insert_line": {
                     "type": "integer",
                     "description": "For 'insert': line number to insert at (1-indexed)"
@@ -222,9 +625,71 @@ enum AnthropicContent {
 #[serde(tag = "type")]
 enum AnthropicContentBlock {
     #[serde(rename = "text")]
-    Text { text: String },
+    Text {
+        text: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
+    },
     #[serde(rename = "image")]
-    Image { source: AnthropicImageSource },
+    Image {
+        source: AnthropicImageSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
+    },
+}
+
+/// Anthropic accepts at most this many `cache_control` breakpoints per request
+const MAX_CACHE_BREAKPOINTS: usize = 4;
+
+impl AnthropicCacheControl {
+    fn ephemeral() -> Self {
+        Self {
+            cache_type: "ephemeral".to_string(),
+        }
+    }
+}
+
+impl AnthropicMessage {
+    /// Put a cache breakpoint on the last block; false if the content can't carry one
+    fn set_cache_breakpoint(&mut self) -> bool {
+        if let AnthropicContent::Text(text) = &mut self.content {
+            self.content = AnthropicContent::Blocks(vec![AnthropicContentBlock::Text {
+                text: std::mem::take(text),
+                cache_control: None,
+            }]);
+        }
+        match &mut self.content {
+            AnthropicContent::Blocks(blocks) => match blocks.last_mut() {
+                Some(AnthropicContentBlock::Text { cache_control, .. })
+                | Some(AnthropicContentBlock::Image { cache_control, .. }) => {
+                    *cache_control = Some(AnthropicCacheControl::ephemeral());
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Mark the last message of earlier turns as cache breakpoints, newest first,
+/// leaving room for the system prompt breakpoint when that is cached too
+fn apply_history_cache_breakpoints(messages: &mut [AnthropicMessage], system_cached: bool) {
+    let mut budget = MAX_CACHE_BREAKPOINTS - usize::from(system_cached);
+
+    // A turn ends with the assistant message that precedes the next user message
+    let turn_ends: Vec<usize> = (0..messages.len().saturating_sub(1))
+        .filter(|&i| messages[i].role == "assistant" && messages[i + 1].role == "user")
+        .collect();
+
+    for &i in turn_ends.iter().rev() {
+        if budget == 0 {
+            break;
+        }
+        if messages[i].set_cache_breakpoint() {
+            budget -= 1;
+        }
+    }
 }
 
 /// Content block for assistant response (used in tool use loop)
@@ -239,6 +704,11 @@ enum AnthropicResponseContentBlock {
         name: String,
         input: serde_json::Value,
     },
+    /// Thinking must be passed back unchanged alongside tool results
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String, signature: String },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
 }
 
 /// Tool result block for user message
@@ -274,12 +744,208 @@ struct AnthropicResponseContent {
     id: Option<String>,
     name: Option<String>,
     input: Option<serde_json::Value>,
+    // Thinking fields
+    thinking: Option<String>,
+    signature: Option<String>,
+    data: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 struct AnthropicUsage {
     input_tokens: u32,
     output_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u32>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
+}
+
+/// Parser turning `text/event-stream` bytes into stream chunks
+trait SseParser {
+    /// Bytes of a not yet complete event
+    fn buffer_mut(&mut self) -> &mut Vec<u8>;
+    /// Handle one event's `data:` payload
+    fn handle_event(&mut self, data: &str) -> Result<Vec<StreamChunk>, String>;
+    /// Build the terminal chunk from the state accumulated so far
+    fn done(&self) -> StreamChunk;
+
+    /// Feed raw bytes from the response body and return all chunks completed by them
+    fn feed(&mut self, bytes: &[u8]) -> Result<Vec<StreamChunk>, String> {
+        let mut chunks = Vec::new();
+        for data in drain_sse_events(self.buffer_mut(), bytes) {
+            chunks.extend(self.handle_event(&data)?);
+        }
+        Ok(chunks)
+    }
+}
+
+/// Move complete events out of `buffer` and return their joined `data:` payloads
+fn drain_sse_events(buffer: &mut Vec<u8>, bytes: &[u8]) -> Vec<String> {
+    // Normalize CRLF so events are always separated by a blank "\n\n" line
+    buffer.extend(bytes.iter().filter(|b| **b != b'\r'));
+
+    let mut events = Vec::new();
+    while let Some(pos) = buffer.windows(2).position(|w| w == b"\n\n") {
+        let event: Vec<u8> = buffer.drain(..pos + 2).collect();
+        let event = String::from_utf8_lossy(&event);
+
+        // Only the data lines matter - the JSON payload carries its own "type"
+        let data = event
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|d| d.trim_start())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !data.is_empty() {
+            events.push(data);
+        }
+    }
+    events
+}
+
+/// Turn an SSE response body into a chunk stream that ends after `Done`
+fn sse_stream<P: SseParser>(
+    response: reqwest::Response,
+    parser: P,
+) -> impl Stream<Item = Result<StreamChunk, String>> {
+    let state = Some((response, parser, VecDeque::new()));
+    futures::stream::unfold(state, |state| async move {
+        let (mut response, mut parser, mut pending) = state?;
+        loop {
+            if let Some(chunk) = pending.pop_front() {
+                // Stop polling the body once the terminal chunk is delivered
+                let next = if matches!(chunk, StreamChunk::Done { .. }) {
+                    None
+                } else {
+                    Some((response, parser, pending))
+                };
+                return Some((Ok(chunk), next));
+            }
+
+            match response.chunk().await {
+                Ok(Some(bytes)) => match parser.feed(&bytes) {
+                    Ok(chunks) => pending.extend(chunks),
+                    Err(e) => return Some((Err(e), None)),
+                },
+                // Body ended without a terminal event - still report what we have
+                Ok(None) => return Some((Ok(parser.done()), None)),
+                Err(e) => return Some((Err(format!("Stream read failed: {}", e)), None)),
+            }
+        }
+    })
+}
+
+/// Incremental parser for Anthropic `text/event-stream` response bodies
+#[derive(Debug, Default)]
+struct AnthropicSseParser {
+    buffer: Vec<u8>,
+    input_tokens: u32,
+    output_tokens: u32,
+    cache_creation_input_tokens: Option<u32>,
+    cache_read_input_tokens: Option<u32>,
+    stop_reason: Option<String>,
+}
+
+impl SseParser for AnthropicSseParser {
+    fn buffer_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.buffer
+    }
+
+    fn handle_event(&mut self, data: &str) -> Result<Vec<StreamChunk>, String> {
+        Ok(self.event_chunk(data)?.into_iter().collect())
+    }
+
+    fn done(&self) -> StreamChunk {
+        StreamChunk::Done {
+            stop_reason: self.stop_reason.clone(),
+            usage: Some(TokenUsage {
+                input_tokens: self.input_tokens,
+                output_tokens: self.output_tokens,
+                cache_creation_input_tokens: self.cache_creation_input_tokens,
+                cache_read_input_tokens: self.cache_read_input_tokens,
+            }),
+        }
+    }
+}
+
+impl AnthropicSseParser {
+    /// Decode one Anthropic event - each carries at most one chunk
+    fn event_chunk(&mut self, data: &str) -> Result<Option<StreamChunk>, String> {
+        let event: serde_json::Value = serde_json::from_str(data)
+            .map_err(|e| format!("Failed to parse stream event: {}", e))?;
+
+        match event.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+            "message_start" => {
+                if let Some(usage) = event.pointer("/message/usage") {
+                    self.input_tokens = usage
+                        .get("input_tokens")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(0) as u32;
+                    self.output_tokens = usage
+                        .get("output_tokens")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(0) as u32;
+                    self.cache_creation_input_tokens = usage
+                        .get("cache_creation_input_tokens")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as u32);
+                    self.cache_read_input_tokens = usage
+                        .get("cache_read_input_tokens")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as u32);
+                }
+                Ok(None)
+            }
+            "content_block_start" => {
+                let block = event.get("content_block");
+                let block_type = block
+                    .and_then(|b| b.get("type"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                if block_type == "tool_use" {
+                    let field = |key: &str| {
+                        block
+                            .and_then(|b| b.get(key))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or_default()
+                            .to_string()
+                    };
+                    Ok(Some(StreamChunk::ToolUseStart {
+                        id: field("id"),
+                        name: field("name"),
+                    }))
+                } else {
+                    Ok(None)
+                }
+            }
+            "content_block_delta" => {
+                let text = event
+                    .pointer("/delta/text")
+                    .and_then(|v| v.as_str())
+                    .filter(|_| event.pointer("/delta/type").and_then(|v| v.as_str()) == Some("text_delta"));
+                Ok(text.map(|t| StreamChunk::Text { text: t.to_string() }))
+            }
+            "message_delta" => {
+                if let Some(reason) = event.pointer("/delta/stop_reason").and_then(|v| v.as_str()) {
+                    self.stop_reason = Some(reason.to_string());
+                }
+                if let Some(output) = event.pointer("/usage/output_tokens").and_then(|v| v.as_u64()) {
+                    self.output_tokens = output as u32;
+                }
+                Ok(None)
+            }
+            "message_stop" => Ok(Some(self.done())),
+            "error" => {
+                let message = event
+                    .pointer("/error/message")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Unknown stream error");
+                Err(format!("API stream error: {}", message))
+            }
+            // ping, content_block_stop and unknown events carry nothing to surface
+            _ => Ok(None),
+        }
+    }
 }
 
 // OpenAI API types
@@ -291,13 +957,98 @@ struct OpenAIRequest {
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    /// OpenAI's name for stop sequences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<OpenAITool>>,
     stream: bool,
 }
 
-#[derive(Debug, Serialize)]
+/// OpenAI function tool definition
+#[derive(Debug, Clone, Serialize)]
+struct OpenAITool {
+    #[serde(rename = "type")]
+    tool_type: String,
+    function: OpenAIFunctionDefinition,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OpenAIFunctionDefinition {
+    name: String,
+    description: String,
+    parameters: serde_json::Value,
+}
+
+impl From<AnthropicTool> for OpenAITool {
+    /// OpenAI function-calling format, sharing the Anthropic schema
+    fn from(tool: AnthropicTool) -> Self {
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIFunctionDefinition {
+                name: tool.name,
+                description: tool.description,
+                parameters: tool.input_schema,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 struct OpenAIMessage {
     role: String,
-    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<OpenAIContent>,
+    /// For assistant messages requesting tool calls
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<OpenAIToolCall>>,
+    /// For tool messages carrying a tool result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+/// OpenAI content - can be string or array of content parts
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+enum OpenAIContent {
+    Text(String),
+    Parts(Vec<OpenAIContentPart>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+enum OpenAIContentPart {
+    #[serde(rename = "text")]
+    Text { text: String },
+    #[serde(rename = "image_url")]
+    ImageUrl { image_url: OpenAIImageUrl },
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OpenAIImageUrl {
+    /// Data URL, e.g. "data:image/png;base64,..."
+    url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OpenAIToolCall {
+    id: String,
+    #[serde(rename = "type", default = "default_tool_call_type")]
+    call_type: String,
+    function: OpenAIFunctionCall,
+}
+
+fn default_tool_call_type() -> String {
+    "function".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OpenAIFunctionCall {
+    name: String,
+    /// JSON-encoded arguments
+    arguments: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -310,11 +1061,13 @@ struct OpenAIResponse {
 #[derive(Debug, Deserialize)]
 struct OpenAIChoice {
     message: OpenAIMessageContent,
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAIMessageContent {
     content: Option<String>,
+    tool_calls: Option<Vec<OpenAIToolCall>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -323,46 +1076,391 @@ struct OpenAIUsage {
     completion_tokens: u32,
 }
 
-pub struct ChatClient {
-    http_client: reqwest::Client,
+/// Incremental parser for OpenAI-compatible `stream: true` response bodies
+#[derive(Debug, Default)]
+struct OpenAISseParser {
+    buffer: Vec<u8>,
+    /// Tool call ids by delta index - only the first delta of a call carries the id
+    tool_call_ids: HashMap<u64, String>,
+    stop_reason: Option<String>,
+    usage: Option<TokenUsage>,
 }
 
-impl ChatClient {
-    pub fn new() -> Self {
-        Self {
-            http_client: reqwest::Client::new(),
-        }
+impl SseParser for OpenAISseParser {
+    fn buffer_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.buffer
     }
 
-    pub async fn send(&self, request: ChatRequest) -> Result<ChatResponse, String> {
-        match request.config.provider.as_str() {
-            "anthropic" => self.send_anthropic(request).await,
-            "bedrock" => self.send_bedrock(request).await,
-            "openai" | "azure" | "custom" => self.send_openai(request).await,
-            _ => Err(format!("Unsupported provider: {}", request.config.provider)),
+    fn handle_event(&mut self, data: &str) -> Result<Vec<StreamChunk>, String> {
+        if data.trim() == "[DONE]" {
+            return Ok(vec![self.done()]);
         }
-    }
 
-    /// Send message using Anthropic API (supports official and third-party proxies)
-    /// Implements tool use loop for memory operations when workspace is provided
-    async fn send_anthropic(&self, request: ChatRequest) -> Result<ChatResponse, String> {
-        let base_url = request
-            .config
-            .base_url
-            .unwrap_or_else(|| "https://api.anthropic.com".to_string());
-        let model = request
-            .config
-            .model
-            .clone()
-            .unwrap_or_else(|| "claude-sonnet-4-20250514".to_string());
-        let api_key = request
+        let event: serde_json::Value = serde_json::from_str(data)
+            .map_err(|e| format!("Failed to parse stream event: {}", e))?;
+
+        if let Some(message) = event.pointer("/error/message").and_then(|v| v.as_str()) {
+            return Err(format!("API stream error: {}", message));
+        }
+
+        if let Some(usage) = event.get("usage").filter(|u| !u.is_null()) {
+            let tokens = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+            self.usage = Some(TokenUsage {
+                input_tokens: tokens("prompt_tokens"),
+                output_tokens: tokens("completion_tokens"),
+                ..Default::default()
+            });
+        }
+
+        let Some(choice) = event.pointer("/choices/0") else {
+            return Ok(Vec::new());
+        };
+        if let Some(reason) = choice.get("finish_reason").and_then(|v| v.as_str()) {
+            self.stop_reason = Some(reason.to_string());
+        }
+
+        let delta = choice.get("delta");
+        let mut chunks = Vec::new();
+        if let Some(text) = delta
+            .and_then(|d| d.get("content"))
+            .and_then(|v| v.as_str())
+            .filter(|t| !t.is_empty())
+        {
+            chunks.push(StreamChunk::Text { text: text.to_string() });
+        }
+
+        let tool_calls = delta
+            .and_then(|d| d.get("tool_calls"))
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for call in tool_calls {
+            let index = call.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+            if let Some(id) = call.get("id").and_then(|v| v.as_str()) {
+                self.tool_call_ids.insert(index, id.to_string());
+                let name = call
+                    .pointer("/function/name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                chunks.push(StreamChunk::ToolUseStart {
+                    id: id.to_string(),
+                    name: name.to_string(),
+                });
+            }
+            if let Some(arguments) = call
+                .pointer("/function/arguments")
+                .and_then(|v| v.as_str())
+                .filter(|a| !a.is_empty())
+            {
+                chunks.push(StreamChunk::ToolInputDelta {
+                    id: self.tool_call_ids.get(&index).cloned().unwrap_or_default(),
+                    partial_json: arguments.to_string(),
+                });
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    fn done(&self) -> StreamChunk {
+        StreamChunk::Done {
+            stop_reason: self.stop_reason.clone(),
+            usage: self.usage.clone(),
+        }
+    }
+}
+
+// ============ Tool Registry ============
+
+/// Handler run when the model calls a tool, given the tool's JSON input
+/// `Err` text is sent back to the model as the tool result too
+pub type ToolHandler = Arc<dyn Fn(serde_json::Value) -> Result<String, String> + Send + Sync>;
+
+/// Tools the non-streaming loop offers to the model, keyed by name
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<(AnthropicTool, ToolHandler)>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a tool, replacing any tool of the same name
+    pub fn register<F>(&mut self, name: &str, description: &str, input_schema: serde_json::Value, handler: F)
+    where
+        F: Fn(serde_json::Value) -> Result<String, String> + Send + Sync + 'static,
+    {
+        let definition = AnthropicTool {
+            name: name.to_string(),
+            description: description.to_string(),
+            input_schema,
+        };
+        self.insert(definition, Arc::new(handler));
+    }
+
+    fn insert(&mut self, definition: AnthropicTool, handler: ToolHandler) {
+        self.tools.retain(|(d, _)| d.name != definition.name);
+        self.tools.push((definition, handler));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Run a tool and return the text for its tool result
+    pub fn dispatch(&self, name: &str, input: serde_json::Value) -> String {
+        match self.tools.iter().find(|(d, _)| d.name == name) {
+            Some((_, handler)) => handler(input).unwrap_or_else(|e| e),
+            None => format!("Unknown tool: {}", name),
+        }
+    }
+
+    fn anthropic_tools(&self) -> Option<Vec<AnthropicTool>> {
+        (!self.is_empty()).then(|| self.tools.iter().map(|(d, _)| d.clone()).collect())
+    }
+
+    fn openai_tools(&self) -> Option<Vec<OpenAITool>> {
+        self.anthropic_tools()
+            .map(|tools| tools.into_iter().map(OpenAITool::from).collect())
+    }
+
+    fn bedrock_tool_config(&self) -> Option<ToolConfiguration> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut builder = ToolConfiguration::builder();
+        for (definition, _) in &self.tools {
+            let spec = ToolSpecification::builder()
+                .name(&definition.name)
+                .description(&definition.description)
+                .input_schema(ToolInputSchema::Json(json_to_document(&definition.input_schema)))
+                .build()
+                .unwrap();
+            builder = builder.tools(BedrockTool::ToolSpec(spec));
+        }
+        Some(builder.build().unwrap())
+    }
+}
+
+/// Convert a JSON value into a Bedrock Document
+fn json_to_document(value: &serde_json::Value) -> Document {
+    use aws_smithy_types::Number;
+    use serde_json::Value;
+
+    match value {
+        Value::Null => Document::Null,
+        Value::Bool(b) => Document::Bool(*b),
+        Value::Number(n) => Document::Number(match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => Number::PosInt(u),
+            (None, Some(i)) => Number::NegInt(i),
+            _ => Number::Float(n.as_f64().unwrap_or_default()),
+        }),
+        Value::String(s) => Document::String(s.clone()),
+        Value::Array(items) => Document::Array(items.iter().map(json_to_document).collect()),
+        Value::Object(map) => Document::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), json_to_document(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Convert Bedrock tool input into JSON
+/// Whole-number floats become integers so handlers can read them with `as_u64`
+fn document_to_json(document: &Document) -> serde_json::Value {
+    use aws_smithy_types::Number;
+    use serde_json::Value;
+
+    match document {
+        Document::Null => Value::Null,
+        Document::Bool(b) => Value::Bool(*b),
+        Document::Number(Number::PosInt(u)) => Value::from(*u),
+        Document::Number(Number::NegInt(i)) => Value::from(*i),
+        Document::Number(Number::Float(f)) if f.fract() == 0.0 && *f >= 0.0 && *f < u64::MAX as f64 => {
+            Value::from(*f as u64)
+        }
+        Document::Number(Number::Float(f)) => Value::from(*f),
+        Document::String(s) => Value::String(s.clone()),
+        Document::Array(items) => Value::Array(items.iter().map(document_to_json).collect()),
+        Document::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), document_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+pub struct ChatClient {
+    http_client: reqwest::Client,
+    /// Extra tools offered alongside the built-in memory tool
+    tools: ToolRegistry,
+    /// Responses reused for requests that set `cache`
+    response_cache: Option<ResponseCache>,
+}
+
+impl ChatClient {
+    pub fn new() -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            tools: ToolRegistry::new(),
+            response_cache: None,
+        }
+    }
+
+    /// Reuse stored responses in `send` for requests that opt in with `cache`
+    pub fn with_response_cache(mut self, cache: ResponseCache) -> Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
+    /// Offer additional tools in the non-streaming tool-use loop
+    /// Tools here replace built-in tools of the same name
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Tools for a request: memory when a workspace is set plus the client's tools,
+    /// narrowed to `request.tools` when given
+    fn tool_registry(&self, request: &ChatRequest) -> Result<ToolRegistry, String> {
+        let mut registry = ToolRegistry::new();
+        if request.tool_enabled(MEMORY_TOOL_NAME) {
+            if let Some(memory) = Self::open_memory_tool(request)? {
+                registry.insert(
+                    create_memory_tool(),
+                    Arc::new(move |input| Self::execute_memory_command(&memory, &input)),
+                );
+            }
+        }
+        for (definition, handler) in &self.tools.tools {
+            if request.tool_enabled(&definition.name) {
+                registry.insert(definition.clone(), handler.clone());
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Send an HTTP request, retrying transient failures according to `policy`
+    /// `build` is called once per attempt since a RequestBuilder is consumed by sending
+    async fn send_with_retry<F>(
+        &self,
+        policy: &RetryPolicy,
+        build: F,
+    ) -> Result<reqwest::Response, String>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let mut attempt = 0u32;
+        loop {
+            let response = match build().send().await {
+                Ok(response) => response,
+                Err(e) if (e.is_timeout() || e.is_connect()) && attempt < policy.max_retries => {
+                    let delay = policy.delay_for(attempt, None);
+                    log::warn!("Request failed ({}), retrying in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(format!("Request failed: {}", e)),
+            };
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+
+            if policy.should_retry(status.as_u16(), attempt) {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after);
+                let delay = policy.delay_for(attempt, retry_after);
+                log::warn!(
+                    "API returned {}, retry {}/{} in {:?}",
+                    status,
+                    attempt + 1,
+                    policy.max_retries,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("API error ({}): {}", status, text));
+        }
+    }
+
+    pub async fn send(&self, request: ChatRequest) -> Result<ChatResponse, String> {
+        request.validate()?;
+
+        let uses_memory = request.workspace.is_some() && request.tool_enabled(MEMORY_TOOL_NAME);
+        let cached = self
+            .response_cache
+            .as_ref()
+            .filter(|_| request.cache && !uses_memory)
+            .map(|cache| (cache, ResponseCache::key(&request)));
+        if let Some((cache, key)) = &cached {
+            if let Some(response) = cache.get(key) {
+                log::info!("Serving chat response from cache");
+                return Ok(response);
+            }
+        }
+
+        let response = match request.config.provider.as_str() {
+            "anthropic" => self.send_anthropic(request).await,
+            "bedrock" => self
+                .send_bedrock(request)
+                .await
+                .map_err(|e| format!("Bedrock request failed: {}", e)),
+            "openai" | "azure" | "custom" => self.send_openai(request).await,
+            _ => Err(format!("Unsupported provider: {}", request.config.provider)),
+        }?;
+
+        if let Some((cache, key)) = &cached {
+            if let Err(e) = cache.put(key, &response) {
+                log::warn!("{}", e);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Send message using Anthropic API (supports official and third-party proxies)
+    /// Implements the tool use loop over the request's tool registry
+    async fn send_anthropic(&self, request: ChatRequest) -> Result<ChatResponse, String> {
+        let max_iterations = request.tool_iteration_limit();
+        let max_tokens = request.effective_max_tokens();
+        let registry = self.tool_registry(&request)?;
+        let base_url = request
+            .config
+            .base_url
+            .unwrap_or_else(|| "https://api.anthropic.com".to_string());
+        let model = request
+            .config
+            .model
+            .as_deref()
+            .map(|m| crate::model::resolve("anthropic", m))
+            .unwrap_or_else(|| "claude-sonnet-4-20250514".to_string());
+        let api_key = request
             .config
             .api_key
             .clone()
             .ok_or("API key is required for Anthropic provider")?;
-        let system_prompt = request.system_prompt.clone();
-        let max_tokens = request.max_tokens.unwrap_or(4096);
+        let enable_prompt_caching = request.enable_prompt_caching;
+        let cache_history = request.cache_history;
+        let system_prompt = request
+            .system_prompt
+            .clone()
+            .map(|p| AnthropicSystem::new(p, enable_prompt_caching));
         let temperature = request.temperature;
+        let top_p = request.top_p;
+        let stop_sequences = request.stop_sequences.clone();
+        let thinking = request.thinking.as_ref().map(ThinkingConfig::to_request_field);
+        let retry_policy = request.config.retry_policy.clone();
 
         // Convert initial messages to Anthropic format
         let mut messages: Vec<AnthropicMessage> = request
@@ -370,20 +1468,21 @@ impl ChatClient {
             .into_iter()
             .map(|m| Self::convert_to_anthropic_message(m))
             .collect();
+        if cache_history {
+            apply_history_cache_breakpoints(&mut messages, enable_prompt_caching && system_prompt.is_some());
+        }
 
-        // Set up memory tool if workspace is provided
-        let tools = request.workspace.as_ref().map(|_| vec![create_memory_tool()]);
-        let memory_tool = request.workspace.as_ref().map(|ws| MemoryTool::new(Path::new(ws)));
+        let tools = registry.anthropic_tools();
 
         // Track total usage across the loop
-        let mut total_input_tokens = 0u32;
-        let mut total_output_tokens = 0u32;
+        let mut total_usage = TokenUsage::default();
         let mut final_text = String::new();
         let mut final_model = model.clone();
+        let mut final_stop_reason = STOP_REASON_MAX_ITERATIONS.to_string();
+        let mut thinking_parts: Vec<String> = Vec::new();
 
         // Tool use loop - continue until end_turn
-        const MAX_ITERATIONS: u32 = 10;
-        for iteration in 0..MAX_ITERATIONS {
+        for iteration in 0..max_iterations {
             log::info!("Anthropic request iteration {}", iteration + 1);
 
             let api_request = AnthropicRequest {
@@ -392,26 +1491,29 @@ impl ChatClient {
                 system: system_prompt.clone(),
                 messages: messages.clone(),
                 temperature,
+                top_p,
+                stop_sequences: stop_sequences.clone(),
                 tools: tools.clone(),
+                thinking: thinking.clone(),
                 stream: false,
             };
 
             let response = self
-                .http_client
-                .post(format!("{}/v1/messages", base_url))
-                .header("Content-Type", "application/json")
-                .header("x-api-key", &api_key)
-                .header("anthropic-version", "2023-06-01")
-                .json(&api_request)
-                .send()
-                .await
-                .map_err(|e| format!("Request failed: {}", e))?;
-
-            if !response.status().is_success() {
-                let status = response.status();
-                let text = response.text().await.unwrap_or_default();
-                return Err(format!("API error ({}): {}", status, text));
-            }
+                .send_with_retry(&retry_policy, || {
+                    let builder = self
+                        .http_client
+                        .post(format!("{}/v1/messages", base_url))
+                        .header("Content-Type", "application/json")
+                        .header("x-api-key", &api_key)
+                        .header("anthropic-version", "2023-06-01");
+                    let builder = if enable_prompt_caching || cache_history {
+                        builder.header("anthropic-beta", PROMPT_CACHING_BETA)
+                    } else {
+                        builder
+                    };
+                    builder.json(&api_request)
+                })
+                .await?;
 
             let api_response: AnthropicResponse = response
                 .json()
@@ -419,9 +1521,23 @@ impl ChatClient {
                 .map_err(|e| format!("Failed to parse response: {}", e))?;
 
             // Accumulate usage
-            total_input_tokens += api_response.usage.input_tokens;
-            total_output_tokens += api_response.usage.output_tokens;
+            let usage = &api_response.usage;
+            total_usage.input_tokens += usage.input_tokens;
+            total_usage.output_tokens += usage.output_tokens;
+            if let Some(created) = usage.cache_creation_input_tokens {
+                *total_usage.cache_creation_input_tokens.get_or_insert(0) += created;
+            }
+            if let Some(read) = usage.cache_read_input_tokens {
+                *total_usage.cache_read_input_tokens.get_or_insert(0) += read;
+            }
             final_model = api_response.model.clone();
+            thinking_parts.extend(
+                api_response
+                    .content
+                    .iter()
+                    .filter(|c| c.content_type == "thinking")
+                    .filter_map(|c| c.thinking.clone()),
+            );
 
             // Check stop reason
             let stop_reason = api_response.stop_reason.as_deref().unwrap_or("end_turn");
@@ -435,10 +1551,11 @@ impl ChatClient {
                     .filter_map(|c| c.text.clone())
                     .collect::<Vec<_>>()
                     .join("");
+                final_stop_reason = stop_reason.to_string();
                 break;
             } else if stop_reason == "tool_use" {
                 // Handle tool use
-                if let Some(ref tool) = memory_tool {
+                if !registry.is_empty() {
                     // Add assistant message with tool use to conversation
                     let assistant_content: Vec<AnthropicResponseContentBlock> = api_response
                         .content
@@ -454,6 +1571,15 @@ impl ChatClient {
                                     name: c.name.clone().unwrap_or_default(),
                                     input: c.input.clone().unwrap_or(serde_json::json!({})),
                                 }
+                            } else if c.content_type == "thinking" {
+                                AnthropicResponseContentBlock::Thinking {
+                                    thinking: c.thinking.clone().unwrap_or_default(),
+                                    signature: c.signature.clone().unwrap_or_default(),
+                                }
+                            } else if c.content_type == "redacted_thinking" {
+                                AnthropicResponseContentBlock::RedactedThinking {
+                                    data: c.data.clone().unwrap_or_default(),
+                                }
                             } else {
                                 AnthropicResponseContentBlock::Text {
                                     text: String::new(),
@@ -475,21 +1601,11 @@ impl ChatClient {
                             if let (Some(id), Some(name), Some(input)) =
                                 (&content_block.id, &content_block.name, &content_block.input)
                             {
-                                log::info!("Executing memory tool: {} with input: {:?}", name, input);
-
-                                if name == "memory" {
-                                    // Parse and execute memory command
-                                    let result = Self::execute_memory_command(tool, input);
-                                    tool_results.push(AnthropicToolResultBlock {
-                                        tool_use_id: id.clone(),
-                                        content: result,
-                                    });
-                                } else {
-                                    tool_results.push(AnthropicToolResultBlock {
-                                        tool_use_id: id.clone(),
-                                        content: format!("Unknown tool: {}", name),
-                                    });
-                                }
+                                log::info!("Executing tool: {} with input: {:?}", name, input);
+                                tool_results.push(AnthropicToolResultBlock {
+                                    tool_use_id: id.clone(),
+                                    content: registry.dispatch(name, input.clone()),
+                                });
                             }
                         }
                     }
@@ -502,14 +1618,15 @@ impl ChatClient {
                         });
                     }
                 } else {
-                    // No memory tool available, but got tool_use - extract any text and return
-                    log::warn!("Got tool_use but no memory tool available");
+                    // No tools available, but got tool_use - extract any text and return
+                    log::warn!("Got tool_use but no tools available");
                     final_text = api_response
                         .content
                         .iter()
                         .filter_map(|c| c.text.clone())
                         .collect::<Vec<_>>()
                         .join("");
+                    final_stop_reason = stop_reason.to_string();
                     break;
                 }
             } else {
@@ -520,6 +1637,7 @@ impl ChatClient {
                     .filter_map(|c| c.text.clone())
                     .collect::<Vec<_>>()
                     .join("");
+                final_stop_reason = stop_reason.to_string();
                 break;
             }
         }
@@ -527,13 +1645,82 @@ impl ChatClient {
         Ok(ChatResponse {
             content: final_text,
             model: final_model,
-            usage: Some(TokenUsage {
-                input_tokens: total_input_tokens,
-                output_tokens: total_output_tokens,
-            }),
+            usage: Some(total_usage),
+            stop_reason: Some(final_stop_reason),
+            thinking: (!thinking_parts.is_empty()).then(|| thinking_parts.join("\n\n")),
         })
     }
 
+    /// Stream a single Anthropic response as incremental chunks
+    /// Tool calls are not executed here; a `ToolUseStart` chunk tells the caller
+    /// the turn stopped for tool use so it can fall back to `send` for the loop
+    pub async fn send_anthropic_stream(
+        &self,
+        request: ChatRequest,
+    ) -> Result<impl Stream<Item = Result<StreamChunk, String>>, String> {
+        request.validate()?;
+        let tools = self.tool_registry(&request)?.anthropic_tools();
+        let max_tokens = request.effective_max_tokens();
+        let base_url = request
+            .config
+            .base_url
+            .unwrap_or_else(|| "https://api.anthropic.com".to_string());
+        let model = request
+            .config
+            .model
+            .map(|m| crate::model::resolve("anthropic", &m))
+            .unwrap_or_else(|| "claude-sonnet-4-20250514".to_string());
+        let api_key = request
+            .config
+            .api_key
+            .ok_or("API key is required for Anthropic provider")?;
+
+        let mut messages: Vec<AnthropicMessage> = request
+            .messages
+            .into_iter()
+            .map(Self::convert_to_anthropic_message)
+            .collect();
+        if request.cache_history {
+            let system_cached = request.enable_prompt_caching && request.system_prompt.is_some();
+            apply_history_cache_breakpoints(&mut messages, system_cached);
+        }
+
+        let api_request = AnthropicRequest {
+            model,
+            max_tokens,
+            system: request
+                .system_prompt
+                .map(|p| AnthropicSystem::new(p, request.enable_prompt_caching)),
+            messages,
+            temperature: request.temperature,
+            top_p: request.top_p,
+            stop_sequences: request.stop_sequences,
+            tools,
+            thinking: request.thinking.as_ref().map(ThinkingConfig::to_request_field),
+            stream: true,
+        };
+
+        let response = self
+            .send_with_retry(&request.config.retry_policy, || {
+                let builder = self
+                    .http_client
+                    .post(format!("{}/v1/messages", base_url))
+                    .header("Content-Type", "application/json")
+                    .header("Accept", "text/event-stream")
+                    .header("x-api-key", &api_key)
+                    .header("anthropic-version", "2023-06-01");
+                let builder = if request.enable_prompt_caching || request.cache_history {
+                    builder.header("anthropic-beta", PROMPT_CACHING_BETA)
+                } else {
+                    builder
+                };
+                builder.json(&api_request)
+            })
+            .await?;
+
+        Ok(sse_stream(response, AnthropicSseParser::default()))
+    }
+
     /// Convert a ChatMessage to AnthropicMessage
     fn convert_to_anthropic_message(m: ChatMessage) -> AnthropicMessage {
         let content = match m.content {
@@ -542,13 +1729,17 @@ impl ChatClient {
                 let anthropic_blocks: Vec<AnthropicContentBlock> = blocks
                     .into_iter()
                     .map(|b| match b {
-                        ContentBlock::Text { text } => AnthropicContentBlock::Text { text },
+                        ContentBlock::Text { text } => AnthropicContentBlock::Text {
+                            text,
+                            cache_control: None,
+                        },
                         ContentBlock::Image { source } => AnthropicContentBlock::Image {
                             source: AnthropicImageSource {
                                 source_type: source.source_type,
                                 media_type: source.media_type,
                                 data: source.data,
                             },
+                            cache_control: None,
                         },
                     })
                     .collect();
@@ -561,9 +1752,30 @@ impl ChatClient {
         }
     }
 
+    /// Create the memory tool for a request, if it has a workspace
+    fn open_memory_tool(request: &ChatRequest) -> Result<Option<MemoryTool>, String> {
+        let Some(ref workspace) = request.workspace else {
+            return Ok(None);
+        };
+        let tool = match request.memories_dir {
+            Some(ref dir) => MemoryTool::with_memories_dir(Path::new(workspace), dir)?,
+            None => MemoryTool::new(Path::new(workspace)),
+        };
+        Ok(Some(tool))
+    }
+
     /// Execute a memory tool command
-    fn execute_memory_command(tool: &MemoryTool, input: &serde_json::Value) -> String {
-        // Parse command from input
+    fn execute_memory_command(tool: &MemoryTool, input: &serde_json::Value) -> Result<String, String> {
+        let result = tool.execute(Self::parse_memory_command(input)?);
+        if result.success {
+            Ok(result.output)
+        } else {
+            Err(result.error.unwrap_or_else(|| "Unknown error".to_string()))
+        }
+    }
+
+    /// Parse a memory command from tool input
+    fn parse_memory_command(input: &serde_json::Value) -> Result<crate::memory_tool::MemoryToolCommand, String> {
         let command = input.get("command").and_then(|v| v.as_str()).unwrap_or("");
         let path = input.get("path").and_then(|v| v.as_str()).unwrap_or("");
 
@@ -601,6 +1813,10 @@ impl ChatClient {
                     path: path.to_string(),
                     old_str: old_str.to_string(),
                     new_str: new_str.to_string(),
+                    expected_occurrences: input
+                        .get("expected_occurrences")
+                        .and_then(|v| v.as_u64())
+                        .map(|n| n as usize),
                 }
             }
             "insert" => {
@@ -625,50 +1841,33 @@ impl ChatClient {
                     new_path: new_path.to_string(),
                 }
             }
+            "undo" => MemoryToolCommand::Undo,
+            "list" => MemoryToolCommand::List {
+                subpath: Some(path.to_string()),
+            },
+            "grep" => {
+                let pattern = input.get("pattern").and_then(|v| v.as_str()).unwrap_or("");
+                MemoryToolCommand::Grep {
+                    pattern: pattern.to_string(),
+                }
+            }
             _ => {
-                return format!("Unknown memory command: {}", command);
+                return Err(format!("Unknown memory command: {}", command));
             }
         };
 
-        let result = tool.execute(cmd);
-        if result.success {
-            result.output
-        } else {
-            result.error.unwrap_or_else(|| "Unknown error".to_string())
-        }
-    }
-
-    /// Map Anthropic model ID to Bedrock model ID
-    fn map_to_bedrock_model(model: &str) -> String {
-        match model {
-            // Claude 4.5 models (cross-region inference)
-            "claude-sonnet-4-20250514" => "us.anthropic.claude-sonnet-4-5-20250929-v1:0".to_string(),
-            "claude-opus-4-20250514" => "global.anthropic.claude-opus-4-5-20251101-v1:0".to_string(),
-            // Claude 3.5 models
-            "claude-3-5-sonnet-20241022" => "us.anthropic.claude-3-5-sonnet-20241022-v2:0".to_string(),
-            "claude-3-5-haiku-20241022" => "us.anthropic.claude-3-5-haiku-20241022-v1:0".to_string(),
-            // Claude 3 models
-            "claude-3-sonnet-20240229" => "us.anthropic.claude-3-sonnet-20240229-v1:0".to_string(),
-            "claude-3-haiku-20240307" => "us.anthropic.claude-3-haiku-20240307-v1:0".to_string(),
-            "claude-3-opus-20240229" => "us.anthropic.claude-3-opus-20240229-v1:0".to_string(),
-            // If already in bedrock format, use as-is
-            _ => {
-                if model.starts_with("anthropic.")
-                    || model.starts_with("us.anthropic.")
-                    || model.starts_with("global.anthropic.")
-                {
-                    model.to_string()
-                } else {
-                    // Default to cross-region inference format
-                    format!("us.anthropic.{}-v1:0", model)
-                }
-            }
-        }
+        Ok(cmd)
     }
 
     /// Send message using AWS Bedrock Converse API
-    /// Implements tool use loop for memory operations when workspace is provided
-    async fn send_bedrock(&self, request: ChatRequest) -> Result<ChatResponse, String> {
+    /// Implements the tool use loop over the request's tool registry
+    async fn send_bedrock(&self, request: ChatRequest) -> Result<ChatResponse, BedrockError> {
+        let max_iterations = request.tool_iteration_limit();
+        let registry = self
+            .tool_registry(&request)
+            .map_err(|message| BedrockError::Other { message })?;
+        let inference_config = Self::build_bedrock_inference_config(&request);
+        let additional_fields = Self::build_bedrock_additional_fields(&request);
         let region = request
             .config
             .region
@@ -679,10 +1878,8 @@ impl ChatClient {
             .model
             .clone()
             .unwrap_or_else(|| "us.anthropic.claude-sonnet-4-5-20250929-v1:0".to_string());
-        let model_id = Self::map_to_bedrock_model(&raw_model);
+        let model_id = crate::model::resolve("bedrock", &raw_model);
         let system_prompt = request.system_prompt.clone();
-        let max_tokens = request.max_tokens;
-        let temperature = request.temperature;
 
         log::info!("Bedrock request: region={}, model_id={}", region, model_id);
 
@@ -695,6 +1892,10 @@ impl ChatClient {
             config_loader = config_loader.profile_name(profile);
         }
 
+        // Retries are driven by our RetryPolicy below, so turn off the SDK's own
+        let retry_policy = request.config.retry_policy.clone();
+        config_loader = config_loader.retry_config(aws_config::retry::RetryConfig::disabled());
+
         let aws_config = config_loader.load().await;
         let client = aws_sdk_bedrockruntime::Client::new(&aws_config);
 
@@ -705,18 +1906,17 @@ impl ChatClient {
             .map(|m| Self::convert_to_bedrock_message(m))
             .collect();
 
-        // Set up memory tool if workspace is provided
-        let tool_config = request.workspace.as_ref().map(|_| Self::create_bedrock_memory_tool());
-        let memory_tool = request.workspace.as_ref().map(|ws| MemoryTool::new(Path::new(ws)));
+        let tool_config = registry.bedrock_tool_config();
 
         // Track total usage across the loop
         let mut total_input_tokens = 0u32;
         let mut total_output_tokens = 0u32;
         let mut final_text = String::new();
+        let mut final_stop_reason = STOP_REASON_MAX_ITERATIONS.to_string();
+        let mut thinking_parts: Vec<String> = Vec::new();
 
         // Tool use loop - continue until end_turn
-        const MAX_ITERATIONS: u32 = 10;
-        for iteration in 0..MAX_ITERATIONS {
+        for iteration in 0..max_iterations {
             log::info!("Bedrock request iteration {}", iteration + 1);
 
             // Build converse request
@@ -737,30 +1937,51 @@ impl ChatClient {
             }
 
             // Add inference config
-            let mut inference_config =
-                aws_sdk_bedrockruntime::types::InferenceConfiguration::builder();
-            if let Some(mt) = max_tokens {
-                inference_config = inference_config.max_tokens(mt as i32);
-            }
-            if let Some(temp) = temperature {
-                inference_config = inference_config.temperature(temp);
+            converse_request = converse_request.inference_config(inference_config.clone());
+            if let Some(ref fields) = additional_fields {
+                converse_request = converse_request.additional_model_request_fields(fields.clone());
             }
-            converse_request = converse_request.inference_config(inference_config.build());
 
-            // Send request
-            let response = converse_request
-                .send()
-                .await
-                .map_err(|e| {
-                    log::error!("Bedrock converse error: {:?}", e);
-                    format!("Bedrock request failed (model: {}): {}", model_id, e)
-                })?;
+            // Send request, retrying transient errors
+            let mut attempt = 0u32;
+            let response = loop {
+                match converse_request.clone().send().await {
+                    Ok(response) => break response,
+                    Err(e) => {
+                        let error = BedrockError::from_converse_error(&e);
+                        let raw = e.raw_response();
+                        // Throttles are retryable even when no HTTP status made it back
+                        let status = raw
+                            .map(|r| r.status().as_u16())
+                            .or_else(|| error.is_retryable().then_some(429));
+                        if let Some(status) = status.filter(|s| retry_policy.should_retry(*s, attempt)) {
+                            let retry_after = raw
+                                .and_then(|r| r.headers().get("retry-after"))
+                                .and_then(parse_retry_after);
+                            let delay = retry_policy.delay_for(attempt, retry_after);
+                            log::warn!(
+                                "Bedrock returned {}, retry {}/{} in {:?}",
+                                status,
+                                attempt + 1,
+                                retry_policy.max_retries,
+                                delay
+                            );
+                            tokio::time::sleep(delay).await;
+                            attempt += 1;
+                            continue;
+                        }
+                        log::error!("Bedrock converse error (model: {}): {:?}", model_id, e);
+                        return Err(error);
+                    }
+                }
+            };
 
             // Accumulate usage
             if let Some(u) = response.usage() {
                 total_input_tokens += u.input_tokens() as u32;
                 total_output_tokens += u.output_tokens() as u32;
             }
+            thinking_parts.extend(Self::extract_bedrock_thinking(&response));
 
             // Check stop reason - returns &StopReason
             let stop_reason = response.stop_reason();
@@ -769,13 +1990,19 @@ impl ChatClient {
             let is_tool_use = *stop_reason == BedrockStopReason::ToolUse;
             let is_end_turn = *stop_reason == BedrockStopReason::EndTurn;
 
+            if let Some(error) = BedrockError::from_stop_reason(stop_reason, Self::extract_bedrock_text(&response)) {
+                log::warn!("Bedrock request blocked (model: {}): {}", model_id, error);
+                return Err(error);
+            }
+
             if is_end_turn {
                 // Final response - extract text and return
                 final_text = Self::extract_bedrock_text(&response);
+                final_stop_reason = stop_reason.as_str().to_string();
                 break;
             } else if is_tool_use {
                 // Handle tool use
-                if let Some(ref tool) = memory_tool {
+                if !registry.is_empty() {
                     // Get content blocks from response
                     let content_blocks = Self::extract_bedrock_content_blocks(&response);
 
@@ -796,27 +2023,15 @@ impl ChatClient {
                             let tool_name = tool_use.name();
                             let input = tool_use.input();
 
-                            log::info!("Executing Bedrock memory tool: {} with input: {:?}", tool_name, input);
-
-                            if tool_name == "memory" {
-                                // Parse and execute memory command
-                                let result = Self::execute_memory_command_from_document(tool, input);
-                                tool_results.push(BedrockContent::ToolResult(
-                                    ToolResultBlock::builder()
-                                        .tool_use_id(tool_use_id)
-                                        .content(ToolResultContentBlock::Text(result))
-                                        .build()
-                                        .unwrap(),
-                                ));
-                            } else {
-                                tool_results.push(BedrockContent::ToolResult(
-                                    ToolResultBlock::builder()
-                                        .tool_use_id(tool_use_id)
-                                        .content(ToolResultContentBlock::Text(format!("Unknown tool: {}", tool_name)))
-                                        .build()
-                                        .unwrap(),
-                                ));
-                            }
+                            log::info!("Executing Bedrock tool: {} with input: {:?}", tool_name, input);
+                            let result = registry.dispatch(tool_name, document_to_json(input));
+                            tool_results.push(BedrockContent::ToolResult(
+                                ToolResultBlock::builder()
+                                    .tool_use_id(tool_use_id)
+                                    .content(ToolResultContentBlock::Text(result))
+                                    .build()
+                                    .unwrap(),
+                            ));
                         }
                     }
 
@@ -830,14 +2045,16 @@ impl ChatClient {
                         messages.push(user_builder.build().unwrap());
                     }
                 } else {
-                    // No memory tool available - extract text and return
-                    log::warn!("Got tool_use but no memory tool available");
+                    // No tools available - extract text and return
+                    log::warn!("Got tool_use but no tools available");
                     final_text = Self::extract_bedrock_text(&response);
+                    final_stop_reason = stop_reason.as_str().to_string();
                     break;
                 }
             } else {
                 // Other stop reasons - extract text and return
                 final_text = Self::extract_bedrock_text(&response);
+                final_stop_reason = stop_reason.as_str().to_string();
                 break;
             }
         }
@@ -848,10 +2065,46 @@ impl ChatClient {
             usage: Some(TokenUsage {
                 input_tokens: total_input_tokens,
                 output_tokens: total_output_tokens,
+                ..Default::default()
             }),
+            stop_reason: Some(final_stop_reason),
+            thinking: (!thinking_parts.is_empty()).then(|| thinking_parts.join("\n\n")),
         })
     }
 
+    /// Model-specific request fields for Bedrock, currently extended thinking
+    fn build_bedrock_additional_fields(request: &ChatRequest) -> Option<Document> {
+        let thinking = request.thinking.as_ref()?;
+        Some(json_to_document(&serde_json::json!({
+            "thinking": thinking.to_request_field()
+        })))
+    }
+
+    /// Map sampling parameters onto Bedrock's InferenceConfiguration
+    fn build_bedrock_inference_config(
+        request: &ChatRequest,
+    ) -> aws_sdk_bedrockruntime::types::InferenceConfiguration {
+        let mut inference_config = aws_sdk_bedrockruntime::types::InferenceConfiguration::builder();
+        // With thinking on, send the limit validation checked the budget against
+        let max_tokens = match request.thinking {
+            Some(_) => Some(request.effective_max_tokens()),
+            None => request.max_tokens,
+        };
+        if let Some(mt) = max_tokens {
+            inference_config = inference_config.max_tokens(mt as i32);
+        }
+        if let Some(temp) = request.temperature {
+            inference_config = inference_config.temperature(temp);
+        }
+        if let Some(top_p) = request.top_p {
+            inference_config = inference_config.top_p(top_p);
+        }
+        if let Some(ref stop_sequences) = request.stop_sequences {
+            inference_config = inference_config.set_stop_sequences(Some(stop_sequences.clone()));
+        }
+        inference_config.build()
+    }
+
     /// Convert ChatMessage to Bedrock Message
     fn convert_to_bedrock_message(m: ChatMessage) -> BedrockMessage {
         let role = match m.role.as_str() {
@@ -902,78 +2155,6 @@ impl ChatClient {
         builder.build().unwrap()
     }
 
-    /// Create Bedrock memory tool configuration
-    fn create_bedrock_memory_tool() -> ToolConfiguration {
-        let input_schema = Document::Object(
-            [
-                ("type".to_string(), Document::String("object".to_string())),
-                ("properties".to_string(), Document::Object(
-                    [
-                        ("command".to_string(), Document::Object(
-                            [
-                                ("type".to_string(), Document::String("string".to_string())),
-                                ("enum".to_string(), Document::Array(vec![
-                                    Document::String("view".to_string()),
-                                    Document::String("create".to_string()),
-                                    Document::String("str_replace".to_string()),
-                                    Document::String("insert".to_string()),
-                                    Document::String("delete".to_string()),
-                                ])),
-                                ("description".to_string(), Document::String("The operation to perform".to_string())),
-                            ].into_iter().collect()
-                        )),
-                        ("path".to_string(), Document::Object(
-                            [
-                                ("type".to_string(), Document::String("string".to_string())),
-                                ("description".to_string(), Document::String("File path relative to memories directory".to_string())),
-                            ].into_iter().collect()
-                        )),
-                        ("file_text".to_string(), Document::Object(
-                            [
-                                ("type".to_string(), Document::String("string".to_string())),
-                                ("description".to_string(), Document::String("For 'create': content of the new file".to_string())),
-                            ].into_iter().collect()
-                        )),
-                        ("old_str".to_string(), Document::Object(
-                            [
-                                ("type".to_string(), Document::String("string".to_string())),
-                                ("description".to_string(), Document::String("For 'str_replace': text to find".to_string())),
-                            ].into_iter().collect()
-                        )),
-                        ("new_str".to_string(), Document::Object(
-                            [
-                                ("type".to_string(), Document::String("string".to_string())),
-                                ("description".to_string(), Document::String("For 'str_replace' or 'insert': new text".to_string())),
-                            ].into_iter().collect()
-                        )),
-                        ("insert_line".to_string(), Document::Object(
-                            [
-                                ("type".to_string(), Document::String("integer".to_string())),
-                                ("description".to_string(), Document::String("For 'insert': line number (1-indexed)".to_string())),
-                            ].into_iter().collect()
-                        )),
-                    ].into_iter().collect()
-                )),
-                ("required".to_string(), Document::Array(vec![
-                    Document::String("command".to_string()),
-                    Document::String("path".to_string()),
-                ])),
-            ].into_iter().collect()
-        );
-
-        let tool_spec = ToolSpecification::builder()
-            .name("memory")
-            .description("Manage persistent memory files. Use this to save, update, or read information that should persist across conversations.")
-            .input_schema(ToolInputSchema::Json(input_schema))
-            .build()
-            .unwrap();
-
-        ToolConfiguration::builder()
-            .tools(BedrockTool::ToolSpec(tool_spec))
-            .build()
-            .unwrap()
-    }
-
     /// Extract text content from Bedrock response
     fn extract_bedrock_text(response: &aws_sdk_bedrockruntime::operation::converse::ConverseOutput) -> String {
         response
@@ -1000,6 +2181,19 @@ impl ChatClient {
             .unwrap_or_default()
     }
 
+    /// Extract reasoning text from Bedrock response
+    fn extract_bedrock_thinking(response: &aws_sdk_bedrockruntime::operation::converse::ConverseOutput) -> Vec<String> {
+        Self::extract_bedrock_content_blocks(response)
+            .iter()
+            .filter_map(|c| match c {
+                BedrockContent::ReasoningContent(ReasoningContentBlock::ReasoningText(reasoning)) => {
+                    Some(reasoning.text().to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Extract content blocks from Bedrock response
     fn extract_bedrock_content_blocks(response: &aws_sdk_bedrockruntime::operation::converse::ConverseOutput) -> Vec<BedrockContent> {
         response
@@ -1014,169 +2208,266 @@ impl ChatClient {
             .unwrap_or_default()
     }
 
-    /// Execute memory command from Bedrock Document input
-    fn execute_memory_command_from_document(tool: &MemoryTool, input: &Document) -> String {
-        use crate::memory_tool::MemoryToolCommand;
+    /// Send message using OpenAI-compatible API
+    /// Implements the tool use loop over the request's tool registry
+    async fn send_openai(&self, request: ChatRequest) -> Result<ChatResponse, String> {
+        let max_iterations = request.tool_iteration_limit();
+        let registry = self.tool_registry(&request)?;
+        let endpoint = OpenAIEndpoint::from_config(&request.config)?;
+        let model = request
+            .config
+            .model
+            .clone()
+            .unwrap_or_else(|| "gpt-4o".to_string());
 
-        // Helper to extract string from Document
-        fn get_str(doc: &Document, key: &str) -> Option<String> {
-            if let Document::Object(map) = doc {
-                map.get(key).and_then(|v| {
-                    if let Document::String(s) = v {
-                        Some(s.clone())
-                    } else {
-                        None
-                    }
-                })
-            } else {
-                None
-            }
-        }
+        // Build messages with optional system prompt
+        let mut messages: Vec<OpenAIMessage> = Vec::new();
 
-        fn get_int(doc: &Document, key: &str) -> Option<u32> {
-            if let Document::Object(map) = doc {
-                map.get(key).and_then(|v| {
-                    if let Document::Number(n) = v {
-                        // Convert through f64
-                        Some(n.to_f64_lossy() as u32)
-                    } else {
-                        None
-                    }
-                })
-            } else {
-                None
-            }
+        if let Some(system) = request.system_prompt {
+            messages.push(OpenAIMessage {
+                role: "system".to_string(),
+                content: Some(OpenAIContent::Text(system)),
+                tool_calls: None,
+                tool_call_id: None,
+            });
         }
 
-        let command = get_str(input, "command").unwrap_or_default();
-        let path = get_str(input, "path").unwrap_or_default();
+        messages.extend(request.messages.into_iter().map(Self::convert_to_openai_message));
 
-        let cmd = match command.as_str() {
-            "view" => MemoryToolCommand::View {
-                path,
-                view_range: None,
-            },
-            "create" => {
-                let file_text = get_str(input, "file_text").unwrap_or_default();
-                MemoryToolCommand::Create { path, file_text }
-            }
-            "str_replace" => {
-                let old_str = get_str(input, "old_str").unwrap_or_default();
-                let new_str = get_str(input, "new_str").unwrap_or_default();
-                MemoryToolCommand::StrReplace { path, old_str, new_str }
-            }
-            "insert" => {
-                let insert_line = get_int(input, "insert_line").unwrap_or(1);
-                let new_str = get_str(input, "new_str").unwrap_or_default();
-                MemoryToolCommand::Insert { path, insert_line, new_str }
-            }
-            "delete" => MemoryToolCommand::Delete { path },
-            "rename" => {
-                let new_path = get_str(input, "new_path").unwrap_or_default();
-                MemoryToolCommand::Rename { old_path: path, new_path }
+        let tools = registry.openai_tools();
+
+        // Track total usage across the loop
+        let mut total_usage: Option<TokenUsage> = None;
+        let mut final_text = String::new();
+        let mut final_model = model.clone();
+        let mut final_stop_reason = STOP_REASON_MAX_ITERATIONS.to_string();
+
+        // Tool use loop - continue until the model stops calling tools
+        for iteration in 0..max_iterations {
+            log::info!("OpenAI request iteration {}", iteration + 1);
+
+            let api_request = OpenAIRequest {
+                model: model.clone(),
+                messages: messages.clone(),
+                max_tokens: request.max_tokens,
+                temperature: request.temperature,
+                top_p: request.top_p,
+                stop: request.stop_sequences.clone(),
+                tools: tools.clone(),
+                stream: false,
+            };
+
+            let response = self
+                .send_with_retry(&request.config.retry_policy, || {
+                    self.http_client
+                        .post(&endpoint.url)
+                        .header("Content-Type", "application/json")
+                        .header(endpoint.auth_header, &endpoint.auth_value)
+                        .json(&api_request)
+                })
+                .await?;
+
+            let api_response: OpenAIResponse = response
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+            // Accumulate usage
+            if let Some(u) = api_response.usage {
+                let usage = total_usage.get_or_insert_with(TokenUsage::default);
+                usage.input_tokens += u.prompt_tokens;
+                usage.output_tokens += u.completion_tokens;
             }
-            _ => {
-                return format!("Unknown memory command: {}", command);
+            final_model = api_response.model;
+
+            let Some(choice) = api_response.choices.into_iter().next() else {
+                final_stop_reason = "no_choices".to_string();
+                break;
+            };
+            log::info!("OpenAI finish reason: {:?}", choice.finish_reason);
+
+            let tool_calls = choice.message.tool_calls.unwrap_or_default();
+            if tool_calls.is_empty() || registry.is_empty() {
+                if !tool_calls.is_empty() {
+                    log::warn!("Got tool_calls but no tools available");
+                }
+                final_text = choice.message.content.unwrap_or_default();
+                final_stop_reason = choice.finish_reason.unwrap_or_else(|| "stop".to_string());
+                break;
             }
-        };
 
-        let result = tool.execute(cmd);
-        if result.success {
-            result.output
-        } else {
-            result.error.unwrap_or_else(|| "Unknown error".to_string())
+            // Add assistant message with tool calls to conversation
+            messages.push(OpenAIMessage {
+                role: "assistant".to_string(),
+                content: choice.message.content.map(OpenAIContent::Text),
+                tool_calls: Some(tool_calls.clone()),
+                tool_call_id: None,
+            });
+
+            // Execute each call and answer it with a tool message
+            for call in &tool_calls {
+                log::info!(
+                    "Executing OpenAI tool: {} with arguments: {}",
+                    call.function.name,
+                    call.function.arguments
+                );
+
+                let result = match serde_json::from_str::<serde_json::Value>(&call.function.arguments) {
+                    Ok(input) => registry.dispatch(&call.function.name, input),
+                    Err(e) => format!("Invalid tool arguments: {}", e),
+                };
+
+                messages.push(OpenAIMessage {
+                    role: "tool".to_string(),
+                    content: Some(OpenAIContent::Text(result)),
+                    tool_calls: None,
+                    tool_call_id: Some(call.id.clone()),
+                });
+            }
         }
+
+        Ok(ChatResponse {
+            content: final_text,
+            model: final_model,
+            usage: total_usage,
+            stop_reason: Some(final_stop_reason),
+            thinking: None,
+        })
     }
 
-    /// Send message using OpenAI-compatible API
-    async fn send_openai(&self, request: ChatRequest) -> Result<ChatResponse, String> {
-        let base_url = request
-            .config
-            .base_url
-            .unwrap_or_else(|| "https://api.openai.com".to_string());
+    /// Stream a single OpenAI-compatible response as incremental chunks
+    /// Tool calls are surfaced as `ToolUseStart` / `ToolInputDelta` chunks, not executed
+    pub async fn send_openai_stream(
+        &self,
+        request: ChatRequest,
+    ) -> Result<impl Stream<Item = Result<StreamChunk, String>>, String> {
+        request.validate()?;
+        let tools = self.tool_registry(&request)?.openai_tools();
+        let endpoint = OpenAIEndpoint::from_config(&request.config)?;
         let model = request
             .config
             .model
+            .clone()
             .unwrap_or_else(|| "gpt-4o".to_string());
-        let api_key = request
-            .config
-            .api_key
-            .ok_or("API key is required for OpenAI provider")?;
 
-        // Build messages with optional system prompt
         let mut messages: Vec<OpenAIMessage> = Vec::new();
-
         if let Some(system) = request.system_prompt {
             messages.push(OpenAIMessage {
                 role: "system".to_string(),
-                content: system,
+                content: Some(OpenAIContent::Text(system)),
+                tool_calls: None,
+                tool_call_id: None,
             });
         }
-
-        messages.extend(request.messages.into_iter().map(|m| {
-            // OpenAI multimodal support is limited, convert to text for now
-            let content = match m.content {
-                MessageContent::Text(text) => text,
-                MessageContent::Blocks(blocks) => blocks
-                    .into_iter()
-                    .filter_map(|b| match b {
-                        ContentBlock::Text { text } => Some(text),
-                        ContentBlock::Image { .. } => Some("[Image attached - OpenAI vision API not fully supported yet]".to_string()),
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n"),
-            };
-            OpenAIMessage {
-                role: m.role,
-                content,
-            }
-        }));
+        messages.extend(request.messages.into_iter().map(Self::convert_to_openai_message));
 
         let api_request = OpenAIRequest {
-            model: model.clone(),
+            model,
             messages,
             max_tokens: request.max_tokens,
             temperature: request.temperature,
-            stream: false,
+            top_p: request.top_p,
+            stop: request.stop_sequences,
+            tools,
+            stream: true,
         };
 
         let response = self
-            .http_client
-            .post(format!("{}/v1/chat/completions", base_url))
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", api_key))
-            .json(&api_request)
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .send_with_retry(&request.config.retry_policy, || {
+                self.http_client
+                    .post(&endpoint.url)
+                    .header("Content-Type", "application/json")
+                    .header("Accept", "text/event-stream")
+                    .header(endpoint.auth_header, &endpoint.auth_value)
+                    .json(&api_request)
+            })
+            .await?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(format!("API error ({}): {}", status, text));
+        Ok(sse_stream(response, OpenAISseParser::default()))
+    }
+
+    /// Convert a ChatMessage to OpenAIMessage, sending images as data URL parts
+    fn convert_to_openai_message(m: ChatMessage) -> OpenAIMessage {
+        let content = match m.content {
+            MessageContent::Text(text) => OpenAIContent::Text(text),
+            MessageContent::Blocks(blocks) => OpenAIContent::Parts(
+                blocks
+                    .into_iter()
+                    .map(|b| match b {
+                        ContentBlock::Text { text } => OpenAIContentPart::Text { text },
+                        ContentBlock::Image { source } => OpenAIContentPart::ImageUrl {
+                            image_url: OpenAIImageUrl {
+                                url: format!("data:{};base64,{}", source.media_type, source.data),
+                            },
+                        },
+                    })
+                    .collect(),
+            ),
+        };
+        OpenAIMessage {
+            role: m.role,
+            content: Some(content),
+            tool_calls: None,
+            tool_call_id: None,
         }
+    }
+}
 
-        let api_response: OpenAIResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
+// ============ Estimation ============
 
-        let content = api_response
-            .choices
-            .first()
-            .and_then(|c| c.message.content.clone())
-            .unwrap_or_default();
+/// Tokens counted per image when estimating (about a 1000x1000 px image)
+pub const IMAGE_TOKEN_ESTIMATE: u64 = 1600;
 
-        let usage = api_response.usage.map(|u| TokenUsage {
-            input_tokens: u.prompt_tokens,
-            output_tokens: u.completion_tokens,
-        });
+/// Input size and cost of a request before it is sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Estimate {
+    pub input_tokens: u64,
+    pub est_cost_usd: f64,
+    /// False when the model isn't in the price table and a default price was used
+    pub pricing_known: bool,
+}
 
-        Ok(ChatResponse {
-            content,
-            model: api_response.model,
-            usage,
-        })
+impl ChatClient {
+    /// Estimate input tokens of the system prompt and messages, priced for the configured model
+    pub fn estimate(request: &ChatRequest) -> Estimate {
+        let provider = request.config.provider.as_str();
+        let model = match request.config.model.as_deref() {
+            Some(model) => crate::model::resolve(provider, model),
+            None => match provider {
+                "anthropic" => "claude-sonnet-4-20250514".to_string(),
+                "bedrock" => "us.anthropic.claude-sonnet-4-5-20250929-v1:0".to_string(),
+                _ => "gpt-4o".to_string(),
+            },
+        };
+
+        let system_tokens = request
+            .system_prompt
+            .as_deref()
+            .map(crate::skill::count_tokens)
+            .unwrap_or(0);
+        let message_tokens: u64 = request
+            .messages
+            .iter()
+            .map(|m| match &m.content {
+                MessageContent::Text(text) => crate::skill::count_tokens(text),
+                MessageContent::Blocks(blocks) => blocks
+                    .iter()
+                    .map(|b| match b {
+                        ContentBlock::Text { text } => crate::skill::count_tokens(text),
+                        ContentBlock::Image { .. } => IMAGE_TOKEN_ESTIMATE,
+                    })
+                    .sum(),
+            })
+            .sum();
+        let input_tokens = system_tokens + message_tokens;
+
+        let pricing = crate::model::pricing(provider, &model);
+        let price = pricing.unwrap_or(crate::model::FALLBACK_PRICING);
+        Estimate {
+            input_tokens,
+            est_cost_usd: input_tokens as f64 * price.input_per_mtok / 1_000_000.0,
+            pricing_known: pricing.is_some(),
+        }
     }
 }
 
@@ -1185,3 +2476,964 @@ impl Default for ChatClient {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Canned HTTP response served by `mock_server`
+    struct MockResponse {
+        status: u16,
+        headers: Vec<(&'static str, &'static str)>,
+        body: String,
+    }
+
+    impl MockResponse {
+        fn json(body: serde_json::Value) -> Self {
+            Self {
+                status: 200,
+                headers: Vec::new(),
+                body: body.to_string(),
+            }
+        }
+    }
+
+    /// Minimal HTTP server that replies with queued responses in order
+    /// Returns the base URL and the recorded request bodies
+    async fn mock_server(responses: Vec<MockResponse>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let recorded = bodies.clone();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+
+                // Read headers, then the body according to Content-Length
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let header_end = loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let headers = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+                let content_length = headers
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .and_then(|v| v.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buf[header_end..]).to_string());
+
+                let mut reply = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    response.status,
+                    response.body.len()
+                );
+                for (name, value) in &response.headers {
+                    reply.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                reply.push_str("\r\n");
+                reply.push_str(&response.body);
+                socket.write_all(reply.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+        });
+
+        (base_url, bodies)
+    }
+
+    fn test_request(provider: &str, base_url: String, workspace: Option<String>) -> ChatRequest {
+        ChatRequest {
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: MessageContent::Text("Remember that I like tea".to_string()),
+            }],
+            config: ApiConfig {
+                provider: provider.to_string(),
+                api_key: Some("test-key".to_string()),
+                base_url: Some(base_url),
+                model: None,
+                region: None,
+                aws_profile: None,
+                deployment: None,
+                api_version: None,
+                retry_policy: RetryPolicy {
+                    base_delay: Duration::from_millis(1),
+                    max_delay: Duration::from_millis(5),
+                    ..RetryPolicy::default()
+                },
+            },
+            system_prompt: None,
+            max_tokens: None,
+            temperature: None,
+            top_p: None,
+            stop_sequences: None,
+            workspace,
+            memories_dir: None,
+            enable_prompt_caching: false,
+            cache_history: false,
+            max_tool_iterations: None,
+            tools: None,
+            thinking: None,
+            cache: false,
+        }
+    }
+
+    const RECORDED_SSE: &str = "event: message_start\n\
+data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"model\":\"claude-sonnet-4-20250514\",\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n\
+event: content_block_start\n\
+data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n\
+event: ping\n\
+data: {\"type\":\"ping\"}\n\n\
+event: content_block_delta\n\
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n\
+event: content_block_delta\n\
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\", world\"}}\n\n\
+event: content_block_stop\n\
+data: {\"type\":\"content_block_stop\",\"index\":0}\n\n\
+event: content_block_start\n\
+data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"memory\",\"input\":{}}}\n\n\
+event: content_block_delta\n\
+data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"command\\\"\"}}\n\n\
+event: message_delta\n\
+data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":7}}\n\n\
+event: message_stop\n\
+data: {\"type\":\"message_stop\"}\n\n";
+
+    #[test]
+    fn test_sse_deltas_in_order() {
+        let mut parser = AnthropicSseParser::default();
+
+        // Split mid-event to make sure partial events are buffered
+        let bytes = RECORDED_SSE.as_bytes();
+        let (first, second) = bytes.split_at(bytes.len() / 2);
+        let mut chunks = parser.feed(first).unwrap();
+        chunks.extend(parser.feed(second).unwrap());
+
+        assert_eq!(chunks.len(), 4);
+        assert!(matches!(&chunks[0], StreamChunk::Text { text } if text == "Hello"));
+        assert!(matches!(&chunks[1], StreamChunk::Text { text } if text == ", world"));
+        assert!(matches!(
+            &chunks[2],
+            StreamChunk::ToolUseStart { id, name } if id == "toolu_1" && name == "memory"
+        ));
+        match &chunks[3] {
+            StreamChunk::Done { stop_reason, usage } => {
+                assert_eq!(stop_reason.as_deref(), Some("tool_use"));
+                let usage = usage.as_ref().unwrap();
+                assert_eq!(usage.input_tokens, 12);
+                assert_eq!(usage.output_tokens, 7);
+            }
+            other => panic!("expected Done, got {:?}", other),
+        }
+    }
+
+    const RECORDED_OPENAI_SSE: &str = "\
+data: {\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"\"}}]}\n\n\
+data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hel\"}}]}\n\n\
+data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"}}]}\n\n\
+data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"memory\",\"arguments\":\"\"}}]}}]}\n\n\
+data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"command\\\"\"}}]}}]}\n\n\
+data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\":\\\"view\\\"}\"}}]}}]}\n\n\
+data: {\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n\
+data: [DONE]\n\n";
+
+    #[test]
+    fn test_openai_sse_deltas_in_order() {
+        let mut parser = OpenAISseParser::default();
+
+        let bytes = RECORDED_OPENAI_SSE.as_bytes();
+        let (first, second) = bytes.split_at(bytes.len() / 3);
+        let mut chunks = parser.feed(first).unwrap();
+        chunks.extend(parser.feed(second).unwrap());
+
+        assert_eq!(chunks.len(), 6);
+        assert!(matches!(&chunks[0], StreamChunk::Text { text } if text == "Hel"));
+        assert!(matches!(&chunks[1], StreamChunk::Text { text } if text == "lo"));
+        assert!(matches!(
+            &chunks[2],
+            StreamChunk::ToolUseStart { id, name } if id == "call_1" && name == "memory"
+        ));
+        let arguments: String = chunks[3..5]
+            .iter()
+            .map(|c| match c {
+                StreamChunk::ToolInputDelta { id, partial_json } => {
+                    assert_eq!(id, "call_1");
+                    partial_json.as_str()
+                }
+                other => panic!("expected ToolInputDelta, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(arguments, r#"{"command":"view"}"#);
+        assert!(matches!(
+            &chunks[5],
+            StreamChunk::Done { stop_reason, .. } if stop_reason.as_deref() == Some("tool_calls")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_openai_stream_ends_at_done() {
+        use futures::StreamExt;
+
+        let (base_url, bodies) = mock_server(vec![MockResponse {
+            status: 200,
+            headers: vec![("Content-Type", "text/event-stream")],
+            // Anything after [DONE] must not be surfaced
+            body: format!("{}data: {{\"choices\":[{{\"delta\":{{\"content\":\"late\"}}}}]}}\n\n", RECORDED_OPENAI_SSE),
+        }])
+        .await;
+
+        let stream = ChatClient::new()
+            .send_openai_stream(test_request("custom", base_url, None))
+            .await
+            .unwrap();
+        let chunks: Vec<_> = stream.collect::<Vec<_>>().await.into_iter().map(|c| c.unwrap()).collect();
+        assert!(matches!(chunks.last(), Some(StreamChunk::Done { .. })));
+        assert!(!chunks.iter().any(|c| matches!(c, StreamChunk::Text { text } if text == "late")));
+
+        let body: serde_json::Value = serde_json::from_str(&bodies.lock().unwrap()[0]).unwrap();
+        assert_eq!(body["stream"], true);
+    }
+
+    #[test]
+    fn test_sse_error_event() {
+        let mut parser = AnthropicSseParser::default();
+        let body = "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n";
+        let err = parser.feed(body.as_bytes()).unwrap_err();
+        assert!(err.contains("Overloaded"));
+    }
+
+    #[tokio::test]
+    async fn test_openai_tool_calls_loop() {
+        let dir = tempdir().unwrap();
+        let (base_url, bodies) = mock_server(vec![
+            MockResponse::json(serde_json::json!({
+                "model": "gpt-4o",
+                "choices": [{
+                    "finish_reason": "tool_calls",
+                    "message": {
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {
+                                "name": "memory",
+                                "arguments": "{\"command\":\"create\",\"path\":\"prefs.md\",\"file_text\":\"likes tea\"}"
+                            }
+                        }]
+                    }
+                }],
+                "usage": { "prompt_tokens": 10, "completion_tokens": 5 }
+            })),
+            MockResponse::json(serde_json::json!({
+                "model": "gpt-4o",
+                "choices": [{
+                    "finish_reason": "stop",
+                    "message": { "content": "Saved." }
+                }],
+                "usage": { "prompt_tokens": 20, "completion_tokens": 2 }
+            })),
+        ])
+        .await;
+
+        let workspace = dir.path().to_string_lossy().to_string();
+        let response = ChatClient::new()
+            .send(test_request("openai", base_url, Some(workspace)))
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Saved.");
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.input_tokens, 30);
+        assert_eq!(usage.output_tokens, 7);
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(&bodies[0]).unwrap();
+        assert_eq!(first["tools"][0]["function"]["name"], "memory");
+
+        // Follow-up request carries the assistant tool call and its result
+        let second: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        let messages = second["messages"].as_array().unwrap();
+        assert_eq!(messages[1]["tool_calls"][0]["id"], "call_1");
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["tool_call_id"], "call_1");
+
+        let saved = MemoryTool::new(dir.path()).execute(crate::memory_tool::MemoryToolCommand::View {
+            path: "prefs.md".to_string(),
+            view_range: None,
+        });
+        assert!(saved.output.contains("likes tea"));
+    }
+
+    #[test]
+    fn test_estimate_multimodal_message() {
+        let mut request = test_request("anthropic", "http://unused".to_string(), None);
+        request.config.model = Some("haiku".to_string());
+        request.system_prompt = Some("Be brief.".to_string());
+        let image = || ContentBlock::Image {
+            source: ImageSource {
+                source_type: "base64".to_string(),
+                media_type: "image/png".to_string(),
+                data: "aGVsbG8=".repeat(1000),
+            },
+        };
+        request.messages = vec![ChatMessage {
+            role: "user".to_string(),
+            content: MessageContent::Blocks(vec![
+                ContentBlock::Text {
+                    text: "Compare these two screenshots".to_string(),
+                },
+                image(),
+                image(),
+            ]),
+        }];
+
+        let estimate = ChatClient::estimate(&request);
+        // Images count a fixed amount regardless of their encoded size
+        let expected = crate::skill::count_tokens("Be brief.")
+            + crate::skill::count_tokens("Compare these two screenshots")
+            + 2 * IMAGE_TOKEN_ESTIMATE;
+        assert_eq!(estimate.input_tokens, expected);
+        assert!(estimate.pricing_known);
+        assert!((estimate.est_cost_usd - expected as f64 / 1_000_000.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_estimate_unknown_model_uses_fallback_price() {
+        let mut request = test_request("custom", "http://unused".to_string(), None);
+        request.config.model = Some("llama3:70b".to_string());
+
+        let estimate = ChatClient::estimate(&request);
+        assert!(!estimate.pricing_known);
+        let expected = estimate.input_tokens as f64 * crate::model::FALLBACK_PRICING.input_per_mtok / 1_000_000.0;
+        assert!((estimate.est_cost_usd - expected).abs() < 1e-12);
+        assert!(estimate.input_tokens > 0);
+    }
+
+    #[test]
+    fn test_azure_endpoint_differs_from_openai() {
+        let mut config = test_request("openai", "https://proxy.example.com".to_string(), None).config;
+        config.model = Some("gpt-4o".to_string());
+        let openai = OpenAIEndpoint::from_config(&config).unwrap();
+        assert_eq!(openai.url, "https://proxy.example.com/v1/chat/completions");
+        assert_eq!(openai.auth_header, "Authorization");
+        assert_eq!(openai.auth_value, "Bearer test-key");
+
+        config.provider = "azure".to_string();
+        config.base_url = Some("https://res.openai.azure.com/".to_string());
+        let azure = OpenAIEndpoint::from_config(&config).unwrap();
+        // Deployment falls back to the model name
+        assert_eq!(
+            azure.url,
+            format!(
+                "https://res.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version={}",
+                DEFAULT_AZURE_API_VERSION
+            )
+        );
+        assert_eq!(azure.auth_header, "api-key");
+        assert_eq!(azure.auth_value, "test-key");
+
+        config.deployment = Some("prod-chat".to_string());
+        config.api_version = Some("2025-01-01-preview".to_string());
+        assert_eq!(
+            OpenAIEndpoint::from_config(&config).unwrap().url,
+            "https://res.openai.azure.com/openai/deployments/prod-chat/chat/completions?api-version=2025-01-01-preview"
+        );
+
+        config.deployment = None;
+        config.model = None;
+        assert!(OpenAIEndpoint::from_config(&config).unwrap_err().contains("Deployment"));
+    }
+
+    #[test]
+    fn test_openai_image_content_part() {
+        let message = ChatClient::convert_to_openai_message(ChatMessage {
+            role: "user".to_string(),
+            content: MessageContent::Blocks(vec![
+                ContentBlock::Text {
+                    text: "What is this?".to_string(),
+                },
+                ContentBlock::Image {
+                    source: ImageSource {
+                        source_type: "base64".to_string(),
+                        media_type: "image/png".to_string(),
+                        data: "aGVsbG8=".to_string(),
+                    },
+                },
+            ]),
+        });
+
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["content"][0]["type"], "text");
+        assert_eq!(json["content"][1]["type"], "image_url");
+        assert_eq!(json["content"][1]["image_url"]["url"], "data:image/png;base64,aGVsbG8=");
+    }
+
+    fn rate_limited() -> MockResponse {
+        MockResponse {
+            status: 429,
+            headers: vec![("Retry-After", "0")],
+            body: r#"{"type":"error","error":{"type":"rate_limit_error","message":"slow down"}}"#.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_transient_errors() {
+        let (base_url, bodies) = mock_server(vec![
+            rate_limited(),
+            rate_limited(),
+            MockResponse::json(serde_json::json!({
+                "model": "claude-sonnet-4-20250514",
+                "content": [{ "type": "text", "text": "Hi" }],
+                "stop_reason": "end_turn",
+                "usage": { "input_tokens": 3, "output_tokens": 1 }
+            })),
+        ])
+        .await;
+
+        let response = ChatClient::new()
+            .send(test_request("anthropic", base_url, None))
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Hi");
+        assert_eq!(bodies.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_retries() {
+        let (base_url, bodies) = mock_server(vec![
+            rate_limited(),
+            rate_limited(),
+            rate_limited(),
+            rate_limited(),
+        ])
+        .await;
+
+        let err = ChatClient::new()
+            .send(test_request("openai", base_url, None))
+            .await
+            .unwrap_err();
+
+        assert!(err.starts_with("API error (429"));
+        assert_eq!(bodies.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_retry_policy_delays() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry(529, 0));
+        assert!(!policy.should_retry(400, 0));
+        assert!(!policy.should_retry(429, 3));
+
+        // Retry-After takes precedence over computed backoff
+        let retry_after = parse_retry_after("2");
+        assert_eq!(policy.delay_for(0, retry_after), Duration::from_secs(2));
+
+        // A huge Retry-After is capped instead of stalling the request for a day
+        assert_eq!(policy.delay_for(0, parse_retry_after("86400")), policy.max_delay);
+
+        // Jittered backoff stays between half and the full capped delay
+        let delay = policy.delay_for(10, None);
+        assert!(delay >= policy.max_delay / 2 && delay <= policy.max_delay);
+        assert!(parse_retry_after("soon").is_none());
+    }
+
+    #[test]
+    fn test_cached_system_block_shape() {
+        let cached = serde_json::to_value(AnthropicSystem::new("Be brief".to_string(), true)).unwrap();
+        assert_eq!(
+            cached,
+            serde_json::json!([{
+                "type": "text",
+                "text": "Be brief",
+                "cache_control": { "type": "ephemeral" }
+            }])
+        );
+
+        let plain = serde_json::to_value(AnthropicSystem::new("Be brief".to_string(), false)).unwrap();
+        assert_eq!(plain, serde_json::json!("Be brief"));
+    }
+
+    fn history(roles: &[&str]) -> Vec<AnthropicMessage> {
+        roles
+            .iter()
+            .enumerate()
+            .map(|(i, role)| {
+                ChatClient::convert_to_anthropic_message(ChatMessage {
+                    role: role.to_string(),
+                    content: MessageContent::Text(format!("message {}", i)),
+                })
+            })
+            .collect()
+    }
+
+    fn breakpoints(messages: &[AnthropicMessage]) -> Vec<usize> {
+        let json = serde_json::to_value(messages).unwrap();
+        json.as_array()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m["content"].as_array().is_some_and(|blocks| {
+                blocks.iter().any(|b| b["cache_control"]["type"] == "ephemeral")
+            }))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn test_history_cache_breakpoints() {
+        // Turn ends are the assistant replies followed by a user message
+        let mut messages = history(&["user", "assistant", "user", "assistant", "user", "assistant"]);
+        apply_history_cache_breakpoints(&mut messages, false);
+        assert_eq!(breakpoints(&messages), vec![1, 3]);
+
+        let json = serde_json::to_value(&messages[1]).unwrap();
+        assert_eq!(json["content"][0]["text"], "message 1");
+        // Unmarked messages keep the plain string form
+        assert_eq!(serde_json::to_value(&messages[0]).unwrap()["content"], "message 0");
+
+        // Long history: newest turns win, system prompt keeps one of the four slots
+        let roles: Vec<&str> = (0..11).map(|i| if i % 2 == 0 { "user" } else { "assistant" }).collect();
+        let mut messages = history(&roles);
+        apply_history_cache_breakpoints(&mut messages, true);
+        assert_eq!(breakpoints(&messages), vec![5, 7, 9]);
+
+        let mut messages = history(&roles);
+        apply_history_cache_breakpoints(&mut messages, false);
+        assert_eq!(breakpoints(&messages), vec![3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn test_bedrock_error_classification() {
+        use aws_sdk_bedrockruntime::types::error::{
+            AccessDeniedException, ModelTimeoutException, ThrottlingException, ValidationException,
+        };
+
+        let classify = |err: ConverseError| {
+            BedrockError::from_converse_error(&SdkError::<ConverseError, ()>::service_error(err, ()))
+        };
+
+        let throttled = classify(ConverseError::ThrottlingException(
+            ThrottlingException::builder().message("Too many requests").build(),
+        ));
+        assert!(matches!(&throttled, BedrockError::Throttling { message } if message.contains("Too many requests")));
+        assert!(throttled.is_retryable());
+        assert!(throttled.to_string().starts_with("[throttling] "));
+
+        let denied = classify(ConverseError::AccessDeniedException(
+            AccessDeniedException::builder().message("No access").build(),
+        ));
+        assert_eq!(denied.kind(), "access_denied");
+        assert!(!denied.is_retryable());
+
+        let invalid = classify(ConverseError::ValidationException(
+            ValidationException::builder().message("Bad input").build(),
+        ));
+        assert_eq!(invalid.kind(), "validation_exception");
+
+        let timeout = classify(ConverseError::ModelTimeoutException(
+            ModelTimeoutException::builder().message("Slow").build(),
+        ));
+        assert_eq!(timeout.kind(), "other");
+        assert!(!timeout.is_retryable());
+
+        let json = serde_json::to_value(&denied).unwrap();
+        assert_eq!(json["kind"], "access_denied");
+        assert!(json["message"].as_str().unwrap().contains("No access"));
+
+        let blocked = BedrockError::from_stop_reason(
+            &BedrockStopReason::GuardrailIntervened,
+            "Sorry, I can't help with that.".to_string(),
+        );
+        assert_eq!(
+            blocked,
+            Some(BedrockError::GuardrailIntervened { message: "Sorry, I can't help with that.".to_string() })
+        );
+        assert_eq!(blocked.unwrap().kind(), "guardrail_intervened");
+        assert_eq!(BedrockError::from_stop_reason(&BedrockStopReason::EndTurn, String::new()), None);
+    }
+
+    #[test]
+    fn test_cache_usage_parsed() {
+        let usage: AnthropicUsage = serde_json::from_str(
+            r#"{"input_tokens":5,"output_tokens":2,"cache_creation_input_tokens":1200,"cache_read_input_tokens":0}"#,
+        )
+        .unwrap();
+        assert_eq!(usage.cache_creation_input_tokens, Some(1200));
+        assert_eq!(usage.cache_read_input_tokens, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_tool_iteration_cap_reported() {
+        let dir = tempdir().unwrap();
+        let tool_use = || {
+            MockResponse::json(serde_json::json!({
+                "model": "claude-sonnet-4-20250514",
+                "content": [{
+                    "type": "tool_use",
+                    "id": "toolu_1",
+                    "name": "memory",
+                    "input": { "command": "view", "path": "" }
+                }],
+                "stop_reason": "tool_use",
+                "usage": { "input_tokens": 1, "output_tokens": 1 }
+            }))
+        };
+        let (base_url, bodies) = mock_server(vec![tool_use(), tool_use(), tool_use()]).await;
+
+        let mut request = test_request(
+            "anthropic",
+            base_url,
+            Some(dir.path().to_string_lossy().to_string()),
+        );
+        request.max_tool_iterations = Some(2);
+        let response = ChatClient::new().send(request).await.unwrap();
+
+        assert_eq!(bodies.lock().unwrap().len(), 2);
+        assert_eq!(response.stop_reason.as_deref(), Some(STOP_REASON_MAX_ITERATIONS));
+    }
+
+    #[test]
+    fn test_tool_iteration_limit_clamped() {
+        let mut request = test_request("anthropic", String::new(), None);
+        assert_eq!(request.tool_iteration_limit(), DEFAULT_MAX_TOOL_ITERATIONS);
+        request.max_tool_iterations = Some(1000);
+        assert_eq!(request.tool_iteration_limit(), MAX_TOOL_ITERATIONS_CEILING);
+        request.max_tool_iterations = Some(0);
+        assert_eq!(request.tool_iteration_limit(), 1);
+    }
+
+    #[test]
+    fn test_sampling_params_anthropic() {
+        let request = AnthropicRequest {
+            model: "claude-sonnet-4-20250514".to_string(),
+            max_tokens: 100,
+            system: None,
+            messages: Vec::new(),
+            temperature: None,
+            top_p: Some(0.9),
+            stop_sequences: Some(vec!["</answer>".to_string()]),
+            tools: None,
+            thinking: None,
+            stream: false,
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert!((json["top_p"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+        assert_eq!(json["stop_sequences"], serde_json::json!(["</answer>"]));
+    }
+
+    #[test]
+    fn test_sampling_params_openai() {
+        let request = OpenAIRequest {
+            model: "gpt-4o".to_string(),
+            messages: Vec::new(),
+            max_tokens: None,
+            temperature: None,
+            top_p: Some(0.5),
+            stop: Some(vec!["END".to_string()]),
+            tools: None,
+            stream: false,
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["top_p"], 0.5);
+        assert_eq!(json["stop"], serde_json::json!(["END"]));
+        assert!(json.get("stop_sequences").is_none());
+    }
+
+    #[test]
+    fn test_sampling_params_bedrock() {
+        let mut request = test_request("bedrock", String::new(), None);
+        request.top_p = Some(0.5);
+        request.stop_sequences = Some(vec!["END".to_string()]);
+
+        let config = ChatClient::build_bedrock_inference_config(&request);
+        assert_eq!(config.top_p(), Some(0.5));
+        assert_eq!(config.stop_sequences(), ["END".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_top_p_out_of_range_rejected() {
+        let mut request = test_request("anthropic", "http://127.0.0.1:9".to_string(), None);
+        request.top_p = Some(1.5);
+        let err = ChatClient::new().send(request).await.unwrap_err();
+        assert!(err.contains("top_p"));
+    }
+
+    #[test]
+    fn test_bedrock_view_range_parsed() {
+        use aws_smithy_types::Number;
+
+        let input = Document::Object(
+            [
+                ("command".to_string(), Document::String("view".to_string())),
+                ("path".to_string(), Document::String("notes.md".to_string())),
+                (
+                    "view_range".to_string(),
+                    Document::Array(vec![
+                        Document::Number(Number::PosInt(2)),
+                        Document::Number(Number::Float(5.0)),
+                    ]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        match ChatClient::parse_memory_command(&document_to_json(&input)).unwrap() {
+            crate::memory_tool::MemoryToolCommand::View { path, view_range } => {
+                assert_eq!(path, "notes.md");
+                assert_eq!(view_range, Some((2, 5)));
+            }
+            other => panic!("expected View, got {:?}", other),
+        }
+    }
+
+    /// Registry with an `echo` tool that records every input it receives
+    fn echo_registry() -> (ToolRegistry, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let mut registry = ToolRegistry::new();
+        registry.register(
+            "echo",
+            "Echo the text back",
+            serde_json::json!({ "type": "object", "properties": { "text": { "type": "string" } } }),
+            move |input| {
+                recorded.lock().unwrap().push(input.clone());
+                Ok(format!("echo: {}", input["text"].as_str().unwrap_or_default()))
+            },
+        );
+        (registry, calls)
+    }
+
+    #[tokio::test]
+    async fn test_registered_tool_dispatched_on_tool_use() {
+        let (base_url, bodies) = mock_server(vec![
+            MockResponse::json(serde_json::json!({
+                "model": "claude-sonnet-4-20250514",
+                "content": [{
+                    "type": "tool_use",
+                    "id": "toolu_1",
+                    "name": "echo",
+                    "input": { "text": "hi" }
+                }],
+                "stop_reason": "tool_use",
+                "usage": { "input_tokens": 1, "output_tokens": 1 }
+            })),
+            MockResponse::json(serde_json::json!({
+                "model": "claude-sonnet-4-20250514",
+                "content": [{ "type": "text", "text": "Done." }],
+                "stop_reason": "end_turn",
+                "usage": { "input_tokens": 1, "output_tokens": 1 }
+            })),
+        ])
+        .await;
+
+        let (registry, calls) = echo_registry();
+        let response = ChatClient::new()
+            .with_tools(registry)
+            .send(test_request("anthropic", base_url, None))
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Done.");
+        assert_eq!(*calls.lock().unwrap(), vec![serde_json::json!({ "text": "hi" })]);
+
+        let bodies = bodies.lock().unwrap();
+        let first: serde_json::Value = serde_json::from_str(&bodies[0]).unwrap();
+        assert_eq!(first["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(first["tools"][0]["name"], "echo");
+
+        let second: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        let result = &second["messages"][2]["content"][0];
+        assert_eq!(result["tool_use_id"], "toolu_1");
+        assert_eq!(result["content"], "echo: hi");
+    }
+
+    #[test]
+    fn test_tool_registry_respects_enabled_tools() {
+        let dir = tempdir().unwrap();
+        let (registry, _) = echo_registry();
+        let client = ChatClient::new().with_tools(registry);
+        let mut request = test_request(
+            "anthropic",
+            String::new(),
+            Some(dir.path().to_string_lossy().to_string()),
+        );
+
+        let names = |request: &ChatRequest| -> Vec<String> {
+            client.tool_registry(request).unwrap().tools.iter().map(|(d, _)| d.name.clone()).collect()
+        };
+        assert_eq!(names(&request), vec!["memory", "echo"]);
+
+        request.tools = Some(vec!["echo".to_string()]);
+        assert_eq!(names(&request), vec!["echo"]);
+        let registry = client.tool_registry(&request).unwrap();
+        assert_eq!(registry.dispatch("memory", serde_json::json!({})), "Unknown tool: memory");
+
+        request.tools = Some(Vec::new());
+        assert!(client.tool_registry(&request).unwrap().anthropic_tools().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_thinking_anthropic_request_and_response() {
+        let (base_url, bodies) = mock_server(vec![MockResponse::json(serde_json::json!({
+            "model": "claude-sonnet-4-20250514",
+            "content": [
+                { "type": "thinking", "thinking": "The user likes tea.", "signature": "sig" },
+                { "type": "text", "text": "Noted." }
+            ],
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 1, "output_tokens": 1 }
+        }))])
+        .await;
+
+        let mut request = test_request("anthropic", base_url, None);
+        request.max_tokens = Some(4096);
+        request.thinking = Some(ThinkingConfig { budget_tokens: 2048 });
+        let response = ChatClient::new().send(request).await.unwrap();
+
+        assert_eq!(response.content, "Noted.");
+        assert_eq!(response.thinking.as_deref(), Some("The user likes tea."));
+
+        let body: serde_json::Value = serde_json::from_str(&bodies.lock().unwrap()[0]).unwrap();
+        assert_eq!(body["thinking"], serde_json::json!({ "type": "enabled", "budget_tokens": 2048 }));
+    }
+
+    #[test]
+    fn test_thinking_bedrock_additional_fields() {
+        let mut request = test_request("bedrock", String::new(), None);
+        assert!(ChatClient::build_bedrock_additional_fields(&request).is_none());
+
+        request.thinking = Some(ThinkingConfig { budget_tokens: 2048 });
+        let fields = ChatClient::build_bedrock_additional_fields(&request).unwrap();
+        assert_eq!(
+            document_to_json(&fields),
+            serde_json::json!({ "thinking": { "type": "enabled", "budget_tokens": 2048 } })
+        );
+    }
+
+    #[test]
+    fn test_thinking_budget_validated() {
+        let mut request = test_request("anthropic", String::new(), None);
+        request.thinking = Some(ThinkingConfig { budget_tokens: 512 });
+        assert!(request.validate().unwrap_err().contains("at least 1024"));
+
+        request.thinking = Some(ThinkingConfig { budget_tokens: 4096 });
+        request.max_tokens = Some(4096);
+        assert!(request.validate().unwrap_err().contains("less than max_tokens"));
+
+        request.max_tokens = Some(8192);
+        assert!(request.validate().is_ok());
+    }
+
+    #[test]
+    fn test_thinking_budget_checked_against_default_max_tokens() {
+        let mut request = test_request("anthropic", String::new(), None);
+        request.max_tokens = None;
+        request.thinking = Some(ThinkingConfig { budget_tokens: DEFAULT_MAX_TOKENS });
+        assert!(request.validate().unwrap_err().contains("less than max_tokens (4096)"));
+
+        request.thinking = Some(ThinkingConfig { budget_tokens: 2048 });
+        assert!(request.validate().is_ok());
+        let inference = ChatClient::build_bedrock_inference_config(&request);
+        assert_eq!(inference.max_tokens(), Some(DEFAULT_MAX_TOKENS as i32));
+    }
+
+    #[test]
+    fn test_thinking_rejects_custom_sampling() {
+        let mut request = test_request("anthropic", String::new(), None);
+        request.thinking = Some(ThinkingConfig { budget_tokens: 2048 });
+
+        request.temperature = Some(0.5);
+        assert!(request.validate().unwrap_err().contains("temperature must be 1"));
+        request.temperature = Some(1.0);
+        assert!(request.validate().is_ok());
+
+        request.top_p = Some(0.9);
+        assert!(request.validate().unwrap_err().contains("top_p cannot be set"));
+    }
+
+    fn anthropic_reply(text: &str) -> MockResponse {
+        MockResponse::json(serde_json::json!({
+            "model": "claude-sonnet-4-20250514",
+            "content": [{ "type": "text", "text": text }],
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 3, "output_tokens": 1 }
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_skips_network_for_identical_request() {
+        let dir = tempdir().unwrap();
+        let (base_url, bodies) = mock_server(vec![anthropic_reply("First"), anthropic_reply("Second")]).await;
+        let client = ChatClient::new().with_response_cache(ResponseCache::new(
+            dir.path().to_path_buf(),
+            Duration::from_secs(60),
+        ));
+        let request = ChatRequest {
+            cache: true,
+            ..test_request("anthropic", base_url, None)
+        };
+
+        assert_eq!(client.send(request.clone()).await.unwrap().content, "First");
+        assert_eq!(client.send(request.clone()).await.unwrap().content, "First");
+        assert_eq!(bodies.lock().unwrap().len(), 1);
+
+        // A different prompt or an opted-out request goes to the network
+        let mut changed = request.clone();
+        changed.system_prompt = Some("Be brief".to_string());
+        assert_eq!(client.send(changed).await.unwrap().content, "Second");
+        assert_eq!(bodies.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_response_cache_key_includes_provider_settings() {
+        let request = test_request("bedrock", String::new(), None);
+        let key = ResponseCache::key(&request);
+        assert_eq!(ResponseCache::key(&request.clone()), key);
+
+        let variants: [fn(&mut ApiConfig); 3] = [
+            |c| c.region = Some("eu-west-1".to_string()),
+            |c| c.aws_profile = Some("work".to_string()),
+            |c| c.api_version = Some("2024-10-21".to_string()),
+        ];
+        for change in variants {
+            let mut changed = request.clone();
+            change(&mut changed.config);
+            assert_ne!(ResponseCache::key(&changed), key);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_expires_after_ttl() {
+        let dir = tempdir().unwrap();
+        let (base_url, bodies) = mock_server(vec![anthropic_reply("First"), anthropic_reply("Second")]).await;
+        let client = ChatClient::new()
+            .with_response_cache(ResponseCache::new(dir.path().to_path_buf(), Duration::ZERO));
+        let request = ChatRequest {
+            cache: true,
+            ..test_request("anthropic", base_url, None)
+        };
+
+        client.send(request.clone()).await.unwrap();
+        assert_eq!(client.send(request).await.unwrap().content, "Second");
+        assert_eq!(bodies.lock().unwrap().len(), 2);
+    }
+}
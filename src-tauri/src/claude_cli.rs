@@ -0,0 +1,186 @@
+//! Locating the Claude Code CLI and other executables
+//!
+//! The installer and npm put `claude` in places that are often missing from
+//! the PATH a GUI app inherits, so search those explicitly.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Official install command, suggested when the CLI can't be found
+pub const INSTALL_COMMAND: &str = "curl -fsSL https://claude.ai/install.sh | bash";
+
+const CLI_NAME: &str = "claude";
+
+/// Extensions tried for a bare command name when PATHEXT is unset
+#[cfg(windows)]
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD";
+
+/// Directories searched for the CLI, in order:
+/// PATH, `~/.claude/bin`, `~/.claude/local`, then common npm global prefixes
+pub fn candidate_dirs(path_var: Option<&OsStr>, home: Option<&Path>) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = path_var
+        .map(|p| std::env::split_paths(p).collect())
+        .unwrap_or_default();
+
+    if let Some(home) = home {
+        dirs.push(home.join(".claude").join("bin"));
+        dirs.push(home.join(".claude").join("local"));
+        dirs.push(home.join(".npm-global").join("bin"));
+        dirs.push(home.join(".local").join("bin"));
+    }
+    dirs.push(PathBuf::from("/usr/local/bin"));
+    dirs.push(PathBuf::from("/opt/homebrew/bin"));
+
+    let mut seen = std::collections::HashSet::new();
+    dirs.retain(|d| !d.as_os_str().is_empty() && seen.insert(d.clone()));
+    dirs
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// File names a command may have on disk
+/// On Windows a name without an extension is tried with each PATHEXT extension
+fn executable_names(name: &str) -> Vec<String> {
+    #[cfg(windows)]
+    {
+        if Path::new(name).extension().is_some() {
+            return vec![name.to_string()];
+        }
+        let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| DEFAULT_PATHEXT.to_string());
+        pathext
+            .split(';')
+            .filter(|ext| !ext.is_empty())
+            .map(|ext| format!("{}{}", name, ext.to_lowercase()))
+            .collect()
+    }
+    #[cfg(not(windows))]
+    {
+        vec![name.to_string()]
+    }
+}
+
+/// Find the executable `name` in the first of `dirs` that has it
+/// A name containing a path separator is checked as given
+pub fn find_executable_in(name: &str, dirs: &[PathBuf]) -> Option<PathBuf> {
+    if Path::new(name).components().count() > 1 {
+        let path = PathBuf::from(name);
+        return is_executable(&path).then_some(path);
+    }
+    let names = executable_names(name);
+    dirs.iter()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Resolve a command against the current PATH, like `which`
+pub fn find_on_path(name: &str) -> Option<PathBuf> {
+    let dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|p| std::env::split_paths(&p).collect())
+        .unwrap_or_default();
+    find_executable_in(name, &dirs)
+}
+
+/// Find the CLI in the given search locations
+pub fn locate_cli_in(path_var: Option<&OsStr>, home: Option<&Path>) -> Option<PathBuf> {
+    find_executable_in(CLI_NAME, &candidate_dirs(path_var, home))
+}
+
+/// Find the CLI using the current PATH and home directory
+pub fn locate_cli() -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH");
+    locate_cli_in(path_var.as_deref(), dirs::home_dir().as_deref())
+}
+
+/// Error for a missing CLI, with the install hint
+pub fn not_found_error() -> String {
+    format!(
+        "Claude Code CLI not found. Install it with: {}",
+        INSTALL_COMMAND
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn fake_cli(dir: &Path) -> PathBuf {
+        fake_executable(dir, CLI_NAME)
+    }
+
+    fn fake_executable(dir: &Path, name: &str) -> PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        let path = dir.join(executable_names(name).remove(0));
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_locate_cli_search_order() {
+        let root = tempdir().unwrap();
+        let home = root.path().join("home");
+        let on_path = root.path().join("bin");
+        std::fs::create_dir_all(&on_path).unwrap();
+        let path_var = std::env::join_paths([&on_path]).unwrap();
+
+        // npm global only
+        let npm = fake_cli(&home.join(".npm-global").join("bin"));
+        assert_eq!(locate_cli_in(Some(&path_var), Some(&home)), Some(npm));
+
+        // ~/.claude/bin wins over npm global
+        let installer = fake_cli(&home.join(".claude").join("bin"));
+        assert_eq!(locate_cli_in(Some(&path_var), Some(&home)), Some(installer));
+
+        // PATH wins over everything
+        let first = fake_cli(&on_path);
+        assert_eq!(locate_cli_in(Some(&path_var), Some(&home)), Some(first));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_locate_cli_skips_non_executable() {
+        let root = tempdir().unwrap();
+        let on_path = root.path().join("bin");
+        std::fs::create_dir_all(&on_path).unwrap();
+        std::fs::write(on_path.join("claude"), "not executable").unwrap();
+        let path_var = std::env::join_paths([&on_path]).unwrap();
+
+        let home = root.path().join("home");
+        let installer = fake_cli(&home.join(".claude").join("bin"));
+        assert_eq!(locate_cli_in(Some(&path_var), Some(&home)), Some(installer));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_executable_in_dirs_and_paths() {
+        let root = tempdir().unwrap();
+        let (first, second) = (root.path().join("a"), root.path().join("b"));
+        let tool = fake_executable(&second, "uvx");
+
+        let dirs = vec![first, second];
+        assert_eq!(find_executable_in("uvx", &dirs), Some(tool.clone()));
+        assert_eq!(find_executable_in("npx", &dirs), None);
+
+        // Paths bypass the directory search
+        let tool_path = tool.to_string_lossy().to_string();
+        assert_eq!(find_executable_in(&tool_path, &[]), Some(tool));
+        assert_eq!(find_executable_in("/nonexistent/uvx", &dirs), None);
+    }
+}
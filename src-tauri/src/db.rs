@@ -2,10 +2,11 @@
 //!
 //! Schema designed for future sqlite-vec extension support.
 
-use rusqlite::{Connection, Result, params};
+use crate::migrations::{self, Migration};
+use crate::sqlite_pool::SqlitePool;
+use rusqlite::{Connection, OptionalExtension, Result, params};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use std::sync::Mutex;
 
 // ============ Types ============
 
@@ -20,6 +21,12 @@ pub struct DbSession {
     pub is_flagged: Option<bool>,          // 是否标记
     pub status: Option<String>,            // 状态: todo, in-progress, needs-review, done, cancelled
     pub has_unread: Option<bool>,          // 是否有未读消息
+    /// Set when the user renamed the session; auto-titling leaves it alone
+    #[serde(default)]
+    pub title_edited: Option<bool>,
+    /// Free-form labels; changed only through `set_session_tags`
+    #[serde(default)]
+    pub tags: Vec<String>,
     // pub summary_embedding: Option<Vec<f32>>, // 未来 sqlite-vec 扩展
 }
 
@@ -31,9 +38,42 @@ pub struct DbMessage {
     pub content: String,
     pub timestamp: String,
     pub metadata: Option<String>, // JSON: tool_calls, steps, cost, etc.
+    /// Previous message on this branch (None for the first message)
+    #[serde(default)]
+    pub parent_message_id: Option<String>,
     // pub embedding: Option<Vec<f32>>, // 未来 sqlite-vec 扩展
 }
 
+/// Message search hit with relevance score and highlighted excerpt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageSearchResult {
+    #[serde(flatten)]
+    pub message: DbMessage,
+    /// Relevance score (higher is better), derived from FTS5 bm25
+    pub score: f64,
+    /// Excerpt with matched terms wrapped in `<mark>`
+    pub snippet: String,
+}
+
+/// One page of a cursor-paginated listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Id to pass as the same cursor to fetch the next page, None on the last page
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Build a page from up to `limit + 1` rows fetched in paging order
+    /// The extra row only signals that another page exists
+    pub fn from_rows(mut rows: Vec<T>, limit: u32, id: impl Fn(&T) -> String) -> Self {
+        let has_more = rows.len() > limit as usize;
+        rows.truncate(limit as usize);
+        let next_cursor = if has_more { rows.last().map(id) } else { None };
+        Self { items: rows, next_cursor }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DbToolExecution {
     pub id: String,
@@ -46,27 +86,147 @@ pub struct DbToolExecution {
     pub status: String,         // "running" | "completed" | "error"
 }
 
+/// Output format for `ChatDatabase::export_session`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+/// JSON transcript of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTranscript {
+    pub session: DbSession,
+    pub messages: Vec<TranscriptMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptMessage {
+    #[serde(flatten)]
+    pub message: DbMessage,
+    pub tool_executions: Vec<DbToolExecution>,
+}
+
+/// Fenced code block whose fence is longer than any backtick run in `body`
+fn fenced(body: &str, lang: &str) -> String {
+    let longest = body
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}\n", fence, lang, body.trim_end(), fence)
+}
+
+/// Pretty-print JSON strings, passing anything else through
+fn pretty_json(raw: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(raw)
+        .and_then(|v| serde_json::to_string_pretty(&v))
+        .unwrap_or_else(|_| raw.to_string())
+}
+
+impl SessionTranscript {
+    /// Render as Markdown: metadata header, then role-prefixed turns
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.session.title);
+        out.push_str(&format!("- Created: {}\n", self.session.created_at));
+        out.push_str(&format!("- Updated: {}\n", self.session.updated_at));
+        if let Some(workspace) = &self.session.workspace_path {
+            out.push_str(&format!("- Workspace: {}\n", workspace));
+        }
+
+        for entry in &self.messages {
+            let message = &entry.message;
+            let mut role = message.role.clone();
+            if let Some(first) = role.get_mut(0..1) {
+                first.make_ascii_uppercase();
+            }
+            out.push_str(&format!("\n---\n\n## {}\n\n_{}_\n\n", role, message.timestamp));
+            out.push_str(message.content.trim_end());
+            out.push('\n');
+
+            for exec in &entry.tool_executions {
+                out.push_str(&format!("\n**Tool: {}** ({})\n\n", exec.tool_name, exec.status));
+                out.push_str(&fenced(&pretty_json(&exec.tool_input), "json"));
+                if let Some(output) = &exec.tool_output {
+                    out.push_str("\nOutput:\n\n");
+                    out.push_str(&fenced(&pretty_json(output), ""));
+                }
+            }
+        }
+        out
+    }
+}
+
 // ============ Database ============
 
+/// Workspaces kept in the recently used list
+pub const MAX_RECENT_WORKSPACES: usize = 10;
+
+/// Longest parent chain `get_branch` follows, guarding against cyclic parent links
+const MAX_BRANCH_DEPTH: u32 = 10_000;
+
+/// `path(id, depth)` from leaf `?2` up through its parents in session `?1`, at most `?3` deep
+const BRANCH_PATH_CTE: &str = "WITH RECURSIVE path(id, depth) AS (
+         SELECT id, 0 FROM messages WHERE id = ?2 AND session_id = ?1
+         UNION ALL
+         SELECT m.parent_message_id, path.depth + 1
+         FROM messages m JOIN path ON m.id = path.id
+         WHERE m.session_id = ?1 AND m.parent_message_id IS NOT NULL AND path.depth + 1 < ?3
+     )";
+
+/// Schema steps in version order; never edit a released step, append a new one
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "sessions, messages, tool executions and recent workspaces",
+        apply: ChatDatabase::create_base_schema,
+    },
+    Migration {
+        version: 2,
+        description: "sessions.title_edited",
+        apply: |conn| ChatDatabase::add_column_if_missing(conn, "sessions", "title_edited", "INTEGER DEFAULT 0"),
+    },
+    Migration {
+        version: 3,
+        description: "sessions.tags",
+        apply: |conn| ChatDatabase::add_column_if_missing(conn, "sessions", "tags", "TEXT DEFAULT '[]'"),
+    },
+    Migration {
+        version: 4,
+        description: "messages.parent_message_id",
+        apply: ChatDatabase::migrate_message_parents,
+    },
+    Migration {
+        version: 5,
+        description: "message full-text search",
+        apply: ChatDatabase::init_message_search,
+    },
+];
+
 pub struct ChatDatabase {
-    conn: Mutex<Connection>,
+    pool: SqlitePool,
 }
 
 impl ChatDatabase {
     /// Open or create database at given path
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let conn = Connection::open(path)?;
         let db = Self {
-            conn: Mutex::new(conn),
+            pool: SqlitePool::open(path.as_ref())?,
         };
-        db.init_schema()?;
+        db.migrate()?;
         Ok(db)
     }
 
-    /// Initialize database schema
-    fn init_schema(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+    /// Apply pending schema migrations, returning the resulting schema version
+    pub fn migrate(&self) -> Result<u32> {
+        let mut conn = self.pool.writer();
+        migrations::run(&mut conn, MIGRATIONS)
+    }
 
+    /// Tables as first released; idempotent so unversioned installs can run it
+    fn create_base_schema(conn: &Connection) -> Result<()> {
         conn.execute_batch(
             r#"
             -- Sessions table
@@ -98,6 +258,16 @@ impl ChatDatabase {
                 -- embedding BLOB  -- 未来 sqlite-vec: F32_BLOB
             );
 
+            -- Session tags, one row per tag for indexed filtering
+            CREATE TABLE IF NOT EXISTS session_tags (
+                session_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (session_id, tag)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_session_tags_tag
+                ON session_tags(tag);
+
             -- Index for session messages (ordered by time)
             CREATE INDEX IF NOT EXISTS idx_messages_session
                 ON messages(session_id, timestamp);
@@ -118,27 +288,109 @@ impl ChatDatabase {
             CREATE INDEX IF NOT EXISTS idx_tool_executions_message
                 ON tool_executions(message_id);
 
-            -- Future: Full-text search (FTS5)
-            -- CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts
-            --     USING fts5(content, content=messages, content_rowid=rowid);
+            -- Recently used workspaces, highest last_used first
+            CREATE TABLE IF NOT EXISTS recent_workspaces (
+                path TEXT PRIMARY KEY,
+                last_used INTEGER NOT NULL
+            );
 
             -- Future: Vector search (sqlite-vec)
             -- CREATE VIRTUAL TABLE IF NOT EXISTS messages_vec
             --     USING vec0(embedding float[1536]);
             "#,
+        )
+    }
+
+    /// Add a column to an existing table created by an older version
+    fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+        if !Self::has_column(conn, table, column)? {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+        }
+        Ok(())
+    }
+
+    fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|r| r.ok())
+            .any(|name| name == column);
+        Ok(exists)
+    }
+
+    /// Full-text index over message content, kept in sync by triggers
+    fn init_message_search(conn: &Connection) -> Result<()> {
+        let exists: bool = conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'messages_fts'",
+                [],
+                |_| Ok(true),
+            )
+            .optional()?
+            .unwrap_or(false);
+
+        conn.execute_batch(
+            r#"
+            CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+                content,
+                content='messages',
+                content_rowid='rowid'
+            );
+
+            CREATE TRIGGER IF NOT EXISTS messages_ai AFTER INSERT ON messages BEGIN
+                INSERT INTO messages_fts(rowid, content) VALUES (NEW.rowid, NEW.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS messages_ad AFTER DELETE ON messages BEGIN
+                INSERT INTO messages_fts(messages_fts, rowid, content) VALUES('delete', OLD.rowid, OLD.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS messages_au AFTER UPDATE OF content ON messages BEGIN
+                INSERT INTO messages_fts(messages_fts, rowid, content) VALUES('delete', OLD.rowid, OLD.content);
+                INSERT INTO messages_fts(rowid, content) VALUES (NEW.rowid, NEW.content);
+            END;
+            "#,
         )?;
 
+        // Index messages stored before search existed
+        if !exists {
+            conn.execute("INSERT INTO messages_fts(messages_fts) VALUES('rebuild')", [])?;
+        }
         Ok(())
     }
 
+    /// Add `messages.parent_message_id`, linking existing messages in timestamp order
+    fn migrate_message_parents(conn: &Connection) -> Result<()> {
+        if Self::has_column(conn, "messages", "parent_message_id")? {
+            return Ok(());
+        }
+
+        conn.execute_batch(
+            r#"
+            ALTER TABLE messages ADD COLUMN parent_message_id TEXT;
+
+            UPDATE messages SET parent_message_id = (
+                SELECT prev.id FROM messages prev
+                WHERE prev.session_id = messages.session_id
+                  AND (prev.timestamp < messages.timestamp
+                       OR (prev.timestamp = messages.timestamp AND prev.rowid < messages.rowid))
+                ORDER BY prev.timestamp DESC, prev.rowid DESC LIMIT 1
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_messages_parent
+                ON messages(parent_message_id);
+            "#,
+        )
+    }
+
     // ============ Session CRUD ============
 
     /// Create a new session
     pub fn create_session(&self, session: &DbSession) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
-            "INSERT INTO sessions (id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            "INSERT INTO sessions (id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread, title_edited, tags)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, '[]')",
             params![
                 session.id,
                 session.workspace_path,
@@ -149,34 +401,42 @@ impl ChatDatabase {
                 session.is_flagged.unwrap_or(false) as i32,
                 session.status.clone().unwrap_or_else(|| "todo".to_string()),
                 session.has_unread.unwrap_or(false) as i32,
+                session.title_edited.unwrap_or(false) as i32,
             ],
         )?;
-        Ok(())
+        Self::write_tags(&conn, &session.id, &session.tags)
+    }
+
+    fn row_to_session(row: &rusqlite::Row) -> Result<DbSession> {
+        let is_flagged: i32 = row.get::<_, Option<i32>>(6)?.unwrap_or(0);
+        let has_unread: i32 = row.get::<_, Option<i32>>(8)?.unwrap_or(0);
+        let tags: Option<String> = row.get(10)?;
+        Ok(DbSession {
+            id: row.get(0)?,
+            workspace_path: row.get(1)?,
+            title: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+            summary: row.get(5)?,
+            is_flagged: Some(is_flagged != 0),
+            status: row.get(7)?,
+            has_unread: Some(has_unread != 0),
+            title_edited: Some(row.get::<_, Option<i32>>(9)?.unwrap_or(0) != 0),
+            tags: tags.and_then(|t| serde_json::from_str(&t).ok()).unwrap_or_default(),
+        })
     }
 
     /// Get session by ID
     pub fn get_session(&self, id: &str) -> Result<Option<DbSession>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let mut stmt = conn.prepare(
-            "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread
+            "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread, title_edited, tags
              FROM sessions WHERE id = ?1"
         )?;
 
         let mut rows = stmt.query(params![id])?;
         if let Some(row) = rows.next()? {
-            let is_flagged: i32 = row.get::<_, Option<i32>>(6)?.unwrap_or(0);
-            let has_unread: i32 = row.get::<_, Option<i32>>(8)?.unwrap_or(0);
-            Ok(Some(DbSession {
-                id: row.get(0)?,
-                workspace_path: row.get(1)?,
-                title: row.get(2)?,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
-                summary: row.get(5)?,
-                is_flagged: Some(is_flagged != 0),
-                status: row.get(7)?,
-                has_unread: Some(has_unread != 0),
-            }))
+            Ok(Some(Self::row_to_session(row)?))
         } else {
             Ok(None)
         }
@@ -184,27 +444,13 @@ impl ChatDatabase {
 
     /// Get all sessions for a workspace (ordered by updated_at DESC)
     pub fn get_sessions_by_workspace(&self, workspace_path: Option<&str>) -> Result<Vec<DbSession>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
 
-        let map_row = |row: &rusqlite::Row| -> rusqlite::Result<DbSession> {
-            let is_flagged: i32 = row.get::<_, Option<i32>>(6)?.unwrap_or(0);
-            let has_unread: i32 = row.get::<_, Option<i32>>(8)?.unwrap_or(0);
-            Ok(DbSession {
-                id: row.get(0)?,
-                workspace_path: row.get(1)?,
-                title: row.get(2)?,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
-                summary: row.get(5)?,
-                is_flagged: Some(is_flagged != 0),
-                status: row.get(7)?,
-                has_unread: Some(has_unread != 0),
-            })
-        };
+        let map_row = Self::row_to_session;
 
         if let Some(path) = workspace_path {
             let mut stmt = conn.prepare(
-                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread
+                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread, title_edited, tags
                  FROM sessions WHERE workspace_path = ?1
                  ORDER BY updated_at DESC"
             )?;
@@ -212,7 +458,7 @@ impl ChatDatabase {
             rows.collect()
         } else {
             let mut stmt = conn.prepare(
-                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread
+                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread, title_edited, tags
                  FROM sessions WHERE workspace_path IS NULL
                  ORDER BY updated_at DESC"
             )?;
@@ -221,11 +467,12 @@ impl ChatDatabase {
         }
     }
 
-    /// Update session
+    /// Update session (tags are left as stored; changing the title marks it as user-edited)
     pub fn update_session(&self, session: &DbSession) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
-            "UPDATE sessions SET title = ?2, updated_at = ?3, summary = ?4, is_flagged = ?5, status = ?6, has_unread = ?7
+            "UPDATE sessions SET title = ?2, updated_at = ?3, summary = ?4, is_flagged = ?5, status = ?6, has_unread = ?7,
+                 title_edited = CASE WHEN title IS NOT ?2 THEN 1 ELSE COALESCE(?8, title_edited) END
              WHERE id = ?1",
             params![
                 session.id,
@@ -235,14 +482,84 @@ impl ChatDatabase {
                 session.is_flagged.unwrap_or(false) as i32,
                 session.status.clone().unwrap_or_else(|| "todo".to_string()),
                 session.has_unread.unwrap_or(false) as i32,
+                session.title_edited.map(|edited| edited as i32),
             ],
         )?;
         Ok(())
     }
 
+    /// Replace a session's tags (trimmed, empty and duplicate tags dropped)
+    pub fn set_session_tags(&self, id: &str, tags: &[String]) -> Result<Vec<String>> {
+        let mut conn = self.pool.writer();
+        let mut normalized: Vec<String> = Vec::new();
+        for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            if !normalized.iter().any(|t| t == tag) {
+                normalized.push(tag.to_string());
+            }
+        }
+
+        let tx = conn.transaction()?;
+        Self::write_tags(&tx, id, &normalized)?;
+        tx.commit()?;
+        Ok(normalized)
+    }
+
+    /// Store tags on the session row and in the `session_tags` lookup table
+    fn write_tags(conn: &Connection, id: &str, tags: &[String]) -> Result<()> {
+        let tags_json = serde_json::to_string(tags).unwrap_or_else(|_| "[]".to_string());
+        conn.execute("UPDATE sessions SET tags = ?2 WHERE id = ?1", params![id, tags_json])?;
+        conn.execute("DELETE FROM session_tags WHERE session_id = ?1", params![id])?;
+        for tag in tags {
+            conn.execute(
+                "INSERT OR IGNORE INTO session_tags (session_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Get sessions with a tag for a workspace
+    pub fn get_sessions_by_tag(&self, workspace_path: Option<&str>, tag: &str) -> Result<Vec<DbSession>> {
+        let conn = self.pool.reader()?;
+        let map_row = Self::row_to_session;
+
+        if let Some(path) = workspace_path {
+            let mut stmt = conn.prepare(
+                "SELECT s.id, s.workspace_path, s.title, s.created_at, s.updated_at, s.summary, s.is_flagged,
+                        s.status, s.has_unread, s.title_edited, s.tags
+                 FROM session_tags t JOIN sessions s ON s.id = t.session_id
+                 WHERE t.tag = ?1 AND s.workspace_path = ?2
+                 ORDER BY s.updated_at DESC"
+            )?;
+            let rows = stmt.query_map(params![tag, path], map_row)?;
+            rows.collect()
+        } else {
+            let mut stmt = conn.prepare(
+                "SELECT s.id, s.workspace_path, s.title, s.created_at, s.updated_at, s.summary, s.is_flagged,
+                        s.status, s.has_unread, s.title_edited, s.tags
+                 FROM session_tags t JOIN sessions s ON s.id = t.session_id
+                 WHERE t.tag = ?1 AND s.workspace_path IS NULL
+                 ORDER BY s.updated_at DESC"
+            )?;
+            let rows = stmt.query_map(params![tag], map_row)?;
+            rows.collect()
+        }
+    }
+
+    /// Set a generated title unless the user has renamed the session
+    /// Returns whether the title was changed
+    pub fn set_generated_title(&self, id: &str, title: &str) -> Result<bool> {
+        let conn = self.pool.writer();
+        let changed = conn.execute(
+            "UPDATE sessions SET title = ?2 WHERE id = ?1 AND COALESCE(title_edited, 0) = 0",
+            params![id, title],
+        )?;
+        Ok(changed > 0)
+    }
+
     /// Update session flag status
     pub fn update_session_flag(&self, id: &str, is_flagged: bool) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             "UPDATE sessions SET is_flagged = ?2 WHERE id = ?1",
             params![id, is_flagged as i32],
@@ -252,7 +569,7 @@ impl ChatDatabase {
 
     /// Update session status
     pub fn update_session_status(&self, id: &str, status: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             "UPDATE sessions SET status = ?2 WHERE id = ?1",
             params![id, status],
@@ -262,7 +579,7 @@ impl ChatDatabase {
 
     /// Update session unread status
     pub fn update_session_unread(&self, id: &str, has_unread: bool) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             "UPDATE sessions SET has_unread = ?2 WHERE id = ?1",
             params![id, has_unread as i32],
@@ -272,27 +589,13 @@ impl ChatDatabase {
 
     /// Get flagged sessions for a workspace
     pub fn get_flagged_sessions(&self, workspace_path: Option<&str>) -> Result<Vec<DbSession>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
 
-        let map_row = |row: &rusqlite::Row| -> rusqlite::Result<DbSession> {
-            let is_flagged: i32 = row.get::<_, Option<i32>>(6)?.unwrap_or(0);
-            let has_unread: i32 = row.get::<_, Option<i32>>(8)?.unwrap_or(0);
-            Ok(DbSession {
-                id: row.get(0)?,
-                workspace_path: row.get(1)?,
-                title: row.get(2)?,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
-                summary: row.get(5)?,
-                is_flagged: Some(is_flagged != 0),
-                status: row.get(7)?,
-                has_unread: Some(has_unread != 0),
-            })
-        };
+        let map_row = Self::row_to_session;
 
         if let Some(path) = workspace_path {
             let mut stmt = conn.prepare(
-                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread
+                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread, title_edited, tags
                  FROM sessions WHERE workspace_path = ?1 AND is_flagged = 1
                  ORDER BY updated_at DESC"
             )?;
@@ -300,7 +603,7 @@ impl ChatDatabase {
             rows.collect()
         } else {
             let mut stmt = conn.prepare(
-                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread
+                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread, title_edited, tags
                  FROM sessions WHERE workspace_path IS NULL AND is_flagged = 1
                  ORDER BY updated_at DESC"
             )?;
@@ -311,27 +614,13 @@ impl ChatDatabase {
 
     /// Get sessions by status for a workspace
     pub fn get_sessions_by_status(&self, workspace_path: Option<&str>, status: &str) -> Result<Vec<DbSession>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
 
-        let map_row = |row: &rusqlite::Row| -> rusqlite::Result<DbSession> {
-            let is_flagged: i32 = row.get::<_, Option<i32>>(6)?.unwrap_or(0);
-            let has_unread: i32 = row.get::<_, Option<i32>>(8)?.unwrap_or(0);
-            Ok(DbSession {
-                id: row.get(0)?,
-                workspace_path: row.get(1)?,
-                title: row.get(2)?,
-                created_at: row.get(3)?,
-                updated_at: row.get(4)?,
-                summary: row.get(5)?,
-                is_flagged: Some(is_flagged != 0),
-                status: row.get(7)?,
-                has_unread: Some(has_unread != 0),
-            })
-        };
+        let map_row = Self::row_to_session;
 
         if let Some(path) = workspace_path {
             let mut stmt = conn.prepare(
-                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread
+                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread, title_edited, tags
                  FROM sessions WHERE workspace_path = ?1 AND status = ?2
                  ORDER BY updated_at DESC"
             )?;
@@ -339,7 +628,7 @@ impl ChatDatabase {
             rows.collect()
         } else {
             let mut stmt = conn.prepare(
-                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread
+                "SELECT id, workspace_path, title, created_at, updated_at, summary, is_flagged, status, has_unread, title_edited, tags
                  FROM sessions WHERE workspace_path IS NULL AND status = ?1
                  ORDER BY updated_at DESC"
             )?;
@@ -350,7 +639,7 @@ impl ChatDatabase {
 
     /// Delete session and its messages
     pub fn delete_session(&self, id: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         // Delete messages first
         conn.execute("DELETE FROM messages WHERE session_id = ?1", params![id])?;
         // Delete tool executions
@@ -359,6 +648,7 @@ impl ChatDatabase {
              (SELECT id FROM messages WHERE session_id = ?1)",
             params![id],
         )?;
+        conn.execute("DELETE FROM session_tags WHERE session_id = ?1", params![id])?;
         // Delete session
         conn.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
         Ok(())
@@ -366,12 +656,22 @@ impl ChatDatabase {
 
     // ============ Message CRUD ============
 
-    /// Append a message to a session
-    pub fn append_message(&self, message: &DbMessage) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+    fn row_to_message(row: &rusqlite::Row) -> Result<DbMessage> {
+        Ok(DbMessage {
+            id: row.get(0)?,
+            session_id: row.get(1)?,
+            role: row.get(2)?,
+            content: row.get(3)?,
+            timestamp: row.get(4)?,
+            metadata: row.get(5)?,
+            parent_message_id: row.get(6)?,
+        })
+    }
+
+    fn insert_message(conn: &Connection, message: &DbMessage, parent_message_id: Option<&str>) -> Result<()> {
         conn.execute(
-            "INSERT INTO messages (id, session_id, role, content, timestamp, metadata)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO messages (id, session_id, role, content, timestamp, metadata, parent_message_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 message.id,
                 message.session_id,
@@ -379,54 +679,142 @@ impl ChatDatabase {
                 message.content,
                 message.timestamp,
                 message.metadata,
+                parent_message_id,
             ],
         )?;
         Ok(())
     }
 
+    /// The session's latest message, which ends its active branch
+    fn latest_message_id(conn: &Connection, session_id: &str) -> Result<Option<String>> {
+        conn.query_row(
+            "SELECT id FROM messages WHERE session_id = ?1
+             ORDER BY timestamp DESC, rowid DESC LIMIT 1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+
+    /// Append a message to a session
+    /// Without an explicit parent, the message continues from the session's latest message
+    pub fn append_message(&self, message: &DbMessage) -> Result<()> {
+        let conn = self.pool.writer();
+        let parent = match &message.parent_message_id {
+            Some(parent) => Some(parent.clone()),
+            None => Self::latest_message_id(&conn, &message.session_id)?,
+        };
+        Self::insert_message(&conn, message, parent.as_deref())
+    }
+
+    /// Start a new branch: store `message` as a reply to `message_id`
+    /// Messages after `message_id` on the old branch are kept but no longer on this path
+    pub fn branch_from(&self, message_id: &str, message: &DbMessage) -> Result<()> {
+        let conn = self.pool.writer();
+        let session_id: String = conn.query_row(
+            "SELECT session_id FROM messages WHERE id = ?1",
+            params![message_id],
+            |row| row.get(0),
+        )?;
+        let message = DbMessage {
+            session_id,
+            ..message.clone()
+        };
+        Self::insert_message(&conn, &message, Some(message_id))
+    }
+
+    /// Messages on the path from the first message to `leaf_id` (oldest first)
+    /// Only follows parents within `session_id`, and stops after `MAX_BRANCH_DEPTH` messages
+    pub fn get_branch(&self, session_id: &str, leaf_id: &str) -> Result<Vec<DbMessage>> {
+        let conn = self.pool.reader()?;
+        let mut stmt = conn.prepare(&format!(
+            "{}
+             SELECT m.id, m.session_id, m.role, m.content, m.timestamp, m.metadata, m.parent_message_id
+             FROM path JOIN messages m ON m.id = path.id AND m.session_id = ?1
+             ORDER BY path.depth DESC",
+            BRANCH_PATH_CTE
+        ))?;
+
+        let rows = stmt.query_map(params![session_id, leaf_id, MAX_BRANCH_DEPTH], Self::row_to_message)?;
+        rows.collect()
+    }
+
+    /// Messages on the branch ending at the session's latest message
+    pub fn get_active_branch(&self, session_id: &str) -> Result<Vec<DbMessage>> {
+        let leaf = Self::latest_message_id(&*self.pool.reader()?, session_id)?;
+        match leaf {
+            Some(leaf) => self.get_branch(session_id, &leaf),
+            None => Ok(Vec::new()),
+        }
+    }
+
     /// Get all messages for a session (ordered by timestamp)
     pub fn get_messages(&self, session_id: &str) -> Result<Vec<DbMessage>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let mut stmt = conn.prepare(
-            "SELECT id, session_id, role, content, timestamp, metadata
+            "SELECT id, session_id, role, content, timestamp, metadata, parent_message_id
              FROM messages WHERE session_id = ?1
              ORDER BY timestamp ASC"
         )?;
 
-        let rows = stmt.query_map(params![session_id], |row| {
-            Ok(DbMessage {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                timestamp: row.get(4)?,
-                metadata: row.get(5)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![session_id], Self::row_to_message)?;
 
         rows.collect()
     }
 
+    /// Page through a session's active branch, oldest first within each page
+    /// `after_id` (or no cursor) walks forward from the start; `before_id` alone walks
+    /// backward from that message. Either way `next_cursor` continues in the same direction.
+    /// A `limit` of 0 is treated as 1
+    pub fn get_messages_page(
+        &self,
+        session_id: &str,
+        before_id: Option<&str>,
+        after_id: Option<&str>,
+        limit: u32,
+    ) -> Result<Page<DbMessage>> {
+        let limit = limit.max(1);
+        let conn = self.pool.reader()?;
+        let Some(leaf) = Self::latest_message_id(&conn, session_id)? else {
+            return Ok(Page { items: Vec::new(), next_cursor: None });
+        };
+
+        // Older messages sit deeper in the path
+        let backward = before_id.is_some() && after_id.is_none();
+        let order = if backward { "ASC" } else { "DESC" };
+        let mut stmt = conn.prepare(&format!(
+            "{}
+             SELECT m.id, m.session_id, m.role, m.content, m.timestamp, m.metadata, m.parent_message_id
+             FROM path JOIN messages m ON m.id = path.id AND m.session_id = ?1
+             WHERE (?4 IS NULL OR path.depth < (SELECT depth FROM path WHERE id = ?4))
+               AND (?5 IS NULL OR path.depth > (SELECT depth FROM path WHERE id = ?5))
+             ORDER BY path.depth {}
+             LIMIT ?6",
+            BRANCH_PATH_CTE, order
+        ))?;
+
+        let rows = stmt.query_map(
+            params![session_id, leaf, MAX_BRANCH_DEPTH, after_id, before_id, limit as i64 + 1],
+            Self::row_to_message,
+        )?;
+        let mut page = Page::from_rows(rows.collect::<Result<Vec<_>>>()?, limit, |m| m.id.clone());
+        if backward {
+            page.items.reverse();
+        }
+        Ok(page)
+    }
+
     /// Get recent messages (for context window)
     pub fn get_recent_messages(&self, session_id: &str, limit: u32) -> Result<Vec<DbMessage>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let mut stmt = conn.prepare(
-            "SELECT id, session_id, role, content, timestamp, metadata
+            "SELECT id, session_id, role, content, timestamp, metadata, parent_message_id
              FROM messages WHERE session_id = ?1
              ORDER BY timestamp DESC
              LIMIT ?2"
         )?;
 
-        let rows = stmt.query_map(params![session_id, limit], |row| {
-            Ok(DbMessage {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                timestamp: row.get(4)?,
-                metadata: row.get(5)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![session_id, limit], Self::row_to_message)?;
 
         // Reverse to get chronological order
         let mut messages: Vec<DbMessage> = rows.collect::<Result<Vec<_>>>()?;
@@ -434,9 +822,45 @@ impl ChatDatabase {
         Ok(messages)
     }
 
+    /// Search message content, best match first
+    pub fn search_messages(&self, query: &str, limit: u32) -> Result<Vec<MessageSearchResult>> {
+        // Quote each term so punctuation is matched literally instead of parsed as FTS5 syntax
+        let fts_query = query
+            .split_whitespace()
+            .map(|t| t.replace('"', ""))
+            .filter(|t| !t.is_empty())
+            .map(|t| format!("\"{}\"", t))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        if fts_query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let conn = self.pool.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.session_id, m.role, m.content, m.timestamp, m.metadata, m.parent_message_id,
+                    -bm25(messages_fts) AS score,
+                    snippet(messages_fts, 0, '<mark>', '</mark>', '...', 24) AS snippet
+             FROM messages m
+             JOIN messages_fts ON m.rowid = messages_fts.rowid
+             WHERE messages_fts MATCH ?1
+             ORDER BY score DESC, m.timestamp DESC
+             LIMIT ?2"
+        )?;
+
+        let rows = stmt.query_map(params![fts_query, limit], |row| {
+            Ok(MessageSearchResult {
+                message: Self::row_to_message(row)?,
+                score: row.get(7)?,
+                snippet: row.get(8)?,
+            })
+        })?;
+        rows.collect()
+    }
+
     /// Update message metadata
     pub fn update_message_metadata(&self, id: &str, metadata: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             "UPDATE messages SET metadata = ?2 WHERE id = ?1",
             params![id, metadata],
@@ -448,7 +872,7 @@ impl ChatDatabase {
 
     /// Record tool execution
     pub fn add_tool_execution(&self, exec: &DbToolExecution) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             "INSERT INTO tool_executions
              (id, message_id, tool_name, tool_input, tool_output, started_at, finished_at, status)
@@ -469,7 +893,7 @@ impl ChatDatabase {
 
     /// Update tool execution result
     pub fn update_tool_execution(&self, id: &str, output: &str, finished_at: &str, status: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             "UPDATE tool_executions
              SET tool_output = ?2, finished_at = ?3, status = ?4
@@ -481,7 +905,7 @@ impl ChatDatabase {
 
     /// Get tool executions for a message
     pub fn get_tool_executions(&self, message_id: &str) -> Result<Vec<DbToolExecution>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let mut stmt = conn.prepare(
             "SELECT id, message_id, tool_name, tool_input, tool_output, started_at, finished_at, status
              FROM tool_executions WHERE message_id = ?1
@@ -504,11 +928,35 @@ impl ChatDatabase {
         rows.collect()
     }
 
+    // ============ Export ============
+
+    /// Export a session's active branch with its tool executions
+    pub fn export_session(&self, session_id: &str, format: ExportFormat) -> Result<String> {
+        let session = self
+            .get_session(session_id)?
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        let messages = self
+            .get_active_branch(session_id)?
+            .into_iter()
+            .map(|message| {
+                let tool_executions = self.get_tool_executions(&message.id)?;
+                Ok(TranscriptMessage { message, tool_executions })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let transcript = SessionTranscript { session, messages };
+
+        match format {
+            ExportFormat::Markdown => Ok(transcript.to_markdown()),
+            ExportFormat::Json => serde_json::to_string_pretty(&transcript)
+                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e))),
+        }
+    }
+
     // ============ Statistics ============
 
     /// Get session message count
     pub fn get_message_count(&self, session_id: &str) -> Result<u32> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let count: u32 = conn.query_row(
             "SELECT COUNT(*) FROM messages WHERE session_id = ?1",
             params![session_id],
@@ -519,7 +967,7 @@ impl ChatDatabase {
 
     /// Get total sessions count for workspace
     pub fn get_session_count(&self, workspace_path: Option<&str>) -> Result<u32> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let count: u32 = if let Some(path) = workspace_path {
             conn.query_row(
                 "SELECT COUNT(*) FROM sessions WHERE workspace_path = ?1",
@@ -535,6 +983,36 @@ impl ChatDatabase {
         };
         Ok(count)
     }
+
+    // ============ Workspaces ============
+
+    /// Record a workspace as most recently used, keeping at most `MAX_RECENT_WORKSPACES`
+    pub fn touch_workspace(&self, path: &str) -> Result<()> {
+        let conn = self.pool.writer();
+        conn.execute(
+            "INSERT INTO recent_workspaces (path, last_used)
+             VALUES (?1, (SELECT COALESCE(MAX(last_used), 0) + 1 FROM recent_workspaces))
+             ON CONFLICT(path) DO UPDATE SET last_used = excluded.last_used",
+            params![path],
+        )?;
+        conn.execute(
+            "DELETE FROM recent_workspaces WHERE path NOT IN (
+                SELECT path FROM recent_workspaces ORDER BY last_used DESC LIMIT ?1
+             )",
+            params![MAX_RECENT_WORKSPACES],
+        )?;
+        Ok(())
+    }
+
+    /// Get recently used workspaces, most recent first
+    pub fn get_recent_workspaces(&self) -> Result<Vec<String>> {
+        let conn = self.pool.reader()?;
+        let mut stmt = conn.prepare("SELECT path FROM recent_workspaces ORDER BY last_used DESC")?;
+        let paths = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+        Ok(paths)
+    }
 }
 
 #[cfg(test)]
@@ -558,6 +1036,8 @@ mod tests {
             is_flagged: Some(false),
             status: Some("todo".to_string()),
             has_unread: Some(false),
+            title_edited: None,
+            tags: Vec::new(),
         };
 
         db.create_session(&session).unwrap();
@@ -586,6 +1066,8 @@ mod tests {
             is_flagged: None,
             status: None,
             has_unread: None,
+            title_edited: None,
+            tags: Vec::new(),
         };
         db.create_session(&session).unwrap();
 
@@ -597,6 +1079,7 @@ mod tests {
             content: "Hello".to_string(),
             timestamp: "2024-01-01T00:00:01Z".to_string(),
             metadata: None,
+            parent_message_id: None,
         };
         let msg2 = DbMessage {
             id: "msg2".to_string(),
@@ -605,6 +1088,7 @@ mod tests {
             content: "Hi there!".to_string(),
             timestamp: "2024-01-01T00:00:02Z".to_string(),
             metadata: Some(r#"{"cost": 0.001}"#.to_string()),
+            parent_message_id: None,
         };
 
         db.append_message(&msg1).unwrap();
@@ -614,5 +1098,431 @@ mod tests {
         assert_eq!(messages.len(), 2);
         assert_eq!(messages[0].role, "user");
         assert_eq!(messages[1].role, "assistant");
+        // Appends without a parent continue the conversation
+        assert_eq!(messages[1].parent_message_id.as_deref(), Some("msg1"));
+    }
+
+    #[test]
+    fn test_branch_excludes_abandoned_tail() {
+        let dir = tempdir().unwrap();
+        let db = ChatDatabase::open(dir.path().join("test.db")).unwrap();
+
+        let message = |id: &str, role: &str, second: u32| DbMessage {
+            id: id.to_string(),
+            session_id: "s".to_string(),
+            role: role.to_string(),
+            content: format!("content of {}", id),
+            timestamp: format!("2024-01-01T00:00:{:02}Z", second),
+            metadata: None,
+            parent_message_id: None,
+        };
+        db.append_message(&message("m1", "user", 1)).unwrap();
+        db.append_message(&message("m2", "assistant", 2)).unwrap();
+        db.append_message(&message("m3", "user", 3)).unwrap();
+        db.append_message(&message("m4", "assistant", 4)).unwrap();
+
+        // Edit the second user message: branch after m2, then continue the new branch
+        db.branch_from("m2", &message("m3b", "user", 5)).unwrap();
+        db.append_message(&DbMessage {
+            parent_message_id: Some("m3b".to_string()),
+            ..message("m4b", "assistant", 6)
+        })
+        .unwrap();
+
+        let ids = |messages: Vec<DbMessage>| messages.into_iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(ids(db.get_branch("s", "m4b").unwrap()), vec!["m1", "m2", "m3b", "m4b"]);
+        assert_eq!(ids(db.get_branch("s", "m4").unwrap()), vec!["m1", "m2", "m3", "m4"]);
+        // A leaf from another session yields nothing
+        assert!(db.get_branch("other", "m4b").unwrap().is_empty());
+
+        // Cyclic parent links stop at the depth cap instead of recursing forever
+        db.pool
+            .writer()
+            .execute_batch("UPDATE messages SET parent_message_id = 'm4' WHERE id = 'm1'")
+            .unwrap();
+        assert_eq!(db.get_branch("s", "m4").unwrap().len(), MAX_BRANCH_DEPTH as usize);
+        assert_eq!(db.get_messages("s").unwrap().len(), 6);
+        assert!(db.branch_from("missing", &message("x", "user", 7)).is_err());
+    }
+
+    #[test]
+    fn test_export_session() {
+        let dir = tempdir().unwrap();
+        let db = ChatDatabase::open(dir.path().join("test.db")).unwrap();
+        db.create_session(&DbSession {
+            id: "s".to_string(),
+            workspace_path: Some("/work".to_string()),
+            title: "Export me".to_string(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            updated_at: "2024-01-01T00:05:00Z".to_string(),
+            summary: None,
+            is_flagged: None,
+            status: None,
+            has_unread: None,
+            title_edited: None,
+            tags: Vec::new(),
+        })
+        .unwrap();
+        for (id, role, content) in [("m1", "user", "List files"), ("m2", "assistant", "Here they are.")] {
+            db.append_message(&DbMessage {
+                id: id.to_string(),
+                session_id: "s".to_string(),
+                role: role.to_string(),
+                content: content.to_string(),
+                timestamp: format!("2024-01-01T00:00:0{}Z", &id[1..]),
+                metadata: None,
+                parent_message_id: None,
+            })
+            .unwrap();
+        }
+        db.add_tool_execution(&DbToolExecution {
+            id: "t1".to_string(),
+            message_id: "m2".to_string(),
+            tool_name: "Bash".to_string(),
+            tool_input: r#"{"command":"ls"}"#.to_string(),
+            tool_output: Some("a.txt\nb.txt".to_string()),
+            started_at: "2024-01-01T00:00:02Z".to_string(),
+            finished_at: None,
+            status: "completed".to_string(),
+        })
+        .unwrap();
+        // An older, abandoned reply to m1 is not part of the export
+        db.branch_from("m1", &DbMessage {
+            id: "m2old".to_string(),
+            session_id: "s".to_string(),
+            role: "assistant".to_string(),
+            content: "First draft".to_string(),
+            timestamp: "2024-01-01T00:00:01Z".to_string(),
+            metadata: None,
+            parent_message_id: None,
+        })
+        .unwrap();
+
+        let markdown = db.export_session("s", ExportFormat::Markdown).unwrap();
+        assert!(!markdown.contains("First draft"));
+        assert!(markdown.starts_with("# Export me\n"));
+        assert!(markdown.contains("- Workspace: /work"));
+        let user = markdown.find("## User").unwrap();
+        let assistant = markdown.find("## Assistant").unwrap();
+        assert!(user < assistant);
+        assert!(markdown.contains("**Tool: Bash** (completed)\n\n```json\n{\n  \"command\": \"ls\"\n}\n```"));
+        assert!(markdown.contains("```\na.txt\nb.txt\n```"));
+
+        let json = db.export_session("s", ExportFormat::Json).unwrap();
+        let transcript: SessionTranscript = serde_json::from_str(&json).unwrap();
+        assert_eq!(transcript.session.title, "Export me");
+        assert_eq!(transcript.messages.len(), 2);
+        assert_eq!(transcript.messages[1].message.content, "Here they are.");
+        assert_eq!(transcript.messages[1].tool_executions[0].tool_name, "Bash");
+
+        assert!(db.export_session("missing", ExportFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_generated_title_respects_user_edits() {
+        let dir = tempdir().unwrap();
+        let db = ChatDatabase::open(dir.path().join("test.db")).unwrap();
+        let session = |id: &str, title_edited: Option<bool>| DbSession {
+            id: id.to_string(),
+            workspace_path: None,
+            title: "New Chat".to_string(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+            summary: None,
+            is_flagged: None,
+            status: None,
+            has_unread: None,
+            title_edited,
+            tags: Vec::new(),
+        };
+        db.create_session(&session("auto", None)).unwrap();
+        db.create_session(&session("edited", Some(true))).unwrap();
+
+        assert!(db.set_generated_title("auto", "Tokio runtime setup").unwrap());
+        assert_eq!(db.get_session("auto").unwrap().unwrap().title, "Tokio runtime setup");
+
+        assert!(!db.set_generated_title("edited", "Something else").unwrap());
+        let edited = db.get_session("edited").unwrap().unwrap();
+        assert_eq!(edited.title, "New Chat");
+        assert_eq!(edited.title_edited, Some(true));
+
+        // A rename through update_session survives later generated titles
+        let mut renamed = db.get_session("auto").unwrap().unwrap();
+        renamed.title = "My rename".to_string();
+        renamed.title_edited = None;
+        db.update_session(&renamed).unwrap();
+        assert!(!db.set_generated_title("auto", "Another generated title").unwrap());
+        let renamed = db.get_session("auto").unwrap().unwrap();
+        assert_eq!(renamed.title, "My rename");
+        assert_eq!(renamed.title_edited, Some(true));
+    }
+
+    #[test]
+    fn test_sessions_by_tag() {
+        let dir = tempdir().unwrap();
+        let db = ChatDatabase::open(dir.path().join("test.db")).unwrap();
+        for (id, updated_at) in [("a", "2024-01-01T00:00:01Z"), ("b", "2024-01-01T00:00:02Z"), ("c", "2024-01-01T00:00:03Z")] {
+            db.create_session(&DbSession {
+                id: id.to_string(),
+                workspace_path: Some("/w".to_string()),
+                title: id.to_string(),
+                created_at: updated_at.to_string(),
+                updated_at: updated_at.to_string(),
+                summary: None,
+                is_flagged: None,
+                status: None,
+                has_unread: None,
+                title_edited: None,
+                tags: Vec::new(),
+            })
+            .unwrap();
+        }
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        db.set_session_tags("a", &tags(&["rust", "research"])).unwrap();
+        db.set_session_tags("b", &tags(&["research", " research ", ""])).unwrap();
+        db.set_session_tags("c", &tags(&["rust"])).unwrap();
+
+        let ids = |sessions: Vec<DbSession>| sessions.into_iter().map(|s| s.id).collect::<Vec<_>>();
+        assert_eq!(ids(db.get_sessions_by_tag(Some("/w"), "research").unwrap()), vec!["b", "a"]);
+        assert_eq!(ids(db.get_sessions_by_tag(Some("/w"), "rust").unwrap()), vec!["c", "a"]);
+        assert!(db.get_sessions_by_tag(None, "rust").unwrap().is_empty());
+        assert_eq!(db.get_session("b").unwrap().unwrap().tags, vec!["research"]);
+
+        // update_session keeps stored tags
+        let mut session = db.get_session("a").unwrap().unwrap();
+        session.title = "Renamed".to_string();
+        session.tags.clear();
+        db.update_session(&session).unwrap();
+        assert_eq!(db.get_session("a").unwrap().unwrap().tags, vec!["rust", "research"]);
+
+        db.set_session_tags("a", &[]).unwrap();
+        assert_eq!(ids(db.get_sessions_by_tag(Some("/w"), "rust").unwrap()), vec!["c"]);
+    }
+
+    #[test]
+    fn test_fence_longer_than_content_backticks() {
+        assert_eq!(fenced("a ```` b", ""), "`````\na ```` b\n`````\n");
+    }
+
+    #[test]
+    fn test_search_messages() {
+        let dir = tempdir().unwrap();
+        let db = ChatDatabase::open(dir.path().join("test.db")).unwrap();
+
+        for (i, (session, content)) in [
+            ("s1", "How do I configure the tokio runtime?"),
+            ("s1", "Use the multi-threaded scheduler."),
+            ("s2", "Explain lifetimes in Rust"),
+        ]
+        .iter()
+        .enumerate()
+        {
+            db.append_message(&DbMessage {
+                id: format!("m{}", i),
+                session_id: session.to_string(),
+                role: "user".to_string(),
+                content: content.to_string(),
+                timestamp: format!("2024-01-01T00:00:0{}Z", i),
+                metadata: None,
+                parent_message_id: None,
+            })
+            .unwrap();
+        }
+
+        let hits = db.search_messages("tokio", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message.id, "m0");
+        assert_eq!(hits[0].message.session_id, "s1");
+        assert!(hits[0].snippet.contains("<mark>tokio</mark>"));
+
+        // Deleted sessions drop out of the index
+        db.delete_session("s2").unwrap();
+        assert!(db.search_messages("lifetimes", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_messages_punctuated_input() {
+        let dir = tempdir().unwrap();
+        let db = ChatDatabase::open(dir.path().join("test.db")).unwrap();
+        db.append_message(&DbMessage {
+            id: "m0".to_string(),
+            session_id: "s1".to_string(),
+            role: "user".to_string(),
+            content: "what's new in C++ for the foo-bar crate".to_string(),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            metadata: None,
+            parent_message_id: None,
+        })
+        .unwrap();
+
+        // FTS5 operators and quotes in user input must not surface as syntax errors
+        for query in ["what's", "C++", "foo-bar", "\"foo", "(crate)", "crate*"] {
+            let hits = db.search_messages(query, 10).unwrap();
+            assert_eq!(hits.len(), 1, "query {:?}", query);
+        }
+        assert!(db.search_messages("\"", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recent_workspaces() {
+        let dir = tempdir().unwrap();
+        let db = ChatDatabase::open(dir.path().join("test.db")).unwrap();
+        assert!(db.get_recent_workspaces().unwrap().is_empty());
+
+        db.touch_workspace("/a").unwrap();
+        db.touch_workspace("/b").unwrap();
+        db.touch_workspace("/a").unwrap();
+        assert_eq!(db.get_recent_workspaces().unwrap(), vec!["/a", "/b"]);
+
+        for i in 0..MAX_RECENT_WORKSPACES {
+            db.touch_workspace(&format!("/w{}", i)).unwrap();
+        }
+        let recent = db.get_recent_workspaces().unwrap();
+        assert_eq!(recent.len(), MAX_RECENT_WORKSPACES);
+        assert_eq!(recent[0], format!("/w{}", MAX_RECENT_WORKSPACES - 1));
+        assert!(!recent.contains(&"/b".to_string()));
+    }
+
+    #[test]
+    fn test_messages_page_walks_without_gaps() {
+        let dir = tempdir().unwrap();
+        let db = ChatDatabase::open(dir.path().join("test.db")).unwrap();
+        for i in 0..50 {
+            db.append_message(&DbMessage {
+                id: format!("msg{:02}", i),
+                session_id: "s1".to_string(),
+                role: "user".to_string(),
+                content: format!("Message {}", i),
+                // Pairs share a timestamp so ties are ordered by insertion
+                timestamp: format!("2024-01-01T00:00:{:02}Z", i / 2),
+                metadata: None,
+                parent_message_id: None,
+            })
+            .unwrap();
+        }
+        // An abandoned reply is not on the active branch
+        db.branch_from("msg10", &DbMessage {
+            id: "stale".to_string(),
+            session_id: "s1".to_string(),
+            role: "assistant".to_string(),
+            content: "Abandoned".to_string(),
+            timestamp: "2024-01-01T00:00:05Z".to_string(),
+            metadata: None,
+            parent_message_id: None,
+        })
+        .unwrap();
+        let all: Vec<String> = (0..50).map(|i| format!("msg{:02}", i)).collect();
+
+        let mut forward = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = db.get_messages_page("s1", None, cursor.as_deref(), 10).unwrap();
+            assert!(page.items.len() <= 10);
+            forward.extend(page.items.into_iter().map(|m| m.id));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(forward, all);
+
+        // Walking backward from the last message yields everything before it
+        let mut backward = Vec::new();
+        let mut cursor = Some("msg49".to_string());
+        while let Some(before) = cursor {
+            let page = db.get_messages_page("s1", Some(&before), None, 10).unwrap();
+            backward.splice(0..0, page.items.into_iter().map(|m| m.id));
+            cursor = page.next_cursor;
+        }
+        assert_eq!(backward, all[..49]);
+
+        let page = db.get_messages_page("s1", None, None, 0).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.next_cursor.as_deref(), Some("msg00"));
+        assert!(db.get_messages_page("empty", None, None, 10).unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn test_migrates_unversioned_schema_without_data_loss() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("old.db");
+        {
+            // Schema as shipped before tags, branching and search
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE sessions (
+                    id TEXT PRIMARY KEY, workspace_path TEXT, title TEXT NOT NULL,
+                    created_at TEXT NOT NULL, updated_at TEXT NOT NULL, summary TEXT,
+                    is_flagged INTEGER DEFAULT 0, status TEXT DEFAULT 'todo', has_unread INTEGER DEFAULT 0
+                );
+                CREATE TABLE messages (
+                    id TEXT PRIMARY KEY, session_id TEXT NOT NULL, role TEXT NOT NULL,
+                    content TEXT NOT NULL, timestamp TEXT NOT NULL, metadata TEXT
+                );
+                INSERT INTO sessions (id, title, created_at, updated_at, is_flagged)
+                    VALUES ('s1', 'Legacy', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z', 1);
+                INSERT INTO messages VALUES ('m1', 's1', 'user', 'legacy question', '2024-01-01T00:00:01Z', NULL);
+                INSERT INTO messages VALUES ('m2', 's1', 'assistant', 'legacy answer', '2024-01-01T00:00:02Z', NULL);
+                "#,
+            )
+            .unwrap();
+        }
+
+        let db = ChatDatabase::open(&db_path).unwrap();
+        assert_eq!(db.migrate().unwrap(), MIGRATIONS.last().unwrap().version);
+
+        let session = db.get_session("s1").unwrap().unwrap();
+        assert_eq!(session.title, "Legacy");
+        assert_eq!(session.is_flagged, Some(true));
+        assert!(session.tags.is_empty());
+
+        let messages = db.get_messages("s1").unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].parent_message_id.as_deref(), Some("m1"));
+        assert_eq!(db.search_messages("legacy answer", 10).unwrap()[0].message.id, "m2");
+
+        // Reopening leaves the migrated data untouched
+        drop(db);
+        let db = ChatDatabase::open(&db_path).unwrap();
+        assert_eq!(db.get_messages("s1").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_parallel_reads_during_writes() {
+        let dir = tempdir().unwrap();
+        let db = ChatDatabase::open(dir.path().join("test.db")).unwrap();
+        let message = |i: usize| DbMessage {
+            id: format!("msg{:03}", i),
+            session_id: "s1".to_string(),
+            role: "user".to_string(),
+            content: format!("concurrent message {}", i),
+            timestamp: format!("2024-01-01T00:{:02}:{:02}Z", i / 60, i % 60),
+            metadata: None,
+            parent_message_id: None,
+        };
+        db.append_message(&message(0)).unwrap();
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for i in 1..200 {
+                    db.append_message(&message(i)).unwrap();
+                }
+            });
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut last = 0;
+                    for _ in 0..100 {
+                        // Counts only grow and every read succeeds without "database is locked"
+                        let count = db.get_messages("s1").unwrap().len();
+                        assert!(count >= last);
+                        last = count;
+                        db.search_messages("concurrent", 5).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(db.get_message_count("s1").unwrap(), 200);
     }
 }
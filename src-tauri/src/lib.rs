@@ -1,33 +1,47 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use claude_agent_sdk_rs::{
     query_stream, ClaudeAgentOptions, ContentBlock, Message as ClaudeMessage,
-    PermissionMode, McpServers,
+    PermissionMode, McpServerConfig, McpServers, ToolResultBlock, ToolResultContent,
 };
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Manager, State};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 mod browser;
 mod chat;
+mod claude_cli;
 mod db;
 mod mcp;
 mod memory_index;
 mod memory_tool;
+mod migrations;
+mod model;
+mod response_cache;
 mod rss;
 mod rss_db;
+mod rss_scheduler;
+mod rss_topics;
+mod secrets;
+mod session_title;
 mod skill;
-
-use chat::{ApiConfig, ChatClient, ChatMessage as SimpleChatMessage, ChatRequest, ChatResponse};
-use db::{ChatDatabase, DbSession, DbMessage};
-use mcp::{McpManager, McpServerInfo, AddMcpServerRequest};
-use skill::{SkillManager, SkillInfo, SkillMetadata, FileItem, SearchSkill};
-use memory_index::{MemoryIndex, SearchResult as MemorySearchResult, SyncResult as MemorySyncResult, MemoryStats};
-use memory_tool::{MemoryTool, MemoryToolCommand, MemoryToolResult};
+mod sqlite_pool;
+mod url_guard;
+mod workspace_files;
+
+use chat::{ApiConfig, ChatClient, ChatMessage as SimpleChatMessage, ChatRequest, ChatResponse, Estimate, RetryPolicy, StreamChunk, ThinkingConfig, ToolRegistry};
+use workspace_files::WorkspaceFile;
+use db::{ChatDatabase, DbSession, DbMessage, ExportFormat, MessageSearchResult, Page};
+use mcp::{McpManager, McpServerInfo, McpHealth, AddMcpServerRequest};
+use skill::{SkillManager, SkillInfo, SkillMetadata, SkillScope, SkillUpdateSummary, FileItem, SearchSkill};
+use memory_index::{MemoryArchiveManifest, MemoryIndex, SearchMode as MemorySearchMode, SearchResult as MemorySearchResult, SyncResult as MemorySyncResult, MemoryStats};
+use memory_tool::{MemoryTool, MemoryToolCommand, MemoryToolResult, DEFAULT_MEMORIES_DIR};
+use response_cache::{ResponseCache, DEFAULT_RESPONSE_CACHE_TTL};
 
 // ============ Types ============
 
@@ -51,7 +65,7 @@ pub struct Message {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionEvent {
-    pub event_type: String, // "text_delta" | "complete" | "error"
+    pub event_type: String, // "text_delta" | "tool_use" | "tool_result" | "complete" | "error" | "cancelled"
     pub session_id: String,
     pub data: serde_json::Value,
 }
@@ -63,6 +77,8 @@ pub struct AppState {
     messages: Mutex<HashMap<String, Vec<Message>>>,
     workspace: Mutex<Option<String>>,
     db: Arc<ChatDatabase>,
+    /// Cancellation tokens for in-flight turns, keyed by session id
+    cancellations: Mutex<HashMap<String, Arc<CancellationToken>>>,
 }
 
 impl AppState {
@@ -72,6 +88,47 @@ impl AppState {
             messages: Mutex::new(HashMap::new()),
             workspace: Mutex::new(None),
             db: Arc::new(db),
+            cancellations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Mark a session as processing and register a cancellation token for the turn
+    fn begin_turn(&self, session_id: &str) -> Arc<CancellationToken> {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(session_id) {
+            session.is_processing = true;
+        }
+        let token = Arc::new(CancellationToken::new());
+        self.cancellations
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), token.clone());
+        token
+    }
+
+    /// Mark a session as no longer processing and drop its cancellation token
+    /// Does nothing if a newer turn has replaced `token` in the meantime
+    fn end_turn(&self, session_id: &str, token: &Arc<CancellationToken>) {
+        {
+            let mut cancellations = self.cancellations.lock().unwrap();
+            if !cancellations.get(session_id).is_some_and(|current| Arc::ptr_eq(current, token)) {
+                return;
+            }
+            cancellations.remove(session_id);
+        }
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(session_id) {
+            session.is_processing = false;
+            session.updated_at = chrono::Utc::now().to_rfc3339();
+        }
+    }
+
+    /// Cancel the in-flight turn for a session, returning whether one was running
+    fn cancel_turn(&self, session_id: &str) -> bool {
+        match self.cancellations.lock().unwrap().get(session_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
         }
     }
 }
@@ -202,6 +259,10 @@ fn set_workspace(state: State<AppState>, path: String) -> Result<(), String> {
     let mut workspace = state.workspace.lock().unwrap();
     *workspace = Some(path.clone());
     log::info!("Workspace set to: {}", path);
+    // Remember it for the next launch
+    if let Err(e) = state.db.touch_workspace(&path) {
+        log::warn!("Failed to persist workspace: {}", e);
+    }
     Ok(())
 }
 
@@ -211,6 +272,12 @@ fn get_workspace(state: State<AppState>) -> Option<String> {
     workspace.clone()
 }
 
+/// Recently used workspaces, most recent first
+#[tauri::command]
+fn get_recent_workspaces(state: State<AppState>) -> Result<Vec<String>, String> {
+    state.db.get_recent_workspaces().map_err(|e| e.to_string())
+}
+
 // ============ Database Commands ============
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -236,6 +303,8 @@ fn db_create_session(
         is_flagged: Some(false),
         status: Some("todo".to_string()),
         has_unread: Some(false),
+        title_edited: Some(false),
+        tags: Vec::new(),
     };
 
     state.db.create_session(&session)
@@ -289,15 +358,54 @@ fn db_append_message(
         .map_err(|e| format!("Failed to append message: {}", e))
 }
 
+/// All messages in a session, or only the branch ending at `leaf_id`
 #[tauri::command]
 fn db_get_messages(
     state: State<AppState>,
     session_id: String,
+    leaf_id: Option<String>,
 ) -> Result<Vec<DbMessage>, String> {
-    state.db.get_messages(&session_id)
+    match leaf_id {
+        Some(leaf_id) => state.db.get_branch(&session_id, &leaf_id),
+        None => state.db.get_messages(&session_id),
+    }
+    .map_err(|e| format!("Failed to get messages: {}", e))
+}
+
+/// One page of a session's active branch, see `ChatDatabase::get_messages_page`
+#[tauri::command]
+fn db_get_messages_page(
+    state: State<AppState>,
+    session_id: String,
+    before_id: Option<String>,
+    after_id: Option<String>,
+    limit: u32,
+) -> Result<Page<DbMessage>, String> {
+    state.db.get_messages_page(&session_id, before_id.as_deref(), after_id.as_deref(), limit)
         .map_err(|e| format!("Failed to get messages: {}", e))
 }
 
+/// Store `message` as a new reply to `message_id`, starting a branch
+#[tauri::command]
+fn db_branch_from(
+    state: State<AppState>,
+    message_id: String,
+    message: DbMessage,
+) -> Result<(), String> {
+    state.db.branch_from(&message_id, &message)
+        .map_err(|e| format!("Failed to branch from message: {}", e))
+}
+
+#[tauri::command]
+fn db_search_messages(
+    state: State<AppState>,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<MessageSearchResult>, String> {
+    state.db.search_messages(&query, limit.unwrap_or(50))
+        .map_err(|e| format!("Failed to search messages: {}", e))
+}
+
 #[tauri::command]
 fn db_get_recent_messages(
     state: State<AppState>,
@@ -335,6 +443,28 @@ fn db_get_session_with_messages(
     }
 }
 
+/// Title a session from its first exchange unless the user renamed it
+/// Returns the new title, or None when the session keeps its user-edited title
+#[tauri::command]
+async fn db_generate_session_title(
+    state: State<'_, AppState>,
+    session_id: String,
+    config: ApiConfig,
+) -> Result<Option<String>, String> {
+    let db = state.db.clone();
+    session_title::generate_session_title(&db, &session_id, config).await
+}
+
+#[tauri::command]
+fn db_export_session(
+    state: State<AppState>,
+    session_id: String,
+    format: ExportFormat,
+) -> Result<String, String> {
+    state.db.export_session(&session_id, format)
+        .map_err(|e| format!("Failed to export session: {}", e))
+}
+
 #[tauri::command]
 fn db_update_session_flag(
     state: State<AppState>,
@@ -374,6 +504,27 @@ fn db_get_flagged_sessions(
         .map_err(|e| format!("Failed to get flagged sessions: {}", e))
 }
 
+/// Replace a session's tags, returning the normalized list
+#[tauri::command]
+fn db_set_session_tags(
+    state: State<AppState>,
+    session_id: String,
+    tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+    state.db.set_session_tags(&session_id, &tags)
+        .map_err(|e| format!("Failed to set session tags: {}", e))
+}
+
+#[tauri::command]
+fn db_get_sessions_by_tag(
+    state: State<AppState>,
+    workspace_path: Option<String>,
+    tag: String,
+) -> Result<Vec<DbSession>, String> {
+    state.db.get_sessions_by_tag(workspace_path.as_deref(), &tag)
+        .map_err(|e| format!("Failed to get sessions by tag: {}", e))
+}
+
 #[tauri::command]
 fn db_get_sessions_by_status(
     state: State<AppState>,
@@ -391,9 +542,10 @@ fn mcp_list_servers() -> Result<Vec<McpServerInfo>, String> {
     McpManager::list().map_err(|e| e.to_string())
 }
 
+/// Add a server; a stdio command missing from PATH is rejected unless `force` is set
 #[tauri::command]
-fn mcp_add_server(config: AddMcpServerRequest) -> Result<(), String> {
-    McpManager::add(config).map_err(|e| e.to_string())
+fn mcp_add_server(config: AddMcpServerRequest, force: Option<bool>) -> Result<(), String> {
+    McpManager::add(config, force.unwrap_or(false)).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -407,8 +559,8 @@ fn mcp_toggle_server(name: String, disabled: bool) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn mcp_update_server(name: String, config: AddMcpServerRequest) -> Result<(), String> {
-    McpManager::update(&name, config).map_err(|e| e.to_string())
+fn mcp_update_server(name: String, config: AddMcpServerRequest, force: Option<bool>) -> Result<(), String> {
+    McpManager::update(&name, config, force.unwrap_or(false)).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -416,6 +568,12 @@ fn mcp_get_server(name: String) -> Result<Option<McpServerInfo>, String> {
     McpManager::get(&name).map_err(|e| e.to_string())
 }
 
+/// Start or contact a server and list its tools
+#[tauri::command]
+async fn mcp_health_check(name: String) -> Result<McpHealth, String> {
+    McpManager::health_check(&name).await.map_err(|e| e.to_string())
+}
+
 // ============ Skills Commands ============
 
 #[tauri::command]
@@ -444,28 +602,55 @@ fn skill_read_file(name: String, file_path: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-fn skill_install_from_content(content: String, filename: String) -> Result<String, String> {
-    SkillManager::install_from_content(&content, &filename).map_err(|e| e.to_string())
+fn skill_install_from_content(
+    content: String,
+    filename: String,
+    workspace: Option<String>,
+    force: Option<bool>,
+) -> Result<String, String> {
+    let scope = SkillScope::from_workspace(workspace.as_deref());
+    SkillManager::install_from_content(&content, &filename, &scope, force.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn skill_install_from_url(url: String, workspace: Option<String>) -> Result<String, String> {
+    let scope = SkillScope::from_workspace(workspace.as_deref());
+    SkillManager::install_from_url(&url, &scope).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn skill_install_from_zip(
+    zip_base64: String,
+    source: String,
+    workspace: Option<String>,
+) -> Result<String, String> {
+    let scope = SkillScope::from_workspace(workspace.as_deref());
+    SkillManager::install_from_zip(&zip_base64, &source, &scope).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn skill_install_from_url(url: String) -> Result<String, String> {
-    SkillManager::install_from_url(&url).await.map_err(|e| e.to_string())
+fn skill_delete(name: String, workspace: Option<String>) -> Result<(), String> {
+    let scope = SkillScope::from_workspace(workspace.as_deref());
+    SkillManager::delete(&name, &scope).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn skill_install_from_zip(zip_base64: String, source: String) -> Result<String, String> {
-    SkillManager::install_from_zip(&zip_base64, &source).map_err(|e| e.to_string())
+async fn skill_update(name: String, workspace: Option<String>) -> Result<SkillUpdateSummary, String> {
+    let scope = SkillScope::from_workspace(workspace.as_deref());
+    SkillManager::update(&name, &scope).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn skill_delete(name: String) -> Result<(), String> {
-    SkillManager::delete(&name).map_err(|e| e.to_string())
+fn skill_set_enabled(name: String, enabled: bool, workspace: Option<String>) -> Result<(), String> {
+    let scope = SkillScope::from_workspace(workspace.as_deref());
+    SkillManager::set_enabled(&name, enabled, &scope).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn skill_open_folder(name: String) -> Result<(), String> {
-    SkillManager::open_folder(&name).map_err(|e| e.to_string())
+fn skill_open_folder(name: String, workspace: Option<String>) -> Result<(), String> {
+    let scope = SkillScope::from_workspace(workspace.as_deref());
+    SkillManager::open_folder(&name, &scope).map_err(|e| e.to_string())
 }
 
 /// Open a directory in the system file manager
@@ -510,18 +695,31 @@ async fn skill_search(query: String) -> Result<Vec<SearchSkill>, String> {
 
 // ============ Memory Commands ============
 
+/// Open a workspace's memory index, scanning the configured memories directory
+fn open_memory_index(workspace: &Path, memories_dir: Option<&str>) -> Result<MemoryIndex, String> {
+    MemoryIndex::open_with_memories_dir(workspace, memories_dir.unwrap_or(DEFAULT_MEMORIES_DIR))
+        .map_err(|e| format!("Failed to open memory index: {}", e))
+}
+
 #[tauri::command]
-async fn memory_sync(workspace: String) -> Result<MemorySyncResult, String> {
+async fn memory_sync(
+    workspace: String,
+    full: Option<bool>,
+    memories_dir: Option<String>,
+) -> Result<MemorySyncResult, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let index = MemoryIndex::open(&workspace_path)
-        .map_err(|e| format!("Failed to open memory index: {}", e))?;
+    let index = open_memory_index(&workspace_path, memories_dir.as_deref())?;
 
-    index.sync()
-        .map_err(|e| format!("Failed to sync memory: {}", e))
+    let result = if full.unwrap_or(false) {
+        index.full_resync()
+    } else {
+        index.sync()
+    };
+    result.map_err(|e| format!("Failed to sync memory: {}", e))
 }
 
 #[tauri::command]
@@ -529,148 +727,108 @@ async fn memory_search(
     workspace: String,
     query: String,
     max_results: Option<usize>,
+    min_score: Option<f32>,
+    mode: Option<MemorySearchMode>,
+    memories_dir: Option<String>,
 ) -> Result<Vec<MemorySearchResult>, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let index = MemoryIndex::open(&workspace_path)
-        .map_err(|e| format!("Failed to open memory index: {}", e))?;
+    let index = open_memory_index(&workspace_path, memories_dir.as_deref())?;
 
     let limit = max_results.unwrap_or(10);
-    index.search(&query, limit)
+    index.search(&query, limit, min_score, mode.unwrap_or_default())
         .map_err(|e| format!("Failed to search memory: {}", e))
 }
 
 #[tauri::command]
-async fn memory_get_context(workspace: String) -> Result<String, String> {
+async fn memory_get_context(workspace: String, memories_dir: Option<String>) -> Result<String, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let index = MemoryIndex::open(&workspace_path)
-        .map_err(|e| format!("Failed to open memory index: {}", e))?;
+    let index = open_memory_index(&workspace_path, memories_dir.as_deref())?;
 
     index.get_context()
         .map_err(|e| format!("Failed to get memory context: {}", e))
 }
 
 #[tauri::command]
-async fn memory_get_stats(workspace: String) -> Result<MemoryStats, String> {
+async fn memory_get_stats(workspace: String, memories_dir: Option<String>) -> Result<MemoryStats, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let index = MemoryIndex::open(&workspace_path)
-        .map_err(|e| format!("Failed to open memory index: {}", e))?;
+    let index = open_memory_index(&workspace_path, memories_dir.as_deref())?;
 
     index.get_stats()
         .map_err(|e| format!("Failed to get memory stats: {}", e))
 }
 
-// ============ Workspace File Search ============
+/// Export the workspace memory files and index to a zip archive at `path`
+#[tauri::command]
+async fn memory_export(
+    workspace: String,
+    path: String,
+    memories_dir: Option<String>,
+) -> Result<MemoryArchiveManifest, String> {
+    let workspace_path = PathBuf::from(&workspace);
+    if !workspace_path.exists() {
+        return Err(format!("Workspace does not exist: {}", workspace));
+    }
 
-/// File search result for @file mention
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WorkspaceFile {
-    pub name: String,
-    pub path: String,
-    pub relative_path: String,
-    pub is_dir: bool,
-    pub size: Option<u64>,
+    let index = open_memory_index(&workspace_path, memories_dir.as_deref())?;
+
+    index.export(Path::new(&path))
+        .map_err(|e| format!("Failed to export memory: {}", e))
 }
 
-/// Search files in workspace directory
+/// Restore a memory archive into the workspace and rebuild its index
 #[tauri::command]
-async fn search_workspace_files(
+async fn memory_import(
     workspace: String,
-    query: String,
-    max_results: Option<usize>,
-) -> Result<Vec<WorkspaceFile>, String> {
+    path: String,
+    memories_dir: Option<String>,
+) -> Result<MemoryStats, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let limit = max_results.unwrap_or(20);
-    let query_lower = query.to_lowercase();
-    let mut results = Vec::new();
-
-    // Recursively search files
-    fn search_dir(
-        dir: &PathBuf,
-        base: &PathBuf,
-        query: &str,
-        results: &mut Vec<WorkspaceFile>,
-        limit: usize,
-        depth: usize,
-    ) {
-        if results.len() >= limit || depth > 5 {
-            return;
-        }
-
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                if results.len() >= limit {
-                    break;
-                }
+    let memories_dir = memories_dir.as_deref().unwrap_or(DEFAULT_MEMORIES_DIR);
+    let index = MemoryIndex::import(Path::new(&path), &workspace_path, memories_dir)
+        .map_err(|e| format!("Failed to import memory: {}", e))?;
 
-                let path = entry.path();
-                let name = path.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_string();
-
-                // Skip hidden files and common ignored directories
-                if name.starts_with('.') ||
-                   name == "node_modules" ||
-                   name == "target" ||
-                   name == "dist" ||
-                   name == "build" ||
-                   name == "__pycache__" {
-                    continue;
-                }
+    index.get_stats()
+        .map_err(|e| format!("Failed to get memory stats: {}", e))
+}
 
-                let relative_path = path.strip_prefix(base)
-                    .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_default();
-
-                // Match against name or path
-                if name.to_lowercase().contains(query) ||
-                   relative_path.to_lowercase().contains(query) {
-                    let metadata = fs::metadata(&path).ok();
-                    results.push(WorkspaceFile {
-                        name: name.clone(),
-                        path: path.to_string_lossy().to_string(),
-                        relative_path,
-                        is_dir: path.is_dir(),
-                        size: metadata.map(|m| m.len()),
-                    });
-                }
+// ============ Workspace File Search ============
 
-                // Recurse into directories
-                if path.is_dir() && results.len() < limit {
-                    search_dir(&path, base, query, results, limit, depth + 1);
-                }
-            }
-        }
+/// Search files in workspace directory
+/// `.gitignore` / `.ignore` rules apply unless `respect_gitignore` is false
+#[tauri::command]
+async fn search_workspace_files(
+    workspace: String,
+    query: String,
+    max_results: Option<usize>,
+    respect_gitignore: Option<bool>,
+) -> Result<Vec<WorkspaceFile>, String> {
+    let workspace_path = PathBuf::from(&workspace);
+    if !workspace_path.exists() {
+        return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    search_dir(&workspace_path, &workspace_path, &query_lower, &mut results, limit, 0);
-
-    // Sort: directories first, then by name
-    results.sort_by(|a, b| {
-        match (a.is_dir, b.is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.name.cmp(&b.name),
-        }
-    });
-
-    Ok(results)
+    Ok(workspace_files::search_files(
+        &workspace_path,
+        &query,
+        max_results.unwrap_or(20),
+        respect_gitignore.unwrap_or(true),
+    ))
 }
 
 /// Read file content for @file mention injection
@@ -700,82 +858,36 @@ async fn read_file_for_mention(
         return Ok(listing);
     }
 
-    // Read file content
-    let content = fs::read_to_string(&file_path)
-        .map_err(|e| format!("Failed to read file: {}", e))?;
-
-    // Optionally limit lines
-    if let Some(limit) = max_lines {
-        let lines: Vec<&str> = content.lines().take(limit).collect();
-        let truncated = lines.len() < content.lines().count();
-        let mut result = lines.join("\n");
-        if truncated {
-            result.push_str(&format!("\n\n... (truncated, showing first {} lines)", limit));
-        }
-        Ok(result)
-    } else {
-        Ok(content)
-    }
+    workspace_files::read_file_preview(&file_path, max_lines)
 }
 
 /// Fetch URL content for @url mention
+/// Internal addresses are refused unless their host is in `allowed_hosts`
 #[tauri::command]
-async fn fetch_url_for_mention(url: String) -> Result<String, String> {
-    // Basic URL validation
-    if !url.starts_with("http://") && !url.starts_with("https://") {
-        return Err("Invalid URL: must start with http:// or https://".to_string());
-    }
-
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-
-    let response = client.get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch URL: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("HTTP error: {}", response.status()));
-    }
-
-    let content_type = response.headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
-
-    // Only process text content
-    if !content_type.contains("text") && !content_type.contains("json") && !content_type.contains("xml") {
-        return Err(format!("Unsupported content type: {}", content_type));
-    }
-
-    let text = response.text()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-
-    // Limit content size (max 50KB)
-    if text.len() > 50 * 1024 {
-        Ok(format!("{}\n\n... (content truncated, showing first 50KB)", &text[..50 * 1024]))
-    } else {
-        Ok(text)
-    }
+async fn fetch_url_for_mention(url: String, allowed_hosts: Option<Vec<String>>) -> Result<String, String> {
+    url_guard::fetch_text(&url, &allowed_hosts.unwrap_or_default()).await
 }
 
 // ============ Memory Tool Commands ============
 
+/// Memory tool rooted at the configured memories directory (default `.flowq/memories`)
+fn memory_tool_for(workspace: &Path, memories_dir: Option<&str>) -> Result<MemoryTool, String> {
+    MemoryTool::with_memories_dir(workspace, memories_dir.unwrap_or(DEFAULT_MEMORIES_DIR))
+}
+
 #[tauri::command]
 async fn memory_tool_view(
     workspace: String,
     path: String,
     view_range: Option<(u32, u32)>,
+    memories_dir: Option<String>,
 ) -> Result<MemoryToolResult, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let tool = MemoryTool::new(&workspace_path);
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
     Ok(tool.execute(MemoryToolCommand::View { path, view_range }))
 }
 
@@ -784,13 +896,14 @@ async fn memory_tool_create(
     workspace: String,
     path: String,
     file_text: String,
+    memories_dir: Option<String>,
 ) -> Result<MemoryToolResult, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let tool = MemoryTool::new(&workspace_path);
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
     Ok(tool.execute(MemoryToolCommand::Create { path, file_text }))
 }
 
@@ -800,14 +913,21 @@ async fn memory_tool_str_replace(
     path: String,
     old_str: String,
     new_str: String,
+    expected_occurrences: Option<usize>,
+    memories_dir: Option<String>,
 ) -> Result<MemoryToolResult, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let tool = MemoryTool::new(&workspace_path);
-    Ok(tool.execute(MemoryToolCommand::StrReplace { path, old_str, new_str }))
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
+    Ok(tool.execute(MemoryToolCommand::StrReplace {
+        path,
+        old_str,
+        new_str,
+        expected_occurrences,
+    }))
 }
 
 #[tauri::command]
@@ -816,13 +936,14 @@ async fn memory_tool_insert(
     path: String,
     insert_line: u32,
     new_str: String,
+    memories_dir: Option<String>,
 ) -> Result<MemoryToolResult, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let tool = MemoryTool::new(&workspace_path);
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
     Ok(tool.execute(MemoryToolCommand::Insert { path, insert_line, new_str }))
 }
 
@@ -830,13 +951,14 @@ async fn memory_tool_insert(
 async fn memory_tool_delete(
     workspace: String,
     path: String,
+    memories_dir: Option<String>,
 ) -> Result<MemoryToolResult, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let tool = MemoryTool::new(&workspace_path);
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
     Ok(tool.execute(MemoryToolCommand::Delete { path }))
 }
 
@@ -845,16 +967,79 @@ async fn memory_tool_rename(
     workspace: String,
     old_path: String,
     new_path: String,
+    memories_dir: Option<String>,
 ) -> Result<MemoryToolResult, String> {
     let workspace_path = PathBuf::from(&workspace);
     if !workspace_path.exists() {
         return Err(format!("Workspace does not exist: {}", workspace));
     }
 
-    let tool = MemoryTool::new(&workspace_path);
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
     Ok(tool.execute(MemoryToolCommand::Rename { old_path, new_path }))
 }
 
+#[tauri::command]
+async fn memory_tool_undo(
+    workspace: String,
+    memories_dir: Option<String>,
+) -> Result<MemoryToolResult, String> {
+    let workspace_path = PathBuf::from(&workspace);
+    if !workspace_path.exists() {
+        return Err(format!("Workspace does not exist: {}", workspace));
+    }
+
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
+    Ok(tool.execute(MemoryToolCommand::Undo))
+}
+
+/// Tree of memory files with sizes
+#[tauri::command]
+async fn memory_tool_list(
+    workspace: String,
+    subpath: Option<String>,
+    memories_dir: Option<String>,
+) -> Result<MemoryToolResult, String> {
+    let workspace_path = PathBuf::from(&workspace);
+    if !workspace_path.exists() {
+        return Err(format!("Workspace does not exist: {}", workspace));
+    }
+
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
+    Ok(tool.execute(MemoryToolCommand::List { subpath }))
+}
+
+/// Search memory files by regex
+#[tauri::command]
+async fn memory_tool_grep(
+    workspace: String,
+    pattern: String,
+    memories_dir: Option<String>,
+) -> Result<MemoryToolResult, String> {
+    let workspace_path = PathBuf::from(&workspace);
+    if !workspace_path.exists() {
+        return Err(format!("Workspace does not exist: {}", workspace));
+    }
+
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
+    Ok(tool.execute(MemoryToolCommand::Grep { pattern }))
+}
+
+/// Dry-run a memory command, returning the diff it would apply
+#[tauri::command]
+async fn memory_tool_preview(
+    workspace: String,
+    command: MemoryToolCommand,
+    memories_dir: Option<String>,
+) -> Result<MemoryToolResult, String> {
+    let workspace_path = PathBuf::from(&workspace);
+    if !workspace_path.exists() {
+        return Err(format!("Workspace does not exist: {}", workspace));
+    }
+
+    let tool = memory_tool_for(&workspace_path, memories_dir.as_deref())?;
+    Ok(tool.preview(command))
+}
+
 // ============ API Settings ============
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -871,6 +1056,12 @@ pub struct ApiSettings {
     pub bedrock_access_key_id: Option<String>,
     pub bedrock_secret_access_key: Option<String>,
     pub bedrock_model: Option<String>,
+    /// Hard spending cap for a conversation (USD)
+    #[serde(default)]
+    pub max_budget_usd: Option<f64>,
+    /// Token budget for skills injected into the system prompt
+    #[serde(default)]
+    pub max_skill_tokens: Option<u64>,
 }
 
 // ============ Simple Chat Commands ============
@@ -884,23 +1075,59 @@ pub struct SimpleChatRequest {
     pub model: Option<String>,
     pub region: Option<String>,
     pub aws_profile: Option<String>,
+    /// Azure OpenAI deployment name
+    #[serde(default)]
+    pub deployment: Option<String>,
+    /// Azure OpenAI API version
+    #[serde(default)]
+    pub api_version: Option<String>,
     pub system_prompt: Option<String>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub stop_sequences: Option<Vec<String>>,
     /// Workspace path for memory context injection
     pub workspace: Option<String>,
+    /// Memories directory relative to the workspace (default `.flowq/memories`)
+    #[serde(default)]
+    pub memories_dir: Option<String>,
+    /// Cache the (potentially large) system prompt across tool-use iterations
+    #[serde(default)]
+    pub enable_prompt_caching: bool,
+    /// Cache the conversation prefix at the end of earlier turns (Anthropic only)
+    #[serde(default)]
+    pub cache_history: bool,
+    /// Cap on memory tool round trips for this request
+    #[serde(default)]
+    pub max_tool_iterations: Option<u32>,
+    /// Tools to offer by name (default: all available)
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    /// Extended thinking budget (Anthropic and Bedrock)
+    #[serde(default)]
+    pub thinking: Option<ThinkingConfig>,
+    /// Reuse the stored response of an identical earlier request
+    #[serde(default)]
+    pub cache: bool,
+}
+
+/// Streaming chunk event emitted by `chat_send_stream`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatStreamEvent {
+    pub stream_id: String,
+    pub chunk: StreamChunk,
 }
 
-#[tauri::command]
-async fn chat_send(request: SimpleChatRequest) -> Result<ChatResponse, String> {
-    log::info!("chat_send called with provider: {}", request.provider);
-
+/// Convert a frontend chat request into a ChatRequest, injecting memory context
+fn build_chat_request(request: SimpleChatRequest) -> ChatRequest {
     // Build system prompt with memory context if workspace is provided
     let system_prompt = if let Some(ref workspace) = request.workspace {
         let workspace_path = PathBuf::from(workspace);
         if workspace_path.exists() {
             // Try to get memory context
-            let memory_context = MemoryIndex::open(&workspace_path)
+            let memory_context = open_memory_index(&workspace_path, request.memories_dir.as_deref())
                 .ok()
                 .and_then(|index| index.get_context().ok())
                 .unwrap_or_default();
@@ -921,6 +1148,9 @@ You have a `memory` tool for persistent storage. Use it ONLY when the user expli
 - `str_replace`: Update text (path, old_str, new_str)
 - `insert`: Insert at line (path, insert_line, new_str)
 - `delete`: Delete file
+- `undo`: Revert the most recent change
+- `list`: Show all memory files with sizes (path: subdirectory, or "" for all)
+- `grep`: Search memory files (pattern: regex)
 
 ## Important
 - When asked "who are you" or general questions, respond normally without mentioning memory
@@ -946,8 +1176,7 @@ You have a `memory` tool for persistent storage. Use it ONLY when the user expli
         request.system_prompt
     };
 
-    let client = ChatClient::new();
-    let chat_request = ChatRequest {
+    ChatRequest {
         messages: request.messages,
         config: ApiConfig {
             provider: request.provider,
@@ -956,16 +1185,169 @@ You have a `memory` tool for persistent storage. Use it ONLY when the user expli
             model: request.model,
             region: request.region,
             aws_profile: request.aws_profile,
+            deployment: request.deployment,
+            api_version: request.api_version,
+            retry_policy: RetryPolicy::default(),
         },
         system_prompt,
         max_tokens: request.max_tokens,
         temperature: request.temperature,
+        top_p: request.top_p,
+        stop_sequences: request.stop_sequences,
         workspace: request.workspace,
+        memories_dir: request.memories_dir,
+        enable_prompt_caching: request.enable_prompt_caching,
+        cache_history: request.cache_history,
+        max_tool_iterations: request.max_tool_iterations,
+        tools: request.tools,
+        thinking: request.thinking,
+        cache: request.cache,
+    }
+}
+
+/// Fill a missing API key from the encrypted secret store
+fn with_stored_api_key(app: &AppHandle, mut request: SimpleChatRequest) -> SimpleChatRequest {
+    if request.api_key.as_deref().is_some_and(|k| !k.is_empty()) {
+        return request;
+    }
+    if let Some(name) = secrets::provider_api_key_name(&request.provider) {
+        request.api_key = secrets::load_app_secret(app, &name);
+    }
+    request
+}
+
+/// Fill missing credentials in agent settings from the encrypted secret store
+fn with_stored_credentials(app: &AppHandle, mut settings: ApiSettings) -> ApiSettings {
+    let fill = |value: &mut Option<String>, name: &str| {
+        if value.as_deref().map_or(true, str::is_empty) {
+            *value = secrets::load_app_secret(app, name);
+        }
     };
+    if settings.provider == "bedrock" {
+        if settings.bedrock_auth_method.as_deref() == Some("access_key") {
+            fill(&mut settings.bedrock_access_key_id, secrets::BEDROCK_ACCESS_KEY_ID);
+            fill(&mut settings.bedrock_secret_access_key, secrets::BEDROCK_SECRET_ACCESS_KEY);
+        }
+    } else {
+        fill(&mut settings.anthropic_api_key, secrets::ANTHROPIC_API_KEY);
+    }
+    settings
+}
+
+/// Opt-in chat tool that reads text files inside the workspace
+const WORKSPACE_READ_TOOL: &str = "workspace_read";
+
+/// Extra chat tools that are only offered when the request names them
+fn requested_chat_tools(request: &ChatRequest) -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+    let requested = |name: &str| request.tools.iter().flatten().any(|t| t == name);
+
+    if let (true, Some(workspace)) = (requested(WORKSPACE_READ_TOOL), request.workspace.clone()) {
+        registry.register(
+            WORKSPACE_READ_TOOL,
+            "Read a text file in the current workspace. Large files are truncated.",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "File path relative to the workspace root"
+                    }
+                },
+                "required": ["path"]
+            }),
+            move |input| {
+                let path = input["path"].as_str().ok_or("path is required")?;
+                let root = PathBuf::from(&workspace)
+                    .canonicalize()
+                    .map_err(|e| format!("Invalid workspace: {}", e))?;
+                let target = root
+                    .join(path)
+                    .canonicalize()
+                    .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                if !target.starts_with(&root) {
+                    return Err(format!("Path is outside the workspace: {}", path));
+                }
+                workspace_files::read_file_preview(&target, None)
+            },
+        );
+    }
+    registry
+}
+
+#[tauri::command]
+async fn chat_send(app: AppHandle, request: SimpleChatRequest) -> Result<ChatResponse, String> {
+    log::info!("chat_send called with provider: {}", request.provider);
 
+    let chat_request = build_chat_request(with_stored_api_key(&app, request));
+    let mut client = ChatClient::new().with_tools(requested_chat_tools(&chat_request));
+    if let Ok(cache_dir) = app.path().app_cache_dir() {
+        client = client.with_response_cache(ResponseCache::new(
+            cache_dir.join("responses"),
+            DEFAULT_RESPONSE_CACHE_TTL,
+        ));
+    }
     client.send(chat_request).await
 }
 
+/// Estimate input tokens and cost for a request without sending it
+#[tauri::command]
+fn chat_estimate(request: SimpleChatRequest) -> Estimate {
+    ChatClient::estimate(&build_chat_request(request))
+}
+
+/// Stream a chat response as `chat-stream` events
+/// Anthropic and OpenAI-compatible providers stream natively; Bedrock emits the full reply as one chunk
+#[tauri::command]
+async fn chat_send_stream(
+    app: AppHandle,
+    request: SimpleChatRequest,
+    stream_id: String,
+) -> Result<(), String> {
+    log::info!("chat_send_stream called with provider: {}", request.provider);
+
+    let emit_chunk = |chunk: StreamChunk| {
+        let event = ChatStreamEvent {
+            stream_id: stream_id.clone(),
+            chunk,
+        };
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.emit("chat-stream", &event);
+        } else {
+            let _ = app.emit("chat-stream", &event);
+        }
+    };
+
+    let chat_request = build_chat_request(with_stored_api_key(&app, request));
+    let client = ChatClient::new().with_tools(requested_chat_tools(&chat_request));
+
+    match chat_request.config.provider.as_str() {
+        "anthropic" => {
+            let stream = client.send_anthropic_stream(chat_request).await?;
+            futures::pin_mut!(stream);
+            while let Some(chunk) = stream.next().await {
+                emit_chunk(chunk?);
+            }
+        }
+        "openai" | "azure" | "custom" => {
+            let stream = client.send_openai_stream(chat_request).await?;
+            futures::pin_mut!(stream);
+            while let Some(chunk) = stream.next().await {
+                emit_chunk(chunk?);
+            }
+        }
+        _ => {
+            let response = client.send(chat_request).await?;
+            emit_chunk(StreamChunk::Text { text: response.content });
+            emit_chunk(StreamChunk::Done {
+                stop_reason: response.stop_reason,
+                usage: response.usage,
+            });
+        }
+    }
+    Ok(())
+}
+
 // ============ Claude Agent Commands ============
 
 #[tauri::command]
@@ -1003,12 +1385,7 @@ async fn send_message(
     }
 
     // Mark session as processing
-    {
-        let mut sessions = state.sessions.lock().unwrap();
-        if let Some(session) = sessions.get_mut(&session_id) {
-            session.is_processing = true;
-        }
-    }
+    let cancel = state.begin_turn(&session_id);
 
     // Get current workspace
     let workspace_path = {
@@ -1073,27 +1450,30 @@ async fn send_message(
         system_prompt
     };
 
-    // Load skills and add to system prompt
+    // Load skills and add to system prompt, within the skill token budget
     // Skills are loaded from: ~/.claude/skills/ and {workspace}/.claude/skills/
     let skills_prompt = {
-        let mut skills_content = String::new();
-        let ws_path = workspace_path.as_deref();
-        if let Ok(skills) = SkillManager::list_all(ws_path) {
-            if !skills.is_empty() {
-                skills_content.push_str("\n\n# Available Skills\n\n");
-                skills_content.push_str("The following skills are installed and available. Use them when relevant:\n\n");
-                for skill in &skills {
-                    // Use the full path stored in SkillInfo
-                    if let Ok(content) = SkillManager::get_content_from_path(&skill.path) {
-                        skills_content.push_str(&format!("## Skill: {}\n\n", skill.name));
-                        skills_content.push_str(&content);
-                        skills_content.push_str("\n\n---\n\n");
-                    }
+        let budget = api_settings
+            .as_ref()
+            .and_then(|s| s.max_skill_tokens)
+            .unwrap_or(skill::DEFAULT_MAX_SKILL_TOKENS);
+        let scope = SkillScope::from_workspace(workspace_path.as_deref());
+        match SkillManager::assemble_prompt(&scope, budget) {
+            Ok(assembled) => {
+                if !assembled.included.is_empty() {
+                    log::info!(
+                        "Loaded {} skills ({} tokens) into system prompt from global and workspace directories",
+                        assembled.included.len(),
+                        assembled.tokens
+                    );
                 }
-                log::info!("Loaded {} skills into system prompt from global and workspace directories", skills.len());
+                assembled.prompt
+            }
+            Err(e) => {
+                log::warn!("Failed to load skills: {}", e);
+                String::new()
             }
         }
-        skills_content
     };
 
     // Combine system prompt with skills
@@ -1107,32 +1487,31 @@ async fn send_message(
     // Convert final_system_prompt to SystemPrompt type
     let system_prompt_option = final_system_prompt.map(claude_agent_sdk_rs::SystemPrompt::from);
 
-    // Load MCP servers from ~/.claude.json
-    let mcp_servers = dirs::home_dir()
-        .map(|home| home.join(".claude.json"))
-        .filter(|p| {
-            if p.exists() {
-                log::info!("Loading MCP config from: {:?}", p);
-                true
-            } else {
-                false
-            }
-        })
-        .map(McpServers::Path)
-        .unwrap_or_default();
+    // MCP servers from ~/.claude.json plus the in-process browser server
+    let configured_servers = McpManager::agent_servers().unwrap_or_else(|e| {
+        log::warn!("Failed to load MCP config: {}", e);
+        HashMap::new()
+    });
+    let mcp_servers = agent_mcp_servers(configured_servers);
+
+    let max_budget_usd = api_settings
+        .as_ref()
+        .and_then(|s| s.max_budget_usd)
+        .filter(|b| *b > 0.0);
 
     // Build environment variables and model
     let mut env_vars: HashMap<String, String> = HashMap::new();
     let mut model_option: Option<String> = None;
 
     // Apply API settings (provider, model, credentials)
+    let api_settings = api_settings.map(|settings| with_stored_credentials(&app, settings));
     if let Some(ref settings) = api_settings {
         log::info!("Applying API settings: provider={}", settings.provider);
 
         if settings.provider == "bedrock" {
             // For Bedrock, set model and AWS credentials via environment variables
             if let Some(ref model) = settings.bedrock_model {
-                model_option = Some(model.clone());
+                model_option = Some(model::resolve("bedrock", model));
             }
             if let Some(ref region) = settings.bedrock_region {
                 env_vars.insert("AWS_REGION".to_string(), region.clone());
@@ -1155,7 +1534,7 @@ async fn send_message(
         } else {
             // For Anthropic direct API
             if let Some(ref model) = settings.anthropic_model {
-                model_option = Some(model.clone());
+                model_option = Some(model::resolve("anthropic", model));
             }
             if let Some(ref api_key) = settings.anthropic_api_key {
                 env_vars.insert("ANTHROPIC_API_KEY".to_string(), api_key.clone());
@@ -1166,6 +1545,10 @@ async fn send_message(
         }
     }
 
+    // The SDK only searches PATH, which a GUI app often inherits without
+    // the installer and npm locations, so hand it the binary we found
+    let cli_path = claude_cli::locate_cli();
+
     // Build options using struct initialization
     let options = ClaudeAgentOptions {
         permission_mode: Some(PermissionMode::BypassPermissions),
@@ -1176,6 +1559,8 @@ async fn send_message(
         mcp_servers,
         model: model_option,
         env: env_vars,
+        max_budget_usd,
+        cli_path: cli_path.clone(),
         ..Default::default()
     };
 
@@ -1185,101 +1570,50 @@ async fn send_message(
         .await
         .map_err(|e| {
             log::error!("Failed to query Claude: {}", e);
+            state.end_turn(&session_id, &cancel);
+            if cli_path.is_none() {
+                return claude_cli::not_found_error();
+            }
             format!("Failed to query Claude: {}", e)
         })?;
 
-    let mut assistant_content = String::new();
-    let assistant_msg_id = Uuid::new_v4().to_string();
+    let mut turn = TurnEvents::new(&session_id, max_budget_usd);
+    let emit = |event: SessionEvent| {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.emit("session-event", &event);
+        } else {
+            let _ = app.emit("session-event", &event);
+        }
+    };
 
     log::info!("Starting to process stream...");
 
-    // Process stream
-    while let Some(message) = stream.next().await {
+    // Process stream until it ends or the turn is cancelled
+    while let Some(message) = next_unless_cancelled(&mut stream, &cancel).await {
         log::info!("Received message: {:?}", message);
         match message {
             Ok(ClaudeMessage::Assistant(msg)) => {
                 log::info!("Assistant message received with {} content blocks", msg.message.content.len());
                 for block in &msg.message.content {
-                    match block {
-                        ContentBlock::Text(text_block) => {
-                            log::info!("Text block: {}", text_block.text);
-                            assistant_content.push_str(&text_block.text);
-                            // Emit text delta event to main window
-                            log::info!("Emitting text_delta event for session: {}", session_id);
-                            let event_data = SessionEvent {
-                                event_type: "text_delta".to_string(),
-                                session_id: session_id.clone(),
-                                data: serde_json::json!({
-                                    "text": assistant_content,
-                                    "message_id": assistant_msg_id
-                                }),
-                            };
-                            if let Some(window) = app.get_webview_window("main") {
-                                let _ = window.emit("session-event", &event_data);
-                            } else {
-                                let _ = app.emit("session-event", &event_data);
-                            }
-                        }
-                        ContentBlock::ToolUse(tool_use) => {
-                            log::info!("Tool use: {} ({}) - input: {:?}", tool_use.name, tool_use.id, tool_use.input);
-                            // Emit tool_use event so UI can show progress
-                            let tool_event = SessionEvent {
-                                event_type: "tool_use".to_string(),
-                                session_id: session_id.clone(),
-                                data: serde_json::json!({
-                                    "tool_id": tool_use.id,
-                                    "tool_name": tool_use.name,
-                                    "tool_input": tool_use.input,
-                                    "message_id": assistant_msg_id
-                                }),
-                            };
-                            if let Some(window) = app.get_webview_window("main") {
-                                let _ = window.emit("session-event", &tool_event);
-                            } else {
-                                let _ = app.emit("session-event", &tool_event);
-                            }
-                        }
-                        _ => {
-                            log::info!("Other content block type");
-                        }
-                    }
+                    turn.assistant_block(block, msg.parent_tool_use_id.as_deref())
+                        .into_iter()
+                        .for_each(&emit);
+                }
+            }
+            // Tool results come back from the CLI as user messages
+            Ok(ClaudeMessage::User(user)) => {
+                for block in user.content.iter().flatten() {
+                    turn.user_block(block).into_iter().for_each(&emit);
                 }
             }
             Ok(ClaudeMessage::Result(result)) => {
                 log::info!("Result received: cost={:?}, turns={:?}", result.total_cost_usd, result.num_turns);
-                // Emit complete event to main window
-                let complete_event = SessionEvent {
-                    event_type: "complete".to_string(),
-                    session_id: session_id.clone(),
-                    data: serde_json::json!({
-                        "message_id": assistant_msg_id,
-                        "content": assistant_content,  // Include final content
-                        "cost": result.total_cost_usd,
-                        "turns": result.num_turns
-                    }),
-                };
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.emit("session-event", &complete_event);
-                } else {
-                    let _ = app.emit("session-event", &complete_event);
-                }
+                turn.result(result.total_cost_usd, result.num_turns).into_iter().for_each(&emit);
                 break;
             }
             Err(e) => {
                 log::error!("Error in stream: {}", e);
-                // Emit error event to main window
-                let error_event = SessionEvent {
-                    event_type: "error".to_string(),
-                    session_id: session_id.clone(),
-                    data: serde_json::json!({
-                        "error": e.to_string()
-                    }),
-                };
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.emit("session-event", &error_event);
-                } else {
-                    let _ = app.emit("session-event", &error_event);
-                }
+                emit(turn.error(&e.to_string()));
                 break;
             }
             other => {
@@ -1289,12 +1623,18 @@ async fn send_message(
     }
     log::info!("Stream processing complete");
 
+    if cancel.is_cancelled() {
+        log::info!("Turn cancelled for session: {}", session_id);
+        emit(turn.cancelled());
+    }
+
     // Save assistant message
+    let assistant_msg_id = turn.message_id.clone();
     let assistant_message = Message {
         id: assistant_msg_id.clone(),
         session_id: session_id.clone(),
         role: "assistant".to_string(),
-        content: assistant_content,
+        content: turn.content,
         timestamp: chrono::Utc::now().to_rfc3339(),
     };
 
@@ -1306,15 +1646,268 @@ async fn send_message(
     }
 
     // Mark session as not processing
-    {
-        let mut sessions = state.sessions.lock().unwrap();
-        if let Some(session) = sessions.get_mut(&session_id) {
-            session.is_processing = false;
-            session.updated_at = chrono::Utc::now().to_rfc3339();
+    state.end_turn(&session_id, &cancel);
+
+    Ok(assistant_msg_id)
+}
+
+/// Wait for the next stream item, or `None` once the turn is cancelled
+async fn next_unless_cancelled<S>(stream: &mut S, cancel: &CancellationToken) -> Option<S::Item>
+where
+    S: futures::Stream + Unpin,
+{
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => None,
+        item = stream.next() => item,
+    }
+}
+
+// ============ Turn Events ============
+
+/// Maps one agent turn's stream onto `session-event` payloads
+struct TurnEvents {
+    session_id: String,
+    message_id: String,
+    max_budget_usd: Option<f64>,
+    /// Answer text so far; saved as the assistant message
+    content: String,
+    /// Extended thinking so far, streamed separately and kept out of the saved answer
+    thinking: String,
+    tool_timeline: ToolTimeline,
+}
+
+impl TurnEvents {
+    fn new(session_id: &str, max_budget_usd: Option<f64>) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            message_id: Uuid::new_v4().to_string(),
+            max_budget_usd,
+            content: String::new(),
+            thinking: String::new(),
+            tool_timeline: ToolTimeline::default(),
         }
     }
 
-    Ok(assistant_msg_id)
+    fn event(&self, event_type: &str, data: serde_json::Value) -> SessionEvent {
+        SessionEvent {
+            event_type: event_type.to_string(),
+            session_id: self.session_id.clone(),
+            data,
+        }
+    }
+
+    /// `tool_result` events for every call still running
+    fn finish_tools(&mut self) -> Vec<SessionEvent> {
+        let finished = self.tool_timeline.finish_all();
+        self.tool_result_events(finished)
+    }
+
+    /// `tool_result` events for the running calls made by one agent
+    /// (`parent_tool_use_id` is None for the main agent, or the subagent's Task call)
+    fn finish_agent_tools(&mut self, parent_tool_use_id: Option<&str>) -> Vec<SessionEvent> {
+        let finished = self.tool_timeline.finish_owned_by(parent_tool_use_id);
+        self.tool_result_events(finished)
+    }
+
+    fn tool_result_events(&self, finished: Vec<serde_json::Value>) -> Vec<SessionEvent> {
+        finished
+            .into_iter()
+            .map(|data| self.event("tool_result", data))
+            .collect()
+    }
+
+    /// Events for one content block of an assistant message
+    /// Subagent messages carry the id of the Task call that started them in `parent_tool_use_id`
+    fn assistant_block(&mut self, block: &ContentBlock, parent_tool_use_id: Option<&str>) -> Vec<SessionEvent> {
+        match block {
+            ContentBlock::Text(text_block) => {
+                log::info!("Text block: {}", text_block.text);
+                // This agent moved on, so its earlier tool calls have finished
+                let mut events = self.finish_agent_tools(parent_tool_use_id);
+                self.content.push_str(&text_block.text);
+                events.push(self.event(
+                    "text_delta",
+                    serde_json::json!({
+                        "text": self.content,
+                        "message_id": self.message_id
+                    }),
+                ));
+                events
+            }
+            ContentBlock::ToolUse(tool_use) => {
+                log::info!("Tool use: {} ({}) - input: {:?}", tool_use.name, tool_use.id, tool_use.input);
+                let started_at = self.tool_timeline.start(&tool_use.id, parent_tool_use_id);
+                // Lets the UI show progress
+                vec![self.event(
+                    "tool_use",
+                    serde_json::json!({
+                        "tool_id": tool_use.id,
+                        "tool_name": tool_use.name,
+                        "tool_input": tool_use.input,
+                        "message_id": self.message_id,
+                        "started_at": started_at
+                    }),
+                )]
+            }
+            ContentBlock::ToolResult(_) => self.user_block(block),
+            ContentBlock::Thinking(thinking_block) => {
+                let mut events = self.finish_agent_tools(parent_tool_use_id);
+                self.thinking.push_str(&thinking_block.thinking);
+                events.push(self.event(
+                    "thinking_delta",
+                    serde_json::json!({
+                        "thinking": self.thinking,
+                        "message_id": self.message_id
+                    }),
+                ));
+                events
+            }
+            _ => {
+                log::info!("Other content block type");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Events for a block of a user message, where the CLI returns tool results
+    fn user_block(&mut self, block: &ContentBlock) -> Vec<SessionEvent> {
+        match block {
+            ContentBlock::ToolResult(result) => self
+                .tool_timeline
+                .finish_block(result)
+                .map(|data| self.event("tool_result", data))
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Events for the final result of the turn
+    /// `budget_exceeded` follows `complete` when the spending cap stopped the CLI
+    fn result(&mut self, total_cost_usd: Option<f64>, num_turns: u32) -> Vec<SessionEvent> {
+        let mut events = self.finish_tools();
+        events.push(self.event(
+            "complete",
+            serde_json::json!({
+                "message_id": self.message_id,
+                "content": self.content,
+                "cost": total_cost_usd,
+                "turns": num_turns
+            }),
+        ));
+
+        if let (Some(spent), Some(budget)) = (total_cost_usd, self.max_budget_usd) {
+            if spent >= budget {
+                log::warn!("Budget exceeded: spent ${:.4} of ${:.4}", spent, budget);
+                events.push(self.event(
+                    "budget_exceeded",
+                    serde_json::json!({
+                        "spent": spent,
+                        "budget": budget
+                    }),
+                ));
+            }
+        }
+        events
+    }
+
+    fn error(&self, error: &str) -> SessionEvent {
+        self.event("error", serde_json::json!({ "error": error }))
+    }
+
+    fn cancelled(&self) -> SessionEvent {
+        self.event(
+            "cancelled",
+            serde_json::json!({
+                "message_id": self.message_id,
+                "content": self.content
+            }),
+        )
+    }
+}
+
+// ============ Tool Timeline ============
+
+/// Characters of tool output included in a `tool_result` event
+const TOOL_RESULT_PREVIEW_CHARS: usize = 2000;
+
+/// Pairs each `tool_use` event with a `tool_result` event so the UI can show durations
+#[derive(Default)]
+struct ToolTimeline {
+    /// Start time and owning agent (`parent_tool_use_id`, None for the main agent) of open calls
+    started: HashMap<String, (chrono::DateTime<chrono::Utc>, Option<String>)>,
+}
+
+impl ToolTimeline {
+    /// Record the start of a tool call, returning its timestamp
+    fn start(&mut self, tool_id: &str, parent_tool_use_id: Option<&str>) -> String {
+        let now = chrono::Utc::now();
+        self.started
+            .insert(tool_id.to_string(), (now, parent_tool_use_id.map(str::to_string)));
+        now.to_rfc3339()
+    }
+
+    /// Event data for a finished call, or `None` if its start wasn't seen
+    fn finish(&mut self, tool_id: &str, is_error: bool, output: Option<&str>) -> Option<serde_json::Value> {
+        let (started_at, _) = self.started.remove(tool_id)?;
+        let finished_at = chrono::Utc::now();
+        let result = output.map(|text| {
+            if text.chars().count() > TOOL_RESULT_PREVIEW_CHARS {
+                format!("{}...", text.chars().take(TOOL_RESULT_PREVIEW_CHARS).collect::<String>())
+            } else {
+                text.to_string()
+            }
+        });
+        Some(serde_json::json!({
+            "tool_id": tool_id,
+            "success": !is_error,
+            "result": result,
+            "started_at": started_at.to_rfc3339(),
+            "finished_at": finished_at.to_rfc3339(),
+            "elapsed_ms": (finished_at - started_at).num_milliseconds()
+        }))
+    }
+
+    /// Finish a call from a `tool_result` content block
+    fn finish_block(&mut self, block: &ToolResultBlock) -> Option<serde_json::Value> {
+        let output = match &block.content {
+            Some(ToolResultContent::Text(text)) => Some(text.clone()),
+            Some(ToolResultContent::Blocks(blocks)) => Some(
+                blocks
+                    .iter()
+                    .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            None => None,
+        };
+        self.finish(&block.tool_use_id, block.is_error.unwrap_or(false), output.as_deref())
+    }
+
+    /// Finish every open call without a result
+    fn finish_all(&mut self) -> Vec<serde_json::Value> {
+        let ids: Vec<String> = self.started.keys().cloned().collect();
+        ids.iter().filter_map(|id| self.finish(id, false, None)).collect()
+    }
+
+    /// Finish the open calls one agent made, without a result
+    fn finish_owned_by(&mut self, parent_tool_use_id: Option<&str>) -> Vec<serde_json::Value> {
+        let ids: Vec<String> = self
+            .started
+            .iter()
+            .filter(|(_, (_, owner))| owner.as_deref() == parent_tool_use_id)
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.iter().filter_map(|id| self.finish(id, false, None)).collect()
+    }
+}
+
+/// Stop the in-flight turn for a session
+/// The stream loop emits a `cancelled` session event once it stops
+#[tauri::command]
+fn cancel_message(state: State<'_, AppState>, session_id: String) -> bool {
+    state.cancel_turn(&session_id)
 }
 
 // ============ Claude Code CLI Commands ============
@@ -1324,6 +1917,8 @@ pub struct ClaudeCodeStatus {
     pub installed: bool,
     pub version: Option<String>,
     pub path: Option<String>,
+    /// Suggested install command when the CLI is missing
+    pub install_command: Option<String>,
 }
 
 /// Check if Claude Code CLI is installed
@@ -1331,17 +1926,13 @@ pub struct ClaudeCodeStatus {
 async fn check_claude_code() -> Result<ClaudeCodeStatus, String> {
     use std::process::Command;
 
-    // Try to find claude in PATH
-    let output = Command::new("which")
-        .arg("claude")
-        .output();
-
-    match output {
-        Ok(result) if result.status.success() => {
-            let path = String::from_utf8_lossy(&result.stdout).trim().to_string();
+    // Search PATH plus the installer and npm global locations
+    match claude_cli::locate_cli() {
+        Some(cli_path) => {
+            let path = cli_path.to_string_lossy().to_string();
 
             // Try to get version
-            let version_output = Command::new("claude")
+            let version_output = Command::new(&cli_path)
                 .arg("--version")
                 .output();
 
@@ -1357,13 +1948,15 @@ async fn check_claude_code() -> Result<ClaudeCodeStatus, String> {
                 installed: true,
                 version,
                 path: Some(path),
+                install_command: None,
             })
         }
-        _ => {
+        None => {
             Ok(ClaudeCodeStatus {
                 installed: false,
                 version: None,
                 path: None,
+                install_command: Some(claude_cli::INSTALL_COMMAND.to_string()),
             })
         }
     }
@@ -1377,7 +1970,7 @@ async fn install_claude_code(app: AppHandle) -> Result<String, String> {
     // Run the official install script
     let output = Command::new("bash")
         .arg("-c")
-        .arg("curl -fsSL https://claude.ai/install.sh | bash")
+        .arg(claude_cli::INSTALL_COMMAND)
         .output()
         .map_err(|e| format!("Failed to run install script: {}", e))?;
 
@@ -1393,7 +1986,7 @@ async fn install_claude_code(app: AppHandle) -> Result<String, String> {
 /// Get the install command for manual installation
 #[tauri::command]
 fn get_claude_code_install_command() -> String {
-    "curl -fsSL https://claude.ai/install.sh | bash".to_string()
+    claude_cli::INSTALL_COMMAND.to_string()
 }
 
 // ============ Environment Check Commands ============
@@ -1419,14 +2012,8 @@ pub struct EnvironmentStatus {
 fn check_tool(name: &str, version_arg: &str) -> ToolStatus {
     use std::process::Command;
 
-    let which_output = Command::new("which")
-        .arg(name)
-        .output();
-
-    match which_output {
-        Ok(result) if result.status.success() => {
-            let path = String::from_utf8_lossy(&result.stdout).trim().to_string();
-
+    match claude_cli::find_on_path(name) {
+        Some(path) => {
             // Try to get version
             let version_output = Command::new(name)
                 .arg(version_arg)
@@ -1446,10 +2033,10 @@ fn check_tool(name: &str, version_arg: &str) -> ToolStatus {
                 name: name.to_string(),
                 installed: true,
                 version,
-                path: Some(path),
+                path: Some(path.to_string_lossy().to_string()),
             }
         }
-        _ => {
+        None => {
             ToolStatus {
                 name: name.to_string(),
                 installed: false,
@@ -1600,6 +2187,58 @@ async fn browser_screenshot(tab_id: u32) -> Result<serde_json::Value, String> {
     server.send_command(browser::BrowserRequest::Screenshot { tab_id }).await
 }
 
+/// Navigate back in a tab's history
+#[tauri::command]
+async fn browser_back(tab_id: u32) -> Result<serde_json::Value, String> {
+    let server = browser::get_browser_relay();
+    server.send_command(browser::BrowserRequest::Back { tab_id }).await
+}
+
+/// Navigate forward in a tab's history
+#[tauri::command]
+async fn browser_forward(tab_id: u32) -> Result<serde_json::Value, String> {
+    let server = browser::get_browser_relay();
+    server.send_command(browser::BrowserRequest::Forward { tab_id }).await
+}
+
+/// Reload a tab, optionally bypassing the cache
+#[tauri::command]
+async fn browser_reload(tab_id: u32, ignore_cache: Option<bool>) -> Result<serde_json::Value, String> {
+    let server = browser::get_browser_relay();
+    server.send_command(browser::BrowserRequest::Reload {
+        tab_id,
+        ignore_cache: ignore_cache.unwrap_or(false),
+    }).await
+}
+
+/// List the browser tools with their input schemas
+#[tauri::command]
+fn browser_list_tools() -> Vec<browser::BrowserTool> {
+    browser::browser_tools()
+}
+
+/// Invoke a browser tool by name
+#[tauri::command]
+async fn browser_call_tool(name: String, args: serde_json::Value) -> Result<serde_json::Value, String> {
+    let server = browser::get_browser_relay();
+    browser::call_browser_tool(&server, &name, &args).await
+}
+
+/// Wait until an element matching the selector exists (default timeout 5s)
+#[tauri::command]
+async fn browser_wait_for_selector(
+    tab_id: u32,
+    selector: String,
+    timeout_ms: Option<u64>,
+) -> Result<serde_json::Value, String> {
+    let server = browser::get_browser_relay();
+    server.send_command(browser::BrowserRequest::WaitForSelector {
+        tab_id,
+        selector,
+        timeout_ms: timeout_ms.unwrap_or(5000),
+    }).await
+}
+
 // ============ App Entry ============
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1627,13 +2266,28 @@ pub fn run() {
             std::fs::create_dir_all(&app_data_dir)
                 .expect("Failed to create app data directory");
 
+            // Before the frontend loads settings, move any plaintext API keys out of them
+            secrets::migrate_app_settings(&app_data_dir);
+
             let db_path = app_data_dir.join("chat_history.db");
             log::info!("Initializing database at: {:?}", db_path);
 
             let db = ChatDatabase::open(&db_path)
                 .expect("Failed to open database");
 
-            app.manage(AppState::new(db));
+            // Restore the last workspace if it still exists
+            let last_workspace = db
+                .get_recent_workspaces()
+                .unwrap_or_default()
+                .into_iter()
+                .next()
+                .filter(|path| PathBuf::from(path).exists());
+            let state = AppState::new(db);
+            if let Some(path) = last_workspace {
+                log::info!("Restored workspace: {}", path);
+                *state.workspace.lock().unwrap() = Some(path);
+            }
+            app.manage(state);
 
             // Start browser relay server and HTTP API in background
             let browser_server = browser::get_browser_relay();
@@ -1652,6 +2306,9 @@ pub fn run() {
                 }
             });
 
+            // Start background RSS refresh
+            rss_scheduler::start(app.handle().clone(), rss_db::get_rss_db(&app_data_dir));
+
             log::info!("Tauri app started with CLAUDE_CODE_USE_BEDROCK={}",
                 std::env::var("CLAUDE_CODE_USE_BEDROCK").unwrap_or_default());
             Ok(())
@@ -1676,6 +2333,7 @@ pub fn run() {
             // Workspace commands
             set_workspace,
             get_workspace,
+            get_recent_workspaces,
             // Database commands (SQLite)
             db_create_session,
             db_get_sessions,
@@ -1684,18 +2342,28 @@ pub fn run() {
             db_delete_session,
             db_append_message,
             db_get_messages,
+            db_get_messages_page,
+            db_branch_from,
+            db_search_messages,
             db_get_recent_messages,
             db_update_message_metadata,
             db_get_session_with_messages,
+            db_export_session,
+            db_generate_session_title,
             db_update_session_flag,
             db_update_session_status,
             db_update_session_unread,
             db_get_flagged_sessions,
+            db_set_session_tags,
+            db_get_sessions_by_tag,
             db_get_sessions_by_status,
             // Claude commands
             send_message,
+            cancel_message,
             // Simple chat commands
             chat_send,
+            chat_send_stream,
+            chat_estimate,
             // MCP commands
             mcp_list_servers,
             mcp_add_server,
@@ -1703,6 +2371,7 @@ pub fn run() {
             mcp_toggle_server,
             mcp_update_server,
             mcp_get_server,
+            mcp_health_check,
             // Skills commands
             skill_list,
             skill_get_content,
@@ -1713,6 +2382,8 @@ pub fn run() {
             skill_install_from_url,
             skill_install_from_zip,
             skill_delete,
+            skill_update,
+            skill_set_enabled,
             skill_open_folder,
             skill_search,
             open_directory,
@@ -1721,6 +2392,8 @@ pub fn run() {
             memory_search,
             memory_get_context,
             memory_get_stats,
+            memory_export,
+            memory_import,
             // Workspace file search commands
             search_workspace_files,
             read_file_for_mention,
@@ -1732,6 +2405,10 @@ pub fn run() {
             memory_tool_insert,
             memory_tool_delete,
             memory_tool_rename,
+            memory_tool_undo,
+            memory_tool_preview,
+            memory_tool_list,
+            memory_tool_grep,
             // Claude Code CLI commands
             check_claude_code,
             install_claude_code,
@@ -1754,26 +2431,238 @@ pub fn run() {
             browser_type,
             browser_scroll,
             browser_screenshot,
+            browser_wait_for_selector,
+            browser_back,
+            browser_forward,
+            browser_reload,
+            browser_list_tools,
+            browser_call_tool,
             // RSS commands
             rss::rss_fetch,
             rss::rss_fetch_and_parse,
             rss::rss_parse,
+            rss::rss_discover_feeds,
+            rss_scheduler::rss_refresh_feed,
+            rss_scheduler::rss_refresh_all,
+            rss_topics::rss_classify_article,
+            rss_topics::rss_classify_recent,
+            secrets::store_secret,
+            secrets::load_secret,
             rss_db::rss_get_feeds,
             rss_db::rss_create_feed,
             rss_db::rss_update_feed,
+            rss_db::rss_reset_feed_health,
             rss_db::rss_delete_feed,
             rss_db::rss_get_categories,
             rss_db::rss_create_category,
             rss_db::rss_delete_category,
             rss_db::rss_upsert_articles,
             rss_db::rss_get_articles,
+            rss_db::rss_get_articles_page,
             rss_db::rss_get_recent_articles,
             rss_db::rss_search_articles,
             rss_db::rss_mark_article_read,
+            rss_db::rss_mark_feed_read,
+            rss_db::rss_mark_all_read,
             rss_db::rss_toggle_article_starred,
             rss_db::rss_get_starred_articles,
             rss_db::rss_cleanup_old_articles,
+            rss_db::rss_import_opml,
+            rss_db::rss_export_opml,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// MCP servers offered to the agent: the configured servers plus the in-process browser server
+fn agent_mcp_servers(mut servers: HashMap<String, McpServerConfig>) -> McpServers {
+    servers.insert(
+        browser::BROWSER_MCP_SERVER_NAME.to_string(),
+        McpServerConfig::Sdk(browser::browser_mcp_server()),
+    );
+    McpServers::Dict(servers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn state_with_session(session_id: &str) -> (tempfile::TempDir, AppState) {
+        let dir = tempdir().unwrap();
+        let state = AppState::new(ChatDatabase::open(dir.path().join("chat.db")).unwrap());
+        state.sessions.lock().unwrap().insert(
+            session_id.to_string(),
+            Session {
+                id: session_id.to_string(),
+                title: "New Chat".to_string(),
+                created_at: String::new(),
+                updated_at: String::new(),
+                is_processing: false,
+            },
+        );
+        (dir, state)
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_stream_and_clears_processing() {
+        let (_dir, state) = state_with_session("s1");
+        assert!(!state.cancel_turn("s1"));
+
+        let cancel = state.begin_turn("s1");
+        assert!(state.sessions.lock().unwrap()["s1"].is_processing);
+
+        // One message arrives, then the stream stalls until cancelled
+        let mut stream = futures::stream::iter(vec![1]).chain(futures::stream::pending());
+        assert_eq!(next_unless_cancelled(&mut stream, &cancel).await, Some(1));
+
+        assert!(state.cancel_turn("s1"));
+        assert_eq!(next_unless_cancelled(&mut stream, &cancel).await, None);
+
+        state.end_turn("s1", &cancel);
+        assert!(!state.sessions.lock().unwrap()["s1"].is_processing);
+        assert!(!state.cancel_turn("s1"));
+    }
+
+    #[test]
+    fn test_finished_turn_keeps_newer_turn_token() {
+        let (_dir, state) = state_with_session("s1");
+        let first = state.begin_turn("s1");
+        let second = state.begin_turn("s1");
+
+        // The first turn finishing late must not drop the second turn's token
+        state.end_turn("s1", &first);
+        assert!(state.sessions.lock().unwrap()["s1"].is_processing);
+        assert!(state.cancel_turn("s1"));
+        assert!(second.is_cancelled());
+        assert!(!first.is_cancelled());
+
+        state.end_turn("s1", &second);
+        assert!(!state.sessions.lock().unwrap()["s1"].is_processing);
+        assert!(!state.cancel_turn("s1"));
+    }
+
+    #[test]
+    fn test_tool_timeline_pairs_start_and_result() {
+        let mut timeline = ToolTimeline::default();
+        let started_at = timeline.start("toolu_1", None);
+
+        let block = ToolResultBlock {
+            tool_use_id: "toolu_1".to_string(),
+            content: Some(ToolResultContent::Text("x".repeat(TOOL_RESULT_PREVIEW_CHARS + 10))),
+            is_error: Some(true),
+        };
+        let data = timeline.finish_block(&block).unwrap();
+        assert_eq!(data["tool_id"], "toolu_1");
+        assert_eq!(data["success"], false);
+        assert_eq!(data["started_at"], started_at.as_str());
+        assert!(data["elapsed_ms"].as_i64().unwrap() >= 0);
+        assert_eq!(data["result"].as_str().unwrap().len(), TOOL_RESULT_PREVIEW_CHARS + 3);
+
+        // Each call reports once; unmatched results are ignored
+        assert!(timeline.finish_block(&block).is_none());
+        assert!(timeline.finish_all().is_empty());
+
+        // Calls still open when the model moves on finish without output
+        timeline.start("toolu_2", None);
+        let flushed = timeline.finish_all();
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(flushed[0]["tool_id"], "toolu_2");
+        assert_eq!(flushed[0]["success"], true);
+        assert!(flushed[0]["result"].is_null());
+    }
+
+    #[test]
+    fn test_agent_options_include_browser_server() {
+        let mut configured = HashMap::new();
+        configured.insert(
+            "fs".to_string(),
+            McpServerConfig::Stdio(claude_agent_sdk_rs::McpStdioServerConfig {
+                command: "npx".to_string(),
+                args: None,
+                env: None,
+            }),
+        );
+
+        let options = ClaudeAgentOptions {
+            mcp_servers: agent_mcp_servers(configured),
+            ..Default::default()
+        };
+        let McpServers::Dict(servers) = options.mcp_servers else {
+            panic!("expected a server map");
+        };
+        assert!(servers.contains_key("fs"));
+        let McpServerConfig::Sdk(browser) = &servers["browser"] else {
+            panic!("browser should be an in-process server");
+        };
+        assert_eq!(browser.name, "browser");
+    }
+
+    fn event_types(events: &[SessionEvent]) -> Vec<&str> {
+        events.iter().map(|e| e.event_type.as_str()).collect()
+    }
+
+    #[test]
+    fn test_turn_events_stream_thinking_separately() {
+        let mut turn = TurnEvents::new("s1", None);
+        let thinking = |text: &str| {
+            ContentBlock::Thinking(claude_agent_sdk_rs::ThinkingBlock {
+                thinking: text.to_string(),
+                signature: String::new(),
+            })
+        };
+
+        let events = turn.assistant_block(&thinking("Let me "), None);
+        let events = [events, turn.assistant_block(&thinking("check."), None)].concat();
+        assert_eq!(event_types(&events), vec!["thinking_delta", "thinking_delta"]);
+        assert_eq!(events[1].session_id, "s1");
+        assert_eq!(events[1].data["thinking"], "Let me check.");
+        assert_eq!(events[1].data["message_id"], turn.message_id.as_str());
+
+        // Thinking never leaks into the saved answer
+        let done = turn.result(Some(0.01), 1);
+        assert_eq!(event_types(&done), vec!["complete"]);
+        assert_eq!(done[0].data["content"], "");
+        assert!(turn.content.is_empty());
+    }
+
+    #[test]
+    fn test_turn_events_budget_exceeded_after_complete() {
+        let mut turn = TurnEvents::new("s1", Some(0.5));
+        assert_eq!(event_types(&turn.result(Some(0.25), 2)), vec!["complete"]);
+
+        let events = turn.result(Some(0.5), 3);
+        assert_eq!(event_types(&events), vec!["complete", "budget_exceeded"]);
+        assert_eq!(events[1].data["spent"], 0.5);
+        assert_eq!(events[1].data["budget"], 0.5);
+
+        // No cap configured, or no cost reported: nothing to flag
+        assert_eq!(event_types(&TurnEvents::new("s1", None).result(Some(9.0), 1)), vec!["complete"]);
+        assert_eq!(event_types(&TurnEvents::new("s1", Some(0.5)).result(None, 1)), vec!["complete"]);
+    }
+
+    #[test]
+    fn test_turn_events_subagent_text_keeps_task_open() {
+        let mut turn = TurnEvents::new("s1", None);
+        let tool_use = |id: &str, name: &str| {
+            ContentBlock::ToolUse(claude_agent_sdk_rs::ToolUseBlock {
+                id: id.to_string(),
+                name: name.to_string(),
+                input: Default::default(),
+            })
+        };
+        let text = |text: &str| ContentBlock::Text(claude_agent_sdk_rs::TextBlock { text: text.to_string() });
+
+        // The main agent starts a Task; the subagent runs a tool and then writes text
+        assert_eq!(event_types(&turn.assistant_block(&tool_use("task_1", "Task"), None)), vec!["tool_use"]);
+        assert_eq!(event_types(&turn.assistant_block(&tool_use("grep_1", "Grep"), Some("task_1"))), vec!["tool_use"]);
+        let events = turn.assistant_block(&text("Found it."), Some("task_1"));
+        assert_eq!(event_types(&events), vec!["tool_result", "text_delta"]);
+        assert_eq!(events[0].data["tool_id"], "grep_1");
+
+        // The Task call only finishes once the main agent moves on
+        let events = turn.assistant_block(&text("Done."), None);
+        assert_eq!(event_types(&events), vec!["tool_result", "text_delta"]);
+        assert_eq!(events[0].data["tool_id"], "task_1");
+    }
+}
@@ -1,13 +1,19 @@
 //! MCP Server Configuration Manager
 //!
 //! Manages MCP server configurations stored in ~/.claude.json
-//! This module handles CRUD operations for MCP servers without
-//! actually spawning connections - that's handled by Claude Code CLI.
+//! This module handles CRUD operations for MCP servers. Real connections are
+//! handled by Claude Code CLI; the health check only starts a server long
+//! enough to run `initialize` and `tools/list`.
 
+use claude_agent_sdk_rs::{McpHttpServerConfig, McpServerConfig, McpSseServerConfig, McpStdioServerConfig};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
 
 // ============ Types ============
 
@@ -43,6 +49,15 @@ pub struct McpToolInfo {
     pub description: String,
 }
 
+/// Result of probing a configured MCP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpHealth {
+    pub reachable: bool,
+    pub tool_count: usize,
+    pub tools: Vec<McpToolInfo>,
+    pub error: Option<String>,
+}
+
 // ============ Error Type ============
 
 #[derive(Debug)]
@@ -52,6 +67,7 @@ pub enum McpError {
     JsonError(serde_json::Error),
     InvalidConfig(String),
     ServerNotFound(String),
+    MissingPrerequisites(Vec<String>),
 }
 
 impl std::fmt::Display for McpError {
@@ -62,6 +78,11 @@ impl std::fmt::Display for McpError {
             McpError::JsonError(e) => write!(f, "JSON error: {}", e),
             McpError::InvalidConfig(msg) => write!(f, "Invalid config: {}", msg),
             McpError::ServerNotFound(name) => write!(f, "Server not found: {}", name),
+            McpError::MissingPrerequisites(missing) => write!(
+                f,
+                "Missing prerequisites not found on PATH: {}. Install them or add the server anyway with force",
+                missing.join(", ")
+            ),
         }
     }
 }
@@ -131,8 +152,51 @@ impl McpManager {
     }
 
     /// Add a new MCP server
-    pub fn add(request: AddMcpServerRequest) -> Result<()> {
+    /// Unless `force` is set, fails if a stdio command or its prerequisites aren't on PATH
+    pub fn add(request: AddMcpServerRequest, force: bool) -> Result<()> {
         let mut config = Self::read_config()?;
+        Self::add_to_config(&mut config, request, force)?;
+        Self::write_config(&config)
+    }
+
+    /// Check that a stdio server's command and its runtime can be found
+    pub fn validate(request: &AddMcpServerRequest) -> Result<()> {
+        let missing = Self::missing_prerequisites(request);
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(McpError::MissingPrerequisites(missing))
+        }
+    }
+
+    /// Commands a stdio request needs that aren't on PATH, with install hints
+    fn missing_prerequisites(request: &AddMcpServerRequest) -> Vec<String> {
+        let Some(command) = request.command.as_deref().filter(|_| request.transport == "stdio") else {
+            return Vec::new();
+        };
+        // Launchers also need the runtime reported by check_environment
+        let runtime: &[&str] = match command {
+            "npx" | "npm" => &["node"],
+            "uvx" => &["uv"],
+            _ => &[],
+        };
+
+        std::iter::once(command)
+            .chain(runtime.iter().copied())
+            .filter(|name| crate::claude_cli::find_on_path(name).is_none())
+            .map(|name| match name {
+                "node" | "npx" | "npm" => format!("{} (install Node.js)", name),
+                "uv" | "uvx" => format!("{} (install uv)", name),
+                _ => name.to_string(),
+            })
+            .collect()
+    }
+
+    /// Insert a server entry into a parsed config
+    fn add_to_config(config: &mut serde_json::Value, request: AddMcpServerRequest, force: bool) -> Result<()> {
+        if !force {
+            Self::validate(&request)?;
+        }
 
         // Get or create mcpServers object
         let mcp_servers = config
@@ -180,8 +244,6 @@ impl McpManager {
         }
 
         mcp_servers.insert(request.name, serde_json::Value::Object(server_config));
-        Self::write_config(&config)?;
-
         Ok(())
     }
 
@@ -221,10 +283,14 @@ impl McpManager {
     }
 
     /// Update an existing MCP server
-    pub fn update(name: &str, request: AddMcpServerRequest) -> Result<()> {
+    pub fn update(name: &str, request: AddMcpServerRequest, force: bool) -> Result<()> {
+        // Validate before removing so a bad update keeps the old config
+        if !force {
+            Self::validate(&request)?;
+        }
         // Remove old and add new with same name
         Self::remove(name)?;
-        Self::add(request)?;
+        Self::add(request, true)?;
         Ok(())
     }
 
@@ -240,6 +306,51 @@ impl McpManager {
         Ok(server)
     }
 
+    /// Enabled servers from ~/.claude.json in the form the agent SDK accepts
+    pub fn agent_servers() -> Result<HashMap<String, McpServerConfig>> {
+        let config = Self::read_config()?;
+        Ok(Self::agent_servers_from(&config))
+    }
+
+    fn agent_servers_from(config: &Value) -> HashMap<String, McpServerConfig> {
+        config
+            .get("mcpServers")
+            .and_then(|s| s.as_object())
+            .map(|obj| {
+                obj.iter()
+                    .filter_map(|(name, value)| Some((name.clone(), Self::agent_server(name, value)?)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// SDK config for one server entry, None if it is disabled or incomplete
+    fn agent_server(name: &str, value: &Value) -> Option<McpServerConfig> {
+        let server = Self::parse_server(name, value);
+        if server.disabled == Some(true) {
+            return None;
+        }
+        if server.transport == "stdio" {
+            return Some(McpServerConfig::Stdio(McpStdioServerConfig {
+                command: server.command?,
+                args: server.args,
+                env: server.env,
+            }));
+        }
+
+        let url = server.url?;
+        Some(if Self::is_sse(value) {
+            McpServerConfig::Sse(McpSseServerConfig { url, headers: server.headers })
+        } else {
+            McpServerConfig::Http(McpHttpServerConfig { url, headers: server.headers })
+        })
+    }
+
+    /// parse_server folds sse into http, so check the raw type
+    fn is_sse(value: &Value) -> bool {
+        value.get("type").and_then(|v| v.as_str()) == Some("sse")
+    }
+
     /// Parse server config from JSON
     fn parse_server(name: &str, value: &serde_json::Value) -> McpServerInfo {
         let obj = value.as_object();
@@ -295,6 +406,346 @@ impl McpManager {
     }
 }
 
+// ============ Health Check ============
+
+/// Time allowed for a server to start and list its tools
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// MCP protocol version sent in `initialize`
+const PROTOCOL_VERSION: &str = "2025-03-26";
+
+const INITIALIZE_ID: u64 = 1;
+const TOOLS_LIST_ID: u64 = 2;
+
+impl McpManager {
+    /// Start or contact a configured server and list its tools
+    pub async fn health_check(name: &str) -> Result<McpHealth> {
+        let config = Self::read_config()?;
+        let value = config
+            .get("mcpServers")
+            .and_then(|s| s.get(name))
+            .ok_or_else(|| McpError::ServerNotFound(name.to_string()))?;
+        let mut server = Self::parse_server(name, value);
+        if Self::is_sse(value) {
+            server.transport = "sse".to_string();
+        }
+        Ok(Self::check_server(&server).await)
+    }
+
+    /// Probe a server config; failures are reported in the result, not as errors
+    /// `transport` may also be "sse" for the legacy HTTP+SSE transport
+    pub async fn check_server(server: &McpServerInfo) -> McpHealth {
+        let probe = async {
+            match server.transport.as_str() {
+                "http" => probe_http(server).await,
+                "sse" => probe_sse(server).await,
+                _ => probe_stdio(server).await,
+            }
+        };
+        let outcome = tokio::time::timeout(HEALTH_CHECK_TIMEOUT, probe)
+            .await
+            .unwrap_or_else(|_| Err(format!("Timed out after {}s", HEALTH_CHECK_TIMEOUT.as_secs())));
+
+        match outcome {
+            Ok(tools) => McpHealth {
+                reachable: true,
+                tool_count: tools.len(),
+                tools,
+                error: None,
+            },
+            Err(e) => McpHealth {
+                reachable: false,
+                tool_count: 0,
+                tools: Vec::new(),
+                error: Some(e),
+            },
+        }
+    }
+}
+
+fn initialize_request() -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": INITIALIZE_ID,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "flowq", "version": env!("CARGO_PKG_VERSION") }
+        }
+    })
+}
+
+fn initialized_notification() -> Value {
+    serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })
+}
+
+fn tools_list_request() -> Value {
+    serde_json::json!({ "jsonrpc": "2.0", "id": TOOLS_LIST_ID, "method": "tools/list" })
+}
+
+fn has_id(message: &Value, id: u64) -> bool {
+    message.get("id").and_then(|i| i.as_u64()) == Some(id)
+}
+
+/// Return the JSON-RPC result, or the server's error message
+fn rpc_result(response: &Value) -> std::result::Result<&Value, String> {
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| error.to_string());
+        return Err(format!("Server error: {}", message));
+    }
+    response.get("result").ok_or_else(|| "Response has no result".to_string())
+}
+
+/// Extract tools from a `tools/list` response
+fn parse_tools(response: &Value) -> std::result::Result<Vec<McpToolInfo>, String> {
+    let tools = rpc_result(response)?
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .ok_or("tools/list response has no tools")?;
+
+    Ok(tools
+        .iter()
+        .filter_map(|tool| {
+            Some(McpToolInfo {
+                name: tool.get("name")?.as_str()?.to_string(),
+                description: tool
+                    .get("description")
+                    .and_then(|d| d.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Write one newline-delimited JSON-RPC message
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> std::result::Result<(), String> {
+    writer
+        .write_all(format!("{}\n", message).as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to server: {}", e))
+}
+
+/// Read lines until the response with `id`, skipping logs and notifications
+async fn read_response<R: AsyncBufRead + Unpin>(lines: &mut Lines<R>, id: u64) -> std::result::Result<Value, String> {
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| format!("Failed to read from server: {}", e))?
+    {
+        if let Ok(message) = serde_json::from_str::<Value>(&line) {
+            if has_id(&message, id) {
+                return Ok(message);
+            }
+        }
+    }
+    Err("Server exited before responding".to_string())
+}
+
+async fn probe_stdio(server: &McpServerInfo) -> std::result::Result<Vec<McpToolInfo>, String> {
+    let command = server.command.as_deref().ok_or("No command configured")?;
+    let mut child = tokio::process::Command::new(command)
+        .args(server.args.iter().flatten())
+        .envs(server.env.iter().flatten())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", command, e))?;
+
+    let mut stdin = child.stdin.take().ok_or("Failed to open server stdin")?;
+    let stdout = child.stdout.take().ok_or("Failed to open server stdout")?;
+    let mut lines = BufReader::new(stdout).lines();
+
+    write_message(&mut stdin, &initialize_request()).await?;
+    rpc_result(&read_response(&mut lines, INITIALIZE_ID).await?)?;
+    write_message(&mut stdin, &initialized_notification()).await?;
+    write_message(&mut stdin, &tools_list_request()).await?;
+    let tools = parse_tools(&read_response(&mut lines, TOOLS_LIST_ID).await?);
+
+    let _ = child.kill().await;
+    tools
+}
+
+/// Find the response with `id` in a JSON or `text/event-stream` body
+fn parse_http_response(body: &str, id: u64) -> Option<Value> {
+    if let Ok(message) = serde_json::from_str::<Value>(body) {
+        return Some(message).filter(|m| has_id(m, id));
+    }
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .find(|m| has_id(m, id))
+}
+
+/// POST one JSON-RPC message, tracking the `Mcp-Session-Id` the server assigns
+async fn post_message(
+    client: &reqwest::Client,
+    server: &McpServerInfo,
+    url: &str,
+    session_id: &mut Option<String>,
+    message: &Value,
+) -> std::result::Result<String, String> {
+    let mut request = client
+        .post(url)
+        .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+        .json(message);
+    for (key, value) in server.headers.iter().flatten() {
+        request = request.header(key, value);
+    }
+    if let Some(id) = session_id.as_deref() {
+        request = request.header("Mcp-Session-Id", id);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+    if let Some(id) = response.headers().get("mcp-session-id").and_then(|v| v.to_str().ok()) {
+        *session_id = Some(id.to_string());
+    }
+    response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))
+}
+
+async fn probe_http(server: &McpServerInfo) -> std::result::Result<Vec<McpToolInfo>, String> {
+    let url = server.url.as_deref().ok_or("No URL configured")?;
+    let client = reqwest::Client::builder()
+        .timeout(HEALTH_CHECK_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let mut session_id = None;
+
+    let body = post_message(&client, server, url, &mut session_id, &initialize_request()).await?;
+    let init = parse_http_response(&body, INITIALIZE_ID).ok_or("Invalid initialize response")?;
+    rpc_result(&init)?;
+
+    post_message(&client, server, url, &mut session_id, &initialized_notification()).await?;
+    let body = post_message(&client, server, url, &mut session_id, &tools_list_request()).await?;
+    parse_tools(&parse_http_response(&body, TOOLS_LIST_ID).ok_or("Invalid tools/list response")?)
+}
+
+/// Split one `text/event-stream` event into its name (default "message") and data
+fn parse_sse_event(block: &str) -> Option<(String, String)> {
+    let mut event = "message";
+    let mut data = Vec::new();
+    for line in block.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            event = value.trim();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    if data.is_empty() {
+        None
+    } else {
+        Some((event.to_string(), data.join("\n")))
+    }
+}
+
+/// Reads events from a streaming `text/event-stream` response as they arrive
+struct SseReader {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+}
+
+impl SseReader {
+    fn new(response: reqwest::Response) -> Self {
+        Self { response, buffer: Vec::new() }
+    }
+
+    /// Next event with data, or None once the stream ends
+    async fn next_event(&mut self) -> std::result::Result<Option<(String, String)>, String> {
+        loop {
+            if let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+                let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                match parse_sse_event(&String::from_utf8_lossy(&block)) {
+                    Some(event) => return Ok(Some(event)),
+                    None => continue,
+                }
+            }
+            let chunk = self
+                .response
+                .chunk()
+                .await
+                .map_err(|e| format!("Failed to read event stream: {}", e))?;
+            match chunk {
+                // Line endings may be CRLF; dropping CR leaves blank lines as "\n\n"
+                Some(chunk) => self.buffer.extend(chunk.iter().filter(|&&b| b != b'\r')),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Read `message` events until the JSON-RPC response with `id`
+    async fn read_response(&mut self, id: u64) -> std::result::Result<Value, String> {
+        while let Some((event, data)) = self.next_event().await? {
+            if event != "message" {
+                continue;
+            }
+            if let Ok(message) = serde_json::from_str::<Value>(&data) {
+                if has_id(&message, id) {
+                    return Ok(message);
+                }
+            }
+        }
+        Err("Server closed the event stream before responding".to_string())
+    }
+}
+
+/// Legacy HTTP+SSE transport: requests are POSTed to the endpoint announced by the
+/// stream's `endpoint` event, and responses arrive on the stream
+async fn probe_sse(server: &McpServerInfo) -> std::result::Result<Vec<McpToolInfo>, String> {
+    let url = server.url.as_deref().ok_or("No URL configured")?;
+    let client = reqwest::Client::builder()
+        .timeout(HEALTH_CHECK_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let mut request = client.get(url).header(reqwest::header::ACCEPT, "text/event-stream");
+    for (key, value) in server.headers.iter().flatten() {
+        request = request.header(key, value);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+    let mut events = SseReader::new(response);
+
+    let endpoint = loop {
+        match events.next_event().await? {
+            Some((event, data)) if event == "endpoint" => break data,
+            Some(_) => continue,
+            None => return Err("Event stream closed before announcing an endpoint".to_string()),
+        }
+    };
+    let endpoint = reqwest::Url::parse(url)
+        .and_then(|base| base.join(endpoint.trim()))
+        .map_err(|e| format!("Invalid endpoint: {}", e))?;
+    let mut session_id = None;
+
+    post_message(&client, server, endpoint.as_str(), &mut session_id, &initialize_request()).await?;
+    rpc_result(&events.read_response(INITIALIZE_ID).await?)?;
+
+    post_message(&client, server, endpoint.as_str(), &mut session_id, &initialized_notification()).await?;
+    post_message(&client, server, endpoint.as_str(), &mut session_id, &tools_list_request()).await?;
+    parse_tools(&events.read_response(TOOLS_LIST_ID).await?)
+}
+
 // ============ Tests ============
 
 #[cfg(test)]
@@ -352,4 +803,192 @@ mod tests {
 
         assert_eq!(server.disabled, Some(true));
     }
+
+    fn add_request(command: &str) -> AddMcpServerRequest {
+        AddMcpServerRequest {
+            name: "local".to_string(),
+            transport: "stdio".to_string(),
+            command: Some(command.to_string()),
+            args: None,
+            env: None,
+            url: None,
+            headers: None,
+        }
+    }
+
+    #[test]
+    fn test_add_rejects_missing_command_unless_forced() {
+        let mut config = serde_json::json!({});
+        let err = McpManager::add_to_config(&mut config, add_request("flowq-no-such-command"), false).unwrap_err();
+        assert!(matches!(&err, McpError::MissingPrerequisites(missing) if missing == &["flowq-no-such-command"]));
+        assert!(err.to_string().contains("flowq-no-such-command"));
+        assert!(config.get("mcpServers").is_none());
+
+        McpManager::add_to_config(&mut config, add_request("flowq-no-such-command"), true).unwrap();
+        assert_eq!(config["mcpServers"]["local"]["command"], "flowq-no-such-command");
+
+        // Commands on PATH and http servers pass validation
+        McpManager::add_to_config(&mut config, add_request("sh"), false).unwrap();
+        let mut http = add_request("flowq-no-such-command");
+        http.transport = "http".to_string();
+        assert!(McpManager::validate(&http).is_ok());
+    }
+
+    fn stdio_server(script: &str) -> McpServerInfo {
+        McpServerInfo {
+            name: "mock".to_string(),
+            transport: "stdio".to_string(),
+            disabled: None,
+            command: Some("sh".to_string()),
+            args: Some(vec!["-c".to_string(), script.to_string()]),
+            env: None,
+            url: None,
+            headers: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_check_mock_stdio_server() {
+        // Answers initialize, logs a notification, then lists two tools
+        let server = stdio_server(
+            r#"read line
+echo '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-03-26","capabilities":{},"serverInfo":{"name":"mock","version":"1"}}}'
+read line
+read line
+echo '{"jsonrpc":"2.0","method":"notifications/message","params":{}}'
+echo '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"read_file","description":"Read a file"},{"name":"ping"}]}}'
+read line"#,
+        );
+
+        let health = McpManager::check_server(&server).await;
+        assert!(health.reachable, "{:?}", health.error);
+        assert_eq!(health.tool_count, 2);
+        assert_eq!(health.tools[0].name, "read_file");
+        assert_eq!(health.tools[1].description, "");
+    }
+
+    #[tokio::test]
+    async fn test_health_check_reports_failures() {
+        // Reads the request, then exits without answering
+        let health = McpManager::check_server(&stdio_server("read line; exit 1")).await;
+        assert!(!health.reachable);
+        assert_eq!(health.error.as_deref(), Some("Server exited before responding"));
+
+        let mut server = stdio_server("");
+        server.command = Some("flowq-no-such-command".to_string());
+        let health = McpManager::check_server(&server).await;
+        assert!(health.error.unwrap().starts_with("Failed to start flowq-no-such-command"));
+
+        let server = stdio_server(
+            r#"read line; echo '{"jsonrpc":"2.0","id":1,"error":{"code":-32602,"message":"Unsupported protocol version"}}'"#,
+        );
+        let health = McpManager::check_server(&server).await;
+        assert_eq!(health.error.as_deref(), Some("Server error: Unsupported protocol version"));
+    }
+
+    #[test]
+    fn test_parse_http_response() {
+        let json = r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[]}}"#;
+        assert!(parse_http_response(json, 2).is_some());
+        assert!(parse_http_response(json, 1).is_none());
+
+        let sse = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\nevent: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{\"tools\":[{\"name\":\"search\"}]}}\n\n";
+        let response = parse_http_response(sse, 2).unwrap();
+        assert_eq!(parse_tools(&response).unwrap()[0].name, "search");
+    }
+
+    /// Read one HTTP request (headers and Content-Length body) from a test connection
+    async fn read_http_request(socket: &mut tokio::net::TcpStream) -> (String, String) {
+        use tokio::io::AsyncReadExt;
+
+        let mut data = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            data.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&data).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .to_lowercase()
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:").and_then(|v| v.trim().parse::<usize>().ok()))
+                    .unwrap_or(0);
+                if body.len() >= length || n == 0 {
+                    return (head.to_string(), body.to_string());
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_check_mock_sse_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sse", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (head, _) = read_http_request(&mut stream).await;
+            assert!(head.starts_with("GET /sse"));
+            assert!(head.to_lowercase().contains("accept: text/event-stream"));
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\nevent: endpoint\r\ndata: /messages?session=1\r\n\r\n")
+                .await
+                .unwrap();
+
+            // Each POST is acknowledged; responses go out on the event stream
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let (head, body) = read_http_request(&mut socket).await;
+                assert!(head.starts_with("POST /messages?session=1"));
+                socket
+                    .write_all(b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .await
+                    .unwrap();
+                let message: Value = serde_json::from_str(&body).unwrap();
+                let reply = match message["id"].as_u64() {
+                    Some(INITIALIZE_ID) => serde_json::json!({"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2024-11-05","capabilities":{}}}),
+                    Some(TOOLS_LIST_ID) => serde_json::json!({"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"search","description":"Search"}]}}),
+                    _ => continue,
+                };
+                stream
+                    .write_all(format!("event: message\ndata: {}\n\n", reply).as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let server = McpServerInfo {
+            name: "remote".to_string(),
+            transport: "sse".to_string(),
+            disabled: None,
+            command: None,
+            args: None,
+            env: None,
+            url: Some(url),
+            headers: None,
+        };
+        let health = McpManager::check_server(&server).await;
+        assert!(health.reachable, "{:?}", health.error);
+        assert_eq!(health.tools[0].name, "search");
+    }
+
+    #[test]
+    fn test_agent_servers_from_config() {
+        let config = serde_json::json!({
+            "mcpServers": {
+                "fs": { "command": "npx", "args": ["-y", "server-fs"], "env": { "ROOT": "/tmp" } },
+                "remote": { "type": "sse", "url": "https://example.com/sse" },
+                "api": { "type": "http", "url": "https://example.com/mcp" },
+                "off": { "command": "node", "disabled": true },
+                "broken": { "type": "http" }
+            }
+        });
+
+        let servers = McpManager::agent_servers_from(&config);
+        let mut names: Vec<&str> = servers.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, ["api", "fs", "remote"]);
+        assert!(matches!(&servers["fs"], McpServerConfig::Stdio(s) if s.command == "npx"));
+        assert!(matches!(&servers["remote"], McpServerConfig::Sse(s) if s.url == "https://example.com/sse"));
+        assert!(matches!(servers["api"], McpServerConfig::Http(_)));
+    }
 }
@@ -2,30 +2,60 @@
 //!
 //! SQLite-based indexing for memory files with FTS5 full-text search.
 //! This module handles:
-//! - File tracking (hash-based change detection)
+//! - File tracking (mtime/size fast path, hash-based change detection)
 //! - Markdown chunking
-//! - FTS5 indexing and search
+//! - FTS5 indexing and search, with optional synonym/stem query expansion
 
 use rusqlite::{params, Connection, Result};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::SystemTime;
 
+use crate::memory_tool::{MemoryTool, DEFAULT_MEMORIES_DIR};
+
 // ============ Configuration ============
 
 /// Chunking parameters
 const MAX_CHARS_PER_CHUNK: usize = 1600; // ~400 tokens
 const OVERLAP_CHARS: usize = 320; // ~80 tokens
 
+/// Format version written to memory archives; newer archives are rejected
+pub const MEMORY_ARCHIVE_VERSION: u32 = 1;
+
+/// Archive entry names
+const ARCHIVE_MANIFEST: &str = "manifest.json";
+const ARCHIVE_INDEX: &str = "index.sqlite";
+const ARCHIVE_FILES_PREFIX: &str = "files/";
+
+/// Synonym groups used by `SearchMode::Expanded`, extended by `.flowq/synonyms.json`
+const DEFAULT_SYNONYMS: &[&[&str]] = &[
+    &["car", "vehicle", "automobile"],
+    &["bug", "defect", "issue"],
+    &["error", "failure", "exception"],
+    &["config", "configuration", "settings"],
+    &["doc", "docs", "documentation"],
+    &["meeting", "call", "sync"],
+    &["deadline", "due"],
+];
+
+/// Suffixes stripped by the expansion stemmer, longest first
+const STEM_SUFFIXES: &[&str] = &["ing", "ed", "es", "s"];
+
+/// Shortest stem kept after stripping a suffix
+const MIN_STEM_CHARS: usize = 3;
+
 // ============ Types ============
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrackedFile {
     pub path: String,
     pub hash: String,
+    /// Modification time in milliseconds since the Unix epoch
     pub mtime: i64,
     pub size: i64,
 }
@@ -47,7 +77,10 @@ pub struct SearchResult {
     pub start_line: u32,
     pub end_line: u32,
     pub text: String,
-    pub score: f64,
+    /// Relevance score (higher is better), derived from FTS5 bm25
+    pub score: f32,
+    /// Excerpt around the match with matched terms wrapped in `**`
+    pub snippet: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,19 +88,59 @@ pub struct SyncResult {
     pub files_added: usize,
     pub files_updated: usize,
     pub files_removed: usize,
+    /// Files skipped because they did not change since the last sync
+    pub files_unchanged: usize,
     pub chunks_created: usize,
 }
 
+/// How search terms are matched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Every term must appear as written
+    #[default]
+    Exact,
+    /// Each term also matches its synonyms and other word forms sharing its stem
+    Expanded,
+}
+
+/// Manifest stored at the root of a memory archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryArchiveManifest {
+    pub version: u32,
+    pub created_at: i64,
+    /// Source files included in the archive, relative to the workspace
+    pub files: Vec<TrackedFile>,
+}
+
 // ============ Memory Index Database ============
 
 pub struct MemoryIndex {
     conn: Mutex<Connection>,
     workspace: PathBuf,
+    /// Memories directory relative to the workspace, as configured for the memory tool
+    memories_subdir: PathBuf,
+    /// Lowercase term to the synonyms it expands to
+    synonyms: HashMap<String, Vec<String>>,
+}
+
+/// Apply the memory tool's rules to a configured memories directory
+fn check_memories_subdir(memories_subdir: &str) -> Result<()> {
+    if memories_subdir.is_empty() {
+        return Err(rusqlite::Error::InvalidPath(PathBuf::from("Memories directory must not be empty")));
+    }
+    MemoryTool::check_relative(memories_subdir).map_err(|e| rusqlite::Error::InvalidPath(PathBuf::from(e)))
 }
 
 impl MemoryIndex {
     /// Open or create memory index database for a workspace
     pub fn open(workspace: &Path) -> Result<Self> {
+        Self::open_with_memories_dir(workspace, DEFAULT_MEMORIES_DIR)
+    }
+
+    /// Open the index for a workspace whose memory files live in a custom subdirectory
+    pub fn open_with_memories_dir(workspace: &Path, memories_subdir: &str) -> Result<Self> {
+        check_memories_subdir(memories_subdir)?;
         let flowq_dir = workspace.join(".flowq");
         fs::create_dir_all(&flowq_dir).map_err(|e| {
             rusqlite::Error::InvalidPath(PathBuf::from(format!("Failed to create .flowq dir: {}", e)))
@@ -79,6 +152,8 @@ impl MemoryIndex {
         let index = Self {
             conn: Mutex::new(conn),
             workspace: workspace.to_path_buf(),
+            memories_subdir: PathBuf::from(memories_subdir),
+            synonyms: load_synonyms(&flowq_dir),
         };
 
         index.init_schema()?;
@@ -133,7 +208,7 @@ impl MemoryIndex {
 
     /// Get the memories directory path
     fn memories_dir(&self) -> PathBuf {
-        self.workspace.join(".flowq").join("memories")
+        self.workspace.join(&self.memories_subdir)
     }
 
     /// Get MEMORY.md path
@@ -141,12 +216,25 @@ impl MemoryIndex {
         self.workspace.join("MEMORY.md")
     }
 
-    /// Sync all memory files to the index
+    /// Drop all tracking state and re-index every memory file from scratch
+    pub fn full_resync(&self) -> Result<SyncResult> {
+        {
+            let conn = self.conn.lock().unwrap();
+            conn.execute_batch(
+                "DELETE FROM chunks_fts; DELETE FROM chunks; DELETE FROM files;",
+            )?;
+        }
+        self.sync()
+    }
+
+    /// Incrementally sync memory files to the index
+    /// Files whose mtime and size match the tracked entry are skipped without being read
     pub fn sync(&self) -> Result<SyncResult> {
         let mut result = SyncResult {
             files_added: 0,
             files_updated: 0,
             files_removed: 0,
+            files_unchanged: 0,
             chunks_created: 0,
         };
 
@@ -159,7 +247,7 @@ impl MemoryIndex {
             memory_files.push(memory_md);
         }
 
-        // Check .flowq/memories/ directory
+        // Check the memories directory
         let memories_dir = self.memories_dir();
         if memories_dir.exists() {
             self.collect_markdown_files(&memories_dir, &mut memory_files);
@@ -181,50 +269,61 @@ impl MemoryIndex {
 
             current_paths.insert(relative_path.clone());
 
-            // Check if file needs updating
-            let file_info = self.get_file_info(&file_path)?;
+            let tracked = tracked_files.iter().find(|f| f.path == relative_path);
 
-            let needs_update = if let Some(tracked) = tracked_files.iter().find(|f| f.path == relative_path) {
-                tracked.hash != file_info.hash || tracked.mtime != file_info.mtime
-            } else {
-                true
-            };
-
-            if needs_update {
-                // Read and chunk the file
-                let content = fs::read_to_string(&file_path).map_err(|e| {
-                    rusqlite::Error::InvalidPath(PathBuf::from(format!("Failed to read file: {}", e)))
-                })?;
+            // Fast path: unchanged metadata means unchanged content
+            let (mtime, size) = self.get_file_stat(&file_path)?;
+            if let Some(tracked) = tracked {
+                if tracked.mtime == mtime && tracked.size == size {
+                    result.files_unchanged += 1;
+                    continue;
+                }
+            }
 
-                let chunks = chunk_markdown(&content);
+            let file_info = self.get_file_info(&file_path)?;
 
-                // Update file tracking
-                if tracked_paths.contains(&relative_path) {
+            // Touched but identical content - refresh metadata without re-chunking
+            if let Some(tracked) = tracked {
+                if tracked.hash == file_info.hash {
                     self.update_file(&file_info)?;
-                    result.files_updated += 1;
-                } else {
-                    self.add_file(&file_info)?;
-                    result.files_added += 1;
+                    result.files_unchanged += 1;
+                    continue;
                 }
+            }
 
-                // Delete old chunks for this file
-                self.delete_chunks_for_file(&relative_path)?;
-
-                // Insert new chunks
-                for chunk in chunks {
-                    let chunk_id = format!("{}:{}:{}", relative_path, chunk.start_line, chunk.end_line);
-                    let full_chunk = Chunk {
-                        id: chunk_id,
-                        path: relative_path.clone(),
-                        start_line: chunk.start_line,
-                        end_line: chunk.end_line,
-                        text: chunk.text,
-                        hash: chunk.hash,
-                        created_at: chrono::Utc::now().timestamp(),
-                    };
-                    self.add_chunk(&full_chunk)?;
-                    result.chunks_created += 1;
-                }
+            // Read and chunk the file
+            let content = fs::read_to_string(&file_path).map_err(|e| {
+                rusqlite::Error::InvalidPath(PathBuf::from(format!("Failed to read file: {}", e)))
+            })?;
+
+            let chunks = chunk_markdown(&content);
+
+            // Update file tracking
+            if tracked_paths.contains(&relative_path) {
+                self.update_file(&file_info)?;
+                result.files_updated += 1;
+            } else {
+                self.add_file(&file_info)?;
+                result.files_added += 1;
+            }
+
+            // Delete old chunks for this file
+            self.delete_chunks_for_file(&relative_path)?;
+
+            // Insert new chunks
+            for chunk in chunks {
+                let chunk_id = format!("{}:{}:{}", relative_path, chunk.start_line, chunk.end_line);
+                let full_chunk = Chunk {
+                    id: chunk_id,
+                    path: relative_path.clone(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    text: chunk.text,
+                    hash: chunk.hash,
+                    created_at: chrono::Utc::now().timestamp(),
+                };
+                self.add_chunk(&full_chunk)?;
+                result.chunks_created += 1;
             }
         }
 
@@ -253,6 +352,22 @@ impl MemoryIndex {
         }
     }
 
+    /// Get file modification time (ms) and size without reading the content
+    fn get_file_stat(&self, path: &Path) -> Result<(i64, i64)> {
+        let metadata = fs::metadata(path).map_err(|e| {
+            rusqlite::Error::InvalidPath(PathBuf::from(format!("Failed to get metadata: {}", e)))
+        })?;
+
+        let mtime = metadata
+            .modified()
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+
+        Ok((mtime, metadata.len() as i64))
+    }
+
     /// Get file info (hash, mtime, size)
     fn get_file_info(&self, path: &Path) -> Result<TrackedFile> {
         let content = fs::read(path).map_err(|e| {
@@ -263,16 +378,7 @@ impl MemoryIndex {
         hasher.update(&content);
         let hash = format!("{:x}", hasher.finalize());
 
-        let metadata = fs::metadata(path).map_err(|e| {
-            rusqlite::Error::InvalidPath(PathBuf::from(format!("Failed to get metadata: {}", e)))
-        })?;
-
-        let mtime = metadata
-            .modified()
-            .unwrap_or(SystemTime::UNIX_EPOCH)
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .map(|d| d.as_secs() as i64)
-            .unwrap_or(0);
+        let (mtime, size) = self.get_file_stat(path)?;
 
         let relative_path = path
             .strip_prefix(&self.workspace)
@@ -284,7 +390,7 @@ impl MemoryIndex {
             path: relative_path,
             hash,
             mtime,
-            size: metadata.len() as i64,
+            size,
         })
     }
 
@@ -387,13 +493,25 @@ impl MemoryIndex {
     }
 
     /// Search memory using FTS5
-    pub fn search(&self, query: &str, max_results: usize) -> Result<Vec<SearchResult>> {
+    /// Results are ordered by descending score, ties broken by most recently modified file
+    pub fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        min_score: Option<f32>,
+        mode: SearchMode,
+    ) -> Result<Vec<SearchResult>> {
         let conn = self.conn.lock().unwrap();
 
         // Build FTS5 query - wrap each term in quotes and join with AND
         let fts_query = query
             .split_whitespace()
-            .map(|t| format!("\"{}\"", t.replace('"', "")))
+            .map(|t| t.replace('"', ""))
+            .filter(|t| !t.is_empty())
+            .map(|t| match mode {
+                SearchMode::Exact => format!("\"{}\"", t),
+                SearchMode::Expanded => self.expand_term(&t),
+            })
             .collect::<Vec<_>>()
             .join(" AND ");
 
@@ -401,30 +519,51 @@ impl MemoryIndex {
             return Ok(Vec::new());
         }
 
+        // bm25() is lower-is-better, so negate it into a relevance score
         let sql = r#"
-            SELECT f.path, f.start_line, f.end_line, c.text,
-                   bm25(chunks_fts) as score
-            FROM chunks_fts f
-            JOIN chunks c ON c.id = f.id
-            WHERE chunks_fts MATCH ?1
-            ORDER BY score
-            LIMIT ?2
+            SELECT path, start_line, end_line, text, score, snippet FROM (
+                SELECT f.path, f.start_line, f.end_line, c.text,
+                       -bm25(chunks_fts) as score,
+                       snippet(chunks_fts, 0, '**', '**', '...', 16) as snippet,
+                       COALESCE(fl.mtime, 0) as mtime
+                FROM chunks_fts f
+                JOIN chunks c ON c.id = f.id
+                LEFT JOIN files fl ON fl.path = c.path
+                WHERE chunks_fts MATCH ?1
+            )
+            WHERE score >= ?2
+            ORDER BY score DESC, mtime DESC
+            LIMIT ?3
         "#;
 
+        let min_score = min_score.map(f64::from).unwrap_or(f64::MIN);
         let mut stmt = conn.prepare(sql)?;
-        let rows = stmt.query_map(params![fts_query, max_results as i64], |row| {
+        let rows = stmt.query_map(params![fts_query, min_score, max_results as i64], |row| {
             Ok(SearchResult {
                 path: row.get(0)?,
                 start_line: row.get(1)?,
                 end_line: row.get(2)?,
                 text: row.get(3)?,
-                score: row.get(4)?,
+                score: row.get::<_, f64>(4)? as f32,
+                snippet: row.get(5)?,
             })
         })?;
 
         rows.collect()
     }
 
+    /// Build an FTS5 OR group matching a term, its synonyms and its stem as a prefix
+    fn expand_term(&self, term: &str) -> String {
+        let term = term.to_lowercase();
+        let mut alternatives = vec![format!("\"{}\"", term)];
+        for synonym in self.synonyms.get(&term).into_iter().flatten() {
+            alternatives.push(format!("\"{}\"", synonym));
+        }
+        alternatives.push(format!("\"{}\"*", stem(&term)));
+        alternatives.dedup();
+        format!("({})", alternatives.join(" OR "))
+    }
+
     /// Get all memory context as a formatted string
     pub fn get_context(&self) -> Result<String> {
         let mut context = String::new();
@@ -439,7 +578,7 @@ impl MemoryIndex {
             }
         }
 
-        // Read files from .flowq/memories/
+        // Read files from the memories directory
         let memories_dir = self.memories_dir();
         if memories_dir.exists() {
             let mut memory_files = Vec::new();
@@ -492,6 +631,197 @@ impl MemoryIndex {
     }
 }
 
+// ============ Query Expansion ============
+
+/// Build the synonym lookup from `DEFAULT_SYNONYMS` plus an optional
+/// `synonyms.json` in the .flowq dir (an array of word groups)
+/// Every word in a group expands to all the others
+fn load_synonyms(flowq_dir: &Path) -> HashMap<String, Vec<String>> {
+    let mut groups: Vec<Vec<String>> = DEFAULT_SYNONYMS
+        .iter()
+        .map(|group| group.iter().map(|w| w.to_string()).collect())
+        .collect();
+
+    let custom = flowq_dir.join("synonyms.json");
+    if let Ok(content) = fs::read_to_string(&custom) {
+        match serde_json::from_str::<Vec<Vec<String>>>(&content) {
+            Ok(extra) => groups.extend(extra),
+            Err(e) => log::warn!("Ignoring invalid {}: {}", custom.display(), e),
+        }
+    }
+
+    let mut synonyms: HashMap<String, Vec<String>> = HashMap::new();
+    for group in &groups {
+        let words: Vec<String> = group
+            .iter()
+            .map(|w| w.trim().to_lowercase().replace('"', ""))
+            .filter(|w| !w.is_empty())
+            .collect();
+        for word in &words {
+            let entry = synonyms.entry(word.clone()).or_default();
+            for other in &words {
+                if other != word && !entry.contains(other) {
+                    entry.push(other.clone());
+                }
+            }
+        }
+    }
+    synonyms
+}
+
+/// Strip a common English suffix so other word forms match as a prefix
+fn stem(term: &str) -> &str {
+    STEM_SUFFIXES
+        .iter()
+        .find_map(|suffix| {
+            term.strip_suffix(suffix)
+                .filter(|rest| rest.chars().count() >= MIN_STEM_CHARS)
+        })
+        .unwrap_or(term)
+}
+
+// ============ Export / Import ============
+
+fn archive_error(message: String) -> rusqlite::Error {
+    rusqlite::Error::InvalidPath(PathBuf::from(message))
+}
+
+/// Whether an archived path is a memory source file (MEMORY.md or under the memories dir)
+fn is_memory_source_path(path: &Path, memories_subdir: &Path) -> bool {
+    path == Path::new("MEMORY.md") || path.starts_with(memories_subdir)
+}
+
+impl MemoryIndex {
+    /// Write the index and all memory source files into a single zip archive
+    pub fn export(&self, path: &Path) -> Result<MemoryArchiveManifest> {
+        self.sync()?;
+
+        let mut sources = Vec::new();
+        let memory_md = self.memory_md_path();
+        if memory_md.exists() {
+            sources.push(memory_md);
+        }
+        let memories_dir = self.memories_dir();
+        if memories_dir.exists() {
+            self.collect_markdown_files(&memories_dir, &mut sources);
+        }
+        sources.sort();
+
+        // Snapshot the database so the archive is consistent with the synced files
+        let snapshot = self.workspace.join(".flowq").join("memory-export.sqlite");
+        let _ = fs::remove_file(&snapshot);
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("VACUUM INTO ?1", params![snapshot.to_string_lossy()])?;
+        let index_bytes = fs::read(&snapshot);
+        let _ = fs::remove_file(&snapshot);
+        let index_bytes = index_bytes
+            .map_err(|e| archive_error(format!("Failed to read index snapshot: {}", e)))?;
+
+        let file = fs::File::create(path)
+            .map_err(|e| archive_error(format!("Failed to create archive: {}", e)))?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        let mut write_entry = |name: &str, data: &[u8]| {
+            zip.start_file(name, options)
+                .map_err(|e| archive_error(format!("Failed to write archive entry {}: {}", name, e)))?;
+            zip.write_all(data)
+                .map_err(|e| archive_error(format!("Failed to write archive entry {}: {}", name, e)))
+        };
+
+        let mut files = Vec::new();
+        for source in &sources {
+            let info = self.get_file_info(source)?;
+            let content = fs::read(source)
+                .map_err(|e| archive_error(format!("Failed to read file: {}", e)))?;
+            // Zip entries always use forward slashes
+            let entry = format!("{}{}", ARCHIVE_FILES_PREFIX, info.path.replace('\\', "/"));
+            write_entry(&entry, &content)?;
+            files.push(info);
+        }
+        write_entry(ARCHIVE_INDEX, &index_bytes)?;
+
+        let manifest = MemoryArchiveManifest {
+            version: MEMORY_ARCHIVE_VERSION,
+            created_at: chrono::Utc::now().timestamp(),
+            files,
+        };
+        let manifest_json = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| archive_error(format!("Failed to serialize manifest: {}", e)))?;
+        write_entry(ARCHIVE_MANIFEST, &manifest_json)?;
+
+        zip.finish()
+            .map_err(|e| archive_error(format!("Failed to finish archive: {}", e)))?;
+        Ok(manifest)
+    }
+
+    /// Restore an archive created by `export` into `workspace` and bring the index up to date
+    /// Existing memory files with the same path are overwritten
+    pub fn import(archive: &Path, workspace: &Path, memories_subdir: &str) -> Result<Self> {
+        check_memories_subdir(memories_subdir)?;
+        let file = fs::File::open(archive)
+            .map_err(|e| archive_error(format!("Failed to open archive: {}", e)))?;
+        let mut zip = zip::ZipArchive::new(file)
+            .map_err(|e| archive_error(format!("Invalid memory archive: {}", e)))?;
+
+        let manifest: MemoryArchiveManifest = {
+            let entry = zip
+                .by_name(ARCHIVE_MANIFEST)
+                .map_err(|_| archive_error("Invalid memory archive: missing manifest".to_string()))?;
+            serde_json::from_reader(entry)
+                .map_err(|e| archive_error(format!("Invalid memory archive manifest: {}", e)))?
+        };
+        if manifest.version == 0 || manifest.version > MEMORY_ARCHIVE_VERSION {
+            return Err(archive_error(format!(
+                "Unsupported memory archive version {} (supported: 1 to {})",
+                manifest.version, MEMORY_ARCHIVE_VERSION
+            )));
+        }
+
+        let flowq_dir = workspace.join(".flowq");
+        fs::create_dir_all(&flowq_dir)
+            .map_err(|e| archive_error(format!("Failed to create .flowq dir: {}", e)))?;
+
+        for i in 0..zip.len() {
+            let mut entry = zip
+                .by_index(i)
+                .map_err(|e| archive_error(format!("Failed to read archive entry: {}", e)))?;
+            if entry.is_dir() {
+                continue;
+            }
+
+            let target = match entry.enclosed_name() {
+                Some(name) if name == Path::new(ARCHIVE_INDEX) => flowq_dir.join("memory.sqlite"),
+                Some(name) => match name.strip_prefix(ARCHIVE_FILES_PREFIX) {
+                    Ok(relative) if is_memory_source_path(relative, Path::new(memories_subdir)) => {
+                        workspace.join(relative)
+                    }
+                    _ => continue,
+                },
+                None => continue,
+            };
+
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| archive_error(format!("Failed to read archive entry: {}", e)))?;
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| archive_error(format!("Failed to create directory: {}", e)))?;
+            }
+            fs::write(&target, content)
+                .map_err(|e| archive_error(format!("Failed to write {}: {}", target.display(), e)))?;
+        }
+
+        // Restored files have new mtimes, so sync re-hashes them and re-indexes any that differ
+        let index = Self::open_with_memories_dir(workspace, memories_subdir)?;
+        index.sync()?;
+        Ok(index)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryStats {
     pub file_count: usize,
@@ -650,6 +980,72 @@ mod tests {
         assert!(stats.chunk_count > 0);
     }
 
+    #[test]
+    fn test_custom_memories_dir() {
+        let dir = tempdir().unwrap();
+        let workspace = dir.path();
+        fs::create_dir_all(workspace.join("notes/memory")).unwrap();
+        fs::write(workspace.join("notes/memory/custom.md"), "# Custom\n\nLives in notes.\n").unwrap();
+        fs::create_dir_all(workspace.join(".flowq/memories")).unwrap();
+        fs::write(workspace.join(".flowq/memories/default.md"), "# Default\n\nIgnored here.\n").unwrap();
+
+        let index = MemoryIndex::open_with_memories_dir(workspace, "notes/memory").unwrap();
+        assert_eq!(index.sync().unwrap().files_added, 1);
+        assert_eq!(index.search("notes", 10, None, SearchMode::Exact).unwrap().len(), 1);
+        assert!(index.search("ignored", 10, None, SearchMode::Exact).unwrap().is_empty());
+        assert!(index.get_context().unwrap().contains("Lives in notes"));
+
+        assert!(MemoryIndex::open_with_memories_dir(workspace, "../elsewhere").is_err());
+        assert!(MemoryIndex::open_with_memories_dir(workspace, "").is_err());
+    }
+
+    #[test]
+    fn test_incremental_sync() {
+        let dir = tempdir().unwrap();
+        let workspace = dir.path();
+        let memories = workspace.join(".flowq").join("memories");
+        fs::create_dir_all(&memories).unwrap();
+        fs::write(memories.join("a.md"), "# A\n\nFirst file.\n").unwrap();
+        fs::write(memories.join("b.md"), "# B\n\nSecond file.\n").unwrap();
+
+        let index = MemoryIndex::open(workspace).unwrap();
+        let result = index.sync().unwrap();
+        assert_eq!(result.files_added, 2);
+
+        // Nothing changed - nothing reprocessed
+        let result = index.sync().unwrap();
+        assert_eq!(result.files_unchanged, 2);
+        assert_eq!(result.chunks_created, 0);
+
+        // Change one file - only that one is re-chunked
+        fs::write(memories.join("a.md"), "# A\n\nFirst file, now edited.\n").unwrap();
+        let result = index.sync().unwrap();
+        assert_eq!(result.files_updated, 1);
+        assert_eq!(result.files_unchanged, 1);
+        assert_eq!(result.chunks_created, 1);
+
+        // Remove the other
+        fs::remove_file(memories.join("b.md")).unwrap();
+        let result = index.sync().unwrap();
+        assert_eq!(result.files_removed, 1);
+        assert_eq!(result.files_unchanged, 1);
+    }
+
+    #[test]
+    fn test_full_resync() {
+        let dir = tempdir().unwrap();
+        let workspace = dir.path();
+        fs::write(workspace.join("MEMORY.md"), "# Memory\n\nContent.\n").unwrap();
+
+        let index = MemoryIndex::open(workspace).unwrap();
+        index.sync().unwrap();
+
+        let result = index.full_resync().unwrap();
+        assert_eq!(result.files_added, 1);
+        assert_eq!(result.files_unchanged, 0);
+        assert_eq!(index.get_stats().unwrap().file_count, 1);
+    }
+
     #[test]
     fn test_memory_index_search() {
         let dir = tempdir().unwrap();
@@ -667,12 +1063,46 @@ mod tests {
         index.sync().unwrap();
 
         // Search for the unique keyword
-        let results = index.search("unique keyword", 10).unwrap();
+        let results = index.search("unique keyword", 10, None, SearchMode::Exact).unwrap();
 
         assert!(!results.is_empty());
         assert!(results[0].text.contains("unique keyword"));
     }
 
+    #[test]
+    fn test_search_scores_and_snippets() {
+        let dir = tempdir().unwrap();
+        let workspace = dir.path();
+        let memories = workspace.join(".flowq").join("memories");
+        fs::create_dir_all(&memories).unwrap();
+        fs::write(
+            memories.join("strong.md"),
+            "# Rust\n\nRust rust rust. Ownership makes rust memory safe.\n",
+        )
+        .unwrap();
+        fs::write(
+            memories.join("weak.md"),
+            "# Notes\n\nA long paragraph about gardening, cooking, travel, music and \
+             many other hobbies that only mentions rust once near the end.\n",
+        )
+        .unwrap();
+
+        let index = MemoryIndex::open(workspace).unwrap();
+        index.sync().unwrap();
+
+        let results = index.search("rust", 10, None, SearchMode::Exact).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].path.ends_with("strong.md"));
+        assert!(results[0].score >= results[1].score);
+        assert!(results[1].snippet.contains("**rust**"));
+
+        // A threshold between the two scores drops the weak match
+        let threshold = (results[0].score + results[1].score) / 2.0;
+        let filtered = index.search("rust", 10, Some(threshold), SearchMode::Exact).unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert!(filtered[0].path.ends_with("strong.md"));
+    }
+
     #[test]
     fn test_get_context() {
         let dir = tempdir().unwrap();
@@ -688,4 +1118,82 @@ mod tests {
         assert!(context.contains("## MEMORY.md"));
         assert!(context.contains("Test content"));
     }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let source = tempdir().unwrap();
+        let memories = source.path().join(".flowq").join("memories");
+        fs::create_dir_all(memories.join("projects")).unwrap();
+        fs::write(source.path().join("MEMORY.md"), "# Memory\n\nPrefers tabs over spaces.\n").unwrap();
+        fs::write(memories.join("projects").join("flowq.md"), "# FlowQ\n\nThe release train ships on Fridays.\n").unwrap();
+
+        let index = MemoryIndex::open(source.path()).unwrap();
+        index.sync().unwrap();
+        let archive = source.path().join("backup.zip");
+        let manifest = index.export(&archive).unwrap();
+        assert_eq!(manifest.version, MEMORY_ARCHIVE_VERSION);
+        assert_eq!(manifest.files.len(), 2);
+
+        let target = tempdir().unwrap();
+        let restored = MemoryIndex::import(&archive, target.path(), DEFAULT_MEMORIES_DIR).unwrap();
+        assert!(target.path().join(".flowq/memories/projects/flowq.md").exists());
+        assert_eq!(restored.get_stats().unwrap().file_count, 2);
+
+        for query in ["tabs", "release train"] {
+            let hits = |idx: &MemoryIndex| -> Vec<(String, u32, u32)> {
+                idx.search(query, 10, None, SearchMode::Exact)
+                    .unwrap()
+                    .into_iter()
+                    .map(|r| (r.path, r.start_line, r.end_line))
+                    .collect()
+            };
+            let expected = hits(&index);
+            assert!(!expected.is_empty());
+            assert_eq!(hits(&restored), expected);
+        }
+    }
+
+    #[test]
+    fn test_import_rejects_newer_archive_version() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("future.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        zip.start_file(ARCHIVE_MANIFEST, zip::write::SimpleFileOptions::default()).unwrap();
+        let manifest = format!(r#"{{"version": {}, "created_at": 0, "files": []}}"#, MEMORY_ARCHIVE_VERSION + 1);
+        zip.write_all(manifest.as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        let err = match MemoryIndex::import(&archive, &dir.path().join("workspace"), DEFAULT_MEMORIES_DIR) {
+            Err(e) => e.to_string(),
+            Ok(_) => panic!("newer archive should be rejected"),
+        };
+        assert!(err.contains("Unsupported memory archive version"), "{}", err);
+    }
+
+    #[test]
+    fn test_expanded_search_uses_seeded_synonyms() {
+        let dir = tempdir().unwrap();
+        let workspace = dir.path();
+        fs::create_dir_all(workspace.join(".flowq")).unwrap();
+        fs::write(workspace.join(".flowq").join("synonyms.json"), r#"[["laptop", "notebook"]]"#).unwrap();
+        fs::write(
+            workspace.join("MEMORY.md"),
+            "# Memory\n\nThe office notebook is kept in the drawer.\n\nShe is parking near the station.\n",
+        )
+        .unwrap();
+
+        let index = MemoryIndex::open(workspace).unwrap();
+        index.sync().unwrap();
+
+        // Exact stays the default and misses both related forms
+        assert!(index.search("laptop", 10, None, SearchMode::default()).unwrap().is_empty());
+        assert!(index.search("parked", 10, None, SearchMode::Exact).unwrap().is_empty());
+
+        let results = index.search("laptop", 10, None, SearchMode::Expanded).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].snippet.contains("**notebook**"));
+
+        // Stemming matches other word forms
+        assert_eq!(index.search("parked", 10, None, SearchMode::Expanded).unwrap().len(), 1);
+    }
 }
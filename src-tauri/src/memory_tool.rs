@@ -10,10 +10,18 @@
 //! - insert: Insert text at a specific line
 //! - delete: Delete a file or directory
 //! - rename: Rename/move a file
+//! - undo: Revert the most recent mutation
+//! - list: Tree of memory files with sizes
+//! - grep: Search memory files by regex
+//!
+//! Every mutating command records a pre-change snapshot in
+//! `.history/` inside the memories directory, which `undo` replays.
+//! `MemoryTool::preview` runs a command as a dry run and returns the diff
+//! it would apply instead of writing.
 
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use crate::memory_index::MemoryIndex;
 
@@ -38,6 +46,9 @@ pub enum MemoryToolCommand {
         path: String,
         old_str: String,
         new_str: String,
+        /// Occurrences of `old_str` that must be present, all of which are replaced (default 1)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected_occurrences: Option<usize>,
     },
     #[serde(rename = "insert")]
     Insert {
@@ -54,6 +65,18 @@ pub enum MemoryToolCommand {
         old_path: String,
         new_path: String,
     },
+    #[serde(rename = "undo")]
+    Undo,
+    #[serde(rename = "list")]
+    List {
+        /// Directory to list, relative to the memories directory (default: root)
+        #[serde(default)]
+        subpath: Option<String>,
+    },
+    #[serde(rename = "grep")]
+    Grep {
+        pattern: String,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +85,9 @@ pub struct MemoryToolResult {
     pub output: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Unified diff of the change a dry run would make
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
 }
 
 impl MemoryToolResult {
@@ -70,6 +96,7 @@ impl MemoryToolResult {
             success: true,
             output,
             error: None,
+            diff: None,
         }
     }
 
@@ -78,25 +105,109 @@ impl MemoryToolResult {
             success: false,
             output: String::new(),
             error: Some(message),
+            diff: None,
+        }
+    }
+
+    fn dry_run(summary: String, diff: String) -> Self {
+        Self {
+            success: true,
+            output: format!("Dry run, nothing written: {}", summary),
+            error: None,
+            diff: Some(diff),
         }
     }
 }
 
+/// One journaled mutation: the state of every touched file before it ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HistoryEntry {
+    operation: String,
+    files: Vec<FileSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileSnapshot {
+    /// Path relative to the memories directory
+    path: String,
+    /// Previous content, or None if the file did not exist
+    content: Option<String>,
+}
+
 // ============ Memory Tool Handler ============
 
+/// Journal directory inside the memories directory
+const HISTORY_DIR: &str = ".history";
+
+/// Journal entries kept for undo; older ones are pruned
+const MAX_HISTORY_ENTRIES: usize = 50;
+
+/// Default memories directory, relative to the workspace
+pub const DEFAULT_MEMORIES_DIR: &str = ".flowq/memories";
+
+/// Matching lines reported by grep before the output is cut off
+const MAX_GREP_MATCHES: usize = 200;
+
+#[derive(Clone)]
 pub struct MemoryTool {
     workspace: PathBuf,
+    /// Memories directory relative to the workspace, shared with the index
+    memories_subdir: String,
     memories_dir: PathBuf,
+    /// Report mutations instead of applying them
+    dry_run: bool,
 }
 
 impl MemoryTool {
     /// Create a new MemoryTool for a workspace
     pub fn new(workspace: &Path) -> Self {
-        let memories_dir = workspace.join(".flowq").join("memories");
+        let memories_dir = workspace.join(DEFAULT_MEMORIES_DIR);
         Self {
             workspace: workspace.to_path_buf(),
+            memories_subdir: DEFAULT_MEMORIES_DIR.to_string(),
             memories_dir,
+            dry_run: false,
+        }
+    }
+
+    /// Create a MemoryTool rooted at a custom memories subdirectory of the workspace
+    pub fn with_memories_dir(workspace: &Path, memories_subdir: &str) -> Result<Self, String> {
+        Self::check_relative(memories_subdir)?;
+        if memories_subdir.is_empty() {
+            return Err("Memories directory must not be empty".to_string());
+        }
+        Ok(Self {
+            workspace: workspace.to_path_buf(),
+            memories_subdir: memories_subdir.to_string(),
+            memories_dir: workspace.join(memories_subdir),
+            dry_run: false,
+        })
+    }
+
+    /// Reject absolute paths and any '..' component
+    pub(crate) fn check_relative(path: &str) -> Result<(), String> {
+        // Block URL-encoded traversal
+        if path.contains("%2e") || path.contains("%2E") {
+            return Err("Path traversal detected: URL-encoded sequences not allowed".to_string());
+        }
+
+        // Block absolute paths (a leading backslash is not a root on Unix, so check it explicitly)
+        if path.starts_with('/') || path.starts_with('\\') {
+            return Err("Absolute paths are not allowed".to_string());
+        }
+
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(_) | Component::CurDir => {}
+                Component::ParentDir => {
+                    return Err("Path traversal detected: '..' is not allowed".to_string());
+                }
+                Component::RootDir | Component::Prefix(_) => {
+                    return Err("Absolute paths are not allowed".to_string());
+                }
+            }
         }
+        Ok(())
     }
 
     /// Execute a memory tool command
@@ -104,32 +215,40 @@ impl MemoryTool {
         match command {
             MemoryToolCommand::View { path, view_range } => self.view(&path, view_range),
             MemoryToolCommand::Create { path, file_text } => self.create(&path, &file_text),
-            MemoryToolCommand::StrReplace { path, old_str, new_str } => {
-                self.str_replace(&path, &old_str, &new_str)
+            MemoryToolCommand::StrReplace { path, old_str, new_str, expected_occurrences } => {
+                self.str_replace(&path, &old_str, &new_str, expected_occurrences)
             }
             MemoryToolCommand::Insert { path, insert_line, new_str } => {
                 self.insert(&path, insert_line, &new_str)
             }
             MemoryToolCommand::Delete { path } => self.delete(&path),
             MemoryToolCommand::Rename { old_path, new_path } => self.rename(&old_path, &new_path),
+            MemoryToolCommand::Undo => self.undo(),
+            MemoryToolCommand::List { subpath } => self.list(subpath.as_deref().unwrap_or("")),
+            MemoryToolCommand::Grep { pattern } => self.grep(&pattern),
         }
     }
 
+    /// Run a command without writing, returning the diff it would apply
+    /// Read-only commands behave exactly as in `execute`
+    pub fn preview(&self, command: MemoryToolCommand) -> MemoryToolResult {
+        let tool = MemoryTool {
+            dry_run: true,
+            ..self.clone()
+        };
+        tool.execute(command)
+    }
+
     /// Validate and resolve a path within the memories directory
     fn resolve_path(&self, requested_path: &str) -> Result<PathBuf, String> {
-        // Block obvious path traversal attempts
-        if requested_path.contains("..") {
-            return Err("Path traversal detected: '..' is not allowed".to_string());
-        }
-
-        // Block URL-encoded traversal
-        if requested_path.contains("%2e") || requested_path.contains("%2E") {
-            return Err("Path traversal detected: URL-encoded sequences not allowed".to_string());
-        }
-
-        // Block absolute paths
-        if requested_path.starts_with('/') || requested_path.starts_with('\\') {
-            return Err("Absolute paths are not allowed".to_string());
+        Self::check_relative(requested_path)?;
+
+        // The undo journal is managed by the tool itself
+        if Path::new(requested_path)
+            .components()
+            .any(|c| c.as_os_str() == HISTORY_DIR)
+        {
+            return Err("The history directory is not accessible".to_string());
         }
 
         // Special case: empty path or "." means memories directory root
@@ -145,16 +264,24 @@ impl MemoryTool {
                 .map_err(|e| format!("Failed to create memories directory: {}", e))?;
         }
 
-        // For existing files/dirs, verify canonical path is within memories_dir
-        if resolved.exists() {
-            let canonical = resolved.canonicalize()
-                .map_err(|e| format!("Failed to resolve path: {}", e))?;
-            let memories_canonical = self.memories_dir.canonicalize()
-                .map_err(|e| format!("Failed to resolve memories directory: {}", e))?;
+        // Verify the deepest existing ancestor canonicalizes into memories_dir, so a
+        // symlinked directory can't redirect a create to somewhere outside it.
+        // symlink_metadata doesn't follow links, so a dangling link is found here
+        // and then rejected by canonicalize below.
+        let mut existing = resolved.as_path();
+        while existing.symlink_metadata().is_err() {
+            existing = existing
+                .parent()
+                .ok_or_else(|| format!("Failed to resolve path: {}", requested_path))?;
+        }
 
-            if !canonical.starts_with(&memories_canonical) {
-                return Err("Path traversal detected: path escapes memories directory".to_string());
-            }
+        let canonical = existing.canonicalize()
+            .map_err(|e| format!("Failed to resolve path: {}", e))?;
+        let memories_canonical = self.memories_dir.canonicalize()
+            .map_err(|e| format!("Failed to resolve memories directory: {}", e))?;
+
+        if !canonical.starts_with(&memories_canonical) {
+            return Err("Path traversal detected: path escapes memories directory".to_string());
         }
 
         Ok(resolved)
@@ -195,6 +322,9 @@ impl MemoryTool {
         for entry in entries {
             let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
             let name = entry.file_name().to_string_lossy().to_string();
+            if name == HISTORY_DIR {
+                continue;
+            }
             let file_type = entry.file_type()
                 .map_err(|e| format!("Failed to get file type: {}", e))?;
 
@@ -243,6 +373,127 @@ impl MemoryTool {
         }
     }
 
+    /// list command: indented tree of files and directories with file sizes
+    fn list(&self, subpath: &str) -> MemoryToolResult {
+        // Nothing stored yet: list an empty root without creating the directory
+        if !self.memories_dir.exists() {
+            return match Self::check_relative(subpath) {
+                Err(e) => MemoryToolResult::error(e),
+                Ok(()) if subpath.is_empty() || subpath == "." => {
+                    MemoryToolResult::success("(empty directory)".to_string())
+                }
+                Ok(()) => MemoryToolResult::error(format!("Not a directory: {}", subpath)),
+            };
+        }
+
+        let resolved = match self.resolve_path(subpath) {
+            Ok(p) => p,
+            Err(e) => return MemoryToolResult::error(e),
+        };
+
+        if !resolved.is_dir() {
+            return MemoryToolResult::error(format!("Not a directory: {}", subpath));
+        }
+
+        let mut lines = Vec::new();
+        if let Err(e) = self.list_tree(&resolved, 0, &mut lines) {
+            return MemoryToolResult::error(e);
+        }
+
+        if lines.is_empty() {
+            MemoryToolResult::success("(empty directory)".to_string())
+        } else {
+            MemoryToolResult::success(lines.join("\n"))
+        }
+    }
+
+    fn list_tree(&self, dir: &Path, depth: usize, lines: &mut Vec<String>) -> Result<(), String> {
+        for path in Self::sorted_entries(dir)? {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let indent = "  ".repeat(depth);
+            if path.is_dir() {
+                lines.push(format!("{}{}/", indent, name));
+                self.list_tree(&path, depth + 1, lines)?;
+            } else {
+                let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                lines.push(format!("{}{} ({} bytes)", indent, name, size));
+            }
+        }
+        Ok(())
+    }
+
+    /// grep command: lines matching a regex across all memory files, as `path:line: text`
+    fn grep(&self, pattern: &str) -> MemoryToolResult {
+        let regex = match regex::Regex::new(pattern) {
+            Ok(r) => r,
+            Err(e) => return MemoryToolResult::error(format!("Invalid pattern: {}", e)),
+        };
+        if !self.memories_dir.exists() {
+            return MemoryToolResult::success(format!("No matches for: {}", pattern));
+        }
+        let root = match self.resolve_path("") {
+            Ok(p) => p,
+            Err(e) => return MemoryToolResult::error(e),
+        };
+
+        let mut files = Vec::new();
+        if let Err(e) = Self::collect_files(&root, &mut files) {
+            return MemoryToolResult::error(e);
+        }
+
+        let mut matches = Vec::new();
+        let mut truncated = false;
+        'files: for file in files {
+            // Skip files that aren't valid UTF-8 text
+            let Ok(content) = fs::read_to_string(&file) else {
+                continue;
+            };
+            let relative = self.relative(&file);
+            for (i, line) in content.lines().enumerate() {
+                if regex.is_match(line) {
+                    if matches.len() == MAX_GREP_MATCHES {
+                        truncated = true;
+                        break 'files;
+                    }
+                    matches.push(format!("{}:{}: {}", relative, i + 1, line));
+                }
+            }
+        }
+
+        if matches.is_empty() {
+            return MemoryToolResult::success(format!("No matches for: {}", pattern));
+        }
+        let mut output = matches.join("\n");
+        if truncated {
+            output.push_str(&format!("\n... (showing first {} matches)", MAX_GREP_MATCHES));
+        }
+        MemoryToolResult::success(output)
+    }
+
+    /// All files below `dir`, in path order
+    fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+        for path in Self::sorted_entries(dir)? {
+            if path.is_dir() {
+                Self::collect_files(&path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Directory entries sorted by name, without the history journal
+    fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, String> {
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read directory: {}", e))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.file_name().is_some_and(|n| n != HISTORY_DIR))
+            .collect();
+        entries.sort();
+        Ok(entries)
+    }
+
     /// Create command: create a new file
     fn create(&self, path: &str, file_text: &str) -> MemoryToolResult {
         let resolved = match self.resolve_path(path) {
@@ -254,6 +505,13 @@ impl MemoryTool {
             return MemoryToolResult::error(format!("File already exists: {}", path));
         }
 
+        if self.dry_run {
+            return MemoryToolResult::dry_run(
+                format!("would create {}", path),
+                line_diff(path, "", file_text),
+            );
+        }
+
         // Create parent directories if needed
         if let Some(parent) = resolved.parent() {
             if !parent.exists() {
@@ -263,6 +521,10 @@ impl MemoryTool {
             }
         }
 
+        if let Err(e) = self.record_history("create", &[&resolved]) {
+            return MemoryToolResult::error(e);
+        }
+
         // Write the file
         if let Err(e) = fs::write(&resolved, file_text) {
             return MemoryToolResult::error(format!("Failed to create file: {}", e));
@@ -275,7 +537,15 @@ impl MemoryTool {
     }
 
     /// str_replace command: replace text in a file
-    fn str_replace(&self, path: &str, old_str: &str, new_str: &str) -> MemoryToolResult {
+    /// Fails unless `old_str` occurs exactly `expected_occurrences` times (default 1),
+    /// so an edit based on stale content is rejected instead of applied
+    fn str_replace(
+        &self,
+        path: &str,
+        old_str: &str,
+        new_str: &str,
+        expected_occurrences: Option<usize>,
+    ) -> MemoryToolResult {
         let resolved = match self.resolve_path(path) {
             Ok(p) => p,
             Err(e) => return MemoryToolResult::error(e),
@@ -304,15 +574,35 @@ impl MemoryTool {
             ));
         }
 
-        if count > 1 {
-            return MemoryToolResult::error(format!(
-                "String found {} times in file. Please provide a more unique string.",
-                count
-            ));
+        match expected_occurrences {
+            None if count > 1 => {
+                return MemoryToolResult::error(format!(
+                    "String found {} times in file. Please provide a more unique string.",
+                    count
+                ));
+            }
+            Some(expected) if count != expected => {
+                return MemoryToolResult::error(format!(
+                    "Expected {} occurrence(s) of the string in {}, found {}. The file may have changed; view it and retry.",
+                    expected, path, count
+                ));
+            }
+            _ => {}
         }
 
-        // Replace and write
         let new_content = content.replace(old_str, new_str);
+        if self.dry_run {
+            return MemoryToolResult::dry_run(
+                format!("would replace text in {}", path),
+                line_diff(path, &content, &new_content),
+            );
+        }
+
+        if let Err(e) = self.record_history("str_replace", &[&resolved]) {
+            return MemoryToolResult::error(e);
+        }
+
+        // Write the replaced content
         if let Err(e) = fs::write(&resolved, &new_content) {
             return MemoryToolResult::error(format!("Failed to write file: {}", e));
         }
@@ -362,8 +652,19 @@ impl MemoryTool {
             lines.insert(insert_idx + i, line);
         }
 
-        // Write back
         let new_content = lines.join("\n");
+        if self.dry_run {
+            return MemoryToolResult::dry_run(
+                format!("would insert {} line(s) at line {} in {}", new_lines.len(), insert_line, path),
+                line_diff(path, &content, &new_content),
+            );
+        }
+
+        if let Err(e) = self.record_history("insert", &[&resolved]) {
+            return MemoryToolResult::error(e);
+        }
+
+        // Write back
         if let Err(e) = fs::write(&resolved, &new_content) {
             return MemoryToolResult::error(format!("Failed to write file: {}", e));
         }
@@ -395,6 +696,22 @@ impl MemoryTool {
             return MemoryToolResult::error("Cannot delete the memories root directory".to_string());
         }
 
+        if self.dry_run {
+            let mut files = Vec::new();
+            if let Err(e) = self.snapshot(&resolved, &mut files) {
+                return MemoryToolResult::error(e);
+            }
+            let diff = files
+                .iter()
+                .map(|f| line_diff(&f.path, f.content.as_deref().unwrap_or_default(), ""))
+                .collect::<String>();
+            return MemoryToolResult::dry_run(format!("would delete {}", path), diff);
+        }
+
+        if let Err(e) = self.record_history("delete", &[&resolved]) {
+            return MemoryToolResult::error(e);
+        }
+
         let result = if resolved.is_dir() {
             fs::remove_dir_all(&resolved)
         } else {
@@ -431,6 +748,13 @@ impl MemoryTool {
             return MemoryToolResult::error(format!("Destination already exists: {}", new_path));
         }
 
+        if self.dry_run {
+            return MemoryToolResult::dry_run(
+                format!("would rename {} to {}", old_path, new_path),
+                format!("rename from {}\nrename to {}\n", old_path, new_path),
+            );
+        }
+
         // Create parent directories for destination if needed
         if let Some(parent) = new_resolved.parent() {
             if !parent.exists() {
@@ -440,6 +764,10 @@ impl MemoryTool {
             }
         }
 
+        if let Err(e) = self.record_rename_history(&old_resolved, &new_resolved) {
+            return MemoryToolResult::error(e);
+        }
+
         if let Err(e) = fs::rename(&old_resolved, &new_resolved) {
             return MemoryToolResult::error(format!("Failed to rename: {}", e));
         }
@@ -450,15 +778,250 @@ impl MemoryTool {
         MemoryToolResult::success(format!("Renamed {} to {}", old_path, new_path))
     }
 
+    /// undo command: restore the state before the most recent mutation
+    fn undo(&self) -> MemoryToolResult {
+        let Some(entry_path) = self.history_entries().pop() else {
+            return MemoryToolResult::error("Nothing to undo".to_string());
+        };
+
+        let entry: HistoryEntry = match fs::read_to_string(&entry_path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        {
+            Ok(entry) => entry,
+            Err(e) => return MemoryToolResult::error(format!("Failed to read history entry: {}", e)),
+        };
+
+        if self.dry_run {
+            let diff = entry
+                .files
+                .iter()
+                .map(|f| {
+                    let current = fs::read_to_string(self.memories_dir.join(&f.path)).unwrap_or_default();
+                    line_diff(&f.path, &current, f.content.as_deref().unwrap_or_default())
+                })
+                .collect::<String>();
+            return MemoryToolResult::dry_run(format!("would undo {}", entry.operation), diff);
+        }
+
+        // Remove files the mutation created first, so a reverted rename can't collide
+        for file in entry.files.iter().filter(|f| f.content.is_none()) {
+            let path = self.memories_dir.join(&file.path);
+            if path.is_file() {
+                if let Err(e) = fs::remove_file(&path) {
+                    return MemoryToolResult::error(format!("Failed to undo {}: {}", file.path, e));
+                }
+            }
+        }
+        for file in &entry.files {
+            if let Some(ref content) = file.content {
+                let path = self.memories_dir.join(&file.path);
+                if let Some(parent) = path.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                if let Err(e) = fs::write(&path, content) {
+                    return MemoryToolResult::error(format!("Failed to undo {}: {}", file.path, e));
+                }
+            }
+        }
+
+        if let Err(e) = fs::remove_file(&entry_path) {
+            return MemoryToolResult::error(format!("Failed to update history: {}", e));
+        }
+
+        // Trigger memory sync
+        self.trigger_sync();
+
+        let paths: Vec<&str> = entry.files.iter().map(|f| f.path.as_str()).collect();
+        MemoryToolResult::success(format!("Undid {} on {}", entry.operation, paths.join(", ")))
+    }
+
+    /// Snapshot the current state of `paths` into the history journal
+    fn record_history(&self, operation: &str, paths: &[&Path]) -> Result<(), String> {
+        let mut files = Vec::new();
+        for path in paths {
+            self.snapshot(path, &mut files)?;
+        }
+        self.write_history(operation, files)
+    }
+
+    /// Journal a rename: old paths get their content back, new paths are removed
+    fn record_rename_history(&self, old_path: &Path, new_path: &Path) -> Result<(), String> {
+        let mut files = Vec::new();
+        self.snapshot(old_path, &mut files)?;
+
+        let old_rel = self.relative(old_path);
+        let new_rel = self.relative(new_path);
+        let moved: Vec<FileSnapshot> = files
+            .iter()
+            .map(|f| {
+                let suffix = Path::new(&f.path).strip_prefix(&old_rel).unwrap_or(Path::new(""));
+                let path = if suffix.as_os_str().is_empty() {
+                    new_rel.clone()
+                } else {
+                    Path::new(&new_rel).join(suffix).to_string_lossy().to_string()
+                };
+                FileSnapshot { path, content: None }
+            })
+            .collect();
+        files.extend(moved);
+
+        self.write_history("rename", files)
+    }
+
+    /// Collect snapshots for a file, every file under a directory, or a missing path
+    fn snapshot(&self, path: &Path, files: &mut Vec<FileSnapshot>) -> Result<(), String> {
+        if path.is_dir() {
+            let entries = fs::read_dir(path)
+                .map_err(|e| format!("Failed to read directory: {}", e))?;
+            for entry in entries.flatten() {
+                self.snapshot(&entry.path(), files)?;
+            }
+        } else if path.is_file() {
+            let content = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to snapshot file: {}", e))?;
+            files.push(FileSnapshot {
+                path: self.relative(path),
+                content: Some(content),
+            });
+        } else {
+            files.push(FileSnapshot {
+                path: self.relative(path),
+                content: None,
+            });
+        }
+        Ok(())
+    }
+
+    /// Journal entry files, oldest first
+    fn history_entries(&self) -> Vec<PathBuf> {
+        let mut entries: Vec<PathBuf> = fs::read_dir(self.memories_dir.join(HISTORY_DIR))
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|ext| ext == "json").unwrap_or(false))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    fn write_history(&self, operation: &str, files: Vec<FileSnapshot>) -> Result<(), String> {
+        let history_dir = self.memories_dir.join(HISTORY_DIR);
+        fs::create_dir_all(&history_dir)
+            .map_err(|e| format!("Failed to create history directory: {}", e))?;
+
+        // Entries are named `{timestamp}-{seq:03}` so names sort in write order. If the
+        // latest entry is not older than now (same instant or clock skew), continue its sequence
+        let mut entries = self.history_entries();
+        let now = chrono::Utc::now().format("%Y%m%dT%H%M%S%.9f").to_string();
+        let latest = entries
+            .last()
+            .and_then(|p| p.file_stem())
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.rsplit_once('-'))
+            .and_then(|(key, seq)| Some((key.to_string(), seq.parse::<u32>().ok()?)));
+        let (key, seq) = match latest {
+            Some((key, seq)) if key >= now => (key, seq + 1),
+            _ => (now, 0),
+        };
+        let entry_path = history_dir.join(format!("{}-{:03}.json", key, seq));
+
+        let entry = HistoryEntry {
+            operation: operation.to_string(),
+            files,
+        };
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize history entry: {}", e))?;
+        fs::write(&entry_path, json).map_err(|e| format!("Failed to write history entry: {}", e))?;
+
+        entries.push(entry_path);
+        let excess = entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        for old in &entries[..excess] {
+            let _ = fs::remove_file(old);
+        }
+        Ok(())
+    }
+
+    /// Path relative to the memories directory, as stored in the journal
+    fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.memories_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
     /// Trigger memory index sync after write operations
     fn trigger_sync(&self) {
         // Best effort sync - don't fail the operation if sync fails
-        if let Ok(index) = MemoryIndex::open(&self.workspace) {
+        if let Ok(index) =
+            MemoryIndex::open_with_memories_dir(&self.workspace, &self.memories_subdir)
+        {
             let _ = index.sync();
         }
     }
 }
 
+/// Lines of unchanged context around a diff hunk
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// Single-hunk unified diff between two versions of a file
+/// Edits touch one contiguous region, so the changed span is found by trimming
+/// the common leading and trailing lines
+fn line_diff(path: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    if old_lines == new_lines {
+        return String::new();
+    }
+
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let max_suffix = old_lines.len().min(new_lines.len()) - prefix;
+    let suffix = old_lines
+        .iter()
+        .rev()
+        .zip(new_lines.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let start = prefix.saturating_sub(DIFF_CONTEXT_LINES);
+    let after = suffix.min(DIFF_CONTEXT_LINES);
+    let old_end = old_lines.len() - suffix;
+    let new_end = new_lines.len() - suffix;
+    let old_len = old_end + after - start;
+    let new_len = new_end + after - start;
+    let line_no = |len: usize| if len == 0 { start } else { start + 1 };
+
+    let mut diff = format!(
+        "--- a/{}\n+++ b/{}\n@@ -{},{} +{},{} @@\n",
+        path,
+        path,
+        line_no(old_len),
+        old_len,
+        line_no(new_len),
+        new_len
+    );
+    for line in &old_lines[start..prefix] {
+        diff.push_str(&format!(" {}\n", line));
+    }
+    for line in &old_lines[prefix..old_end] {
+        diff.push_str(&format!("-{}\n", line));
+    }
+    for line in &new_lines[prefix..new_end] {
+        diff.push_str(&format!("+{}\n", line));
+    }
+    for line in &old_lines[old_end..old_end + after] {
+        diff.push_str(&format!(" {}\n", line));
+    }
+    diff
+}
+
 // ============ Tests ============
 
 #[cfg(test)]
@@ -484,6 +1047,71 @@ mod tests {
         assert!(tool.resolve_path("subdir/file.md").is_ok());
     }
 
+    #[test]
+    fn test_execute_rejects_escapes() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+
+        for path in ["../../etc/passwd", "notes/../../secret.md", "/etc/passwd"] {
+            let result = tool.execute(MemoryToolCommand::Create {
+                path: path.to_string(),
+                file_text: "x".to_string(),
+            });
+            assert!(!result.success, "{} should be rejected", path);
+            assert!(result.error.is_some());
+        }
+
+        // A '..' inside a file name is not traversal
+        assert!(tool.resolve_path("notes..md").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_traversal_blocked() {
+        let dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        fs::write(outside.path().join("secret.md"), "secret").unwrap();
+
+        let tool = MemoryTool::new(dir.path());
+        fs::create_dir_all(&tool.memories_dir).unwrap();
+        std::os::unix::fs::symlink(outside.path(), tool.memories_dir.join("link")).unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("missing.md"),
+            tool.memories_dir.join("dangling.md"),
+        )
+        .unwrap();
+
+        // Reading through the link is blocked
+        let result = tool.view("link/secret.md", None);
+        assert!(!result.success);
+
+        // Creating through the link is blocked and nothing lands outside
+        let result = tool.create("link/new.md", "escaped");
+        assert!(!result.success);
+        assert!(!outside.path().join("new.md").exists());
+
+        // Writing through a dangling link is blocked too
+        let result = tool.create("dangling.md", "escaped");
+        assert!(!result.success);
+        assert!(!outside.path().join("missing.md").exists());
+    }
+
+    #[test]
+    fn test_custom_memories_dir() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::with_memories_dir(dir.path(), "notes/memory").unwrap();
+
+        assert!(tool.create("a.md", "Hello").success);
+        assert!(dir.path().join("notes/memory/a.md").exists());
+
+        // The write synced the index of the same directory
+        let index = MemoryIndex::open_with_memories_dir(dir.path(), "notes/memory").unwrap();
+        assert_eq!(index.get_stats().unwrap().file_count, 1);
+
+        assert!(MemoryTool::with_memories_dir(dir.path(), "../elsewhere").is_err());
+        assert!(MemoryTool::with_memories_dir(dir.path(), "/tmp/memories").is_err());
+    }
+
     #[test]
     fn test_create_and_view() {
         let dir = tempdir().unwrap();
@@ -509,7 +1137,7 @@ mod tests {
         tool.create("test.md", "Hello World");
 
         // Replace text
-        let result = tool.str_replace("test.md", "World", "FlowQ");
+        let result = tool.str_replace("test.md", "World", "FlowQ", None);
         assert!(result.success);
 
         // Verify
@@ -592,4 +1220,256 @@ mod tests {
         assert!(result.output.contains("file2.md"));
         assert!(result.output.contains("subdir/"));
     }
+
+    #[test]
+    fn test_undo_str_replace() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+
+        tool.create("test.md", "Hello World");
+        assert!(tool.str_replace("test.md", "World", "FlowQ", None).success);
+
+        let result = tool.execute(MemoryToolCommand::Undo);
+        assert!(result.success, "{:?}", result.error);
+        let content = fs::read_to_string(tool.memories_dir.join("test.md")).unwrap();
+        assert_eq!(content, "Hello World");
+
+        // Undoing the create removes the file, then the journal is empty
+        assert!(tool.execute(MemoryToolCommand::Undo).success);
+        assert!(!tool.memories_dir.join("test.md").exists());
+        assert!(!tool.execute(MemoryToolCommand::Undo).success);
+    }
+
+    #[test]
+    fn test_undo_delete_and_rename() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+
+        tool.create("notes/a.md", "A");
+        tool.create("notes/b.md", "B");
+        assert!(tool.delete("notes").success);
+        assert!(tool.undo().success);
+        assert_eq!(fs::read_to_string(tool.memories_dir.join("notes/a.md")).unwrap(), "A");
+        assert_eq!(fs::read_to_string(tool.memories_dir.join("notes/b.md")).unwrap(), "B");
+
+        assert!(tool.rename("notes/a.md", "moved.md").success);
+        assert!(tool.undo().success);
+        assert!(tool.memories_dir.join("notes/a.md").exists());
+        assert!(!tool.memories_dir.join("moved.md").exists());
+    }
+
+    #[test]
+    fn test_undo_order_and_history_cap() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+        tool.create("a.md", "A");
+
+        // An entry stamped in the future (clock skew) still sorts before later writes
+        let history_dir = tool.memories_dir.join(HISTORY_DIR);
+        let future = history_dir.join("99991231T235959.000000000-000.json");
+        fs::rename(tool.history_entries().pop().unwrap(), &future).unwrap();
+        tool.create("b.md", "B");
+        assert!(tool.undo().success);
+        assert!(!tool.memories_dir.join("b.md").exists());
+        assert!(tool.memories_dir.join("a.md").exists());
+
+        for i in 0..MAX_HISTORY_ENTRIES + 5 {
+            tool.create(&format!("n{}.md", i), "N");
+        }
+        assert_eq!(tool.history_entries().len(), MAX_HISTORY_ENTRIES);
+        assert!(!future.exists());
+    }
+
+    #[test]
+    fn test_history_hidden() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+
+        tool.create("a.md", "A");
+        let result = tool.view("", None);
+        assert!(!result.output.contains(HISTORY_DIR));
+        assert!(!tool.view(".history", None).success);
+    }
+
+    fn read(tool: &MemoryTool, path: &str) -> String {
+        fs::read_to_string(tool.memories_dir.join(path)).unwrap()
+    }
+
+    #[test]
+    fn test_preview_str_replace() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+        tool.create("test.md", "one\ntwo\nthree\nfour\nfive\nsix");
+
+        let result = tool.preview(MemoryToolCommand::StrReplace {
+            path: "test.md".to_string(),
+            old_str: "four".to_string(),
+            new_str: "4".to_string(),
+            expected_occurrences: None,
+        });
+        assert!(result.success);
+        assert_eq!(
+            result.diff.unwrap(),
+            "--- a/test.md\n+++ b/test.md\n@@ -1,6 +1,6 @@\n one\n two\n three\n-four\n+4\n five\n six\n"
+        );
+        assert_eq!(read(&tool, "test.md"), "one\ntwo\nthree\nfour\nfive\nsix");
+
+        // Nothing was journaled: undo reverts the create, not the preview
+        assert!(tool.undo().success);
+        assert!(!tool.undo().success);
+    }
+
+    #[test]
+    fn test_preview_insert() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+        tool.create("test.md", "Line 1\nLine 3");
+
+        let result = tool.preview(MemoryToolCommand::Insert {
+            path: "test.md".to_string(),
+            insert_line: 2,
+            new_str: "Line 2".to_string(),
+        });
+        assert!(result.success);
+        assert!(result.output.starts_with("Dry run"));
+        assert_eq!(
+            result.diff.unwrap(),
+            "--- a/test.md\n+++ b/test.md\n@@ -1,2 +1,3 @@\n Line 1\n+Line 2\n Line 3\n"
+        );
+        assert_eq!(read(&tool, "test.md"), "Line 1\nLine 3");
+    }
+
+    #[test]
+    fn test_preview_delete() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+        tool.create("notes/a.md", "A");
+        tool.create("notes/b.md", "B");
+
+        let result = tool.preview(MemoryToolCommand::Delete {
+            path: "notes".to_string(),
+        });
+        assert!(result.success);
+        let diff = result.diff.unwrap();
+        assert!(diff.contains("--- a/notes/a.md\n+++ b/notes/a.md\n@@ -1,1 +0,0 @@\n-A\n"));
+        assert!(diff.contains("-B\n"));
+        assert_eq!(read(&tool, "notes/a.md"), "A");
+        assert_eq!(read(&tool, "notes/b.md"), "B");
+    }
+
+    #[test]
+    fn test_preview_create_rename_and_errors() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+
+        let result = tool.preview(MemoryToolCommand::Create {
+            path: "new.md".to_string(),
+            file_text: "hello".to_string(),
+        });
+        assert_eq!(result.diff.unwrap(), "--- a/new.md\n+++ b/new.md\n@@ -0,0 +1,1 @@\n+hello\n");
+        assert!(!tool.memories_dir.join("new.md").exists());
+
+        tool.create("old.md", "Content");
+        let result = tool.preview(MemoryToolCommand::Rename {
+            old_path: "old.md".to_string(),
+            new_path: "new.md".to_string(),
+        });
+        assert!(result.success);
+        assert!(tool.memories_dir.join("old.md").exists());
+        assert!(!tool.memories_dir.join("new.md").exists());
+
+        // Validation still applies in a dry run
+        let result = tool.preview(MemoryToolCommand::StrReplace {
+            path: "old.md".to_string(),
+            old_str: "missing".to_string(),
+            new_str: "x".to_string(),
+            expected_occurrences: None,
+        });
+        assert!(!result.success);
+        assert!(result.diff.is_none());
+    }
+
+    #[test]
+    fn test_list_tree_with_sizes() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+        tool.create("b.md", "12345");
+        tool.create("a/notes.md", "hi");
+        tool.create("a/deep/x.md", "");
+
+        let result = tool.execute(MemoryToolCommand::List { subpath: None });
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(
+            result.output,
+            "a/\n  deep/\n    x.md (0 bytes)\n  notes.md (2 bytes)\nb.md (5 bytes)"
+        );
+
+        let result = tool.execute(MemoryToolCommand::List { subpath: Some("a/deep".to_string()) });
+        assert_eq!(result.output, "x.md (0 bytes)");
+        assert!(!tool.execute(MemoryToolCommand::List { subpath: Some("b.md".to_string()) }).success);
+        assert!(!tool.execute(MemoryToolCommand::List { subpath: Some("../".to_string()) }).success);
+    }
+
+    #[test]
+    fn test_grep_reports_files_and_lines() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+        tool.create("prefs.md", "likes tea\nhates coffee");
+        tool.create("work/projects.md", "FlowQ\nTea tasting app");
+        tool.delete("prefs.md");
+        tool.undo();
+
+        let result = tool.execute(MemoryToolCommand::Grep { pattern: "(?i)tea".to_string() });
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.output, "prefs.md:1: likes tea\nwork/projects.md:2: Tea tasting app");
+
+        let result = tool.execute(MemoryToolCommand::Grep { pattern: "espresso".to_string() });
+        assert_eq!(result.output, "No matches for: espresso");
+        assert!(!tool.execute(MemoryToolCommand::Grep { pattern: "(".to_string() }).success);
+    }
+
+    #[test]
+    fn test_str_replace_expected_occurrences() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+        tool.create("test.md", "tea\ncoffee\ntea");
+
+        // Zero occurrences fails whatever was expected
+        let result = tool.str_replace("test.md", "juice", "water", Some(1));
+        assert!(result.error.unwrap().contains("String not found"));
+
+        // Default requires exactly one
+        let result = tool.str_replace("test.md", "tea", "chai", None);
+        assert!(result.error.unwrap().contains("found 2 times"));
+
+        // A mismatched count is rejected without writing
+        let result = tool.str_replace("test.md", "tea", "chai", Some(1));
+        assert!(result.error.unwrap().contains("Expected 1 occurrence(s) of the string in test.md, found 2"));
+        assert_eq!(read(&tool, "test.md"), "tea\ncoffee\ntea");
+
+        // Matching count replaces every occurrence
+        assert!(tool.str_replace("test.md", "tea", "chai", Some(2)).success);
+        assert_eq!(read(&tool, "test.md"), "chai\ncoffee\nchai");
+
+        // Exactly one occurrence with an explicit expectation
+        assert!(tool.str_replace("test.md", "coffee", "mocha", Some(1)).success);
+        assert_eq!(read(&tool, "test.md"), "chai\nmocha\nchai");
+    }
+
+    #[test]
+    fn test_list_and_grep_before_first_write() {
+        let dir = tempdir().unwrap();
+        let tool = MemoryTool::new(dir.path());
+
+        let result = tool.execute(MemoryToolCommand::List { subpath: None });
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.output, "(empty directory)");
+        assert!(!tool.execute(MemoryToolCommand::List { subpath: Some("notes".to_string()) }).success);
+        assert!(!tool.execute(MemoryToolCommand::List { subpath: Some("../".to_string()) }).success);
+
+        let result = tool.execute(MemoryToolCommand::Grep { pattern: "tea".to_string() });
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.output, "No matches for: tea");
+        assert!(!tool.memories_dir.exists());
+    }
 }
@@ -0,0 +1,109 @@
+//! Versioned schema migrations for the SQLite databases
+//!
+//! The applied version is stored in `PRAGMA user_version`. On open each database
+//! runs its ordered migration list and applies every step above the stored version,
+//! one transaction per step. Databases created before versioning report version 0,
+//! so steps that may already be present in those installs must be idempotent.
+
+use rusqlite::{ffi, Connection, Error, Result};
+
+/// One schema change, applied once in version order
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub apply: fn(&Connection) -> Result<()>,
+}
+
+/// Schema version recorded in the database
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Apply pending migrations and return the resulting schema version
+/// Fails without changes if the database was written by a newer version of the app
+pub fn run(conn: &mut Connection, migrations: &[Migration]) -> Result<u32> {
+    debug_assert!(
+        migrations.windows(2).all(|w| w[0].version < w[1].version),
+        "migrations must be in ascending version order"
+    );
+
+    let current = schema_version(conn)?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if current > latest {
+        return Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_ERROR),
+            Some(format!(
+                "Database schema version {} is newer than supported version {}",
+                current, latest
+            )),
+        ));
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        (migration.apply)(&tx).map_err(|e| {
+            log::error!("Migration {} ({}) failed: {}", migration.version, migration.description, e);
+            e
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        log::info!("Applied migration {}: {}", migration.version, migration.description);
+    }
+
+    Ok(latest.max(current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_notes(conn: &Connection) -> Result<()> {
+        conn.execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT NOT NULL);")
+    }
+
+    fn add_pinned(conn: &Connection) -> Result<()> {
+        conn.execute_batch("ALTER TABLE notes ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;")
+    }
+
+    fn broken(conn: &Connection) -> Result<()> {
+        conn.execute_batch("ALTER TABLE notes ADD COLUMN title TEXT; SELECT * FROM missing_table;")
+    }
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration { version: 1, description: "notes table", apply: create_notes },
+        Migration { version: 2, description: "notes.pinned", apply: add_pinned },
+    ];
+
+    #[test]
+    fn test_run_applies_pending_steps_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(run(&mut conn, &MIGRATIONS[..1]).unwrap(), 1);
+        conn.execute("INSERT INTO notes (body) VALUES ('kept')", []).unwrap();
+
+        // Only the new step runs; re-running is a no-op
+        assert_eq!(run(&mut conn, MIGRATIONS).unwrap(), 2);
+        assert_eq!(run(&mut conn, MIGRATIONS).unwrap(), 2);
+        let (body, pinned): (String, i64) = conn
+            .query_row("SELECT body, pinned FROM notes", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((body.as_str(), pinned), ("kept", 0));
+    }
+
+    #[test]
+    fn test_failed_step_rolls_back_and_newer_schema_is_rejected() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn, MIGRATIONS).unwrap();
+
+        let with_broken = [
+            Migration { version: 1, description: "notes table", apply: create_notes },
+            Migration { version: 2, description: "notes.pinned", apply: add_pinned },
+            Migration { version: 3, description: "broken", apply: broken },
+        ];
+        assert!(run(&mut conn, &with_broken).is_err());
+        assert_eq!(schema_version(&conn).unwrap(), 2);
+        assert!(conn.prepare("SELECT title FROM notes").is_err());
+
+        let err = run(&mut conn, &MIGRATIONS[..1]).unwrap_err();
+        assert!(err.to_string().contains("newer than supported"), "{}", err);
+    }
+}
@@ -0,0 +1,183 @@
+//! Model name resolution shared by the simple-chat and agent paths
+//!
+//! Maps friendly aliases ("sonnet", "haiku", ...) and Anthropic model ids to
+//! the identifier each provider expects. Unknown names pass through.
+//! Also holds the per-model price table used for cost estimates.
+
+/// Friendly alias -> (Anthropic model id, Bedrock model id)
+const ALIASES: &[(&str, &str, &str)] = &[
+    (
+        "sonnet",
+        "claude-sonnet-4-5-20250929",
+        "us.anthropic.claude-sonnet-4-5-20250929-v1:0",
+    ),
+    (
+        "opus",
+        "claude-opus-4-5-20251101",
+        "global.anthropic.claude-opus-4-5-20251101-v1:0",
+    ),
+    (
+        "haiku",
+        "claude-haiku-4-5-20251001",
+        "us.anthropic.claude-haiku-4-5-20251001-v1:0",
+    ),
+];
+
+/// Anthropic model id -> Bedrock model id (cross-region inference where available)
+const BEDROCK_MODELS: &[(&str, &str)] = &[
+    // Claude 4.5 models
+    ("claude-sonnet-4-5-20250929", "us.anthropic.claude-sonnet-4-5-20250929-v1:0"),
+    ("claude-haiku-4-5-20251001", "us.anthropic.claude-haiku-4-5-20251001-v1:0"),
+    ("claude-opus-4-5-20251101", "global.anthropic.claude-opus-4-5-20251101-v1:0"),
+    ("claude-sonnet-4-20250514", "us.anthropic.claude-sonnet-4-5-20250929-v1:0"),
+    ("claude-opus-4-20250514", "global.anthropic.claude-opus-4-5-20251101-v1:0"),
+    // Claude 3.5 models
+    ("claude-3-5-sonnet-20241022", "us.anthropic.claude-3-5-sonnet-20241022-v2:0"),
+    ("claude-3-5-haiku-20241022", "us.anthropic.claude-3-5-haiku-20241022-v1:0"),
+    // Claude 3 models
+    ("claude-3-sonnet-20240229", "us.anthropic.claude-3-sonnet-20240229-v1:0"),
+    ("claude-3-haiku-20240307", "us.anthropic.claude-3-haiku-20240307-v1:0"),
+    ("claude-3-opus-20240229", "us.anthropic.claude-3-opus-20240229-v1:0"),
+];
+
+/// Cross-region inference profile prefixes used by Bedrock model ids
+const BEDROCK_REGION_PREFIXES: &[&str] = &["us.", "eu.", "apac.", "jp.", "au.", "global."];
+
+/// Whether a name is already a Bedrock model id or ARN
+fn is_bedrock_id(model: &str) -> bool {
+    model.starts_with("anthropic.")
+        || model.starts_with("arn:")
+        || BEDROCK_REGION_PREFIXES
+            .iter()
+            .any(|prefix| model.strip_prefix(prefix).is_some_and(|rest| rest.starts_with("anthropic.")))
+}
+
+/// Resolve a model alias or id for a provider
+/// `anthropic` and `bedrock` understand aliases; other providers pass names through
+pub fn resolve(provider: &str, model: &str) -> String {
+    let model = model.trim();
+    let alias = ALIASES
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(model));
+
+    match provider {
+        "anthropic" => alias
+            .map(|(_, anthropic, _)| anthropic.to_string())
+            .unwrap_or_else(|| model.to_string()),
+        "bedrock" => {
+            if let Some((_, _, bedrock)) = alias {
+                return bedrock.to_string();
+            }
+            if is_bedrock_id(model) {
+                return model.to_string();
+            }
+            BEDROCK_MODELS
+                .iter()
+                .find(|(anthropic, _)| *anthropic == model)
+                .map(|(_, bedrock)| bedrock.to_string())
+                // Default to cross-region inference format
+                .unwrap_or_else(|| format!("us.anthropic.{}-v1:0", model))
+        }
+        _ => model.to_string(),
+    }
+}
+
+// ============ Pricing ============
+
+/// USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pricing {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+/// Price family -> (model id fragment, input, output); more specific fragments first
+const PRICING: &[(&str, &str, f64, f64)] = &[
+    ("anthropic", "opus-4-5", 5.0, 25.0),
+    ("anthropic", "opus-4", 15.0, 75.0),
+    ("anthropic", "3-opus", 15.0, 75.0),
+    ("anthropic", "sonnet", 3.0, 15.0),
+    ("anthropic", "haiku-4-5", 1.0, 5.0),
+    ("anthropic", "3-5-haiku", 0.8, 4.0),
+    ("anthropic", "3-haiku", 0.25, 1.25),
+    ("openai", "gpt-4o-mini", 0.15, 0.6),
+    ("openai", "gpt-4o", 2.5, 10.0),
+    ("openai", "gpt-4.1-mini", 0.4, 1.6),
+    ("openai", "gpt-4.1", 2.0, 8.0),
+    ("openai", "o3-mini", 1.1, 4.4),
+];
+
+/// Price assumed for models missing from the table
+pub const FALLBACK_PRICING: Pricing = Pricing {
+    input_per_mtok: 3.0,
+    output_per_mtok: 15.0,
+};
+
+/// Look up pricing for a resolved model id; `None` if the model is unknown
+pub fn pricing(provider: &str, model: &str) -> Option<Pricing> {
+    let family = match provider {
+        "anthropic" | "bedrock" => "anthropic",
+        "openai" | "azure" | "custom" => "openai",
+        _ => return None,
+    };
+    let model = model.to_lowercase();
+    PRICING
+        .iter()
+        .find(|(f, fragment, _, _)| *f == family && model.contains(fragment))
+        .map(|(_, _, input, output)| Pricing {
+            input_per_mtok: *input,
+            output_per_mtok: *output,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_aliases_per_provider() {
+        assert_eq!(resolve("anthropic", "sonnet"), "claude-sonnet-4-5-20250929");
+        assert_eq!(resolve("anthropic", "haiku"), "claude-haiku-4-5-20251001");
+        assert_eq!(resolve("bedrock", "sonnet"), "us.anthropic.claude-sonnet-4-5-20250929-v1:0");
+        assert_eq!(resolve("bedrock", "Haiku"), "us.anthropic.claude-haiku-4-5-20251001-v1:0");
+        // OpenAI-compatible endpoints have their own names
+        assert_eq!(resolve("openai", "sonnet"), "sonnet");
+        assert_eq!(resolve("custom", "haiku"), "haiku");
+    }
+
+    #[test]
+    fn test_resolve_raw_ids() {
+        assert_eq!(resolve("anthropic", "claude-3-5-haiku-20241022"), "claude-3-5-haiku-20241022");
+        assert_eq!(resolve("openai", "gpt-4o"), "gpt-4o");
+
+        assert_eq!(
+            resolve("bedrock", "claude-3-5-haiku-20241022"),
+            "us.anthropic.claude-3-5-haiku-20241022-v1:0"
+        );
+        for id in [
+            "anthropic.claude-3-haiku-20240307-v1:0",
+            "eu.anthropic.claude-sonnet-4-5-20250929-v1:0",
+            "global.anthropic.claude-opus-4-5-20251101-v1:0",
+        ] {
+            assert_eq!(resolve("bedrock", id), id);
+        }
+        assert_eq!(
+            resolve("bedrock", "claude-new-model"),
+            "us.anthropic.claude-new-model-v1:0"
+        );
+    }
+
+    #[test]
+    fn test_pricing_lookup() {
+        let opus = pricing("bedrock", &resolve("bedrock", "opus")).unwrap();
+        assert_eq!(opus.input_per_mtok, 5.0);
+        assert_eq!(pricing("anthropic", "claude-opus-4-1-20250805").unwrap().input_per_mtok, 15.0);
+        assert_eq!(pricing("anthropic", "claude-3-5-haiku-20241022").unwrap().output_per_mtok, 4.0);
+        assert_eq!(pricing("azure", "gpt-4o-mini").unwrap().input_per_mtok, 0.15);
+        assert_eq!(pricing("openai", "gpt-4o-2024-08-06").unwrap().input_per_mtok, 2.5);
+
+        // Fragments are matched within the provider's family only
+        assert!(pricing("openai", "claude-sonnet-4-5").is_none());
+        assert!(pricing("custom", "llama3:70b").is_none());
+    }
+}
@@ -0,0 +1,113 @@
+//! On-disk cache of chat responses for repeated identical requests
+//!
+//! Entries are content-addressed by a SHA-256 of everything that shapes the
+//! model output (provider, endpoint, region, model, messages, system prompt and sampling
+//! parameters) and stored as one JSON file each. Credentials are not part of the key,
+//! but the AWS profile is, since it selects the account the request runs in.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::chat::{ChatRequest, ChatResponse};
+
+/// How long a cached response is served
+pub const DEFAULT_RESPONSE_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Fields of a request that determine the response
+#[derive(Serialize)]
+struct CacheKey<'a> {
+    provider: &'a str,
+    base_url: Option<&'a str>,
+    region: Option<&'a str>,
+    aws_profile: Option<&'a str>,
+    deployment: Option<&'a str>,
+    api_version: Option<&'a str>,
+    model: Option<&'a str>,
+    messages: &'a [crate::chat::ChatMessage],
+    system_prompt: Option<&'a str>,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    stop_sequences: Option<&'a [String]>,
+    tools: Option<&'a [String]>,
+    thinking: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// Unix seconds when the response was stored
+    stored_at: i64,
+    response: ChatResponse,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    /// Content hash identifying a request's cache entry
+    pub fn key(request: &ChatRequest) -> String {
+        let key = CacheKey {
+            provider: &request.config.provider,
+            base_url: request.config.base_url.as_deref(),
+            region: request.config.region.as_deref(),
+            aws_profile: request.config.aws_profile.as_deref(),
+            deployment: request.config.deployment.as_deref(),
+            api_version: request.config.api_version.as_deref(),
+            model: request.config.model.as_deref(),
+            messages: &request.messages,
+            system_prompt: request.system_prompt.as_deref(),
+            max_tokens: request.max_tokens,
+            temperature: request.temperature,
+            top_p: request.top_p,
+            stop_sequences: request.stop_sequences.as_deref(),
+            tools: request.tools.as_deref(),
+            thinking: request.thinking.as_ref().map(|t| t.budget_tokens),
+        };
+        let json = serde_json::to_vec(&key).unwrap_or_default();
+        format!("{:x}", Sha256::digest(&json))
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Cached response for `key`, or None when missing, unreadable or older than the TTL
+    pub fn get(&self, key: &str) -> Option<ChatResponse> {
+        let path = self.entry_path(key);
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+        let age = chrono::Utc::now().timestamp() - entry.stored_at;
+        if age < 0 || age as u64 >= self.ttl.as_secs() {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        Some(entry.response)
+    }
+
+    /// Store a response, replacing any previous entry for `key`
+    pub fn put(&self, key: &str, response: &ChatResponse) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create response cache dir: {}", e))?;
+        let entry = CacheEntry {
+            stored_at: chrono::Utc::now().timestamp(),
+            response: response.clone(),
+        };
+        let json = serde_json::to_vec(&entry)
+            .map_err(|e| format!("Failed to serialize cached response: {}", e))?;
+
+        // Write then rename so a concurrent reader never sees a partial entry
+        let tmp = self.dir.join(format!("{}.tmp", key));
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write response cache: {}", e))?;
+        fs::rename(&tmp, self.entry_path(key))
+            .map_err(|e| format!("Failed to write response cache: {}", e))
+    }
+}
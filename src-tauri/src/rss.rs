@@ -14,6 +14,20 @@ pub struct FetchResult {
     pub status_code: u16,
 }
 
+/// Outcome of a conditional fetch using cached validators
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FetchOutcome {
+    /// Server answered 304; the cached copy is still current
+    NotModified,
+    /// Fresh content with the validators to send next time
+    Updated {
+        articles: Vec<ParsedItem>,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
 /// Parsed RSS feed information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedFeed {
@@ -45,6 +59,14 @@ pub struct ParsedEnclosure {
     pub length: Option<u64>,
 }
 
+/// Feed advertised by a web page via `<link rel="alternate">`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscoveredFeed {
+    pub url: String,
+    pub title: Option<String>,
+    pub feed_type: String,
+}
+
 /// RSS feed manager for fetching and parsing feeds
 pub struct RSSFetcher {
     client: Client,
@@ -130,6 +152,26 @@ impl RSSFetcher {
         })
     }
 
+    /// Fetch with `If-None-Match` / `If-Modified-Since`; only parses when the feed changed
+    pub async fn fetch_conditional(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<FetchOutcome, String> {
+        let result = self.fetch(url, etag, last_modified).await?;
+        if result.status_code == 304 {
+            return Ok(FetchOutcome::NotModified);
+        }
+
+        let parsed = self.parse(&result.content)?;
+        Ok(FetchOutcome::Updated {
+            articles: parsed.items,
+            etag: result.etag,
+            last_modified: result.last_modified,
+        })
+    }
+
     /// Parse RSS or Atom feed from XML content
     pub fn parse(&self, content: &str) -> Result<ParsedFeed, String> {
         // Try RSS 2.0 first, then Atom
@@ -400,7 +442,7 @@ fn extract_atom_enclosures(entry_xml: &str) -> Vec<ParsedEnclosure> {
     enclosures
 }
 
-fn extract_attr(tag: &str, attr: &str) -> Option<String> {
+pub(crate) fn extract_attr(tag: &str, attr: &str) -> Option<String> {
     let patterns = [
         format!("{}=\"", attr),
         format!("{}='", attr),
@@ -419,7 +461,7 @@ fn extract_attr(tag: &str, attr: &str) -> Option<String> {
     None
 }
 
-fn decode_xml_entities(s: &str) -> String {
+pub(crate) fn decode_xml_entities(s: &str) -> String {
     s.replace("&amp;", "&")
         .replace("&lt;", "<")
         .replace("&gt;", ">")
@@ -429,6 +471,59 @@ fn decode_xml_entities(s: &str) -> String {
         .replace("&#x27;", "'")
 }
 
+/// Find RSS/Atom feeds advertised in an HTML page, resolving relative hrefs against `base_url`
+pub fn discover_feeds(html: &str, base_url: &str) -> Vec<DiscoveredFeed> {
+    let base = Url::parse(base_url).ok();
+    let mut feeds: Vec<DiscoveredFeed> = Vec::new();
+    // ASCII lowercasing keeps byte offsets aligned with the original
+    let lower_html = html.to_ascii_lowercase();
+    let mut offset = 0;
+
+    while let Some(pos) = lower_html[offset..].find("<link") {
+        let start = offset + pos;
+        let Some(end) = html[start..].find('>') else { break };
+        let tag = &html[start..=start + end];
+        let lower = &lower_html[start..=start + end];
+        offset = start + end + 1;
+
+        let rel = extract_attr(lower, "rel").unwrap_or_default();
+        if !rel.split_whitespace().any(|r| r == "alternate") {
+            continue;
+        }
+
+        let feed_type = extract_attr(lower, "type").unwrap_or_default();
+        if feed_type != "application/rss+xml" && feed_type != "application/atom+xml" {
+            continue;
+        }
+
+        // Read href/title from the original tag to keep their case
+        let Some(href) = extract_attr(tag, "href").map(|h| decode_xml_entities(&h)) else {
+            continue;
+        };
+        let url = match &base {
+            Some(base) => match base.join(&href) {
+                Ok(url) => url.to_string(),
+                Err(_) => continue,
+            },
+            None => href,
+        };
+
+        if feeds.iter().any(|f| f.url == url) {
+            continue;
+        }
+
+        feeds.push(DiscoveredFeed {
+            url,
+            title: extract_attr(tag, "title")
+                .map(|t| decode_xml_entities(&t))
+                .filter(|t| !t.is_empty()),
+            feed_type,
+        });
+    }
+
+    feeds
+}
+
 // ============ Tauri Commands ============
 
 /// Fetch an RSS feed and return raw content with cache headers
@@ -461,3 +556,116 @@ pub fn rss_parse(content: String) -> Result<ParsedFeed, String> {
     let fetcher = RSSFetcher::new();
     fetcher.parse(&content)
 }
+
+/// Discover feeds advertised by a site's home page
+#[tauri::command]
+pub async fn rss_discover_feeds(site_url: String) -> Result<Vec<DiscoveredFeed>, String> {
+    let fetcher = RSSFetcher::new();
+    let result = fetcher.fetch(&site_url, None, None).await?;
+
+    // The URL may already be a feed
+    if fetcher.parse(&result.content).is_ok() {
+        return Ok(vec![DiscoveredFeed {
+            url: site_url,
+            title: None,
+            feed_type: result.content_type,
+        }]);
+    }
+
+    Ok(discover_feeds(&result.content, &site_url))
+}
+
+// ============ Tests ============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve one canned HTTP response and return the lowercased request
+    async fn serve_once(response: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/feed.xml", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_conditional_fetch_not_modified() {
+        let (url, server) = serve_once("HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n").await;
+
+        let outcome = RSSFetcher::new()
+            .fetch_conditional(&url, Some("\"v1\""), Some("Wed, 01 Jan 2025 00:00:00 GMT"))
+            .await
+            .unwrap();
+        assert!(matches!(outcome, FetchOutcome::NotModified));
+
+        let request = server.await.unwrap();
+        assert!(request.contains("if-none-match: \"v1\""));
+        assert!(request.contains("if-modified-since: wed, 01 jan 2025 00:00:00 gmt"));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_fetch_updated() {
+        let (url, server) = serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/rss+xml\r\nETag: \"v2\"\r\nConnection: close\r\n\r\n\
+             <rss><channel><title>T</title><item><title>A</title><guid>a</guid></item></channel></rss>",
+        )
+        .await;
+
+        let outcome = RSSFetcher::new().fetch_conditional(&url, None, None).await.unwrap();
+        match outcome {
+            FetchOutcome::Updated { articles, etag, .. } => {
+                assert_eq!(articles.len(), 1);
+                assert_eq!(articles[0].guid.as_deref(), Some("a"));
+                assert_eq!(etag.as_deref(), Some("\"v2\""));
+            }
+            other => panic!("expected Updated, got {:?}", other),
+        }
+
+        let request = server.await.unwrap();
+        assert!(!request.contains("if-none-match"));
+    }
+
+    #[test]
+    fn test_discover_no_feeds() {
+        let html = r#"<html><head><link rel="stylesheet" href="/style.css"></head></html>"#;
+        assert!(discover_feeds(html, "https://example.com/").is_empty());
+    }
+
+    #[test]
+    fn test_discover_single_relative_feed() {
+        let html = r#"<head>
+            <LINK REL="alternate" TYPE="application/rss+xml" title="Example &amp; Co" href="/feed.xml">
+        </head>"#;
+        let feeds = discover_feeds(html, "https://example.com/blog/post");
+        assert_eq!(
+            feeds,
+            vec![DiscoveredFeed {
+                url: "https://example.com/feed.xml".to_string(),
+                title: Some("Example & Co".to_string()),
+                feed_type: "application/rss+xml".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_discover_multiple_feeds() {
+        let html = r#"<head>
+            <link rel="alternate" type="application/rss+xml" title="Posts" href="https://example.com/rss">
+            <link rel="alternate" type="application/atom+xml" title="Comments" href="comments.atom"/>
+            <link rel="alternate" type="text/html" hreflang="fr" href="/fr/">
+            <link rel="alternate" type="application/rss+xml" href="https://example.com/rss">
+        </head>"#;
+        let feeds = discover_feeds(html, "https://example.com/blog/");
+        let urls: Vec<_> = feeds.iter().map(|f| f.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://example.com/rss", "https://example.com/blog/comments.atom"]);
+        assert_eq!(feeds[1].feed_type, "application/atom+xml");
+    }
+}
@@ -1,12 +1,35 @@
 //! SQLite storage for RSS feeds and articles
 
+use crate::db::Page;
+use crate::migrations::{self, Migration};
+use crate::sqlite_pool::SqlitePool;
 use rusqlite::{params, Connection, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::sync::Arc;
-use std::sync::Mutex;
 use tauri::Manager;
 
+/// Schema steps in version order; never edit a released step, append a new one
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "feeds, categories and articles with full-text search",
+        apply: RSSDatabase::create_base_schema,
+    },
+    Migration {
+        version: 2,
+        description: "rss_feeds.refresh_interval_minutes",
+        apply: |conn| RSSDatabase::add_column_if_missing(conn, "rss_feeds", "refresh_interval_minutes", "INTEGER"),
+    },
+    Migration {
+        version: 3,
+        description: "rss_feeds.consecutive_failures",
+        apply: |conn| {
+            RSSDatabase::add_column_if_missing(conn, "rss_feeds", "consecutive_failures", "INTEGER NOT NULL DEFAULT 0")
+        },
+    },
+];
+
 /// RSS feed stored in database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredFeed {
@@ -27,6 +50,12 @@ pub struct StoredFeed {
     pub unread_count: i32,
     pub created_at: String,
     pub updated_at: String,
+    /// Minutes between background refreshes (None uses the scheduler default)
+    #[serde(default)]
+    pub refresh_interval_minutes: Option<i32>,
+    /// Failed fetches since the last success (drives refresh backoff)
+    #[serde(default)]
+    pub consecutive_failures: i32,
 }
 
 /// RSS category stored in database
@@ -58,25 +87,59 @@ pub struct StoredArticle {
     pub topics: Option<String>,  // JSON array
 }
 
+/// Article search hit with relevance score and highlighted excerpt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArticleSearchResult {
+    #[serde(flatten)]
+    pub article: StoredArticle,
+    /// Relevance score (higher is better), derived from FTS5 bm25
+    pub score: f64,
+    /// Excerpt with matched terms wrapped in `<mark>`
+    pub snippet: String,
+}
+
+/// Ordering for article search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchOrder {
+    /// Best match first, newer first among equal scores
+    #[default]
+    Relevance,
+    /// Newest first
+    Recent,
+}
+
+/// Summary of an OPML import
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpmlImportResult {
+    pub feeds_added: i32,
+    pub feeds_skipped: i32,
+    pub categories_added: i32,
+}
+
 /// RSS database manager
 pub struct RSSDatabase {
-    conn: Arc<Mutex<Connection>>,
+    pool: SqlitePool,
 }
 
 impl RSSDatabase {
     /// Open or create RSS database
     pub fn open(path: &Path) -> SqliteResult<Self> {
-        let conn = Connection::open(path)?;
         let db = Self {
-            conn: Arc::new(Mutex::new(conn)),
+            pool: SqlitePool::open(path)?,
         };
-        db.init_tables()?;
+        db.migrate()?;
         Ok(db)
     }
 
-    /// Initialize RSS tables
-    fn init_tables(&self) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    /// Apply pending schema migrations, returning the resulting schema version
+    pub fn migrate(&self) -> SqliteResult<u32> {
+        let mut conn = self.pool.writer();
+        migrations::run(&mut conn, MIGRATIONS)
+    }
+
+    /// Tables as first released; idempotent so unversioned installs can run it
+    fn create_base_schema(conn: &Connection) -> SqliteResult<()> {
         conn.execute_batch(r#"
             CREATE TABLE IF NOT EXISTS rss_feeds (
                 id TEXT PRIMARY KEY,
@@ -144,11 +207,26 @@ impl RSSDatabase {
                 INSERT INTO rss_articles_fts(rss_articles_fts, rowid, title, content) VALUES('delete', OLD.rowid, OLD.title, OLD.content);
             END;
 
-            CREATE TRIGGER IF NOT EXISTS rss_articles_au AFTER UPDATE ON rss_articles BEGIN
+            -- Only reindex when indexed columns change, so read/starred flips skip FTS
+            DROP TRIGGER IF EXISTS rss_articles_au;
+            CREATE TRIGGER rss_articles_au AFTER UPDATE OF title, content ON rss_articles BEGIN
                 INSERT INTO rss_articles_fts(rss_articles_fts, rowid, title, content) VALUES('delete', OLD.rowid, OLD.title, OLD.content);
                 INSERT INTO rss_articles_fts(rowid, title, content) VALUES (NEW.rowid, NEW.title, NEW.content);
             END;
-        "#)?;
+        "#)
+    }
+
+    /// Add a column to an existing table created by an older version
+    fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> SqliteResult<()> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|r| r.ok())
+            .any(|name| name == column);
+
+        if !exists {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+        }
         Ok(())
     }
 
@@ -156,10 +234,10 @@ impl RSSDatabase {
 
     /// Create a new feed
     pub fn create_feed(&self, feed: &StoredFeed) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
-            r#"INSERT INTO rss_feeds (id, url, title, description, site_url, icon_url, category_id, tags, status, created_at, updated_at)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"#,
+            r#"INSERT INTO rss_feeds (id, url, title, description, site_url, icon_url, category_id, tags, status, created_at, updated_at, refresh_interval_minutes)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"#,
             params![
                 feed.id,
                 feed.url,
@@ -172,6 +250,7 @@ impl RSSDatabase {
                 feed.status,
                 feed.created_at,
                 feed.updated_at,
+                feed.refresh_interval_minutes,
             ],
         )?;
         Ok(())
@@ -179,11 +258,12 @@ impl RSSDatabase {
 
     /// Get all feeds
     pub fn get_feeds(&self) -> SqliteResult<Vec<StoredFeed>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, url, title, description, site_url, icon_url, category_id, tags,
                       status, error_message, last_fetched_at, etag, last_modified,
-                      article_count, unread_count, created_at, updated_at
+                      article_count, unread_count, created_at, updated_at, refresh_interval_minutes,
+                      consecutive_failures
                FROM rss_feeds ORDER BY title"#,
         )?;
 
@@ -207,6 +287,8 @@ impl RSSDatabase {
                 unread_count: row.get(14)?,
                 created_at: row.get(15)?,
                 updated_at: row.get(16)?,
+                refresh_interval_minutes: row.get(17)?,
+                consecutive_failures: row.get(18)?,
             })
         })?
         .collect::<SqliteResult<Vec<_>>>()?;
@@ -216,11 +298,12 @@ impl RSSDatabase {
 
     /// Get a feed by ID
     pub fn get_feed(&self, id: &str) -> SqliteResult<Option<StoredFeed>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, url, title, description, site_url, icon_url, category_id, tags,
                       status, error_message, last_fetched_at, etag, last_modified,
-                      article_count, unread_count, created_at, updated_at
+                      article_count, unread_count, created_at, updated_at, refresh_interval_minutes,
+                      consecutive_failures
                FROM rss_feeds WHERE id = ?1"#,
         )?;
 
@@ -245,6 +328,8 @@ impl RSSDatabase {
                 unread_count: row.get(14)?,
                 created_at: row.get(15)?,
                 updated_at: row.get(16)?,
+                refresh_interval_minutes: row.get(17)?,
+                consecutive_failures: row.get(18)?,
             }))
         } else {
             Ok(None)
@@ -253,13 +338,14 @@ impl RSSDatabase {
 
     /// Update a feed
     pub fn update_feed(&self, feed: &StoredFeed) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             r#"UPDATE rss_feeds SET
                 title = ?2, description = ?3, site_url = ?4, icon_url = ?5,
                 category_id = ?6, tags = ?7, status = ?8, error_message = ?9,
                 last_fetched_at = ?10, etag = ?11, last_modified = ?12,
-                article_count = ?13, unread_count = ?14, updated_at = ?15
+                article_count = ?13, unread_count = ?14, updated_at = ?15,
+                refresh_interval_minutes = ?16, consecutive_failures = ?17
                WHERE id = ?1"#,
             params![
                 feed.id,
@@ -277,14 +363,28 @@ impl RSSDatabase {
                 feed.article_count,
                 feed.unread_count,
                 feed.updated_at,
+                feed.refresh_interval_minutes,
+                feed.consecutive_failures,
             ],
         )?;
         Ok(())
     }
 
+    /// Clear a feed's failure counter and error state
+    pub fn reset_feed_health(&self, id: &str) -> SqliteResult<()> {
+        let conn = self.pool.writer();
+        conn.execute(
+            r#"UPDATE rss_feeds SET consecutive_failures = 0, status = 'active',
+                error_message = NULL, updated_at = ?2
+               WHERE id = ?1"#,
+            params![id, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
     /// Delete a feed and all its articles
     pub fn delete_feed(&self, id: &str) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         // Articles will be cascade deleted
         conn.execute("DELETE FROM rss_feeds WHERE id = ?1", params![id])?;
         Ok(())
@@ -294,7 +394,7 @@ impl RSSDatabase {
 
     /// Create a new category
     pub fn create_category(&self, category: &StoredCategory) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             "INSERT INTO rss_categories (id, name, color, created_at) VALUES (?1, ?2, ?3, ?4)",
             params![category.id, category.name, category.color, category.created_at],
@@ -304,7 +404,7 @@ impl RSSDatabase {
 
     /// Get all categories with feed counts
     pub fn get_categories(&self) -> SqliteResult<Vec<StoredCategory>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let mut stmt = conn.prepare(
             r#"SELECT c.id, c.name, c.color, c.created_at,
                       (SELECT COUNT(*) FROM rss_feeds WHERE category_id = c.id) as feed_count
@@ -327,7 +427,7 @@ impl RSSDatabase {
 
     /// Delete a category (feeds remain but lose their category)
     pub fn delete_category(&self, id: &str) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         // Update feeds to remove category reference
         conn.execute(
             "UPDATE rss_feeds SET category_id = NULL WHERE category_id = ?1",
@@ -341,7 +441,7 @@ impl RSSDatabase {
 
     /// Insert or update an article
     pub fn upsert_article(&self, article: &StoredArticle) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
 
         // Check if article exists
         let exists: bool = conn.query_row(
@@ -398,9 +498,49 @@ impl RSSDatabase {
         }
     }
 
+    /// Get a single article by ID
+    pub fn get_article(&self, id: &str) -> SqliteResult<Option<StoredArticle>> {
+        let conn = self.pool.reader()?;
+        let mut stmt = conn.prepare(
+            r#"SELECT id, feed_id, title, link, content, summary, author, image_url, enclosures,
+                      published_at, fetched_at, is_read, is_starred, topics
+               FROM rss_articles WHERE id = ?1"#,
+        )?;
+
+        let mut rows = stmt.query_map(params![id], Self::row_to_article)?;
+        rows.next().transpose()
+    }
+
+    /// Most recent articles that have not been topic-classified yet
+    pub fn get_unclassified_articles(&self, limit: i32) -> SqliteResult<Vec<StoredArticle>> {
+        let conn = self.pool.reader()?;
+        let mut stmt = conn.prepare(
+            r#"SELECT id, feed_id, title, link, content, summary, author, image_url, enclosures,
+                      published_at, fetched_at, is_read, is_starred, topics
+               FROM rss_articles WHERE topics IS NULL
+               ORDER BY published_at DESC LIMIT ?1"#,
+        )?;
+
+        let articles = stmt.query_map(params![limit], Self::row_to_article)?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(articles)
+    }
+
+    /// Store an article's topics as a JSON array
+    pub fn set_article_topics(&self, id: &str, topics: &[String]) -> SqliteResult<()> {
+        let conn = self.pool.writer();
+        let topics_json = serde_json::to_string(topics).unwrap_or_else(|_| "[]".to_string());
+        conn.execute(
+            "UPDATE rss_articles SET topics = ?2 WHERE id = ?1",
+            params![id, topics_json],
+        )?;
+        Ok(())
+    }
+
     /// Get articles for a feed
     pub fn get_articles_for_feed(&self, feed_id: &str, limit: i32) -> SqliteResult<Vec<StoredArticle>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, feed_id, title, link, content, summary, author, image_url, enclosures,
                       published_at, fetched_at, is_read, is_starred, topics
@@ -414,9 +554,45 @@ impl RSSDatabase {
         Ok(articles)
     }
 
+    /// Page through a feed's articles, newest first within each page
+    /// `before_id` (or no cursor) walks toward older articles; `after_id` alone walks
+    /// toward newer ones. Either way `next_cursor` continues in the same direction
+    pub fn get_articles_page(
+        &self,
+        feed_id: &str,
+        before_id: Option<&str>,
+        after_id: Option<&str>,
+        limit: u32,
+    ) -> SqliteResult<Page<StoredArticle>> {
+        let conn = self.pool.reader()?;
+        let newer = after_id.is_some() && before_id.is_none();
+        let order = if newer { "ASC" } else { "DESC" };
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT id, feed_id, title, link, content, summary, author, image_url, enclosures,
+                      published_at, fetched_at, is_read, is_starred, topics
+               FROM rss_articles a WHERE feed_id = ?1
+                 AND (?2 IS NULL OR (a.published_at, a.rowid) < (SELECT published_at, rowid FROM rss_articles WHERE id = ?2))
+                 AND (?3 IS NULL OR (a.published_at, a.rowid) > (SELECT published_at, rowid FROM rss_articles WHERE id = ?3))
+               ORDER BY a.published_at {0}, a.rowid {0}
+               LIMIT ?4"#,
+            order
+        ))?;
+
+        let articles = stmt.query_map(
+            params![feed_id, before_id, after_id, limit as i64 + 1],
+            Self::row_to_article,
+        )?
+        .collect::<SqliteResult<Vec<_>>>()?;
+        let mut page = Page::from_rows(articles, limit, |a| a.id.clone());
+        if newer {
+            page.items.reverse();
+        }
+        Ok(page)
+    }
+
     /// Get recent articles across all feeds
     pub fn get_recent_articles(&self, hours: i32, limit: i32) -> SqliteResult<Vec<StoredArticle>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let cutoff = chrono::Utc::now() - chrono::Duration::hours(hours as i64);
         let cutoff_str = cutoff.to_rfc3339();
 
@@ -434,27 +610,47 @@ impl RSSDatabase {
     }
 
     /// Search articles using full-text search
-    pub fn search_articles(&self, query: &str, limit: i32) -> SqliteResult<Vec<StoredArticle>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
+    pub fn search_articles(
+        &self,
+        query: &str,
+        limit: i32,
+        order: SearchOrder,
+    ) -> SqliteResult<Vec<ArticleSearchResult>> {
+        let conn = self.pool.reader()?;
+        let order_by = match order {
+            SearchOrder::Relevance => "score DESC, a.published_at DESC",
+            SearchOrder::Recent => "a.published_at DESC",
+        };
+        // bm25() is lower-is-better; title matches weigh more than body matches
+        let sql = format!(
             r#"SELECT a.id, a.feed_id, a.title, a.link, a.content, a.summary, a.author,
                       a.image_url, a.enclosures, a.published_at, a.fetched_at,
-                      a.is_read, a.is_starred, a.topics
+                      a.is_read, a.is_starred, a.topics,
+                      -bm25(rss_articles_fts, 2.0, 1.0) AS score,
+                      snippet(rss_articles_fts, -1, '<mark>', '</mark>', '...', 24) AS snippet
                FROM rss_articles a
-               JOIN rss_articles_fts fts ON a.rowid = fts.rowid
+               JOIN rss_articles_fts ON a.rowid = rss_articles_fts.rowid
                WHERE rss_articles_fts MATCH ?1
-               ORDER BY a.published_at DESC LIMIT ?2"#,
-        )?;
-
-        let articles = stmt.query_map(params![query, limit], Self::row_to_article)?
-            .collect::<SqliteResult<Vec<_>>>()?;
+               ORDER BY {} LIMIT ?2"#,
+            order_by
+        );
+        let mut stmt = conn.prepare(&sql)?;
+
+        let results = stmt.query_map(params![query, limit], |row| {
+            Ok(ArticleSearchResult {
+                article: Self::row_to_article(row)?,
+                score: row.get(14)?,
+                snippet: row.get(15)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
 
-        Ok(articles)
+        Ok(results)
     }
 
     /// Mark article as read
     pub fn mark_article_read(&self, id: &str, is_read: bool) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             "UPDATE rss_articles SET is_read = ?2 WHERE id = ?1",
             params![id, is_read],
@@ -462,9 +658,45 @@ impl RSSDatabase {
         Ok(())
     }
 
+    /// Mark every article in a feed as read and recompute its counts
+    /// Returns the number of articles that were unread
+    pub fn mark_feed_read(&self, feed_id: &str) -> SqliteResult<usize> {
+        let mut conn = self.pool.writer();
+        let tx = conn.transaction()?;
+        let changed = tx.execute(
+            "UPDATE rss_articles SET is_read = 1 WHERE feed_id = ?1 AND is_read = 0",
+            params![feed_id],
+        )?;
+        tx.execute(
+            r#"UPDATE rss_feeds SET
+                article_count = (SELECT COUNT(*) FROM rss_articles WHERE feed_id = ?1),
+                unread_count = 0
+               WHERE id = ?1"#,
+            params![feed_id],
+        )?;
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Mark all articles as read and recompute every feed's counts
+    /// Returns the number of articles that were unread
+    pub fn mark_all_read(&self) -> SqliteResult<usize> {
+        let mut conn = self.pool.writer();
+        let tx = conn.transaction()?;
+        let changed = tx.execute("UPDATE rss_articles SET is_read = 1 WHERE is_read = 0", [])?;
+        tx.execute(
+            r#"UPDATE rss_feeds SET
+                article_count = (SELECT COUNT(*) FROM rss_articles WHERE feed_id = rss_feeds.id),
+                unread_count = 0"#,
+            [],
+        )?;
+        tx.commit()?;
+        Ok(changed)
+    }
+
     /// Toggle article starred status
     pub fn toggle_article_starred(&self, id: &str) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             "UPDATE rss_articles SET is_starred = NOT is_starred WHERE id = ?1",
             params![id],
@@ -481,7 +713,7 @@ impl RSSDatabase {
 
     /// Get starred articles
     pub fn get_starred_articles(&self, limit: i32) -> SqliteResult<Vec<StoredArticle>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.reader()?;
         let mut stmt = conn.prepare(
             r#"SELECT id, feed_id, title, link, content, summary, author, image_url, enclosures,
                       published_at, fetched_at, is_read, is_starred, topics
@@ -497,7 +729,7 @@ impl RSSDatabase {
 
     /// Delete old articles (retention cleanup)
     pub fn delete_old_articles(&self, days: i32) -> SqliteResult<i32> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         let cutoff = chrono::Utc::now() - chrono::Duration::days(days as i64);
         let cutoff_str = cutoff.to_rfc3339();
 
@@ -512,7 +744,7 @@ impl RSSDatabase {
 
     /// Update feed article counts
     pub fn update_feed_counts(&self, feed_id: &str) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.writer();
         conn.execute(
             r#"UPDATE rss_feeds SET
                 article_count = (SELECT COUNT(*) FROM rss_articles WHERE feed_id = ?1),
@@ -523,6 +755,133 @@ impl RSSDatabase {
         Ok(())
     }
 
+    // ============ OPML ============
+
+    /// Import feeds and categories from OPML
+    /// Feeds already present (by URL) are skipped; categories are matched by name
+    pub fn import_opml(&self, xml: &str) -> SqliteResult<OpmlImportResult> {
+        let mut result = OpmlImportResult::default();
+        let mut categories = self.get_categories()?;
+        let mut known_urls: std::collections::HashSet<String> =
+            self.get_feeds()?.into_iter().map(|f| f.url).collect();
+        let now = chrono::Utc::now().to_rfc3339();
+
+        for outline in parse_opml_outlines(xml) {
+            if !known_urls.insert(outline.xml_url.clone()) {
+                result.feeds_skipped += 1;
+                continue;
+            }
+
+            let category_id = match &outline.category {
+                Some(name) => {
+                    let existing = categories
+                        .iter()
+                        .find(|c| c.name.eq_ignore_ascii_case(name))
+                        .map(|c| c.id.clone());
+                    match existing {
+                        Some(id) => Some(id),
+                        None => {
+                            let category = StoredCategory {
+                                id: uuid::Uuid::new_v4().to_string(),
+                                name: name.clone(),
+                                color: None,
+                                feed_count: 0,
+                                created_at: now.clone(),
+                            };
+                            self.create_category(&category)?;
+                            result.categories_added += 1;
+                            let id = category.id.clone();
+                            categories.push(category);
+                            Some(id)
+                        }
+                    }
+                }
+                None => None,
+            };
+
+            self.create_feed(&StoredFeed {
+                id: uuid::Uuid::new_v4().to_string(),
+                title: outline.title.unwrap_or_else(|| outline.xml_url.clone()),
+                url: outline.xml_url,
+                description: None,
+                site_url: outline.html_url,
+                icon_url: None,
+                category_id,
+                tags: Vec::new(),
+                status: "active".to_string(),
+                error_message: None,
+                last_fetched_at: None,
+                etag: None,
+                last_modified: None,
+                article_count: 0,
+                unread_count: 0,
+                created_at: now.clone(),
+                updated_at: now.clone(),
+                refresh_interval_minutes: None,
+                consecutive_failures: 0,
+            })?;
+            result.feeds_added += 1;
+        }
+
+        Ok(result)
+    }
+
+    /// Export all feeds as OPML, grouped by category
+    pub fn export_opml(&self) -> SqliteResult<String> {
+        let feeds = self.get_feeds()?;
+        let categories = self.get_categories()?;
+
+        let feed_outline = |feed: &StoredFeed, indent: &str| {
+            let mut line = format!(
+                "{}<outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\"",
+                indent,
+                escape_xml(&feed.title),
+                escape_xml(&feed.title),
+                escape_xml(&feed.url)
+            );
+            if let Some(site) = &feed.site_url {
+                line.push_str(&format!(" htmlUrl=\"{}\"", escape_xml(site)));
+            }
+            line.push_str("/>\n");
+            line
+        };
+
+        let mut body = String::new();
+        for category in &categories {
+            let members: Vec<_> = feeds
+                .iter()
+                .filter(|f| f.category_id.as_deref() == Some(category.id.as_str()))
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            let name = escape_xml(&category.name);
+            body.push_str(&format!("    <outline text=\"{}\" title=\"{}\">\n", name, name));
+            for feed in members {
+                body.push_str(&feed_outline(feed, "      "));
+            }
+            body.push_str("    </outline>\n");
+        }
+
+        // Feeds without a (known) category go at the top level
+        for feed in &feeds {
+            let categorized = feed
+                .category_id
+                .as_deref()
+                .map(|id| categories.iter().any(|c| c.id == id))
+                .unwrap_or(false);
+            if !categorized {
+                body.push_str(&feed_outline(feed, "    "));
+            }
+        }
+
+        Ok(format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <opml version=\"2.0\">\n  <head>\n    <title>FlowQ Subscriptions</title>\n  </head>\n  <body>\n{}  </body>\n</opml>\n",
+            body
+        ))
+    }
+
     // Helper to convert row to StoredArticle
     fn row_to_article(row: &rusqlite::Row) -> SqliteResult<StoredArticle> {
         Ok(StoredArticle {
@@ -544,6 +903,75 @@ impl RSSDatabase {
     }
 }
 
+// ============ OPML Parsing ============
+
+/// A feed outline from an OPML document
+struct OpmlOutline {
+    xml_url: String,
+    html_url: Option<String>,
+    title: Option<String>,
+    /// Innermost enclosing category outline
+    category: Option<String>,
+}
+
+/// Collect feed outlines, tracking the category outline each one is nested in
+fn parse_opml_outlines(xml: &str) -> Vec<OpmlOutline> {
+    use crate::rss::{decode_xml_entities, extract_attr};
+
+    let attr = |tag: &str, name: &str| {
+        extract_attr(tag, name)
+            .map(|v| decode_xml_entities(&v).trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let mut outlines = Vec::new();
+    // One entry per open non-self-closing outline; Some(name) for categories
+    let mut stack: Vec<Option<String>> = Vec::new();
+    let mut rest = xml;
+
+    while let Some(pos) = rest.find('<') {
+        rest = &rest[pos..];
+        let Some(end) = rest.find('>') else { break };
+        let tag = &rest[..=end];
+        rest = &rest[end + 1..];
+
+        if tag.starts_with("</outline") {
+            stack.pop();
+            continue;
+        }
+        if !tag.starts_with("<outline") {
+            continue;
+        }
+
+        let self_closing = tag.ends_with("/>");
+        let title = attr(tag, "title").or_else(|| attr(tag, "text"));
+
+        if let Some(xml_url) = attr(tag, "xmlUrl") {
+            outlines.push(OpmlOutline {
+                xml_url,
+                html_url: attr(tag, "htmlUrl"),
+                title,
+                category: stack.iter().rev().find_map(|c| c.clone()),
+            });
+            if !self_closing {
+                stack.push(None);
+            }
+        } else if !self_closing {
+            stack.push(title);
+        }
+    }
+
+    outlines
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
 // ============ Global Instance ============
 
 use std::sync::OnceLock;
@@ -582,14 +1010,27 @@ pub fn rss_create_feed(app: AppHandle, feed: StoredFeed) -> Result<(), String> {
 }
 
 /// Update an existing RSS feed
+/// The failure counter is kept as stored; only refreshes and `rss_reset_feed_health` change it
 #[tauri::command]
-pub fn rss_update_feed(app: AppHandle, feed: StoredFeed) -> Result<(), String> {
+pub fn rss_update_feed(app: AppHandle, mut feed: StoredFeed) -> Result<(), String> {
     let app_data_dir = app.path().app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
     let db = get_rss_db(&app_data_dir);
+    if let Some(stored) = db.get_feed(&feed.id).map_err(|e| e.to_string())? {
+        feed.consecutive_failures = stored.consecutive_failures;
+    }
     db.update_feed(&feed).map_err(|e| e.to_string())
 }
 
+/// Clear a feed's failure backoff and error status
+#[tauri::command]
+pub fn rss_reset_feed_health(app: AppHandle, id: String) -> Result<(), String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let db = get_rss_db(&app_data_dir);
+    db.reset_feed_health(&id).map_err(|e| e.to_string())
+}
+
 /// Delete an RSS feed
 #[tauri::command]
 pub fn rss_delete_feed(app: AppHandle, id: String) -> Result<(), String> {
@@ -657,6 +1098,22 @@ pub fn rss_get_articles(app: AppHandle, feed_id: String, limit: i32) -> Result<V
     db.get_articles_for_feed(&feed_id, limit).map_err(|e| e.to_string())
 }
 
+/// Get one page of a feed's articles, see `RSSDatabase::get_articles_page`
+#[tauri::command]
+pub fn rss_get_articles_page(
+    app: AppHandle,
+    feed_id: String,
+    before_id: Option<String>,
+    after_id: Option<String>,
+    limit: u32,
+) -> Result<Page<StoredArticle>, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let db = get_rss_db(&app_data_dir);
+    db.get_articles_page(&feed_id, before_id.as_deref(), after_id.as_deref(), limit)
+        .map_err(|e| e.to_string())
+}
+
 /// Get recent articles across all feeds
 #[tauri::command]
 pub fn rss_get_recent_articles(app: AppHandle, hours: i32, limit: i32) -> Result<Vec<StoredArticle>, String> {
@@ -668,11 +1125,16 @@ pub fn rss_get_recent_articles(app: AppHandle, hours: i32, limit: i32) -> Result
 
 /// Search articles
 #[tauri::command]
-pub fn rss_search_articles(app: AppHandle, query: String, limit: i32) -> Result<Vec<StoredArticle>, String> {
+pub fn rss_search_articles(
+    app: AppHandle,
+    query: String,
+    limit: i32,
+    order: Option<SearchOrder>,
+) -> Result<Vec<ArticleSearchResult>, String> {
     let app_data_dir = app.path().app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
     let db = get_rss_db(&app_data_dir);
-    db.search_articles(&query, limit).map_err(|e| e.to_string())
+    db.search_articles(&query, limit, order.unwrap_or_default()).map_err(|e| e.to_string())
 }
 
 /// Mark article as read
@@ -684,6 +1146,24 @@ pub fn rss_mark_article_read(app: AppHandle, id: String, is_read: bool) -> Resul
     db.mark_article_read(&id, is_read).map_err(|e| e.to_string())
 }
 
+/// Mark all articles in a feed as read
+#[tauri::command]
+pub fn rss_mark_feed_read(app: AppHandle, feed_id: String) -> Result<usize, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let db = get_rss_db(&app_data_dir);
+    db.mark_feed_read(&feed_id).map_err(|e| e.to_string())
+}
+
+/// Mark all articles in every feed as read
+#[tauri::command]
+pub fn rss_mark_all_read(app: AppHandle) -> Result<usize, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let db = get_rss_db(&app_data_dir);
+    db.mark_all_read().map_err(|e| e.to_string())
+}
+
 /// Toggle article starred status
 #[tauri::command]
 pub fn rss_toggle_article_starred(app: AppHandle, id: String) -> Result<bool, String> {
@@ -710,3 +1190,292 @@ pub fn rss_cleanup_old_articles(app: AppHandle, days: i32) -> Result<i32, String
     let db = get_rss_db(&app_data_dir);
     db.delete_old_articles(days).map_err(|e| e.to_string())
 }
+
+/// Import feeds from an OPML document
+#[tauri::command]
+pub fn rss_import_opml(app: AppHandle, xml: String) -> Result<OpmlImportResult, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let db = get_rss_db(&app_data_dir);
+    db.import_opml(&xml).map_err(|e| e.to_string())
+}
+
+/// Export all feeds as an OPML document
+#[tauri::command]
+pub fn rss_export_opml(app: AppHandle) -> Result<String, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let db = get_rss_db(&app_data_dir);
+    db.export_opml().map_err(|e| e.to_string())
+}
+
+// ============ Tests ============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const SAMPLE_OPML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<opml version="1.0">
+  <head><title>Subscriptions</title></head>
+  <body>
+    <outline text="Tech" title="Tech">
+      <outline type="rss" text="Rust Blog" xmlUrl="https://blog.rust-lang.org/feed.xml" htmlUrl="https://blog.rust-lang.org/"/>
+      <outline text="Languages">
+        <outline type="rss" text="Go &amp; Friends" xmlUrl="https://go.dev/blog/feed.atom"/>
+      </outline>
+    </outline>
+    <outline text="News">
+      <outline type="rss" text="HN" xmlUrl="https://hnrss.org/frontpage"></outline>
+    </outline>
+    <outline type="rss" text="Loose Feed" xmlUrl="https://example.com/rss"/>
+    <outline type="rss" text="Duplicate" xmlUrl="https://example.com/rss"/>
+  </body>
+</opml>"#;
+
+    fn open_db() -> (tempfile::TempDir, RSSDatabase) {
+        let dir = tempdir().unwrap();
+        let db = RSSDatabase::open(&dir.path().join("rss.db")).unwrap();
+        (dir, db)
+    }
+
+    fn category_of(db: &RSSDatabase, url: &str) -> Option<String> {
+        let categories = db.get_categories().unwrap();
+        let feed = db.get_feeds().unwrap().into_iter().find(|f| f.url == url)?;
+        let id = feed.category_id?;
+        categories.into_iter().find(|c| c.id == id).map(|c| c.name)
+    }
+
+    fn test_article(feed_id: &str, id: &str, title: &str, content: &str, published_at: &str) -> StoredArticle {
+        StoredArticle {
+            id: id.to_string(),
+            feed_id: feed_id.to_string(),
+            title: title.to_string(),
+            link: format!("https://example.com/{}", id),
+            content: content.to_string(),
+            summary: None,
+            author: None,
+            image_url: None,
+            enclosures: None,
+            published_at: published_at.to_string(),
+            fetched_at: published_at.to_string(),
+            is_read: false,
+            is_starred: false,
+            topics: None,
+        }
+    }
+
+    #[test]
+    fn test_article_topics_round_trip() {
+        let (_dir, db) = open_db();
+        db.import_opml(r#"<opml><body><outline text="Feed" xmlUrl="https://example.com/rss"/></body></opml>"#)
+            .unwrap();
+        let feed_id = db.get_feeds().unwrap()[0].id.clone();
+        db.upsert_article(&test_article(&feed_id, "a", "A", "", "2024-01-01T00:00:00+00:00")).unwrap();
+        db.upsert_article(&test_article(&feed_id, "b", "B", "", "2024-01-02T00:00:00+00:00")).unwrap();
+
+        let pending: Vec<_> = db.get_unclassified_articles(10).unwrap().into_iter().map(|a| a.id).collect();
+        assert_eq!(pending, vec!["b", "a"]);
+
+        db.set_article_topics("b", &["rust".to_string(), "web".to_string()]).unwrap();
+        let stored = db.get_article("b").unwrap().unwrap();
+        assert_eq!(stored.topics.as_deref(), Some(r#"["rust","web"]"#));
+        assert_eq!(db.get_unclassified_articles(10).unwrap().len(), 1);
+        assert!(db.get_article("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_mark_feed_and_all_read() {
+        let (_dir, db) = open_db();
+        db.import_opml(
+            r#"<opml><body>
+                <outline text="One" xmlUrl="https://one.example.com/rss"/>
+                <outline text="Two" xmlUrl="https://two.example.com/rss"/>
+            </body></opml>"#,
+        )
+        .unwrap();
+        let feeds = db.get_feeds().unwrap();
+        let (one, two) = (feeds[0].id.clone(), feeds[1].id.clone());
+
+        for i in 0..3 {
+            let id = format!("one-{}", i);
+            db.upsert_article(&test_article(&one, &id, "Rust news", "borrow checker", "2024-01-01T00:00:00+00:00"))
+                .unwrap();
+        }
+        db.upsert_article(&test_article(&two, "two-0", "Go news", "goroutines", "2024-01-01T00:00:00+00:00"))
+            .unwrap();
+        db.update_feed_counts(&one).unwrap();
+        db.update_feed_counts(&two).unwrap();
+        assert_eq!(db.get_feed(&one).unwrap().unwrap().unread_count, 3);
+
+        assert_eq!(db.mark_feed_read(&one).unwrap(), 3);
+        let feed = db.get_feed(&one).unwrap().unwrap();
+        assert_eq!((feed.article_count, feed.unread_count), (3, 0));
+        assert_eq!(db.get_feed(&two).unwrap().unwrap().unread_count, 1);
+        assert_eq!(db.mark_feed_read(&one).unwrap(), 0);
+
+        assert_eq!(db.mark_all_read().unwrap(), 1);
+        assert_eq!(db.get_feed(&two).unwrap().unwrap().unread_count, 0);
+        assert!(db.get_articles_for_feed(&one, 10).unwrap().iter().all(|a| a.is_read));
+
+        // FTS index still matches after the bulk updates
+        let hits = db.search_articles("borrow", 10, SearchOrder::Recent).unwrap();
+        assert_eq!(hits.len(), 3);
+    }
+
+    #[test]
+    fn test_search_relevance_vs_recent() {
+        let (_dir, db) = open_db();
+        db.import_opml(r#"<opml><body><outline text="Feed" xmlUrl="https://example.com/rss"/></body></opml>"#)
+            .unwrap();
+        let feed_id = db.get_feeds().unwrap()[0].id.clone();
+
+        db.upsert_article(&test_article(
+            &feed_id,
+            "strong",
+            "Rust async runtime deep dive",
+            "Rust futures, rust executors and the rust async ecosystem explained.",
+            "2024-01-01T00:00:00+00:00",
+        ))
+        .unwrap();
+        db.upsert_article(&test_article(
+            &feed_id,
+            "weak",
+            "Weekly links",
+            "Gardening, cooking, travel, photography, and one post that mentions rust in passing.",
+            "2024-06-01T00:00:00+00:00",
+        ))
+        .unwrap();
+
+        let relevant = db.search_articles("rust", 10, SearchOrder::Relevance).unwrap();
+        assert_eq!(relevant.len(), 2);
+        assert_eq!(relevant[0].article.id, "strong");
+        assert!(relevant[0].score > relevant[1].score);
+        assert!(relevant[1].snippet.contains("<mark>rust</mark>"));
+
+        let recent = db.search_articles("rust", 10, SearchOrder::Recent).unwrap();
+        assert_eq!(recent[0].article.id, "weak");
+    }
+
+    #[test]
+    fn test_opml_import() {
+        let (_dir, db) = open_db();
+
+        let result = db.import_opml(SAMPLE_OPML).unwrap();
+        assert_eq!(result.feeds_added, 4);
+        assert_eq!(result.feeds_skipped, 1);
+        assert_eq!(result.categories_added, 3);
+
+        assert_eq!(category_of(&db, "https://blog.rust-lang.org/feed.xml").as_deref(), Some("Tech"));
+        assert_eq!(category_of(&db, "https://go.dev/blog/feed.atom").as_deref(), Some("Languages"));
+        assert_eq!(category_of(&db, "https://hnrss.org/frontpage").as_deref(), Some("News"));
+        assert_eq!(category_of(&db, "https://example.com/rss"), None);
+
+        let go = db.get_feeds().unwrap().into_iter().find(|f| f.url.contains("go.dev")).unwrap();
+        assert_eq!(go.title, "Go & Friends");
+
+        // Importing again adds nothing
+        let again = db.import_opml(SAMPLE_OPML).unwrap();
+        assert_eq!(again.feeds_added, 0);
+        assert_eq!(again.categories_added, 0);
+    }
+
+    #[test]
+    fn test_opml_round_trip() {
+        let (_dir, db) = open_db();
+        db.import_opml(SAMPLE_OPML).unwrap();
+        let exported = db.export_opml().unwrap();
+
+        let (_dir2, db2) = open_db();
+        let result = db2.import_opml(&exported).unwrap();
+        assert_eq!(result.feeds_added, 4);
+
+        let mut urls: Vec<_> = db2.get_feeds().unwrap().into_iter().map(|f| f.url).collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://blog.rust-lang.org/feed.xml",
+                "https://example.com/rss",
+                "https://go.dev/blog/feed.atom",
+                "https://hnrss.org/frontpage",
+            ]
+        );
+        for url in &urls {
+            assert_eq!(category_of(&db2, url), category_of(&db, url));
+        }
+        assert!(exported.contains("Go &amp; Friends"));
+    }
+
+    #[test]
+    fn test_articles_page_walks_without_gaps() {
+        let (_dir, db) = open_db();
+        db.import_opml(r#"<opml><body><outline text="Feed" xmlUrl="https://example.com/rss"/></body></opml>"#)
+            .unwrap();
+        let feed_id = db.get_feeds().unwrap()[0].id.clone();
+        for i in 0..50 {
+            let published = format!("2024-01-01T00:{:02}:00Z", i);
+            db.upsert_article(&test_article(&feed_id, &format!("a{:02}", i), "Title", "Body", &published))
+                .unwrap();
+        }
+        let newest_first: Vec<String> = (0..50).rev().map(|i| format!("a{:02}", i)).collect();
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = db.get_articles_page(&feed_id, cursor.as_deref(), None, 10).unwrap();
+            assert_eq!(page.items.len(), 10);
+            seen.extend(page.items.into_iter().map(|a| a.id));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, newest_first);
+
+        // Newer pages from the oldest article stay newest first
+        let page = db.get_articles_page(&feed_id, None, Some("a00"), 10).unwrap();
+        let ids: Vec<&str> = page.items.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, ["a10", "a09", "a08", "a07", "a06", "a05", "a04", "a03", "a02", "a01"]);
+        assert_eq!(page.next_cursor.as_deref(), Some("a10"));
+    }
+
+    #[test]
+    fn test_migrates_unversioned_schema_without_data_loss() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("old.db");
+        {
+            // Feeds table before refresh intervals and failure tracking
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE rss_feeds (
+                    id TEXT PRIMARY KEY, url TEXT NOT NULL UNIQUE, title TEXT NOT NULL,
+                    description TEXT, site_url TEXT, icon_url TEXT, category_id TEXT,
+                    tags TEXT DEFAULT '[]', status TEXT DEFAULT 'active', error_message TEXT,
+                    last_fetched_at TEXT, etag TEXT, last_modified TEXT,
+                    article_count INTEGER DEFAULT 0, unread_count INTEGER DEFAULT 0,
+                    created_at TEXT NOT NULL, updated_at TEXT NOT NULL
+                );
+                INSERT INTO rss_feeds (id, url, title, created_at, updated_at)
+                    VALUES ('f1', 'https://example.com/rss', 'Legacy Feed', '2024-01-01', '2024-01-01');
+                "#,
+            )
+            .unwrap();
+        }
+
+        let db = RSSDatabase::open(&db_path).unwrap();
+        assert_eq!(db.migrate().unwrap(), MIGRATIONS.last().unwrap().version);
+
+        let feeds = db.get_feeds().unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].title, "Legacy Feed");
+        assert_eq!(feeds[0].consecutive_failures, 0);
+        assert_eq!(feeds[0].refresh_interval_minutes, None);
+
+        // Tables added by the base step work against the old data
+        db.upsert_article(&test_article("f1", "a1", "Migrated", "Body", "2024-01-02T00:00:00Z")).unwrap();
+        assert_eq!(db.get_articles_for_feed("f1", 10).unwrap().len(), 1);
+    }
+}
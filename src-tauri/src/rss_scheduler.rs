@@ -0,0 +1,524 @@
+//! Background refresh scheduler for RSS feeds
+//!
+//! Polls stored feeds on a fixed tick and refreshes the ones whose
+//! per-feed interval has elapsed, using conditional requests. Failing feeds
+//! back off exponentially until a fetch succeeds.
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::rss::{FetchOutcome, ParsedItem, RSSFetcher};
+use crate::rss_db::{get_rss_db, RSSDatabase, StoredArticle, StoredFeed};
+
+/// Refresh interval used when a feed doesn't specify one
+pub const DEFAULT_REFRESH_INTERVAL_MINUTES: i32 = 60;
+
+/// Consecutive failures after which a feed is marked `error`
+pub const FAILURE_THRESHOLD: i32 = 3;
+
+/// Upper bound for the backed-off refresh interval (one day)
+const MAX_BACKOFF_MINUTES: i32 = 24 * 60;
+
+/// How often the scheduler checks for due feeds
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+
+/// Feeds fetched at once by `rss_refresh_all` when no concurrency is given
+pub const DEFAULT_REFRESH_CONCURRENCY: usize = 4;
+
+/// Event emitted when a background refresh stores new articles
+#[derive(Debug, Clone, Serialize)]
+pub struct NewArticlesEvent {
+    pub feed_id: String,
+    pub new_count: usize,
+}
+
+/// Outcome of refreshing every feed, keyed by feed id
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RefreshAllSummary {
+    pub new_articles: HashMap<String, usize>,
+    pub errors: HashMap<String, String>,
+}
+
+// ============ Due Selection ============
+
+/// Whether a feed should be refreshed at `now`
+pub fn is_feed_due(feed: &StoredFeed, now: DateTime<Utc>) -> bool {
+    if feed.status == "paused" {
+        return false;
+    }
+
+    let Some(last) = feed
+        .last_fetched_at
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+    else {
+        return true;
+    };
+
+    let interval = effective_interval_minutes(feed);
+    now.signed_duration_since(last.with_timezone(&Utc)) >= chrono::Duration::minutes(interval as i64)
+}
+
+/// Refresh interval after backoff: doubles with each consecutive failure, capped at a day
+pub fn effective_interval_minutes(feed: &StoredFeed) -> i32 {
+    let base = feed
+        .refresh_interval_minutes
+        .filter(|m| *m > 0)
+        .unwrap_or(DEFAULT_REFRESH_INTERVAL_MINUTES);
+    let exponent = feed.consecutive_failures.clamp(0, 16) as u32;
+
+    base.saturating_mul(2i32.saturating_pow(exponent))
+        .min(MAX_BACKOFF_MINUTES.max(base))
+}
+
+/// Feeds that should be refreshed at `now`
+pub fn due_feeds(feeds: &[StoredFeed], now: DateTime<Utc>) -> Vec<StoredFeed> {
+    feeds.iter().filter(|f| is_feed_due(f, now)).cloned().collect()
+}
+
+// ============ Refresh ============
+
+/// Convert a parsed item into a stored article (mirrors the frontend RSS manager)
+pub fn parsed_item_to_article(item: &ParsedItem, feed_id: &str, fetched_at: &str) -> StoredArticle {
+    let id = item
+        .guid
+        .clone()
+        .or_else(|| item.link.clone())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let published_at = item
+        .pub_date
+        .as_deref()
+        .and_then(|d| {
+            DateTime::parse_from_rfc2822(d)
+                .or_else(|_| DateTime::parse_from_rfc3339(d))
+                .ok()
+        })
+        .map(|d| d.with_timezone(&Utc).to_rfc3339())
+        .unwrap_or_else(|| fetched_at.to_string());
+
+    let content = item
+        .content
+        .clone()
+        .or_else(|| item.description.clone())
+        .unwrap_or_default();
+
+    let image_url = extract_image_url(&content).or_else(|| {
+        item.enclosures
+            .iter()
+            .find(|e| e.media_type.starts_with("image/"))
+            .map(|e| e.url.clone())
+    });
+
+    StoredArticle {
+        id,
+        feed_id: feed_id.to_string(),
+        title: item.title.clone().unwrap_or_else(|| "Untitled".to_string()),
+        link: item.link.clone().unwrap_or_default(),
+        content,
+        summary: None,
+        author: item.author.clone(),
+        image_url,
+        enclosures: if item.enclosures.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&item.enclosures).ok()
+        },
+        published_at,
+        fetched_at: fetched_at.to_string(),
+        is_read: false,
+        is_starred: false,
+        topics: None,
+    }
+}
+
+/// First `<img src>` in HTML content
+fn extract_image_url(content: &str) -> Option<String> {
+    let img = content.find("<img")?;
+    let rest = &content[img..];
+    let src = rest.find("src=")? + 4;
+    let quote = rest[src..].chars().next()?;
+    if quote != '"' && quote != '\'' {
+        return None;
+    }
+    let value = &rest[src + 1..];
+    value.find(quote).map(|end| value[..end].to_string())
+}
+
+/// Fetch one feed with its cached validators and store any new articles
+/// Returns the number of new articles
+pub async fn refresh_feed(
+    db: &RSSDatabase,
+    fetcher: &RSSFetcher,
+    feed: &StoredFeed,
+) -> Result<usize, String> {
+    let now = Utc::now().to_rfc3339();
+    let mut updated = feed.clone();
+    updated.last_fetched_at = Some(now.clone());
+    updated.updated_at = now.clone();
+
+    let outcome = match fetcher
+        .fetch_conditional(&feed.url, feed.etag.as_deref(), feed.last_modified.as_deref())
+        .await
+    {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            updated.consecutive_failures = feed.consecutive_failures.saturating_add(1);
+            if updated.consecutive_failures >= FAILURE_THRESHOLD {
+                updated.status = "error".to_string();
+            }
+            updated.error_message = Some(e.clone());
+            db.update_feed(&updated).map_err(|e| e.to_string())?;
+            return Err(e);
+        }
+    };
+
+    let mut new_count = 0;
+    if let FetchOutcome::Updated { articles, etag, last_modified } = outcome {
+        for item in &articles {
+            let article = parsed_item_to_article(item, &feed.id, &now);
+            if db.upsert_article(&article).map_err(|e| e.to_string())? {
+                new_count += 1;
+            }
+        }
+        updated.etag = etag;
+        updated.last_modified = last_modified;
+    }
+
+    updated.status = "active".to_string();
+    updated.error_message = None;
+    updated.consecutive_failures = 0;
+    db.update_feed(&updated).map_err(|e| e.to_string())?;
+    db.update_feed_counts(&feed.id).map_err(|e| e.to_string())?;
+
+    Ok(new_count)
+}
+
+/// Refresh feeds with at most `concurrency` fetches in flight
+/// A failing or slow feed only occupies its own slot
+pub async fn refresh_all(
+    db: &RSSDatabase,
+    fetcher: &RSSFetcher,
+    feeds: &[StoredFeed],
+    concurrency: usize,
+) -> RefreshAllSummary {
+    let results: Vec<(String, Result<usize, String>)> = futures::stream::iter(feeds)
+        .map(|feed| async move { (feed.id.clone(), refresh_feed(db, fetcher, feed).await) })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut summary = RefreshAllSummary::default();
+    for (feed_id, result) in results {
+        match result {
+            Ok(new_count) => {
+                summary.new_articles.insert(feed_id, new_count);
+            }
+            Err(e) => {
+                summary.errors.insert(feed_id, e);
+            }
+        }
+    }
+    summary
+}
+
+// ============ Scheduler ============
+
+/// Start the background refresh loop
+pub fn start(app: AppHandle, db: Arc<RSSDatabase>) {
+    tauri::async_runtime::spawn(async move {
+        let fetcher = RSSFetcher::new();
+        loop {
+            let feeds = match db.get_feeds() {
+                Ok(feeds) => feeds,
+                Err(e) => {
+                    log::error!("RSS scheduler failed to load feeds: {}", e);
+                    Vec::new()
+                }
+            };
+
+            for feed in due_feeds(&feeds, Utc::now()) {
+                match refresh_feed(&db, &fetcher, &feed).await {
+                    Ok(0) => {}
+                    Ok(new_count) => {
+                        log::info!("RSS scheduler: {} new articles for {}", new_count, feed.url);
+                        let event = NewArticlesEvent {
+                            feed_id: feed.id.clone(),
+                            new_count,
+                        };
+                        if let Some(window) = app.get_webview_window("main") {
+                            let _ = window.emit("rss-new-articles", &event);
+                        } else {
+                            let _ = app.emit("rss-new-articles", &event);
+                        }
+                    }
+                    Err(e) => log::warn!("RSS scheduler failed to refresh {}: {}", feed.url, e),
+                }
+            }
+
+            tokio::time::sleep(SCHEDULER_TICK).await;
+        }
+    });
+}
+
+// ============ Tauri Commands ============
+
+/// Refresh one stored feed now, using its cached validators
+/// Returns the number of new articles (0 when the server answered 304)
+#[tauri::command]
+pub async fn rss_refresh_feed(app: AppHandle, feed_id: String) -> Result<usize, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let db = get_rss_db(&app_data_dir);
+    let feed = db
+        .get_feed(&feed_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Feed not found: {}", feed_id))?;
+
+    refresh_feed(&db, &RSSFetcher::new(), &feed).await
+}
+
+/// Refresh every feed that isn't paused, `concurrency` at a time
+#[tauri::command]
+pub async fn rss_refresh_all(app: AppHandle, concurrency: Option<usize>) -> Result<RefreshAllSummary, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let db = get_rss_db(&app_data_dir);
+    let feeds: Vec<StoredFeed> = db
+        .get_feeds()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|f| f.status != "paused")
+        .collect();
+
+    let concurrency = concurrency.unwrap_or(DEFAULT_REFRESH_CONCURRENCY);
+    Ok(refresh_all(&db, &RSSFetcher::new(), &feeds, concurrency).await)
+}
+
+// ============ Tests ============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn test_feed(url: &str) -> StoredFeed {
+        feed_with_id("feed-1", url)
+    }
+
+    fn feed_with_id(id: &str, url: &str) -> StoredFeed {
+        let now = Utc::now().to_rfc3339();
+        StoredFeed {
+            id: id.to_string(),
+            url: url.to_string(),
+            title: "Test".to_string(),
+            description: None,
+            site_url: None,
+            icon_url: None,
+            category_id: None,
+            tags: Vec::new(),
+            status: "active".to_string(),
+            error_message: None,
+            last_fetched_at: None,
+            etag: None,
+            last_modified: None,
+            article_count: 0,
+            unread_count: 0,
+            created_at: now.clone(),
+            updated_at: now,
+            refresh_interval_minutes: None,
+            consecutive_failures: 0,
+        }
+    }
+
+    #[test]
+    fn test_feed_due_selection() {
+        let now = Utc::now();
+        let minutes_ago = |m: i64| Some((now - chrono::Duration::minutes(m)).to_rfc3339());
+
+        let never = test_feed("a");
+
+        let mut fresh = test_feed("b");
+        fresh.last_fetched_at = minutes_ago(5);
+
+        let mut stale = test_feed("c");
+        stale.last_fetched_at = minutes_ago(DEFAULT_REFRESH_INTERVAL_MINUTES as i64 + 1);
+
+        let mut custom = test_feed("d");
+        custom.refresh_interval_minutes = Some(2);
+        custom.last_fetched_at = minutes_ago(5);
+
+        let mut paused = test_feed("e");
+        paused.status = "paused".to_string();
+
+        let feeds = vec![never, fresh, stale, custom, paused];
+        let due: Vec<_> = due_feeds(&feeds, now).into_iter().map(|f| f.url).collect();
+        assert_eq!(due, vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn test_backoff_widens_interval() {
+        let mut feed = test_feed("a");
+        feed.refresh_interval_minutes = Some(30);
+        let intervals: Vec<i32> = (0..5)
+            .map(|failures| {
+                feed.consecutive_failures = failures;
+                effective_interval_minutes(&feed)
+            })
+            .collect();
+        assert_eq!(intervals, vec![30, 60, 120, 240, 480]);
+
+        feed.consecutive_failures = 20;
+        assert_eq!(effective_interval_minutes(&feed), MAX_BACKOFF_MINUTES);
+
+        // A failing feed fetched an hour ago is not due yet
+        let now = Utc::now();
+        feed.consecutive_failures = 2;
+        feed.last_fetched_at = Some((now - chrono::Duration::minutes(60)).to_rfc3339());
+        assert!(!is_feed_due(&feed, now));
+        feed.consecutive_failures = 0;
+        assert!(is_feed_due(&feed, now));
+    }
+
+    #[tokio::test]
+    async fn test_failure_counter_and_reset() {
+        // Nothing listens on this port once the listener is dropped
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let dir = tempdir().unwrap();
+        let db = RSSDatabase::open(&dir.path().join("rss.db")).unwrap();
+        db.create_feed(&test_feed(&format!("http://{}/feed.xml", addr))).unwrap();
+
+        let fetcher = RSSFetcher::new();
+        for attempt in 1..=FAILURE_THRESHOLD {
+            let feed = db.get_feed("feed-1").unwrap().unwrap();
+            assert!(refresh_feed(&db, &fetcher, &feed).await.is_err());
+
+            let stored = db.get_feed("feed-1").unwrap().unwrap();
+            assert_eq!(stored.consecutive_failures, attempt);
+            assert!(stored.error_message.is_some());
+            let expected = if attempt >= FAILURE_THRESHOLD { "error" } else { "active" };
+            assert_eq!(stored.status, expected);
+        }
+
+        db.reset_feed_health("feed-1").unwrap();
+        let stored = db.get_feed("feed-1").unwrap().unwrap();
+        assert_eq!(stored.consecutive_failures, 0);
+        assert_eq!(stored.status, "active");
+        assert!(stored.error_message.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_not_modified() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/feed.xml", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+
+        let dir = tempdir().unwrap();
+        let db = RSSDatabase::open(&dir.path().join("rss.db")).unwrap();
+        let mut feed = test_feed(&url);
+        feed.etag = Some("\"v1\"".to_string());
+        feed.status = "error".to_string();
+        feed.error_message = Some("timeout".to_string());
+        feed.consecutive_failures = FAILURE_THRESHOLD;
+        db.create_feed(&feed).unwrap();
+        db.update_feed(&feed).unwrap();
+
+        let new_count = refresh_feed(&db, &RSSFetcher::new(), &feed).await.unwrap();
+        assert_eq!(new_count, 0);
+
+        let request = server.await.unwrap();
+        assert!(request.contains("if-none-match: \"v1\""));
+
+        let stored = db.get_feed("feed-1").unwrap().unwrap();
+        assert_eq!(stored.etag.as_deref(), Some("\"v1\""));
+        assert!(stored.last_fetched_at.is_some());
+        assert!(!is_feed_due(&stored, Utc::now()));
+        // A successful fetch clears the failure state
+        assert_eq!(stored.consecutive_failures, 0);
+        assert_eq!(stored.status, "active");
+        assert!(stored.error_message.is_none());
+    }
+
+    fn rss_body(feed: &str, items: usize) -> String {
+        let items: String = (0..items)
+            .map(|i| format!("<item><title>Item {i}</title><link>https://example.com/{feed}/{i}</link><guid>{feed}-{i}</guid></item>"))
+            .collect();
+        format!(r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Mock</title>{}</channel></rss>"#, items)
+    }
+
+    #[tokio::test]
+    async fn test_refresh_all_attempts_every_feed() {
+        // Serves /ok1 and /ok2, answers /fail with a 500 and stalls /slow briefly
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let n = socket.read(&mut buf).await.unwrap();
+                    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let (status, body) = match path.as_str() {
+                        "/ok1" => ("200 OK", rss_body("ok1", 2)),
+                        "/ok2" => ("200 OK", rss_body("ok2", 1)),
+                        "/slow" => {
+                            tokio::time::sleep(Duration::from_millis(300)).await;
+                            ("200 OK", rss_body("slow", 3))
+                        }
+                        _ => ("500 Internal Server Error", String::new()),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: application/rss+xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        let dir = tempdir().unwrap();
+        let db = RSSDatabase::open(&dir.path().join("rss.db")).unwrap();
+        let feeds: Vec<StoredFeed> = ["slow", "ok1", "fail", "ok2"]
+            .iter()
+            .map(|name| feed_with_id(name, &format!("{}/{}", base, name)))
+            .collect();
+        for feed in &feeds {
+            db.create_feed(feed).unwrap();
+        }
+
+        let summary = refresh_all(&db, &RSSFetcher::new(), &feeds, 2).await;
+        assert_eq!(summary.new_articles.len(), 3);
+        assert_eq!(summary.new_articles["ok1"], 2);
+        assert_eq!(summary.new_articles["ok2"], 1);
+        assert_eq!(summary.new_articles["slow"], 3);
+        assert!(summary.errors["fail"].contains("500"));
+
+        assert_eq!(db.get_feed("ok1").unwrap().unwrap().article_count, 2);
+        assert_eq!(db.get_feed("fail").unwrap().unwrap().consecutive_failures, 1);
+
+        // Nothing new on the second pass
+        let summary = refresh_all(&db, &RSSFetcher::new(), &feeds[1..2], 2).await;
+        assert_eq!(summary.new_articles["ok1"], 0);
+    }
+}
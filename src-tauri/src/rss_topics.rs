@@ -0,0 +1,310 @@
+//! LLM topic classification for RSS articles
+//!
+//! Sends a short prompt through the configured chat provider and stores the
+//! normalized topic list as a JSON array in `rss_articles.topics`.
+
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+
+use crate::chat::{ApiConfig, ChatClient, ChatMessage, ChatRequest, MessageContent};
+use crate::rss_db::{get_rss_db, StoredArticle};
+use crate::secrets;
+
+/// Maximum topics kept per article
+const MAX_TOPICS: usize = 5;
+
+/// Characters of article body included in the prompt
+const MAX_CONTENT_CHARS: usize = 4000;
+
+/// Articles classified per batch call when no limit is given
+const DEFAULT_BATCH_LIMIT: i32 = 20;
+
+const CLASSIFICATION_SYSTEM_PROMPT: &str = "You classify news articles into topics. \
+Reply with a JSON array of 1 to 5 short, lowercase topic names (for example \
+[\"rust\", \"web development\"]) and nothing else.";
+
+/// Provider and model to classify with, as chosen in the frontend
+/// Credentials are never accepted here; the API key comes from the secret store
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClassifierModel {
+    pub provider: String,
+    pub model: Option<String>,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub aws_profile: Option<String>,
+    #[serde(default)]
+    pub deployment: Option<String>,
+    #[serde(default)]
+    pub api_version: Option<String>,
+}
+
+impl ClassifierModel {
+    fn into_api_config(self, api_key: Option<String>) -> ApiConfig {
+        ApiConfig {
+            provider: self.provider,
+            api_key,
+            base_url: self.base_url,
+            model: self.model,
+            region: self.region,
+            aws_profile: self.aws_profile,
+            deployment: self.deployment,
+            api_version: self.api_version,
+            retry_policy: Default::default(),
+        }
+    }
+}
+
+/// Build the API config for a selection, loading its key from the secret store
+fn resolve_config(app: &AppHandle, selection: ClassifierModel) -> ApiConfig {
+    let api_key = secrets::provider_api_key_name(&selection.provider)
+        .and_then(|name| secrets::load_app_secret(app, &name));
+    selection.into_api_config(api_key)
+}
+
+// ============ Prompt & Parsing ============
+
+/// Drop HTML tags and collapse whitespace
+fn plain_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Build the user prompt for one article
+pub fn build_classification_prompt(article: &StoredArticle) -> String {
+    let body = article
+        .summary
+        .as_deref()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or(&article.content);
+    let body = plain_text(body);
+    let body: String = body.chars().take(MAX_CONTENT_CHARS).collect();
+
+    format!("Title: {}\n\nContent:\n{}", article.title.trim(), body)
+}
+
+/// Normalize a single topic: lowercase, no list markers or quotes, single spaces
+fn normalize_topic(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    // "- topic", "* topic", "1. topic", "2) topic"
+    let raw = raw
+        .strip_prefix(['-', '*', '•'])
+        .or_else(|| {
+            let rest = raw.trim_start_matches(|c: char| c.is_ascii_digit());
+            (rest.len() < raw.len()).then(|| rest.strip_prefix(['.', ')'])).flatten()
+        })
+        .unwrap_or(raw);
+    let trimmed = raw
+        .trim_matches(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '`' || c == '#');
+    let topic = trimmed
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    if topic.is_empty() || topic.len() > 50 {
+        None
+    } else {
+        Some(topic)
+    }
+}
+
+/// Parse a model response into a deduplicated topic list
+/// Accepts a JSON array (optionally wrapped in prose or a code fence) or a comma/newline list
+pub fn parse_topics(response: &str) -> Vec<String> {
+    let from_json = match (response.find('['), response.rfind(']')) {
+        (Some(start), Some(end)) if start < end => {
+            serde_json::from_str::<Vec<String>>(&response[start..=end]).ok()
+        }
+        _ => None,
+    };
+    let raw: Vec<String> = from_json.unwrap_or_else(|| {
+        response
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("```"))
+            .flat_map(|line| line.split(','))
+            .map(str::to_string)
+            .collect()
+    });
+
+    let mut topics: Vec<String> = Vec::new();
+    for topic in raw.iter().filter_map(|t| normalize_topic(t)) {
+        if !topics.contains(&topic) {
+            topics.push(topic);
+        }
+        if topics.len() == MAX_TOPICS {
+            break;
+        }
+    }
+    topics
+}
+
+/// Classify one article with the configured provider
+async fn classify(client: &ChatClient, config: ApiConfig, article: &StoredArticle) -> Result<Vec<String>, String> {
+    let request = ChatRequest {
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: MessageContent::Text(build_classification_prompt(article)),
+        }],
+        config,
+        system_prompt: Some(CLASSIFICATION_SYSTEM_PROMPT.to_string()),
+        max_tokens: Some(100),
+        temperature: Some(0.0),
+        top_p: None,
+        stop_sequences: None,
+        workspace: None,
+        memories_dir: None,
+        enable_prompt_caching: false,
+        cache_history: false,
+        max_tool_iterations: None,
+        tools: None,
+        thinking: None,
+        cache: false,
+    };
+    let response = client.send(request).await?;
+    Ok(parse_topics(&response.content))
+}
+
+// ============ Tauri Commands ============
+
+/// Classify an article and store its topics
+#[tauri::command]
+pub async fn rss_classify_article(
+    app: AppHandle,
+    article_id: String,
+    model: ClassifierModel,
+) -> Result<Vec<String>, String> {
+    let config = resolve_config(&app, model);
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let db = get_rss_db(&app_data_dir);
+    let article = db
+        .get_article(&article_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Article not found: {}", article_id))?;
+
+    let topics = classify(&ChatClient::new(), config, &article).await?;
+    db.set_article_topics(&article_id, &topics)
+        .map_err(|e| e.to_string())?;
+    Ok(topics)
+}
+
+/// Classify the most recent unclassified articles, returning how many were stored
+#[tauri::command]
+pub async fn rss_classify_recent(
+    app: AppHandle,
+    model: ClassifierModel,
+    limit: Option<i32>,
+) -> Result<usize, String> {
+    let config = resolve_config(&app, model);
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let db = get_rss_db(&app_data_dir);
+    let articles = db
+        .get_unclassified_articles(limit.unwrap_or(DEFAULT_BATCH_LIMIT))
+        .map_err(|e| e.to_string())?;
+
+    let client = ChatClient::new();
+    let mut classified = 0;
+    for article in &articles {
+        match classify(&client, config.clone(), article).await {
+            Ok(topics) => {
+                db.set_article_topics(&article.id, &topics)
+                    .map_err(|e| e.to_string())?;
+                classified += 1;
+            }
+            Err(e) => log::warn!("[RSS] Failed to classify {}: {}", article.id, e),
+        }
+    }
+    Ok(classified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn article(title: &str, content: &str, summary: Option<&str>) -> StoredArticle {
+        StoredArticle {
+            id: "a1".to_string(),
+            feed_id: "f1".to_string(),
+            title: title.to_string(),
+            link: "https://example.com/a1".to_string(),
+            content: content.to_string(),
+            summary: summary.map(str::to_string),
+            author: None,
+            image_url: None,
+            enclosures: None,
+            published_at: "2024-01-01T00:00:00+00:00".to_string(),
+            fetched_at: "2024-01-01T00:00:00+00:00".to_string(),
+            is_read: false,
+            is_starred: false,
+            topics: None,
+        }
+    }
+
+    #[test]
+    fn test_build_classification_prompt() {
+        let prompt = build_classification_prompt(&article(
+            "  Rust 2024 released ",
+            "<p>The <b>Rust</b>\n\n team shipped   a new edition.</p>",
+            None,
+        ));
+        assert_eq!(
+            prompt,
+            "Title: Rust 2024 released\n\nContent:\nThe Rust team shipped a new edition."
+        );
+
+        // Summary is preferred, long bodies are truncated
+        let prompt = build_classification_prompt(&article("T", "ignored", Some("Short summary")));
+        assert!(prompt.ends_with("Short summary"));
+        let long = "x".repeat(MAX_CONTENT_CHARS * 2);
+        let prompt = build_classification_prompt(&article("T", &long, None));
+        assert_eq!(prompt.len(), "Title: T\n\nContent:\n".len() + MAX_CONTENT_CHARS);
+    }
+
+    #[test]
+    fn test_parse_topics() {
+        assert_eq!(
+            parse_topics(r#"["Rust", "Web  Development", "rust", " AI "]"#),
+            vec!["rust", "web development", "ai"]
+        );
+        // JSON wrapped in prose or a code fence
+        assert_eq!(
+            parse_topics("Sure! Topics:\n```json\n[\"Security\", \"Linux\"]\n```"),
+            vec!["security", "linux"]
+        );
+        // Plain lists
+        assert_eq!(
+            parse_topics("- Databases\n- SQLite, databases\n2. Performance\n3D printing"),
+            vec!["databases", "sqlite", "performance", "3d printing"]
+        );
+        assert_eq!(parse_topics("a, b, c, d, e, f, g").len(), MAX_TOPICS);
+        assert!(parse_topics("   ").is_empty());
+    }
+
+    #[test]
+    fn test_classifier_model_ignores_frontend_key() {
+        let selection: ClassifierModel = serde_json::from_value(serde_json::json!({
+            "provider": "openai",
+            "model": "gpt-4o-mini",
+            "api_key": "sk-from-frontend"
+        }))
+        .unwrap();
+        let config = selection.into_api_config(Some("sk-stored".to_string()));
+        assert_eq!(config.api_key.as_deref(), Some("sk-stored"));
+        assert_eq!(config.model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(secrets::provider_api_key_name("openai").as_deref(), Some("openai_api_key"));
+        assert_eq!(secrets::provider_api_key_name("bedrock"), None);
+    }
+}
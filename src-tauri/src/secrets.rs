@@ -0,0 +1,478 @@
+//! Encrypted storage for API keys and other credentials
+//!
+//! Values are sealed with AES-256-GCM and written to `secrets.json` in the app
+//! data dir. The master key lives in the OS keychain; when no keychain is
+//! available it falls back to a `secrets.key` file readable only by the user.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+
+const KEYCHAIN_SERVICE: &str = "com.flowq.app";
+const KEYCHAIN_USER: &str = "secrets-master-key";
+
+/// Prefix marking the ciphertext format
+const SEALED_PREFIX: &str = "v1:";
+
+/// Secret names used for provider credentials
+pub const ANTHROPIC_API_KEY: &str = "anthropic_api_key";
+pub const BEDROCK_ACCESS_KEY_ID: &str = "bedrock_access_key_id";
+pub const BEDROCK_SECRET_ACCESS_KEY: &str = "bedrock_secret_access_key";
+
+/// Secret holding a provider's API key; None for bedrock, which uses AWS credentials
+pub fn provider_api_key_name(provider: &str) -> Option<String> {
+    match provider {
+        "anthropic" => Some(ANTHROPIC_API_KEY.to_string()),
+        "bedrock" => None,
+        provider => Some(format!("{}_api_key", provider)),
+    }
+}
+
+/// Frontend settings store in the app data dir, and its API settings entry
+const SETTINGS_FILE: &str = "settings.json";
+const API_SETTINGS_KEY: &str = "api_settings";
+
+/// Credential fields older versions kept in plaintext settings, with their secret names
+const SETTINGS_SECRET_FIELDS: &[(&str, &str)] = &[
+    ("anthropicApiKey", ANTHROPIC_API_KEY),
+    ("bedrockAccessKeyId", BEDROCK_ACCESS_KEY_ID),
+    ("bedrockSecretAccessKey", BEDROCK_SECRET_ACCESS_KEY),
+];
+
+pub type MasterKey = [u8; 32];
+
+// ============ Encryption ============
+
+/// Encrypt a value, returning `v1:` + base64(nonce || ciphertext || tag)
+pub fn encrypt(key: &MasterKey, plaintext: &str) -> Result<String, String> {
+    let sealing = LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, key).map_err(|_| "Invalid secret key".to_string())?,
+    );
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| "Failed to generate nonce".to_string())?;
+
+    let mut buffer = plaintext.as_bytes().to_vec();
+    sealing
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut buffer)
+        .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&buffer);
+    Ok(format!("{}{}", SEALED_PREFIX, BASE64.encode(sealed)))
+}
+
+/// Decrypt a value produced by `encrypt`
+pub fn decrypt(key: &MasterKey, sealed: &str) -> Result<String, String> {
+    let encoded = sealed
+        .strip_prefix(SEALED_PREFIX)
+        .ok_or("Unknown secret format")?;
+    let bytes = BASE64
+        .decode(encoded)
+        .map_err(|e| format!("Invalid secret encoding: {}", e))?;
+    if bytes.len() < NONCE_LEN {
+        return Err("Secret is truncated".to_string());
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "Invalid nonce".to_string())?;
+
+    let opening = LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, key).map_err(|_| "Invalid secret key".to_string())?,
+    );
+    let mut buffer = ciphertext.to_vec();
+    let plaintext = opening
+        .open_in_place(nonce, Aad::empty(), &mut buffer)
+        .map_err(|_| "Failed to decrypt secret (wrong key or corrupted data)".to_string())?;
+    String::from_utf8(plaintext.to_vec()).map_err(|e| format!("Secret is not UTF-8: {}", e))
+}
+
+fn generate_key() -> Result<MasterKey, String> {
+    let mut key = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| "Failed to generate secret key".to_string())?;
+    Ok(key)
+}
+
+fn decode_key(encoded: &str) -> Result<MasterKey, String> {
+    BASE64
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| MasterKey::try_from(bytes.as_slice()).ok())
+        .ok_or_else(|| "Stored secret key is malformed".to_string())
+}
+
+// ============ Master Key ============
+
+/// Where the master key is kept
+pub trait Keychain {
+    fn get(&self) -> Result<Option<String>, String>;
+    fn set(&self, value: &str) -> Result<(), String>;
+}
+
+/// The OS keychain (macOS Keychain, Windows Credential Manager, Secret Service)
+pub struct OsKeychain;
+
+impl Keychain for OsKeychain {
+    fn get(&self) -> Result<Option<String>, String> {
+        let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_USER).map_err(|e| e.to_string())?;
+        match entry.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn set(&self, value: &str) -> Result<(), String> {
+        keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_USER)
+            .and_then(|entry| entry.set_password(value))
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Load the master key from the keychain, creating it on first use
+/// Falls back to a user-only key file when the keychain can't be used
+pub fn resolve_master_key(keychain: &dyn Keychain, fallback_path: &Path) -> Result<MasterKey, String> {
+    match keychain.get() {
+        Ok(Some(encoded)) => return decode_key(&encoded),
+        Ok(None) => {
+            // Keep a key that an earlier keychain-less run already used
+            if !fallback_path.exists() {
+                let key = generate_key()?;
+                match keychain.set(&BASE64.encode(key)) {
+                    Ok(()) => return Ok(key),
+                    Err(e) => log::warn!("Keychain unavailable, using key file: {}", e),
+                }
+            }
+        }
+        Err(e) => log::warn!("Keychain unavailable, using key file: {}", e),
+    }
+    file_master_key(fallback_path)
+}
+
+fn file_master_key(path: &Path) -> Result<MasterKey, String> {
+    if path.exists() {
+        let encoded = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read secret key file: {}", e))?;
+        return decode_key(&encoded);
+    }
+
+    let key = generate_key()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    // Created user-only so the key is never readable by others, even briefly
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(BASE64.encode(key).as_bytes()))
+        .map_err(|e| format!("Failed to write secret key file: {}", e))?;
+    Ok(key)
+}
+
+// ============ Secret Store ============
+
+/// Encrypted name -> value map persisted as JSON
+pub struct SecretStore {
+    path: PathBuf,
+    key: MasterKey,
+    lock: Mutex<()>,
+}
+
+impl SecretStore {
+    pub fn new(path: PathBuf, key: MasterKey) -> Self {
+        Self {
+            path,
+            key,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read_all(&self) -> Result<HashMap<String, String>, String> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let raw = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read secrets: {}", e))?;
+        serde_json::from_str(&raw).map_err(|e| format!("Failed to parse secrets: {}", e))
+    }
+
+    fn write_all(&self, secrets: &HashMap<String, String>) -> Result<(), String> {
+        let raw = serde_json::to_string_pretty(secrets)
+            .map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+        std::fs::write(&self.path, raw).map_err(|e| format!("Failed to write secrets: {}", e))
+    }
+
+    /// Encrypt and save a secret; an empty value removes it
+    pub fn store(&self, name: &str, value: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.read_all()?;
+        if value.is_empty() {
+            secrets.remove(name);
+        } else {
+            secrets.insert(name.to_string(), encrypt(&self.key, value)?);
+        }
+        self.write_all(&secrets)
+    }
+
+    /// Load and decrypt a secret
+    pub fn load(&self, name: &str) -> Result<Option<String>, String> {
+        let _guard = self.lock.lock().unwrap();
+        self.read_all()?
+            .get(name)
+            .map(|sealed| decrypt(&self.key, sealed))
+            .transpose()
+    }
+
+    /// Move plaintext credentials from a settings file into the store and scrub them
+    /// A secret already in the store wins over the plaintext copy
+    /// Returns how many fields were removed from the file
+    pub fn migrate_settings(&self, settings_path: &Path) -> Result<usize, String> {
+        let Some(mut settings) = read_settings(settings_path)? else {
+            return Ok(0);
+        };
+        let Some(api) = settings.get_mut(API_SETTINGS_KEY).and_then(Value::as_object_mut) else {
+            return Ok(0);
+        };
+
+        let mut scrubbed = 0;
+        for (field, name) in SETTINGS_SECRET_FIELDS {
+            let Some(value) = api.get(*field) else {
+                continue;
+            };
+            if let Some(plaintext) = value.as_str().filter(|v| !v.is_empty()) {
+                if self.load(name)?.is_none() {
+                    self.store(name, plaintext)?;
+                }
+            }
+            api.remove(*field);
+            scrubbed += 1;
+        }
+
+        if scrubbed > 0 {
+            let raw = serde_json::to_string_pretty(&settings)
+                .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+            std::fs::write(settings_path, raw).map_err(|e| format!("Failed to write settings: {}", e))?;
+        }
+        Ok(scrubbed)
+    }
+}
+
+fn read_settings(path: &Path) -> Result<Option<Value>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = std::fs::read_to_string(path).map_err(|e| format!("Failed to read settings: {}", e))?;
+    serde_json::from_str(&raw)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse settings: {}", e))
+}
+
+/// Whether a settings file still holds any credential field
+fn has_plaintext_credentials(settings_path: &Path) -> bool {
+    let Ok(Some(settings)) = read_settings(settings_path) else {
+        return false;
+    };
+    settings
+        .get(API_SETTINGS_KEY)
+        .and_then(Value::as_object)
+        .is_some_and(|api| SETTINGS_SECRET_FIELDS.iter().any(|(field, _)| api.contains_key(*field)))
+}
+
+// ============ Global Instance ============
+
+static SECRET_STORE: OnceLock<Arc<SecretStore>> = OnceLock::new();
+
+/// Initialize or get the secret store
+pub fn get_secret_store(app_data_dir: &Path) -> Result<Arc<SecretStore>, String> {
+    if let Some(store) = SECRET_STORE.get() {
+        return Ok(store.clone());
+    }
+    std::fs::create_dir_all(app_data_dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    let key = resolve_master_key(&OsKeychain, &app_data_dir.join("secrets.key"))?;
+    let store = Arc::new(SecretStore::new(app_data_dir.join("secrets.json"), key));
+    Ok(SECRET_STORE.get_or_init(|| store).clone())
+}
+
+/// Move credentials left in the app's settings.json into the secret store
+/// The store (and keychain) is only opened when there is something to move
+pub fn migrate_app_settings(app_data_dir: &Path) {
+    let settings_path = app_data_dir.join(SETTINGS_FILE);
+    if !has_plaintext_credentials(&settings_path) {
+        return;
+    }
+    match get_secret_store(app_data_dir).and_then(|store| store.migrate_settings(&settings_path)) {
+        Ok(count) => log::info!("Moved {} plaintext credentials from settings into the secret store", count),
+        Err(e) => log::warn!("Failed to migrate plaintext credentials: {}", e),
+    }
+}
+
+/// Load a secret through the app's store, treating any failure as absent
+pub fn load_app_secret(app: &AppHandle, name: &str) -> Option<String> {
+    let app_data_dir = app.path().app_data_dir().ok()?;
+    match get_secret_store(&app_data_dir).and_then(|store| store.load(name)) {
+        Ok(value) => value,
+        Err(e) => {
+            log::warn!("Failed to load secret {}: {}", name, e);
+            None
+        }
+    }
+}
+
+// ============ Tauri Commands ============
+
+/// Encrypt and persist a secret (an empty value deletes it)
+#[tauri::command]
+pub fn store_secret(app: AppHandle, name: String, value: String) -> Result<(), String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    get_secret_store(&app_data_dir)?.store(&name, &value)
+}
+
+/// Load a decrypted secret
+#[tauri::command]
+pub fn load_secret(app: AppHandle, name: String) -> Result<Option<String>, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    get_secret_store(&app_data_dir)?.load(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    struct UnavailableKeychain;
+
+    impl Keychain for UnavailableKeychain {
+        fn get(&self) -> Result<Option<String>, String> {
+            Err("no keychain".to_string())
+        }
+        fn set(&self, _value: &str) -> Result<(), String> {
+            Err("no keychain".to_string())
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryKeychain(Mutex<Option<String>>);
+
+    impl Keychain for MemoryKeychain {
+        fn get(&self) -> Result<Option<String>, String> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+        fn set(&self, value: &str) -> Result<(), String> {
+            *self.0.lock().unwrap() = Some(value.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let key = generate_key().unwrap();
+        let sealed = encrypt(&key, "sk-ant-secret").unwrap();
+        assert!(sealed.starts_with(SEALED_PREFIX));
+        assert!(!sealed.contains("sk-ant-secret"));
+        assert_eq!(decrypt(&key, &sealed).unwrap(), "sk-ant-secret");
+
+        // Fresh nonce per encryption
+        assert_ne!(encrypt(&key, "sk-ant-secret").unwrap(), sealed);
+
+        let other = generate_key().unwrap();
+        assert!(decrypt(&other, &sealed).is_err());
+        assert!(decrypt(&key, "plaintext").is_err());
+    }
+
+    #[test]
+    fn test_store_persists_encrypted_values() {
+        let dir = tempdir().unwrap();
+        let keychain = MemoryKeychain::default();
+        let key = resolve_master_key(&keychain, &dir.path().join("secrets.key")).unwrap();
+        assert!(keychain.get().unwrap().is_some());
+        assert!(!dir.path().join("secrets.key").exists());
+
+        let store = SecretStore::new(dir.path().join("secrets.json"), key);
+        store.store(ANTHROPIC_API_KEY, "sk-ant-123").unwrap();
+        let on_disk = std::fs::read_to_string(dir.path().join("secrets.json")).unwrap();
+        assert!(!on_disk.contains("sk-ant-123"));
+
+        // Same key from the keychain on the next launch
+        let key = resolve_master_key(&keychain, &dir.path().join("secrets.key")).unwrap();
+        let store = SecretStore::new(dir.path().join("secrets.json"), key);
+        assert_eq!(store.load(ANTHROPIC_API_KEY).unwrap().as_deref(), Some("sk-ant-123"));
+        assert_eq!(store.load("missing").unwrap(), None);
+
+        store.store(ANTHROPIC_API_KEY, "").unwrap();
+        assert_eq!(store.load(ANTHROPIC_API_KEY).unwrap(), None);
+    }
+
+    #[test]
+    fn test_falls_back_to_key_file_without_keychain() {
+        let dir = tempdir().unwrap();
+        let key_path = dir.path().join("secrets.key");
+
+        let key = resolve_master_key(&UnavailableKeychain, &key_path).unwrap();
+        assert!(key_path.exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // The file key is reused, even once a keychain shows up empty
+        assert_eq!(resolve_master_key(&UnavailableKeychain, &key_path).unwrap(), key);
+        assert_eq!(resolve_master_key(&MemoryKeychain::default(), &key_path).unwrap(), key);
+    }
+
+    #[test]
+    fn test_migrate_plaintext_settings() {
+        let dir = tempdir().unwrap();
+        let settings_path = dir.path().join(SETTINGS_FILE);
+        std::fs::write(
+            &settings_path,
+            serde_json::json!({
+                "api_settings": {
+                    "provider": "anthropic",
+                    "anthropicApiKey": "sk-ant-plain",
+                    "bedrockAccessKeyId": "AKIA-plain",
+                    "bedrockSecretAccessKey": ""
+                },
+                "theme": "dark"
+            })
+            .to_string(),
+        )
+        .unwrap();
+        assert!(has_plaintext_credentials(&settings_path));
+
+        let store = SecretStore::new(dir.path().join("secrets.json"), generate_key().unwrap());
+        store.store(BEDROCK_ACCESS_KEY_ID, "AKIA-newer").unwrap();
+        assert_eq!(store.migrate_settings(&settings_path).unwrap(), 3);
+
+        assert_eq!(store.load(ANTHROPIC_API_KEY).unwrap().as_deref(), Some("sk-ant-plain"));
+        assert_eq!(store.load(BEDROCK_ACCESS_KEY_ID).unwrap().as_deref(), Some("AKIA-newer"));
+        assert_eq!(store.load(BEDROCK_SECRET_ACCESS_KEY).unwrap(), None);
+
+        // Credentials are gone from the file; everything else is untouched
+        let on_disk = std::fs::read_to_string(&settings_path).unwrap();
+        assert!(!on_disk.contains("plain"));
+        let settings: Value = serde_json::from_str(&on_disk).unwrap();
+        assert_eq!(settings["api_settings"]["provider"], "anthropic");
+        assert_eq!(settings["theme"], "dark");
+        assert!(!has_plaintext_credentials(&settings_path));
+        assert_eq!(store.migrate_settings(&settings_path).unwrap(), 0);
+    }
+}
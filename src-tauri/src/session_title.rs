@@ -0,0 +1,126 @@
+//! Automatic session titles from the first exchange
+//!
+//! Asks the configured chat provider for a short title once the first
+//! user/assistant exchange exists. Sessions renamed by the user are skipped.
+
+use crate::chat::{ApiConfig, ChatClient, ChatMessage, ChatRequest, MessageContent};
+use crate::db::ChatDatabase;
+
+/// Characters of each message included in the prompt
+const MAX_MESSAGE_CHARS: usize = 1000;
+
+/// Characters kept from the model's answer
+const MAX_TITLE_CHARS: usize = 60;
+
+const TITLE_SYSTEM_PROMPT: &str = "You write titles for chat conversations. \
+Reply with a concise title of at most six words, without quotes or trailing punctuation.";
+
+/// Build the user prompt from the first exchange
+pub fn build_title_prompt(user: &str, assistant: &str) -> String {
+    let clip = |text: &str| text.trim().chars().take(MAX_MESSAGE_CHARS).collect::<String>();
+    format!(
+        "Write a title for this conversation.\n\nUser:\n{}\n\nAssistant:\n{}",
+        clip(user),
+        clip(assistant)
+    )
+}
+
+/// Clean up a model answer: first line, no "Title:" prefix, quotes or trailing period
+pub fn normalize_title(response: &str) -> Option<String> {
+    let line = response.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let title = line
+        .trim()
+        .trim_matches(|c: char| c == '"' || c == '\'' || c == '*' || c == '#' || c == '`')
+        .trim_end_matches('.')
+        .trim();
+    let title: String = title.chars().take(MAX_TITLE_CHARS).collect();
+    let title = title.trim_end().to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+/// Generate and store a title for a session
+/// Returns the new title, or None if the user already renamed the session
+pub async fn generate_session_title(
+    db: &ChatDatabase,
+    session_id: &str,
+    config: ApiConfig,
+) -> Result<Option<String>, String> {
+    let session = db
+        .get_session(session_id)
+        .map_err(|e| format!("Failed to get session: {}", e))?
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+    if session.title_edited.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let messages = db
+        .get_messages(session_id)
+        .map_err(|e| format!("Failed to get messages: {}", e))?;
+    let user = messages
+        .iter()
+        .position(|m| m.role == "user")
+        .ok_or("Session has no user message yet")?;
+    let assistant = messages[user..]
+        .iter()
+        .find(|m| m.role == "assistant")
+        .ok_or("Session has no assistant reply yet")?;
+
+    let request = ChatRequest {
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: MessageContent::Text(build_title_prompt(&messages[user].content, &assistant.content)),
+        }],
+        config,
+        system_prompt: Some(TITLE_SYSTEM_PROMPT.to_string()),
+        max_tokens: Some(30),
+        temperature: Some(0.2),
+        top_p: None,
+        stop_sequences: None,
+        workspace: None,
+        memories_dir: None,
+        enable_prompt_caching: false,
+        cache_history: false,
+        max_tool_iterations: None,
+        tools: None,
+        thinking: None,
+        cache: false,
+    };
+    let response = ChatClient::new().send(request).await?;
+    let title = normalize_title(&response.content).ok_or("Model returned an empty title")?;
+
+    let updated = db
+        .set_generated_title(session_id, &title)
+        .map_err(|e| format!("Failed to update session title: {}", e))?;
+    Ok(updated.then_some(title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_title_prompt() {
+        let prompt = build_title_prompt("  How do I set up tokio?\n", "Add tokio to Cargo.toml. ");
+        assert_eq!(
+            prompt,
+            "Write a title for this conversation.\n\nUser:\nHow do I set up tokio?\n\nAssistant:\nAdd tokio to Cargo.toml."
+        );
+
+        let long = "y".repeat(MAX_MESSAGE_CHARS + 500);
+        let prompt = build_title_prompt(&long, "ok");
+        assert!(prompt.contains(&"y".repeat(MAX_MESSAGE_CHARS)));
+        assert!(!prompt.contains(&"y".repeat(MAX_MESSAGE_CHARS + 1)));
+    }
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(normalize_title("\"Tokio Runtime Setup.\"").as_deref(), Some("Tokio Runtime Setup"));
+        assert_eq!(normalize_title("\nTitle: Rust lifetimes\nExtra").as_deref(), Some("Rust lifetimes"));
+        assert_eq!(normalize_title("  \n "), None);
+        assert_eq!(normalize_title(&"a".repeat(100)).unwrap().len(), MAX_TITLE_CHARS);
+    }
+}
@@ -4,8 +4,9 @@
 //! Each skill is a directory containing SKILL.md and optional .metadata.json
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // ============ Types ============
 
@@ -15,6 +16,17 @@ pub struct SkillInfo {
     pub name: String,
     pub path: String,
     pub token_count: Option<u64>,
+    pub enabled: bool,
+    /// Directory the skill was loaded from
+    pub source_scope: SkillSourceScope,
+}
+
+/// Skills directory a listed skill came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkillSourceScope {
+    Global,
+    Workspace,
 }
 
 /// Skill metadata (stored in .metadata.json)
@@ -27,6 +39,66 @@ pub struct SkillMetadata {
     pub author: Option<String>,
     pub installed_at: String,
     pub updated_at: String,
+    /// Disabled skills stay installed but are not injected into the system prompt
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Cached SKILL.md token count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_count: Option<u64>,
+    /// Tokenizer and content hash the cached token count was computed for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_count_key: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// ============ Token Counting ============
+
+/// Identifies the counting method so cached counts are invalidated when it changes
+#[cfg(feature = "tokenizer")]
+const TOKEN_COUNTER: &str = "cl100k_base";
+#[cfg(not(feature = "tokenizer"))]
+const TOKEN_COUNTER: &str = "bytes/4";
+
+/// Rough token estimate: ~4 bytes per token
+fn estimate_tokens_heuristic(content: &str) -> u64 {
+    content.len() as u64 / 4
+}
+
+/// Count tokens with the cl100k_base BPE
+#[cfg(feature = "tokenizer")]
+pub(crate) fn count_tokens(content: &str) -> u64 {
+    use std::sync::OnceLock;
+    static BPE: OnceLock<Option<tiktoken_rs::CoreBPE>> = OnceLock::new();
+
+    match BPE.get_or_init(|| tiktoken_rs::cl100k_base().ok()) {
+        Some(bpe) => bpe.encode_with_special_tokens(content).len() as u64,
+        None => estimate_tokens_heuristic(content),
+    }
+}
+
+/// Count tokens (byte heuristic; enable the `tokenizer` feature for exact counts)
+#[cfg(not(feature = "tokenizer"))]
+pub(crate) fn count_tokens(content: &str) -> u64 {
+    estimate_tokens_heuristic(content)
+}
+
+/// Default token budget for skills injected into the system prompt
+pub const DEFAULT_MAX_SKILL_TOKENS: u64 = 20_000;
+
+/// Skills section for the system prompt, limited to a token budget
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssembledSkills {
+    /// Prompt text, empty when no skill was included
+    pub prompt: String,
+    /// Included skill names, in prompt order
+    pub included: Vec<String>,
+    /// Skills left out because they did not fit the budget
+    pub dropped: Vec<String>,
+    /// Tokens used by the included skills
+    pub tokens: u64,
 }
 
 /// File item for browsing skill contents
@@ -47,6 +119,58 @@ pub struct SearchSkill {
     pub installs: u64,
 }
 
+/// Where a skill is installed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkillScope {
+    /// ~/.claude/skills/
+    Global,
+    /// {workspace}/.claude/skills/
+    Workspace(PathBuf),
+}
+
+impl SkillScope {
+    /// Workspace scope when a workspace path is given, global otherwise
+    pub fn from_workspace(workspace: Option<&str>) -> Self {
+        match workspace {
+            Some(ws) if !ws.is_empty() => SkillScope::Workspace(PathBuf::from(ws)),
+            _ => SkillScope::Global,
+        }
+    }
+
+    /// Skills directory for this scope, with the global one under `home`
+    fn skills_dir_in(&self, home: &Path) -> PathBuf {
+        match self {
+            SkillScope::Global => home.join(".claude").join("skills"),
+            SkillScope::Workspace(ws) => ws.join(".claude").join("skills"),
+        }
+    }
+
+    /// Skills directory for this scope
+    fn skills_dir(&self) -> Result<PathBuf> {
+        match self {
+            SkillScope::Global => SkillManager::global_skills_dir(),
+            SkillScope::Workspace(ws) => Ok(ws.join(".claude").join("skills")),
+        }
+    }
+}
+
+/// Files changed by a skill update, relative to the skill directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SkillUpdateSummary {
+    pub name: String,
+    pub files_added: Vec<String>,
+    pub files_changed: Vec<String>,
+    pub files_removed: Vec<String>,
+}
+
+/// Location of a skill directory on GitHub
+struct GitHubDir {
+    owner: String,
+    repo: String,
+    branch: String,
+    path: String,
+}
+
 /// Request to install a skill from content
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallSkillRequest {
@@ -54,6 +178,30 @@ pub struct InstallSkillRequest {
     pub filename: String,
 }
 
+/// Problems found in SKILL.md frontmatter
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkillValidationError {
+    pub missing_frontmatter: bool,
+    pub missing_fields: Vec<String>,
+    pub empty_fields: Vec<String>,
+}
+
+impl std::fmt::Display for SkillValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.missing_frontmatter {
+            return write!(f, "missing YAML frontmatter (expected name and description)");
+        }
+        let mut problems = Vec::new();
+        if !self.missing_fields.is_empty() {
+            problems.push(format!("missing {}", self.missing_fields.join(", ")));
+        }
+        if !self.empty_fields.is_empty() {
+            problems.push(format!("empty {}", self.empty_fields.join(", ")));
+        }
+        write!(f, "{}", problems.join("; "))
+    }
+}
+
 // ============ Error Type ============
 
 #[derive(Debug)]
@@ -63,8 +211,10 @@ pub enum SkillError {
     JsonError(serde_json::Error),
     SkillNotFound(String),
     SkillMdNotFound(String),
+    SourceNotFound(String),
     NetworkError(String),
     ZipError(String),
+    Invalid(SkillValidationError),
 }
 
 impl std::fmt::Display for SkillError {
@@ -75,8 +225,10 @@ impl std::fmt::Display for SkillError {
             SkillError::JsonError(e) => write!(f, "JSON error: {}", e),
             SkillError::SkillNotFound(name) => write!(f, "Skill not found: {}", name),
             SkillError::SkillMdNotFound(name) => write!(f, "SKILL.md not found for: {}", name),
+            SkillError::SourceNotFound(name) => write!(f, "No source recorded for skill: {}", name),
             SkillError::NetworkError(msg) => write!(f, "Network error: {}", msg),
             SkillError::ZipError(msg) => write!(f, "ZIP error: {}", msg),
+            SkillError::Invalid(e) => write!(f, "Invalid SKILL.md: {}", e),
         }
     }
 }
@@ -104,7 +256,7 @@ impl SkillManager {
     /// Get the global skills directory path (~/.claude/skills/)
     fn global_skills_dir() -> Result<PathBuf> {
         let home = dirs::home_dir().ok_or(SkillError::HomeNotFound)?;
-        Ok(home.join(".claude").join("skills"))
+        Ok(SkillScope::Global.skills_dir_in(&home))
     }
 
     /// Get the workspace skills directory path ({workspace}/.claude/skills/)
@@ -112,9 +264,9 @@ impl SkillManager {
         PathBuf::from(workspace_path).join(".claude").join("skills")
     }
 
-    /// Ensure global skills directory exists
-    fn ensure_skills_dir() -> Result<PathBuf> {
-        let dir = Self::global_skills_dir()?;
+    /// Ensure the skills directory for a scope exists
+    fn ensure_skills_dir(scope: &SkillScope) -> Result<PathBuf> {
+        let dir = scope.skills_dir()?;
         if !dir.exists() {
             fs::create_dir_all(&dir)?;
         }
@@ -134,7 +286,7 @@ impl SkillManager {
     }
 
     /// List skills from a single directory
-    fn list_from_dir(skills_dir: &PathBuf) -> Vec<SkillInfo> {
+    fn list_from_dir(skills_dir: &PathBuf, source_scope: SkillSourceScope) -> Vec<SkillInfo> {
         if !skills_dir.exists() {
             return Vec::new();
         }
@@ -156,15 +308,19 @@ impl SkillManager {
                         continue;
                     }
 
-                    // Calculate token count from SKILL.md size
                     let token_count = Self::find_skill_md(&path)
-                        .and_then(|p| fs::metadata(&p).ok())
-                        .map(|m| m.len() / 4); // Approximate token count
+                        .and_then(|p| Self::cached_token_count(&path, &p));
+
+                    let enabled = Self::read_metadata(&path)
+                        .map(|m| m.enabled)
+                        .unwrap_or(true);
 
                     skills.push(SkillInfo {
                         name,
                         path: path.to_string_lossy().to_string(),
                         token_count,
+                        enabled,
+                        source_scope,
                     });
                 }
             }
@@ -173,46 +329,139 @@ impl SkillManager {
         skills
     }
 
+    /// Token count for SKILL.md, reusing the value cached in .metadata.json when
+    /// the content and tokenizer haven't changed
+    fn cached_token_count(skill_dir: &Path, skill_md: &Path) -> Option<u64> {
+        let content = fs::read_to_string(skill_md).ok()?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(content.as_bytes());
+        let key = format!("{}:{:x}", TOKEN_COUNTER, hasher.finalize());
+
+        let metadata = Self::read_metadata(skill_dir);
+        if let Some(meta) = &metadata {
+            if meta.token_count_key.as_deref() == Some(key.as_str()) {
+                if let Some(count) = meta.token_count {
+                    return Some(count);
+                }
+            }
+        }
+
+        let count = count_tokens(&content);
+
+        // Only cache for skills that already carry metadata
+        if let Some(mut meta) = metadata {
+            meta.token_count = Some(count);
+            meta.token_count_key = Some(key);
+            if let Ok(json) = serde_json::to_string_pretty(&meta) {
+                let _ = fs::write(skill_dir.join(".metadata.json"), json);
+            }
+        }
+
+        Some(count)
+    }
+
     /// List all installed skills from global directory only
     pub fn list() -> Result<Vec<SkillInfo>> {
         let skills_dir = Self::global_skills_dir()?;
-        let mut skills = Self::list_from_dir(&skills_dir);
+        let mut skills = Self::list_from_dir(&skills_dir, SkillSourceScope::Global);
         skills.sort_by(|a, b| a.name.cmp(&b.name));
         Ok(skills)
     }
 
-    /// List all installed skills from both global and workspace directories
+    /// List all enabled skills from both global and workspace directories
+    /// A workspace skill replaces a global skill with the same name
     /// Skills are loaded from:
     /// 1. ~/.claude/skills/ (global)
     /// 2. {workspace}/.claude/skills/ (workspace-specific)
     pub fn list_all(workspace_path: Option<&str>) -> Result<Vec<SkillInfo>> {
-        let mut skills = Vec::new();
-        let mut seen_names = std::collections::HashSet::new();
+        let global_dir = Self::global_skills_dir().ok();
+        let ws_dir = workspace_path.map(Self::workspace_skills_dir);
+        Ok(Self::list_enabled_from_dirs(global_dir.as_ref(), ws_dir.as_ref()))
+    }
 
-        // Load from global directory first
-        if let Ok(global_dir) = Self::global_skills_dir() {
-            for skill in Self::list_from_dir(&global_dir) {
-                if seen_names.insert(skill.name.clone()) {
-                    skills.push(skill);
-                }
+    /// Merge skills from the global and workspace directories, dropping disabled ones
+    /// Names are unique in the result; the workspace copy wins, even when it is disabled
+    fn list_enabled_from_dirs(
+        global_dir: Option<&PathBuf>,
+        ws_dir: Option<&PathBuf>,
+    ) -> Vec<SkillInfo> {
+        let mut by_name: std::collections::BTreeMap<String, SkillInfo> = std::collections::BTreeMap::new();
+
+        if let Some(global_dir) = global_dir {
+            for skill in Self::list_from_dir(global_dir, SkillSourceScope::Global) {
+                by_name.insert(skill.name.clone(), skill);
             }
         }
 
-        // Load from workspace directory (workspace skills override global ones with same name)
-        if let Some(ws_path) = workspace_path {
-            let ws_dir = Self::workspace_skills_dir(ws_path);
-            for skill in Self::list_from_dir(&ws_dir) {
-                // Remove global skill if workspace has one with same name
-                if seen_names.contains(&skill.name) {
-                    skills.retain(|s| s.name != skill.name);
+        if let Some(ws_dir) = ws_dir {
+            for skill in Self::list_from_dir(ws_dir, SkillSourceScope::Workspace) {
+                if let Some(global) = by_name.insert(skill.name.clone(), skill) {
+                    log::info!("Workspace skill '{}' overrides global skill at {}", global.name, global.path);
                 }
-                seen_names.insert(skill.name.clone());
-                skills.push(skill);
             }
         }
 
-        skills.sort_by(|a, b| a.name.cmp(&b.name));
-        Ok(skills)
+        // BTreeMap keeps the result sorted by name
+        by_name.into_values().filter(|s| s.enabled).collect()
+    }
+
+    /// Build the skills section of the system prompt within `budget` tokens
+    /// Global skills are always considered; workspace skills too for a workspace scope
+    pub fn assemble_prompt(scope: &SkillScope, budget: u64) -> Result<AssembledSkills> {
+        let workspace = match scope {
+            SkillScope::Global => None,
+            SkillScope::Workspace(ws) => Some(ws.to_string_lossy().to_string()),
+        };
+        Ok(Self::assemble_skills(Self::list_all(workspace.as_deref())?, budget))
+    }
+
+    /// Pick skills greedily by priority until the budget is used up
+    /// Priority: workspace skills first, then smaller skills, then by name.
+    /// A skill that doesn't fit is dropped and smaller ones are still tried.
+    fn assemble_skills(skills: Vec<SkillInfo>, budget: u64) -> AssembledSkills {
+        let mut candidates: Vec<(SkillInfo, String, u64)> = skills
+            .into_iter()
+            .filter_map(|skill| {
+                let content = Self::get_content_from_path(&skill.path).ok()?;
+                let section = format!("## Skill: {}\n\n{}\n\n---\n\n", skill.name, content);
+                let tokens = count_tokens(&section);
+                Some((skill, section, tokens))
+            })
+            .collect();
+        candidates.sort_by(|(a, _, a_tokens), (b, _, b_tokens)| {
+            (b.source_scope == SkillSourceScope::Workspace)
+                .cmp(&(a.source_scope == SkillSourceScope::Workspace))
+                .then(a_tokens.cmp(b_tokens))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let mut assembled = AssembledSkills::default();
+        let mut sections = String::new();
+        for (skill, section, tokens) in candidates {
+            if assembled.tokens + tokens > budget {
+                assembled.dropped.push(skill.name);
+                continue;
+            }
+            assembled.tokens += tokens;
+            sections.push_str(&section);
+            assembled.included.push(skill.name);
+        }
+
+        if !assembled.dropped.is_empty() {
+            log::warn!(
+                "Skill token budget ({}) exceeded, dropped: {}",
+                budget,
+                assembled.dropped.join(", ")
+            );
+        }
+        if !assembled.included.is_empty() {
+            assembled.prompt = format!(
+                "\n\n# Available Skills\n\nThe following skills are installed and available. Use them when relevant:\n\n{}",
+                sections
+            );
+        }
+        assembled
     }
 
     /// Get skill content (SKILL.md) from global directory
@@ -258,6 +507,45 @@ impl SkillManager {
         Ok(Some(metadata))
     }
 
+    /// Read .metadata.json from a skill directory, ignoring missing or invalid files
+    fn read_metadata(skill_dir: &Path) -> Option<SkillMetadata> {
+        let content = fs::read_to_string(skill_dir.join(".metadata.json")).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Enable or disable a skill without deleting it
+    pub fn set_enabled(name: &str, enabled: bool, scope: &SkillScope) -> Result<()> {
+        let skills_dir = scope.skills_dir()?;
+        Self::set_enabled_in_dir(&skills_dir.join(name), name, enabled)
+    }
+
+    /// Rewrite .metadata.json in a skill directory with the new enabled flag
+    fn set_enabled_in_dir(skill_dir: &Path, name: &str, enabled: bool) -> Result<()> {
+        if !skill_dir.exists() {
+            return Err(SkillError::SkillNotFound(name.to_string()));
+        }
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut metadata = Self::read_metadata(skill_dir).unwrap_or_else(|| SkillMetadata {
+            name: name.to_string(),
+            description: None,
+            source: None,
+            version: None,
+            author: None,
+            installed_at: now.clone(),
+            updated_at: now.clone(),
+            enabled: true,
+            token_count: None,
+            token_count_key: None,
+        });
+        metadata.enabled = enabled;
+        metadata.updated_at = now;
+
+        let json = serde_json::to_string_pretty(&metadata)?;
+        fs::write(skill_dir.join(".metadata.json"), json)?;
+        Ok(())
+    }
+
     /// List files in a skill directory
     pub fn list_files(name: &str, subpath: Option<&str>) -> Result<Vec<FileItem>> {
         let skills_dir = Self::global_skills_dir()?;
@@ -341,38 +629,162 @@ impl SkillManager {
     }
 
     /// Install skill from content (markdown file)
-    pub fn install_from_content(content: &str, filename: &str) -> Result<String> {
-        let name = Self::extract_skill_name(content, filename);
+    /// Malformed skills are rejected unless `force` is set
+    pub fn install_from_content(
+        content: &str,
+        filename: &str,
+        scope: &SkillScope,
+        force: bool,
+    ) -> Result<String> {
+        Self::install_content_with_source(content, filename, None, scope, force)
+    }
+
+    /// Check that SKILL.md has frontmatter with non-empty `name` and `description`
+    pub fn validate(content: &str) -> std::result::Result<(), SkillValidationError> {
+        let Some(fields) = Self::parse_frontmatter(content) else {
+            return Err(SkillValidationError {
+                missing_frontmatter: true,
+                ..Default::default()
+            });
+        };
+
+        let mut error = SkillValidationError::default();
+        for required in ["name", "description"] {
+            match fields.iter().find(|(k, _)| k == required) {
+                None => error.missing_fields.push(required.to_string()),
+                Some((_, v)) if v.is_empty() => error.empty_fields.push(required.to_string()),
+                Some(_) => {}
+            }
+        }
+
+        if error.missing_fields.is_empty() && error.empty_fields.is_empty() {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    /// Parse top-level `key: value` pairs from YAML frontmatter
+    /// Block scalars (`|`, `>`) are joined from their indented lines
+    fn parse_frontmatter(content: &str) -> Option<Vec<(String, String)>> {
+        let mut lines = content.trim_start_matches('\u{feff}').lines();
+        if lines.next()?.trim() != "---" {
+            return None;
+        }
+
+        let mut fields: Vec<(String, String)> = Vec::new();
+        let mut block_key: Option<String> = None;
+        let mut closed = false;
+
+        for line in lines {
+            if line.trim() == "---" {
+                closed = true;
+                break;
+            }
+
+            // Continuation of a block scalar
+            if line.starts_with(' ') || line.starts_with('\t') || line.trim().is_empty() {
+                if let Some(key) = &block_key {
+                    if let Some((_, value)) = fields.iter_mut().find(|(k, _)| k == key) {
+                        let text = line.trim();
+                        if !text.is_empty() {
+                            if !value.is_empty() {
+                                value.push(' ');
+                            }
+                            value.push_str(text);
+                        }
+                    }
+                }
+                continue;
+            }
+
+            block_key = None;
+            if let Some((key, value)) = line.split_once(':') {
+                let key = key.trim().to_string();
+                let value = value.trim();
+                if matches!(value, "|" | ">" | "|-" | ">-" | "|+" | ">+") {
+                    block_key = Some(key.clone());
+                    fields.push((key, String::new()));
+                } else {
+                    let value = value.trim_matches('"').trim_matches('\'').trim();
+                    fields.push((key, value.to_string()));
+                }
+            }
+        }
+
+        if closed {
+            Some(fields)
+        } else {
+            None
+        }
+    }
+
+    /// Validate SKILL.md content and create the directory it installs into
+    /// Every install path goes through here; validation is skipped when `force` is set
+    fn prepare_skill_dir(
+        content: &str,
+        fallback_name: &str,
+        scope: &SkillScope,
+        force: bool,
+    ) -> Result<(String, PathBuf)> {
+        if !force {
+            Self::validate(content).map_err(SkillError::Invalid)?;
+        }
+
+        let name = Self::extract_skill_name(content, fallback_name);
         let sanitized_name = Self::sanitize_name(&name);
 
-        let skills_dir = Self::ensure_skills_dir()?;
+        let skills_dir = Self::ensure_skills_dir(scope)?;
         let skill_dir = skills_dir.join(&sanitized_name);
-
-        // Create skill directory
         fs::create_dir_all(&skill_dir)?;
 
+        Ok((name, skill_dir))
+    }
+
+    /// Install skill from content, recording where it came from
+    fn install_content_with_source(
+        content: &str,
+        filename: &str,
+        source: Option<String>,
+        scope: &SkillScope,
+        force: bool,
+    ) -> Result<String> {
+        let (name, skill_dir) = Self::prepare_skill_dir(content, filename, scope, force)?;
+
         // Write SKILL.md
         fs::write(skill_dir.join("SKILL.md"), content)?;
 
         // Save metadata
-        Self::save_metadata(&skill_dir, &name, None)?;
+        Self::save_metadata(&skill_dir, &name, source)?;
 
         Ok(format!("Installed: {}", name))
     }
 
     /// Install skill from URL (GitHub directory or direct file)
-    pub async fn install_from_url(url: &str) -> Result<String> {
+    pub async fn install_from_url(url: &str, scope: &SkillScope) -> Result<String> {
         // Check if it's a GitHub directory URL
         if url.contains("github.com") && url.contains("/tree/") {
-            return Self::install_from_github_dir(url).await;
+            return Self::install_from_github_dir(url, scope).await;
         }
 
         // Otherwise, try to fetch as a direct file
-        let client = reqwest::Client::builder()
+        let client = Self::http_client()?;
+        let content = String::from_utf8_lossy(&Self::fetch_bytes(&client, url).await?).to_string();
+
+        let filename = url.rsplit('/').next().unwrap_or("skill");
+        Self::install_content_with_source(&content, filename, Some(url.to_string()), scope, false)
+    }
+
+    /// HTTP client used for all skill downloads
+    fn http_client() -> Result<reqwest::Client> {
+        reqwest::Client::builder()
             .user_agent("Craft-Agent/1.0")
             .build()
-            .map_err(|e| SkillError::NetworkError(e.to_string()))?;
+            .map_err(|e| SkillError::NetworkError(e.to_string()))
+    }
 
+    /// Fetch a URL, failing on non-success status
+    async fn fetch_bytes(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
         let response = client.get(url).send().await
             .map_err(|e| SkillError::NetworkError(e.to_string()))?;
 
@@ -383,16 +795,13 @@ impl SkillManager {
             )));
         }
 
-        let content = response.text().await
+        let bytes = response.bytes().await
             .map_err(|e| SkillError::NetworkError(e.to_string()))?;
-
-        let filename = url.rsplit('/').next().unwrap_or("skill");
-        Self::install_from_content(&content, filename)
+        Ok(bytes.to_vec())
     }
 
-    /// Install skill from GitHub directory URL
-    async fn install_from_github_dir(url: &str) -> Result<String> {
-        // Parse GitHub URL: https://github.com/owner/repo/tree/branch/path
+    /// Parse GitHub URL: https://github.com/owner/repo/tree/branch/path
+    fn parse_github_dir_url(url: &str) -> Result<GitHubDir> {
         let parts: Vec<&str> = url
             .trim_start_matches("https://")
             .trim_start_matches("github.com/")
@@ -403,24 +812,26 @@ impl SkillManager {
             return Err(SkillError::NetworkError("Invalid GitHub URL format".to_string()));
         }
 
-        let owner = parts[0];
-        let repo = parts[1];
-        let branch = parts[3];
-        let path = if parts.len() > 4 {
-            parts[4..].join("/")
-        } else {
-            String::new()
-        };
-
-        let client = reqwest::Client::builder()
-            .user_agent("Craft-Agent/1.0")
-            .build()
-            .map_err(|e| SkillError::NetworkError(e.to_string()))?;
+        Ok(GitHubDir {
+            owner: parts[0].to_string(),
+            repo: parts[1].to_string(),
+            branch: parts[3].to_string(),
+            path: if parts.len() > 4 {
+                parts[4..].join("/")
+            } else {
+                String::new()
+            },
+        })
+    }
 
-        // Fetch directory contents from GitHub API
+    /// Fetch directory contents from GitHub API
+    async fn fetch_github_listing(
+        client: &reqwest::Client,
+        dir: &GitHubDir,
+    ) -> Result<Vec<serde_json::Value>> {
         let api_url = format!(
             "https://api.github.com/repos/{}/{}/contents/{}?ref={}",
-            owner, repo, path, branch
+            dir.owner, dir.repo, dir.path, dir.branch
         );
 
         let response = client.get(&api_url).send().await
@@ -433,8 +844,15 @@ impl SkillManager {
             )));
         }
 
-        let contents: Vec<serde_json::Value> = response.json().await
-            .map_err(|e| SkillError::NetworkError(e.to_string()))?;
+        response.json().await
+            .map_err(|e| SkillError::NetworkError(e.to_string()))
+    }
+
+    /// Install skill from GitHub directory URL
+    async fn install_from_github_dir(url: &str, scope: &SkillScope) -> Result<String> {
+        let dir = Self::parse_github_dir_url(url)?;
+        let client = Self::http_client()?;
+        let contents = Self::fetch_github_listing(&client, &dir).await?;
 
         // Find SKILL.md
         let skill_md_entry = contents.iter()
@@ -457,16 +875,13 @@ impl SkillManager {
         let skill_content = skill_response.text().await
             .map_err(|e| SkillError::NetworkError(e.to_string()))?;
 
-        // Extract skill name and create directory
-        let name = Self::extract_skill_name(&skill_content, &path);
-        let sanitized_name = Self::sanitize_name(&name);
-
-        let skills_dir = Self::ensure_skills_dir()?;
-        let skill_dir = skills_dir.join(&sanitized_name);
-        fs::create_dir_all(&skill_dir)?;
+        // Validate before downloading anything else
+        let (name, skill_dir) = Self::prepare_skill_dir(&skill_content, &dir.path, scope, false)?;
 
         // Download all files recursively
-        Self::download_github_files(&client, &contents, &skill_dir, owner, repo, branch).await?;
+        Self::download_github_files(
+            &client, &contents, &skill_dir, &dir.owner, &dir.repo, &dir.branch,
+        ).await?;
 
         // Save metadata
         Self::save_metadata(&skill_dir, &name, Some(url.to_string()))?;
@@ -527,14 +942,27 @@ impl SkillManager {
     }
 
     /// Install skill from ZIP (base64 encoded)
-    pub fn install_from_zip(zip_base64: &str, source: &str) -> Result<String> {
+    pub fn install_from_zip(zip_base64: &str, source: &str, scope: &SkillScope) -> Result<String> {
         use base64::{Engine, engine::general_purpose::STANDARD};
-        use std::io::{Cursor, Read};
 
         let zip_data = STANDARD.decode(zip_base64)
             .map_err(|e| SkillError::ZipError(format!("Base64 decode error: {}", e)))?;
 
-        let cursor = Cursor::new(&zip_data);
+        let (content, skill_path_prefix) = Self::find_skill_md_in_zip(&zip_data)?;
+        let (name, skill_dir) = Self::prepare_skill_dir(&content, source, scope, false)?;
+
+        Self::extract_zip(&zip_data, &skill_path_prefix, &skill_dir)?;
+
+        Self::save_metadata(&skill_dir, &name, Some(source.to_string()))?;
+
+        Ok(format!("Installed: {}", name))
+    }
+
+    /// Locate SKILL.md in a ZIP archive, returning its content and directory prefix
+    fn find_skill_md_in_zip(zip_data: &[u8]) -> Result<(String, String)> {
+        use std::io::{Cursor, Read};
+
+        let cursor = Cursor::new(zip_data);
         let mut archive = zip::ZipArchive::new(cursor)
             .map_err(|e| SkillError::ZipError(format!("ZIP open error: {}", e)))?;
 
@@ -574,17 +1002,16 @@ impl SkillManager {
         let content = skill_content
             .ok_or_else(|| SkillError::SkillMdNotFound("ZIP archive".to_string()))?;
 
-        let name = Self::extract_skill_name(&content, source);
-        let sanitized_name = Self::sanitize_name(&name);
+        Ok((content, skill_path_prefix))
+    }
 
-        let skills_dir = Self::ensure_skills_dir()?;
-        let skill_dir = skills_dir.join(&sanitized_name);
-        fs::create_dir_all(&skill_dir)?;
+    /// Extract the files under `skill_path_prefix` into `skill_dir`
+    fn extract_zip(zip_data: &[u8], skill_path_prefix: &str, skill_dir: &Path) -> Result<()> {
+        use std::io::{Cursor, Read};
 
-        // Re-open archive for extraction
-        let cursor = Cursor::new(&zip_data);
+        let cursor = Cursor::new(zip_data);
         let mut archive = zip::ZipArchive::new(cursor)
-            .map_err(|e| SkillError::ZipError(format!("ZIP reopen error: {}", e)))?;
+            .map_err(|e| SkillError::ZipError(format!("ZIP open error: {}", e)))?;
 
         // Second pass: extract files
         for i in 0..archive.len() {
@@ -599,7 +1026,7 @@ impl SkillManager {
             }
 
             // Remove the prefix to get relative path
-            let rel_path = if !skill_path_prefix.is_empty() && file_name.starts_with(&skill_path_prefix) {
+            let rel_path = if !skill_path_prefix.is_empty() && file_name.starts_with(skill_path_prefix) {
                 &file_name[skill_path_prefix.len()..]
             } else {
                 &file_name
@@ -626,14 +1053,140 @@ impl SkillManager {
             }
         }
 
-        Self::save_metadata(&skill_dir, &name, Some(source.to_string()))?;
+        Ok(())
+    }
 
-        Ok(format!("Installed: {}", name))
+    /// Re-download a skill from its recorded source
+    pub async fn update(name: &str, scope: &SkillScope) -> Result<SkillUpdateSummary> {
+        let skills_dir = scope.skills_dir()?;
+        Self::update_in_dir(&skills_dir.join(name), name).await
+    }
+
+    /// Replace a skill directory with a fresh copy of its source, keeping local metadata
+    async fn update_in_dir(skill_dir: &Path, name: &str) -> Result<SkillUpdateSummary> {
+        if !skill_dir.exists() {
+            return Err(SkillError::SkillNotFound(name.to_string()));
+        }
+
+        let mut metadata = Self::read_metadata(skill_dir)
+            .ok_or_else(|| SkillError::SourceNotFound(name.to_string()))?;
+        let source = metadata.source.clone()
+            .ok_or_else(|| SkillError::SourceNotFound(name.to_string()))?;
+
+        // Download into a hidden sibling so a failed fetch leaves the skill untouched
+        let parent = skill_dir.parent().unwrap_or(skill_dir);
+        let staging = parent.join(format!(".{}.update", name));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        fs::create_dir_all(&staging)?;
+
+        // Only swap in a fetched copy whose SKILL.md still validates
+        let fetched = Self::fetch_source_into(&source, &staging)
+            .await
+            .and_then(|_| Self::validate_skill_dir(&staging, name));
+        if let Err(e) = fetched {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        let summary = Self::diff_dirs(skill_dir, &staging, name)?;
+
+        fs::remove_dir_all(skill_dir)?;
+        fs::rename(&staging, skill_dir)?;
+
+        metadata.description = Self::find_skill_md(&skill_dir.to_path_buf())
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| Self::extract_skill_description(&content));
+        metadata.updated_at = chrono::Utc::now().to_rfc3339();
+        let json = serde_json::to_string_pretty(&metadata)?;
+        fs::write(skill_dir.join(".metadata.json"), json)?;
+
+        Ok(summary)
+    }
+
+    /// Validate the SKILL.md found in a skill directory
+    fn validate_skill_dir(skill_dir: &Path, name: &str) -> Result<()> {
+        let path = Self::find_skill_md(&skill_dir.to_path_buf())
+            .ok_or_else(|| SkillError::SkillMdNotFound(name.to_string()))?;
+        let content = fs::read_to_string(path)?;
+        Self::validate(&content).map_err(SkillError::Invalid)
+    }
+
+    /// Download a skill source (GitHub directory, ZIP or single file) into `target_dir`
+    async fn fetch_source_into(source: &str, target_dir: &Path) -> Result<()> {
+        if !source.starts_with("http://") && !source.starts_with("https://") {
+            return Err(SkillError::NetworkError(format!(
+                "Cannot update from non-URL source: {}",
+                source
+            )));
+        }
+
+        let client = Self::http_client()?;
+
+        if source.contains("github.com") && source.contains("/tree/") {
+            let dir = Self::parse_github_dir_url(source)?;
+            let contents = Self::fetch_github_listing(&client, &dir).await?;
+            return Self::download_github_files(
+                &client, &contents, &target_dir.to_path_buf(), &dir.owner, &dir.repo, &dir.branch,
+            ).await;
+        }
+
+        let data = Self::fetch_bytes(&client, source).await?;
+        if source.to_lowercase().ends_with(".zip") {
+            let (_, prefix) = Self::find_skill_md_in_zip(&data)?;
+            Self::extract_zip(&data, &prefix, target_dir)
+        } else {
+            fs::write(target_dir.join("SKILL.md"), data)?;
+            Ok(())
+        }
+    }
+
+    /// Compare two skill directories, ignoring .metadata.json
+    fn diff_dirs(old_dir: &Path, new_dir: &Path, name: &str) -> Result<SkillUpdateSummary> {
+        fn collect(root: &Path, dir: &Path, files: &mut std::collections::BTreeMap<String, Vec<u8>>) -> Result<()> {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    collect(root, &path, files)?;
+                } else {
+                    let rel = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+                    if rel != ".metadata.json" {
+                        files.insert(rel, fs::read(&path)?);
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        let mut old_files = std::collections::BTreeMap::new();
+        let mut new_files = std::collections::BTreeMap::new();
+        collect(old_dir, old_dir, &mut old_files)?;
+        collect(new_dir, new_dir, &mut new_files)?;
+
+        let mut summary = SkillUpdateSummary {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        for (path, content) in &new_files {
+            match old_files.get(path) {
+                None => summary.files_added.push(path.clone()),
+                Some(old) if old != content => summary.files_changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        for path in old_files.keys() {
+            if !new_files.contains_key(path) {
+                summary.files_removed.push(path.clone());
+            }
+        }
+
+        Ok(summary)
     }
 
     /// Delete a skill
-    pub fn delete(name: &str) -> Result<()> {
-        let skills_dir = Self::global_skills_dir()?;
+    pub fn delete(name: &str, scope: &SkillScope) -> Result<()> {
+        let skills_dir = scope.skills_dir()?;
         let skill_dir = skills_dir.join(name);
 
         if skill_dir.exists() {
@@ -644,8 +1197,8 @@ impl SkillManager {
     }
 
     /// Open skill folder in file manager
-    pub fn open_folder(name: &str) -> Result<()> {
-        let skills_dir = Self::global_skills_dir()?;
+    pub fn open_folder(name: &str, scope: &SkillScope) -> Result<()> {
+        let skills_dir = scope.skills_dir()?;
         let skill_dir = skills_dir.join(name);
 
         if !skill_dir.exists() {
@@ -786,6 +1339,9 @@ impl SkillManager {
             author: None,
             installed_at: now.clone(),
             updated_at: now,
+            enabled: true,
+            token_count: None,
+            token_count_key: None,
         };
 
         let json = serde_json::to_string_pretty(&metadata)?;
@@ -799,6 +1355,342 @@ impl SkillManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::tempdir;
+
+    fn write_skill(dir: &Path, name: &str) -> PathBuf {
+        let skill_dir = dir.join(name);
+        fs::create_dir_all(&skill_dir).unwrap();
+        fs::write(skill_dir.join("SKILL.md"), format!("# {}\n", name)).unwrap();
+        skill_dir
+    }
+
+    #[test]
+    fn test_disabled_skill_excluded() {
+        let dir = tempdir().unwrap();
+        let global = dir.path().join("global");
+        write_skill(&global, "alpha");
+        let beta = write_skill(&global, "beta");
+
+        SkillManager::set_enabled_in_dir(&beta, "beta", false).unwrap();
+
+        // Still listed for management, but flagged disabled
+        let listed = SkillManager::list_from_dir(&global, SkillSourceScope::Global);
+        assert_eq!(listed.len(), 2);
+        assert!(!listed.iter().find(|s| s.name == "beta").unwrap().enabled);
+
+        let loaded = SkillManager::list_enabled_from_dirs(Some(&global), None);
+        let names: Vec<_> = loaded.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["alpha"]);
+
+        // Re-enabling keeps the rest of the metadata intact
+        SkillManager::set_enabled_in_dir(&beta, "beta", true).unwrap();
+        let metadata = SkillManager::read_metadata(&beta).unwrap();
+        assert!(metadata.enabled);
+        assert_eq!(metadata.name, "beta");
+        assert_eq!(SkillManager::list_enabled_from_dirs(Some(&global), None).len(), 2);
+    }
+
+    #[test]
+    fn test_workspace_skill_overrides_global() {
+        let dir = tempdir().unwrap();
+        let global = dir.path().join("global");
+        let workspace = dir.path().join("workspace");
+        write_skill(&global, "shared");
+        write_skill(&global, "global-only");
+        let ws_shared = write_skill(&workspace, "shared");
+        fs::write(ws_shared.join("SKILL.md"), "# shared (workspace)\n").unwrap();
+
+        let loaded = SkillManager::list_enabled_from_dirs(Some(&global), Some(&workspace));
+        let names: Vec<_> = loaded.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["global-only", "shared"]);
+
+        let shared = &loaded[1];
+        assert_eq!(shared.source_scope, SkillSourceScope::Workspace);
+        assert_eq!(shared.path, ws_shared.to_string_lossy());
+        assert_eq!(
+            SkillManager::get_content_from_path(&shared.path).unwrap(),
+            "# shared (workspace)\n"
+        );
+        assert_eq!(loaded[0].source_scope, SkillSourceScope::Global);
+
+        // A disabled workspace copy hides the global one rather than falling back to it
+        SkillManager::set_enabled_in_dir(&ws_shared, "shared", false).unwrap();
+        let loaded = SkillManager::list_enabled_from_dirs(Some(&global), Some(&workspace));
+        assert!(!loaded.iter().any(|s| s.name == "shared"));
+    }
+
+    fn assemble(global: &PathBuf, workspace: &PathBuf, budget: u64) -> AssembledSkills {
+        SkillManager::assemble_skills(
+            SkillManager::list_enabled_from_dirs(Some(global), Some(workspace)),
+            budget,
+        )
+    }
+
+    #[test]
+    fn test_assemble_prompt_respects_budget() {
+        let dir = tempdir().unwrap();
+        let global = dir.path().join("global");
+        let workspace = dir.path().join("workspace");
+        write_skill(&global, "small");
+        let large = write_skill(&global, "large");
+        fs::write(large.join("SKILL.md"), "lorem ipsum dolor sit amet ".repeat(200)).unwrap();
+        write_skill(&workspace, "local");
+
+        let all = assemble(&global, &workspace, u64::MAX);
+        assert_eq!(all.included, vec!["local", "small", "large"]);
+        assert!(all.dropped.is_empty());
+
+        // Only the small skills fit
+        let budgeted = assemble(&global, &workspace, 100);
+        assert_eq!(budgeted.included, vec!["local", "small"]);
+        assert_eq!(budgeted.dropped, vec!["large"]);
+        assert!(budgeted.tokens <= 100);
+        assert!(budgeted.prompt.contains("## Skill: local"));
+        assert!(!budgeted.prompt.contains("## Skill: large"));
+    }
+
+    #[test]
+    fn test_assemble_prompt_prefers_workspace_skills() {
+        let dir = tempdir().unwrap();
+        let global = dir.path().join("global");
+        let workspace = dir.path().join("workspace");
+        write_skill(&global, "aaa");
+        write_skill(&workspace, "zzz");
+
+        // Room for exactly one of two equally sized skills
+        let one = assemble(&dir.path().join("none"), &workspace, u64::MAX).tokens;
+        let assembled = assemble(&global, &workspace, one);
+        assert_eq!(assembled.included, vec!["zzz"]);
+        assert_eq!(assembled.dropped, vec!["aaa"]);
+
+        let empty = assemble(&global, &workspace, 0);
+        assert!(empty.included.is_empty());
+        assert!(empty.prompt.is_empty());
+    }
+
+    #[test]
+    fn test_token_count_cached_in_metadata() {
+        let dir = tempdir().unwrap();
+        let skill_dir = write_skill(dir.path(), "gamma");
+        SkillManager::set_enabled_in_dir(&skill_dir, "gamma", true).unwrap();
+
+        let listed = SkillManager::list_from_dir(&dir.path().to_path_buf(), SkillSourceScope::Global);
+        let expected = count_tokens("# gamma\n");
+        assert_eq!(listed[0].token_count, Some(expected));
+
+        let metadata = SkillManager::read_metadata(&skill_dir).unwrap();
+        assert_eq!(metadata.token_count, Some(expected));
+        assert!(metadata.token_count_key.unwrap().starts_with(TOKEN_COUNTER));
+
+        // A stale cache entry is recomputed once the content changes
+        fs::write(skill_dir.join("SKILL.md"), "# gamma\n\nMuch longer content now.\n").unwrap();
+        let listed = SkillManager::list_from_dir(&dir.path().to_path_buf(), SkillSourceScope::Global);
+        assert_ne!(listed[0].token_count, Some(expected));
+    }
+
+    #[test]
+    fn test_token_count_heuristic_vs_tokenizer() {
+        let text = "The quick brown fox jumps over the lazy dog.";
+        assert_eq!(estimate_tokens_heuristic(text), 11);
+
+        #[cfg(feature = "tokenizer")]
+        assert_eq!(count_tokens(text), 10);
+        #[cfg(not(feature = "tokenizer"))]
+        assert_eq!(count_tokens(text), 11);
+    }
+
+    /// Serve `body` once over HTTP and return its SKILL.md URL
+    async fn serve_skill_once(body: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/SKILL.md", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await.unwrap();
+            let reply = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(reply.as_bytes()).await.unwrap();
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_update_from_changed_source() {
+        let url = serve_skill_once("---\nname: delta\ndescription: Updated skill\n---\n\nNew content.\n").await;
+
+        let dir = tempdir().unwrap();
+        let skill_dir = write_skill(dir.path(), "delta");
+        fs::write(skill_dir.join("notes.txt"), "stale").unwrap();
+        SkillManager::save_metadata(&skill_dir, "delta", Some(url)).unwrap();
+        SkillManager::set_enabled_in_dir(&skill_dir, "delta", false).unwrap();
+        let before = SkillManager::read_metadata(&skill_dir).unwrap();
+
+        let summary = SkillManager::update_in_dir(&skill_dir, "delta").await.unwrap();
+        assert_eq!(summary.files_changed, vec!["SKILL.md"]);
+        assert_eq!(summary.files_removed, vec!["notes.txt"]);
+        assert!(summary.files_added.is_empty());
+
+        let content = fs::read_to_string(skill_dir.join("SKILL.md")).unwrap();
+        assert!(content.contains("New content."));
+        assert!(!skill_dir.join("notes.txt").exists());
+
+        let after = SkillManager::read_metadata(&skill_dir).unwrap();
+        assert!(!after.enabled);
+        assert_eq!(after.installed_at, before.installed_at);
+        assert_ne!(after.updated_at, before.updated_at);
+        assert_eq!(after.description.as_deref(), Some("Updated skill"));
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_invalid_source() {
+        let url = serve_skill_once("# No frontmatter any more\n").await;
+
+        let dir = tempdir().unwrap();
+        let skill_dir = write_skill(dir.path(), "zeta");
+        SkillManager::save_metadata(&skill_dir, "zeta", Some(url)).unwrap();
+
+        let result = SkillManager::update_in_dir(&skill_dir, "zeta").await;
+        assert!(matches!(result, Err(SkillError::Invalid(_))));
+        assert_eq!(fs::read_to_string(skill_dir.join("SKILL.md")).unwrap(), "# zeta\n");
+        assert!(!dir.path().join(".zeta.update").exists());
+    }
+
+    #[tokio::test]
+    async fn test_update_requires_source() {
+        let dir = tempdir().unwrap();
+        let skill_dir = write_skill(dir.path(), "epsilon");
+        SkillManager::save_metadata(&skill_dir, "epsilon", None).unwrap();
+
+        let result = SkillManager::update_in_dir(&skill_dir, "epsilon").await;
+        assert!(matches!(result, Err(SkillError::SourceNotFound(_))));
+        assert!(skill_dir.join("SKILL.md").exists());
+    }
+
+    #[test]
+    fn test_install_workspace_scope() {
+        let root = tempdir().unwrap();
+        let home = root.path().join("home");
+        let workspace = root.path().join("ws");
+        let content = "---\nname: ws-only\ndescription: Local skill\n---\n";
+        let scope = SkillScope::Workspace(workspace.clone());
+
+        SkillManager::install_from_content(content, "SKILL.md", &scope, false).unwrap();
+
+        let ws_dir = scope.skills_dir_in(&home);
+        let skill_dir = ws_dir.join("ws-only");
+        assert!(skill_dir.join("SKILL.md").exists());
+        assert!(skill_dir.join(".metadata.json").exists());
+
+        // Visible to the workspace, not to the global listing
+        let global_dir = SkillScope::Global.skills_dir_in(&home);
+        let merged = SkillManager::list_enabled_from_dirs(Some(&global_dir), Some(&ws_dir));
+        assert!(merged.iter().any(|s| s.name == "ws-only"));
+        assert!(SkillManager::list_from_dir(&global_dir, SkillSourceScope::Global).is_empty());
+    }
+
+    #[test]
+    fn test_manage_workspace_skill() {
+        let dir = tempdir().unwrap();
+        let scope = SkillScope::Workspace(dir.path().to_path_buf());
+        let content = "---\nname: scoped\ndescription: Local skill\n---\n";
+        SkillManager::install_from_content(content, "SKILL.md", &scope, false).unwrap();
+        let skill_dir = dir.path().join(".claude").join("skills").join("scoped");
+
+        SkillManager::set_enabled("scoped", false, &scope).unwrap();
+        assert!(!SkillManager::read_metadata(&skill_dir).unwrap().enabled);
+
+        SkillManager::delete("scoped", &scope).unwrap();
+        assert!(!skill_dir.exists());
+        assert!(matches!(
+            SkillManager::set_enabled("scoped", true, &scope),
+            Err(SkillError::SkillNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_skill_scope_from_workspace() {
+        assert_eq!(SkillScope::from_workspace(None), SkillScope::Global);
+        assert_eq!(SkillScope::from_workspace(Some("")), SkillScope::Global);
+        assert_eq!(
+            SkillScope::from_workspace(Some("/tmp/ws")),
+            SkillScope::Workspace(PathBuf::from("/tmp/ws"))
+        );
+    }
+
+    #[test]
+    fn test_validate_valid_skill() {
+        let content = "---\nname: \"Good Skill\"\ndescription: >\n  Does useful\n  things\n---\n\n# Body\n";
+        assert!(SkillManager::validate(content).is_ok());
+    }
+
+    #[test]
+    fn test_validate_missing_name() {
+        let content = "---\ndescription: No name here\n---\n";
+        let error = SkillManager::validate(content).unwrap_err();
+        assert_eq!(error.missing_fields, vec!["name"]);
+        assert!(error.empty_fields.is_empty());
+    }
+
+    #[test]
+    fn test_validate_empty_description() {
+        let content = "---\nname: Skill\ndescription: \"\"\n---\n";
+        let error = SkillManager::validate(content).unwrap_err();
+        assert!(error.missing_fields.is_empty());
+        assert_eq!(error.empty_fields, vec!["description"]);
+
+        let error = SkillManager::validate("# No frontmatter").unwrap_err();
+        assert!(error.missing_frontmatter);
+    }
+
+    #[test]
+    fn test_install_rejects_invalid_unless_forced() {
+        let dir = tempdir().unwrap();
+        let scope = SkillScope::Workspace(dir.path().to_path_buf());
+
+        let result = SkillManager::install_from_content("# Plain", "plain.md", &scope, false);
+        assert!(matches!(result, Err(SkillError::Invalid(_))));
+        assert!(!dir.path().join(".claude").join("skills").join("plain").exists());
+
+        SkillManager::install_from_content("# Plain", "plain.md", &scope, true).unwrap();
+        assert!(dir.path().join(".claude").join("skills").join("plain").exists());
+    }
+
+    #[test]
+    fn test_install_from_zip_rejects_missing_frontmatter() {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use std::io::Write;
+
+        let dir = tempdir().unwrap();
+        let scope = SkillScope::Workspace(dir.path().to_path_buf());
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("bare/SKILL.md", options).unwrap();
+        zip.write_all(b"# Bare skill\n\nNo frontmatter here.\n").unwrap();
+        zip.start_file("bare/script.sh", options).unwrap();
+        zip.write_all(b"echo hi\n").unwrap();
+        let archive = STANDARD.encode(zip.finish().unwrap().into_inner());
+
+        let result = SkillManager::install_from_zip(&archive, "bare", &scope);
+        let Err(SkillError::Invalid(error)) = result else {
+            panic!("expected a validation error");
+        };
+        assert!(error.missing_frontmatter);
+        assert!(!dir.path().join(".claude").join("skills").exists());
+    }
+
+    #[test]
+    fn test_metadata_enabled_defaults_true() {
+        let json = r#"{"name":"x","description":null,"source":null,"version":null,
+            "author":null,"installed_at":"a","updated_at":"b"}"#;
+        let metadata: SkillMetadata = serde_json::from_str(json).unwrap();
+        assert!(metadata.enabled);
+    }
 
     #[test]
     fn test_extract_skill_name_from_frontmatter() {
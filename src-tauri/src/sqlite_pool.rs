@@ -0,0 +1,127 @@
+//! Small SQLite connection pool
+//!
+//! Databases run in WAL mode so readers neither block nor wait on the writer.
+//! All writes go through one connection behind a mutex; read-only connections
+//! are opened on demand and kept in an idle list when dropped.
+
+use rusqlite::{Connection, OpenFlags, Result};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// How long a connection waits on a lock held by another connection
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Read connections kept open between uses
+const MAX_IDLE_READERS: usize = 4;
+
+pub struct SqlitePool {
+    path: PathBuf,
+    writer: Mutex<Connection>,
+    idle_readers: Mutex<Vec<Connection>>,
+}
+
+impl SqlitePool {
+    /// Open the database at `path` and switch it to WAL mode
+    pub fn open(path: &Path) -> Result<Self> {
+        let writer = Connection::open(path)?;
+        writer.busy_timeout(BUSY_TIMEOUT)?;
+        // journal_mode reports the resulting mode as a row
+        let _: String = writer.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        writer.pragma_update(None, "synchronous", "NORMAL")?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new(writer),
+            idle_readers: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// The single write connection; also use it for reads that must see uncommitted work
+    pub fn writer(&self) -> MutexGuard<'_, Connection> {
+        self.writer.lock().unwrap()
+    }
+
+    /// A read-only connection that runs concurrently with the writer and other readers
+    pub fn reader(&self) -> Result<PooledReader<'_>> {
+        let idle = self.idle_readers.lock().unwrap().pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => {
+                let conn = Connection::open_with_flags(
+                    &self.path,
+                    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+                )?;
+                conn.busy_timeout(BUSY_TIMEOUT)?;
+                conn
+            }
+        };
+        Ok(PooledReader { pool: self, conn: Some(conn) })
+    }
+}
+
+/// Read connection borrowed from a `SqlitePool`, returned to it on drop
+pub struct PooledReader<'a> {
+    pool: &'a SqlitePool,
+    conn: Option<Connection>,
+}
+
+impl Deref for PooledReader<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("reader used after release")
+    }
+}
+
+impl Drop for PooledReader<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            let mut idle = self.pool.idle_readers.lock().unwrap();
+            if idle.len() < MAX_IDLE_READERS {
+                idle.push(conn);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reads_proceed_during_open_write_transaction() {
+        let dir = tempdir().unwrap();
+        let pool = SqlitePool::open(&dir.path().join("pool.db")).unwrap();
+        pool.writer()
+            .execute_batch("CREATE TABLE items (id INTEGER PRIMARY KEY); INSERT INTO items DEFAULT VALUES;")
+            .unwrap();
+
+        let mut writer = pool.writer();
+        let tx = writer.transaction().unwrap();
+        tx.execute("INSERT INTO items DEFAULT VALUES", []).unwrap();
+
+        // Readers see the last committed snapshot instead of waiting on the writer
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let count: i64 = pool
+                        .reader()
+                        .unwrap()
+                        .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+                        .unwrap();
+                    assert_eq!(count, 1);
+                });
+            }
+        });
+
+        tx.commit().unwrap();
+        drop(writer);
+        let reader = pool.reader().unwrap();
+        let count: i64 = reader.query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+        assert!(reader.execute("INSERT INTO items DEFAULT VALUES", []).is_err());
+    }
+}
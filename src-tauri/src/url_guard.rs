@@ -0,0 +1,236 @@
+//! SSRF protection for fetching user-supplied URLs
+//!
+//! Hosts are resolved up front and rejected if any address is loopback,
+//! private, link-local or otherwise internal, unless explicitly allowlisted.
+//! Redirects are followed manually so every hop is checked, and each request
+//! is pinned to the address that was validated.
+
+use reqwest::Url;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// Redirect hops followed before giving up
+pub const MAX_REDIRECTS: usize = 5;
+
+/// Maximum response body returned, in bytes
+const MAX_CONTENT_BYTES: usize = 50 * 1024;
+
+fn is_blocked_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // Carrier-grade NAT 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // Benchmarking 198.18.0.0/15
+        || (a == 198 && (b == 18 || b == 19))
+}
+
+/// IPv4 address carried inside an IPv6 one: mapped `::ffff:a.b.c.d`,
+/// compatible `::a.b.c.d` or NAT64 `64:ff9b::a.b.c.d`
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    if let Some(v4) = ip.to_ipv4() {
+        return Some(v4);
+    }
+    let segments = ip.segments();
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [.., a, b, c, d] = ip.octets();
+        return Some(Ipv4Addr::new(a, b, c, d));
+    }
+    None
+}
+
+fn is_blocked_ipv6(ip: Ipv6Addr) -> bool {
+    if ip.is_loopback() || ip.is_unspecified() {
+        return true;
+    }
+    if let Some(v4) = embedded_ipv4(ip) {
+        return is_blocked_ipv4(v4);
+    }
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local fe80::/10
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// Whether an address is internal and must not be fetched
+pub fn is_blocked_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_blocked_ipv4(v4),
+        IpAddr::V6(v6) => is_blocked_ipv6(v6),
+    }
+}
+
+fn is_allowlisted(host: &str, allowed_hosts: &[String]) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    allowed_hosts
+        .iter()
+        .any(|allowed| allowed.trim().eq_ignore_ascii_case(host))
+}
+
+/// Check a URL and resolve it to the address the request should use
+pub async fn resolve_checked(url: &Url, allowed_hosts: &[String]) -> Result<SocketAddr, String> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err("Invalid URL: must start with http:// or https://".to_string());
+    }
+    let host = url.host_str().ok_or("Invalid URL: missing host")?;
+    let port = url.port_or_known_default().ok_or("Invalid URL: missing port")?;
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_start_matches('[').trim_end_matches(']'), port))
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+        .collect();
+    let first = *addrs.first().ok_or_else(|| format!("No addresses found for {}", host))?;
+
+    if !is_allowlisted(host, allowed_hosts) {
+        if let Some(blocked) = addrs.iter().find(|a| is_blocked_ip(a.ip())) {
+            return Err(format!(
+                "Blocked request to internal address {} ({})",
+                blocked.ip(),
+                host
+            ));
+        }
+    }
+    Ok(first)
+}
+
+/// Fetch a text resource, validating every redirect hop
+/// Returns at most 50KB of content
+pub async fn fetch_text(url: &str, allowed_hosts: &[String]) -> Result<String, String> {
+    let mut current = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let addr = resolve_checked(&current, allowed_hosts).await?;
+        let host = current.host_str().unwrap_or_default().to_string();
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            // A proxy would connect on its own and bypass the pinned address
+            .no_proxy()
+            .resolve(&host, addr)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+        let response = client
+            .get(current.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch URL: {}", e))?;
+
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or("Redirect without a Location header")?;
+            current = current
+                .join(location)
+                .map_err(|e| format!("Invalid redirect URL: {}", e))?;
+            continue;
+        }
+
+        if !response.status().is_success() {
+            return Err(format!("HTTP error: {}", response.status()));
+        }
+
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+
+        // Only process text content
+        if !content_type.contains("text") && !content_type.contains("json") && !content_type.contains("xml") {
+            return Err(format!("Unsupported content type: {}", content_type));
+        }
+
+        let text = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+
+        if text.len() > MAX_CONTENT_BYTES {
+            let mut end = MAX_CONTENT_BYTES;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            return Ok(format!("{}\n\n... (content truncated, showing first 50KB)", &text[..end]));
+        }
+        return Ok(text);
+    }
+
+    Err(format!("Too many redirects (max {})", MAX_REDIRECTS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn check(url: &str, allowed: &[String]) -> Result<SocketAddr, String> {
+        resolve_checked(&Url::parse(url).unwrap(), allowed).await
+    }
+
+    #[tokio::test]
+    async fn test_blocks_internal_addresses() {
+        for url in [
+            "http://127.0.0.1/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.0.0.1/",
+            "http://192.168.1.1:8080/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[::127.0.0.1]/",
+            "http://[64:ff9b::a9fe:a9fe]/",
+            "http://[64:ff9b::10.0.0.1]/",
+            "http://0.0.0.0/",
+        ] {
+            let err = check(url, &[]).await.unwrap_err();
+            assert!(err.contains("Blocked"), "{} -> {}", url, err);
+        }
+        assert!(check("file:///etc/passwd", &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_allows_public_and_allowlisted_hosts() {
+        let addr = check("https://93.184.216.34/index.html", &[]).await.unwrap();
+        assert_eq!(addr, "93.184.216.34:443".parse().unwrap());
+        // NAT64 of a public address is fine
+        assert!(!is_blocked_ip("64:ff9b::5db8:d822".parse().unwrap()));
+
+        let allowed = vec!["127.0.0.1".to_string()];
+        assert!(check("http://127.0.0.1:3000/", &allowed).await.is_ok());
+        assert!(check("http://10.0.0.1/", &allowed).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redirect_to_internal_address_is_blocked() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/start", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(
+                    b"HTTP/1.1 302 Found\r\nLocation: http://169.254.169.254/latest/meta-data/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+        });
+
+        let err = fetch_text(&url, &["127.0.0.1".to_string()]).await.unwrap_err();
+        assert!(err.contains("169.254.169.254"), "{}", err);
+    }
+}
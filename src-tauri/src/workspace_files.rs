@@ -0,0 +1,265 @@
+//! Workspace file search for @file mentions
+//!
+//! Walks the workspace with the `ignore` crate so `.gitignore` / `.ignore`
+//! rules apply, on top of a small set of directories that are always skipped.
+//! Entries are fuzzy-matched against the query and ranked by match score.
+//! Mentioned files are read with a byte cap and binary files are detected up front.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Names skipped even when ignore files are not respected
+const ALWAYS_SKIP: &[&str] = &[".git", "node_modules", "target", "__pycache__", ".DS_Store"];
+
+/// Directory levels searched below the workspace root
+const MAX_DEPTH: usize = 6;
+
+/// Bytes inspected for NUL bytes when detecting binary files
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+/// Maximum bytes of a mentioned file that are read
+pub const MAX_READ_BYTES: usize = 256 * 1024;
+
+/// File search result for @file mention
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceFile {
+    pub name: String,
+    pub path: String,
+    pub relative_path: String,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    /// Fuzzy match score, higher is better
+    pub score: i64,
+}
+
+/// Find files and directories whose name or relative path fuzzy-matches `query`
+/// Returns the best `limit` matches
+pub fn search_files(root: &Path, query: &str, limit: usize, respect_gitignore: bool) -> Vec<WorkspaceFile> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let walker = WalkBuilder::new(root)
+        .hidden(false)
+        .git_ignore(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .git_global(respect_gitignore)
+        .ignore(respect_gitignore)
+        .parents(respect_gitignore)
+        // Honor .gitignore in workspaces that aren't git repositories too
+        .require_git(false)
+        .max_depth(Some(MAX_DEPTH))
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            !ALWAYS_SKIP.contains(&name.as_ref())
+        })
+        .build();
+
+    let mut results = Vec::new();
+    for entry in walker.flatten() {
+        // Skip the workspace root itself
+        if entry.depth() == 0 {
+            continue;
+        }
+
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative_path = path
+            .strip_prefix(root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        // Match against name or path, keeping the better score
+        let score = matcher
+            .fuzzy_match(&name, query)
+            .max(matcher.fuzzy_match(&relative_path, query));
+        if let Some(score) = score {
+            let metadata = entry.metadata().ok();
+            results.push(WorkspaceFile {
+                name,
+                path: path.to_string_lossy().to_string(),
+                relative_path,
+                is_dir: entry.file_type().is_some_and(|t| t.is_dir()),
+                size: metadata.map(|m| m.len()),
+                score,
+            });
+        }
+    }
+
+    // Sort: best score first, then directories first, then by name
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| b.is_dir.cmp(&a.is_dir))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    results.truncate(limit);
+    results
+}
+
+/// Read a text file for @file mention injection
+/// Binary files yield a short description instead of an error, and at most
+/// `MAX_READ_BYTES` are read regardless of `max_lines`
+pub fn read_file_preview(path: &Path, max_lines: Option<usize>) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let total = file
+        .metadata()
+        .map_err(|e| format!("Failed to read file: {}", e))?
+        .len();
+
+    let mut buf = Vec::new();
+    file.take(MAX_READ_BYTES as u64)
+        .read_to_end(&mut buf)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    if buf[..buf.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        return Ok(format!("Binary file, {} bytes", total));
+    }
+
+    // Drop a multi-byte character cut off by the byte cap
+    let byte_truncated = (buf.len() as u64) < total;
+    if byte_truncated {
+        if let Err(e) = std::str::from_utf8(&buf) {
+            if e.error_len().is_none() {
+                buf.truncate(e.valid_up_to());
+            }
+        }
+    }
+    let mut content = String::from_utf8_lossy(&buf).into_owned();
+
+    // Optionally limit lines
+    if let Some(limit) = max_lines {
+        let mut lines = content.lines();
+        let result = lines.by_ref().take(limit).collect::<Vec<_>>().join("\n");
+        if lines.next().is_some() {
+            return Ok(format!("{}\n\n... (truncated, showing first {} lines)", result, limit));
+        }
+        if !byte_truncated {
+            return Ok(result);
+        }
+        content = result;
+    }
+
+    if byte_truncated {
+        content.push_str(&format!(
+            "\n\n... (truncated, showing first {}KB of {} bytes)",
+            MAX_READ_BYTES / 1024,
+            total
+        ));
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn touch(root: &Path, relative: &str) {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "x").unwrap();
+    }
+
+    fn relative_paths(results: &[WorkspaceFile]) -> Vec<String> {
+        let mut paths: Vec<String> = results.iter().map(|f| f.relative_path.replace('\\', "/")).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_search_respects_gitignore() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join(".gitignore"), "secret.log\nout/\n").unwrap();
+        touch(root, "app.log");
+        touch(root, "secret.log");
+        touch(root, "out/build.log");
+        touch(root, ".github/ci.log");
+        touch(root, "node_modules/pkg/index.log");
+
+        let found = relative_paths(&search_files(root, ".log", 50, true));
+        assert_eq!(found, vec![".github/ci.log", "app.log"]);
+
+        // Without ignore files only the always-skip set applies
+        let found = relative_paths(&search_files(root, ".log", 50, false));
+        assert_eq!(found, vec![".github/ci.log", "app.log", "out/build.log", "secret.log"]);
+    }
+
+    #[test]
+    fn test_search_limit_and_directories_first() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        touch(root, "src/main.rs");
+        touch(root, "src/lib.rs");
+        touch(root, "src_old.rs");
+
+        let results = search_files(root, "src", 50, true);
+        assert!(results[0].is_dir);
+        assert_eq!(results[0].name, "src");
+        assert_eq!(search_files(root, "rs", 2, true).len(), 2);
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        touch(root, "mega_airline.rs");
+        touch(root, "main.rs");
+        touch(root, "docs/readme.md");
+
+        // Exact prefix ranks above a scattered subsequence
+        let results = search_files(root, "main", 50, true);
+        let names: Vec<&str> = results.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["main.rs", "mega_airline.rs"]);
+        assert!(results[0].score > results[1].score);
+
+        // Subsequence queries skip punctuation
+        let results = search_files(root, "mainrs", 50, true);
+        assert_eq!(results[0].name, "main.rs");
+        assert!(search_files(root, "zzz", 50, true).is_empty());
+    }
+
+    #[test]
+    fn test_read_file_preview_binary() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("image.png");
+        std::fs::write(&path, [0x89, b'P', b'N', b'G', 0x00, 0x1a, 0xff]).unwrap();
+
+        assert_eq!(read_file_preview(&path, None).unwrap(), "Binary file, 7 bytes");
+    }
+
+    #[test]
+    fn test_read_file_preview_caps_large_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        let line = "é".repeat(40) + "\n";
+        std::fs::write(&path, line.repeat(MAX_READ_BYTES / line.len() * 3)).unwrap();
+
+        let content = read_file_preview(&path, None).unwrap();
+        assert!(content.len() < MAX_READ_BYTES + 100);
+        assert!(content.contains("truncated, showing first 256KB"));
+        assert!(!content.contains('\u{FFFD}'));
+
+        // Line limit still applies within the cap
+        let content = read_file_preview(&path, Some(2)).unwrap();
+        assert_eq!(content, format!("{}{}\n\n... (truncated, showing first 2 lines)", line, line.trim_end()));
+    }
+
+    #[test]
+    fn test_read_file_preview_max_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        assert_eq!(
+            read_file_preview(&path, Some(2)).unwrap(),
+            "one\ntwo\n\n... (truncated, showing first 2 lines)"
+        );
+        assert_eq!(read_file_preview(&path, Some(3)).unwrap(), "one\ntwo\nthree");
+        assert_eq!(read_file_preview(&path, None).unwrap(), "one\ntwo\nthree\n");
+    }
+}